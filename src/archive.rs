@@ -0,0 +1,93 @@
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+
+// Total bytes an upload may unpack to; a small archive can expand to far more than it weighs
+const MAX_EXTRACTED_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+
+// Unpack a zip, tar.gz or plain tar held in memory into `dest` and return the directory to index:
+// `dest` itself, or the one directory everything was packed under (as in GitHub's archives).
+// Entries whose path would land outside `dest` fail the whole upload; links and device files are
+// skipped, since following them could do the same
+pub fn extract(bytes: &[u8], dest: &Path) -> io::Result<PathBuf> {
+    let mut budget = MAX_EXTRACTED_BYTES;
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        extract_zip(bytes, dest, &mut budget)?;
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        extract_tar(GzDecoder::new(bytes), dest, &mut budget)?;
+    } else if bytes.get(257..262) == Some(b"ustar") {
+        extract_tar(bytes, dest, &mut budget)?;
+    } else {
+        return Err(invalid("not a zip, tar.gz or tar archive".to_string()));
+    }
+    root(dest)
+}
+
+fn extract_zip(bytes: &[u8], dest: &Path, budget: &mut u64) -> io::Result<()> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| invalid(e.to_string()))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| invalid(e.to_string()))?;
+        let path = safe_path(dest, Path::new(entry.name()))?;
+        if entry.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if entry.is_file() && !entry.is_symlink() {
+            write_file(&mut entry, &path, budget)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_tar(reader: impl Read, dest: &Path, budget: &mut u64) -> io::Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = safe_path(dest, &entry.path()?)?;
+        let kind = entry.header().entry_type();
+        if kind.is_dir() {
+            fs::create_dir_all(&path)?;
+        } else if kind.is_file() {
+            write_file(&mut entry, &path, budget)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_file(entry: &mut impl Read, path: &Path, budget: &mut u64) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::File::create(path)?;
+    // One byte past the budget is enough to tell it was exceeded
+    let written = io::copy(&mut entry.take(*budget + 1), &mut file)?;
+    if written > *budget {
+        return Err(invalid(format!("archive unpacks to more than {} bytes", MAX_EXTRACTED_BYTES)));
+    }
+    *budget -= written;
+    Ok(())
+}
+
+// `name` joined onto `dest`, refused when it is absolute or climbs out with `..` (zip slip)
+fn safe_path(dest: &Path, name: &Path) -> io::Result<PathBuf> {
+    let mut path = dest.to_path_buf();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return Err(invalid(format!("archive entry {} points outside the archive", name.display()))),
+        }
+    }
+    Ok(path)
+}
+
+fn root(dest: &Path) -> io::Result<PathBuf> {
+    let entries: Vec<_> = fs::read_dir(dest)?.collect::<io::Result<_>>()?;
+    match entries.as_slice() {
+        [only] if only.file_type()?.is_dir() => Ok(only.path()),
+        _ => Ok(dest.to_path_buf()),
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
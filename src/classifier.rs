@@ -1,101 +1,577 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
-use crate::graph::GraphClient;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use crate::entrypoints::Entrypoint;
+use crate::store::GraphStore;
+
+// Rules used until CLASSIFIER_RULES names a file, and the starting point for one
+const DEFAULT_RULES: &str = include_str!("classifier.toml");
+
+// Facts matched against patterns rather than held against a count
+const VALUE_FACTS: [&str; 5] = ["language", "path", "dependency", "license", "entrypoint"];
+
+// Below this many functions and methods, the async share is left at 0
+const MIN_CALLABLES_FOR_ASYNC: usize = 10;
+
+// Matched values a signal lists before summing up the rest
+const MAX_SIGNAL_VALUES: usize = 5;
+
+// How sharply closeness to the centroids picks a doc type. Embeddings of any two code summaries
+// tend to sit close together, so their cosine similarities are scaled up before the softmax
+const EMBEDDING_TEMPERATURE: f64 = 0.05;
+
+// How much of the repo a summary for the embedding stage takes in
+const SUMMARY_FILES: usize = 300;
+const SUMMARY_SYMBOLS: usize = 50;
+const SUMMARY_README_CHARS: usize = 2000;
+
+// Neutral votes each rule starts with when recalibrating from feedback, and how far its weight
+// may be scaled either way
+const CALIBRATION_PRIOR: f64 = 5.0;
+const MIN_CALIBRATION: f64 = 0.25;
+const MAX_CALIBRATION: f64 = 2.0;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ClassificationResult {
+    // library_reference, cli_tool, sdk, service_api, infra, data_pipeline, mobile_app, consumer
+    // or devdocs with the built-in rules; whatever the rules name otherwise
     pub doc_type: String,
     pub confidence: f64,
-    pub signals: Vec<String>,
+    pub signals: Vec<Signal>,
+    // What every doc type the rules name scored, matched or not
+    #[serde(default)]
+    pub scores: BTreeMap<String, f64>,
+    // Cosine similarity of the embedding to each doc type's centroid, when one was given
+    #[serde(default)]
+    pub similarities: BTreeMap<String, f64>,
+    // How the repo's code is started, what docs for it begin from
+    #[serde(default)]
+    pub entrypoints: Vec<Entrypoint>,
+    // The rules' pick, and whether a user's verdict on the repo replaced it as `doc_type`
+    #[serde(default)]
+    pub predicted: String,
+    #[serde(default)]
+    pub overridden: bool,
 }
 
-pub async fn classify(client: &GraphClient, repo_name: &str) -> ClassificationResult {
-    let mut signals = vec![];
-    let mut consumer_score: f64 = 0.0;
-    let mut devdocs_score: f64 = 0.0;
-
-    // Run all four independent Neo4j queries concurrently instead of sequentially
-    let (counts_r, langs_r, files_r, symbols_r) = tokio::join!(
-        client.count_by_kind(repo_name),
-        client.get_file_languages(repo_name),
-        client.get_all_files(repo_name),
-        client.get_all_symbols(repo_name),
-    );
+// A user's verdict on a repo's classification, kept per repo; every repo's verdicts recalibrate
+// the rule weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Feedback {
+    pub doc_type: String,
+    // What the rules picked when it was given; the same as `doc_type` when confirmed
+    pub predicted: String,
+    // The signals at the time, and the rules among them the user disputed
+    pub signals: Vec<Signal>,
+    #[serde(default)]
+    pub disputed: Vec<String>,
+    // The repo's summary embedding when it was given, making it an example of `doc_type`
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    // Unix seconds
+    pub given_at: i64,
+}
 
-    if let Ok(counts) = counts_r {
-        if let Some(obj) = counts.as_object() {
-            let funcs = obj.get("function").and_then(|v| v.as_i64()).unwrap_or(0);
-            let classes = obj.get("class").and_then(|v| v.as_i64()).unwrap_or(0);
-            let methods = obj.get("method").and_then(|v| v.as_i64()).unwrap_or(0);
+// A rule that matched: what it saw, and the weight it added to a doc type's score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Signal {
+    pub rule: String,
+    pub signal: String,
+    pub weight: f64,
+    pub contributes_to: String,
+}
+
+// What /classify scores a repo with; see classifier.toml for the format
+#[derive(Debug, Deserialize)]
+pub struct Rules {
+    #[serde(default = "default_doc_type")]
+    pub default_doc_type: String,
+    // How much the embedding stage counts against the rules when /classify is given an embedding
+    #[serde(default = "default_embedding_weight")]
+    pub embedding_weight: f64,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
 
-            if methods > funcs {
-                signals.push("more methods than functions -> likely OOP/API".into());
-                devdocs_score += 1.0;
+#[derive(Debug, Deserialize)]
+pub struct Rule {
+    #[serde(default)]
+    pub id: String,
+    pub fact: String,
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    pub min: Option<f64>,
+    pub above: Option<String>,
+    pub signal: String,
+    pub weight: f64,
+    pub doc_type: String,
+    #[serde(skip)]
+    matcher: Option<GlobSet>,
+}
+
+fn default_doc_type() -> String {
+    "devdocs".to_string()
+}
+
+fn default_embedding_weight() -> f64 {
+    0.5
+}
+
+impl Rules {
+    // Rules from TOML, or YAML when `yaml`, with their patterns compiled
+    pub fn parse(text: &str, yaml: bool) -> Result<Rules, String> {
+        let mut rules: Rules = if yaml {
+            serde_yaml::from_str(text).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(text).map_err(|e| e.to_string())?
+        };
+        if !(0.0..=1.0).contains(&rules.embedding_weight) {
+            return Err("embedding_weight must be between 0 and 1".to_string());
+        }
+        let mut ids = HashSet::new();
+        for (i, rule) in rules.rules.iter_mut().enumerate() {
+            if rule.id.is_empty() {
+                let derived = format!("{}.{}", rule.doc_type, rule.fact);
+                rule.id = (1..).map(|n| if n == 1 { derived.clone() } else { format!("{}.{}", derived, n) })
+                    .find(|id| !ids.contains(id))
+                    .unwrap_or(derived);
             }
-            if classes > 5 {
-                signals.push(format!("{} classes detected -> structured codebase", classes));
-                devdocs_score += 0.5;
+            if !ids.insert(rule.id.clone()) {
+                return Err(format!("rule {}: id {} is already taken", i + 1, rule.id));
             }
-            if funcs > 20 {
-                signals.push(format!("{} functions -> large API surface", funcs));
-                devdocs_score += 0.5;
+            let at = |message: String| format!("rule {} ({}): {}", i + 1, rule.id, message);
+            if rule.doc_type.is_empty() || !rule.weight.is_finite() {
+                return Err(at("needs a doc_type and a finite weight".to_string()));
+            }
+            if VALUE_FACTS.contains(&rule.fact.as_str()) {
+                if rule.patterns.is_empty() {
+                    return Err(at("needs patterns".to_string()));
+                }
+                let mut set = GlobSetBuilder::new();
+                for pattern in &rule.patterns {
+                    set.add(GlobBuilder::new(pattern).case_insensitive(true).build().map_err(|e| at(e.to_string()))?);
+                }
+                rule.matcher = Some(set.build().map_err(|e| at(e.to_string()))?);
             }
         }
+        Ok(rules)
     }
 
-    if let Ok(langs) = langs_r {
-        if let Some(obj) = langs.as_object() {
-            let has_python = obj.contains_key("Python");
-            let has_js = obj.contains_key("JavaScript") || obj.contains_key("TypeScript");
-            let has_cpp = obj.contains_key("Cpp");
+    fn load(path: &Path) -> Result<Rules, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let yaml = path.extension().is_some_and(|e| e == "yaml" || e == "yml");
+        Rules::parse(&text, yaml).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+// The rules in force, and the file they were read from as it was when read
+struct Loaded {
+    rules: Arc<Rules>,
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+}
+
+static RULES: Mutex<Option<Loaded>> = Mutex::new(None);
+
+// Classify with the rules in `path` from now on, and again whenever the file changes. On error
+// the rules in force are kept
+pub fn load_rules(path: &Path) -> Result<usize, String> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let rules = Rules::load(path)?;
+    let count = rules.rules.len();
+    *RULES.lock().unwrap() = Some(Loaded { rules: Arc::new(rules), path: Some(path.to_path_buf()), modified });
+    Ok(count)
+}
+
+// The rules in force, re-read first when their file has changed since
+pub fn rules() -> Arc<Rules> {
+    let mut loaded = RULES.lock().unwrap();
+    let current = loaded.get_or_insert_with(|| Loaded {
+        rules: Arc::new(Rules::parse(DEFAULT_RULES, false).expect("built-in classifier rules are valid")),
+        path: None,
+        modified: None,
+    });
+    if let Some(path) = current.path.clone() {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified != current.modified {
+            // Recorded either way, so a broken edit is reported once rather than on every call
+            current.modified = modified;
+            match Rules::load(&path) {
+                Ok(rules) => {
+                    info!("Reloaded {} classifier rules from {}", rules.rules.len(), path.display());
+                    current.rules = Arc::new(rules);
+                }
+                Err(e) => warn!("Classifier rules not reloaded, keeping the previous ones: {}", e),
+            }
+        }
+    }
+    current.rules.clone()
+}
+
+// What the rules are matched against
+#[derive(Default)]
+struct Facts {
+    counts: HashMap<String, f64>,
+    values: HashMap<&'static str, Vec<String>>,
+    spdx: Option<String>,
+    entrypoints: Vec<Entrypoint>,
+}
+
+impl Facts {
+    fn count(&self, name: &str) -> f64 {
+        self.counts.get(name).copied().unwrap_or(0.0)
+    }
+
+    // The signal when `rule` matches: {value} is the count or the values that matched
+    fn evaluate(&self, rule: &Rule) -> Option<Signal> {
+        let value = if let Some(matcher) = &rule.matcher {
+            let mut matched: Vec<&str> = self.values.get(rule.fact.as_str()).into_iter().flatten()
+                .filter(|value| matcher.is_match(value.as_str()))
+                .map(String::as_str)
+                .collect();
+            matched.sort();
+            matched.dedup();
+            if matched.is_empty() {
+                return None;
+            }
+            let more = matched.len().saturating_sub(MAX_SIGNAL_VALUES);
+            matched.truncate(MAX_SIGNAL_VALUES);
+            if more > 0 {
+                format!("{} and {} more", matched.join(", "), more)
+            } else {
+                matched.join(", ")
+            }
+        } else {
+            let count = self.count(&rule.fact);
+            if count < rule.min.unwrap_or(1.0) || rule.above.as_ref().is_some_and(|other| count <= self.count(other)) {
+                return None;
+            }
+            count.to_string()
+        };
+        Some(Signal {
+            rule: rule.id.clone(),
+            signal: self.render(&rule.signal, &value),
+            weight: rule.weight,
+            contributes_to: rule.doc_type.clone(),
+        })
+    }
+
+    // `template` with {value}, {spdx} and {<count>} filled in; anything else is left as written
+    fn render(&self, template: &str, value: &str) -> String {
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some((open, close)) = rest.find('{').and_then(|open| Some((open, open + rest[open..].find('}')?))) {
+            out.push_str(&rest[..open]);
+            let name = &rest[open + 1..close];
+            match name {
+                "value" => out.push_str(value),
+                "spdx" => out.push_str(self.spdx.as_deref().unwrap_or("unrecognised")),
+                _ if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {
+                    out.push_str(&self.count(name).to_string())
+                }
+                _ => out.push_str(&rest[open..=close]),
+            }
+            rest = &rest[close + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+// The repo's doc type by the rules, with weights recalibrated from every repo's feedback, and
+// blended with how close `embedding` (of the repo's `summary`) is to each doc type's centroid
+// when given. A user's verdict on this repo takes precedence
+pub async fn classify(store: &dyn GraphStore, repo_name: &str, embedding: Option<&[f32]>) -> ClassificationResult {
+    let (facts, verdict, feedback) = tokio::join!(
+        gather(store, repo_name),
+        store.classification_feedback(repo_name),
+        store.all_classification_feedback(),
+    );
+    let feedback = feedback.unwrap_or_default();
+    let calibration = calibrate(&feedback);
+    let rules = rules();
+    let mut signals = vec![];
+    // Doc types in the order rules name them, so ties and the no-score confidence are stable
+    let mut scores: Vec<(&str, f64)> = vec![];
+    for rule in &rules.rules {
+        if !scores.iter().any(|(doc_type, _)| *doc_type == rule.doc_type) {
+            scores.push((&rule.doc_type, 0.0));
+        }
+        if let Some(mut signal) = facts.evaluate(rule) {
+            signal.weight *= calibration.get(&rule.id).copied().unwrap_or(1.0);
+            if let Some(score) = scores.iter_mut().find(|(doc_type, _)| *doc_type == rule.doc_type) {
+                score.1 += signal.weight;
+            }
+            signals.push(signal);
+        }
+    }
+
+    // Each doc type's share of the scores, blended with its share of the embedding's closeness
+    let total: f64 = scores.iter().map(|(_, score)| score).sum();
+    let mut shares: Vec<(&str, f64)> = scores.iter()
+        .map(|(doc_type, score)| (*doc_type, if total > 0.0 { score / total } else { 0.0 }))
+        .collect();
+    let centroids = embedding.map(|_| centroids(&feedback)).unwrap_or_default();
+    let similarities: BTreeMap<String, f64> = embedding.into_iter()
+        .flat_map(|embedding| centroids.iter().filter_map(|(doc_type, centroid)| Some((doc_type.clone(), cosine(embedding, centroid)?))))
+        .collect();
+    // Against a single centroid, closeness says nothing about which doc type fits better
+    if similarities.len() > 1 {
+        let weight = if total > 0.0 { rules.embedding_weight } else { 1.0 };
+        let exp: Vec<(&str, f64)> = similarities.iter()
+            .map(|(doc_type, similarity)| (doc_type.as_str(), (similarity / EMBEDDING_TEMPERATURE).exp()))
+            .collect();
+        let sum: f64 = exp.iter().map(|(_, e)| e).sum();
+        for share in &mut shares {
+            share.1 *= 1.0 - weight;
+        }
+        for (doc_type, e) in exp {
+            match shares.iter_mut().find(|(d, _)| *d == doc_type) {
+                Some(share) => share.1 += weight * e / sum,
+                None => shares.push((doc_type, weight * e / sum)),
+            }
+        }
+    }
+
+    let top = shares.iter().map(|(_, share)| *share).fold(0.0, f64::max);
+    let leaders: Vec<&str> = shares.iter().filter(|(_, share)| *share == top).map(|(doc_type, _)| *doc_type).collect();
+    let (doc_type, confidence) = if top <= 0.0 {
+        (rules.default_doc_type.clone(), 1.0 / shares.len().max(1) as f64)
+    } else if leaders.len() > 1 {
+        let doc_type = leaders.iter().find(|d| **d == rules.default_doc_type).unwrap_or(&leaders[0]);
+        (doc_type.to_string(), top)
+    } else {
+        (leaders[0].to_string(), top)
+    };
 
-            if has_python {
-                signals.push("Python detected -> check for FastAPI/Flask routes".into());
-                devdocs_score += 0.5;
+    let scores = scores.into_iter().map(|(doc_type, score)| (doc_type.to_string(), score)).collect();
+    let entrypoints = facts.entrypoints;
+    match verdict {
+        Ok(Some(verdict)) => ClassificationResult {
+            doc_type: verdict.doc_type, confidence: 1.0, signals, scores, similarities, entrypoints, predicted: doc_type, overridden: true,
+        },
+        _ => ClassificationResult {
+            predicted: doc_type.clone(), doc_type, confidence, signals, scores, similarities, entrypoints, overridden: false,
+        },
+    }
+}
+
+// Operator-supplied centroids by doc type; set once at startup
+static CENTROIDS: OnceLock<HashMap<String, Vec<f32>>> = OnceLock::new();
+
+// Read centroids from a JSON object of doc type to embedding, for doc types no verdict has
+// labelled an embedded repo as yet. Returns how many there were
+pub fn load_centroids(path: &Path) -> Result<usize, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let centroids: HashMap<String, Vec<f32>> = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let count = centroids.len();
+    CENTROIDS.set(centroids).ok();
+    Ok(count)
+}
+
+// Each doc type's mean embedding over the repos users labelled with it, falling back to the
+// loaded centroids. Embeddings whose dimension differs from the first one seen are left out
+fn centroids(feedback: &[Feedback]) -> HashMap<String, Vec<f32>> {
+    let mut sums: HashMap<String, (Vec<f64>, usize)> = HashMap::new();
+    let dimension = feedback.iter().find_map(|verdict| verdict.embedding.as_ref()).map(Vec::len);
+    for verdict in feedback {
+        let Some(embedding) = verdict.embedding.as_ref().filter(|e| Some(e.len()) == dimension) else { continue };
+        let (sum, count) = sums.entry(verdict.doc_type.clone()).or_insert_with(|| (vec![0.0; embedding.len()], 0));
+        for (total, value) in sum.iter_mut().zip(embedding) {
+            *total += *value as f64;
+        }
+        *count += 1;
+    }
+    let mut centroids: HashMap<String, Vec<f32>> = CENTROIDS.get().cloned().unwrap_or_default();
+    for (doc_type, (sum, count)) in sums {
+        centroids.insert(doc_type, sum.iter().map(|total| (total / count as f64) as f32).collect());
+    }
+    centroids
+}
+
+// None when the dimensions differ or either vector is all zeros
+fn cosine(a: &[f32], b: &[f32]) -> Option<f64> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (*x as f64, *y as f64);
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    (norm_a > 0.0 && norm_b > 0.0).then(|| dot / (norm_a.sqrt() * norm_b.sqrt()))
+}
+
+// What to embed for the embedding stage: the file tree, the most called functions and the
+// declared types, routes and commands, and the README's outline
+pub async fn summary(store: &dyn GraphStore, repo_name: &str) -> String {
+    let (files_r, hotspots_r, symbols_r, docs_r) = tokio::join!(
+        store.get_all_files(repo_name),
+        store.get_hotspots(repo_name, SUMMARY_SYMBOLS),
+        store.get_all_symbols(repo_name),
+        store.get_docs(repo_name),
+    );
+    let text = |value: &serde_json::Value, key: &str| value.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let mut out = String::from("Files:\n");
+    let mut paths: Vec<String> = files_r.unwrap_or_default().iter().map(|f| text(f, "path")).collect();
+    paths.sort();
+    for path in paths.iter().take(SUMMARY_FILES) {
+        out.push_str(&format!("{}\n", path));
+    }
+    if paths.len() > SUMMARY_FILES {
+        out.push_str(&format!("... and {} more\n", paths.len() - SUMMARY_FILES));
+    }
+
+    out.push_str("\nTop symbols:\n");
+    let mut symbols: Vec<String> = hotspots_r.unwrap_or_default().iter()
+        .map(|s| format!("{} {}", text(s, "kind"), text(s, "name")))
+        .collect();
+    for kind in ["route", "command", "class", "interface", "component", "function"] {
+        symbols.extend(symbols_r.as_deref().unwrap_or_default().iter()
+            .filter(|s| text(s, "kind") == kind)
+            .map(|s| format!("{} {}", kind, text(s, "name"))));
+    }
+    let mut seen = HashSet::new();
+    symbols.retain(|s| seen.insert(s.clone()));
+    for symbol in symbols.iter().take(SUMMARY_SYMBOLS) {
+        out.push_str(&format!("{}\n", symbol));
+    }
+
+    // The README nearest the root
+    let readme = docs_r.unwrap_or_default().into_iter()
+        .filter(|d| text(d, "kind") == "readme")
+        .min_by_key(|d| (text(d, "path").matches('/').count(), text(d, "path")));
+    if let Some(readme) = readme {
+        let title = text(&readme, "title");
+        let mut outline = format!("{}\n", title);
+        let sections = readme.get("sections").and_then(|s| s.as_array()).into_iter().flatten();
+        for section in sections.filter(|s| text(s, "name") != title) {
+            outline.push_str(&format!("{}\n", text(section, "name")));
+            let summary = text(section, "summary");
+            if !summary.is_empty() {
+                outline.push_str(&format!("{}\n", summary));
             }
-            if has_js {
-                signals.push("JS/TS detected -> check for React components".into());
-                consumer_score += 0.5;
+        }
+        out.push_str("\nREADME:\n");
+        out.extend(outline.chars().take(SUMMARY_README_CHARS));
+        out.push('\n');
+    }
+    out
+}
+
+// A user's verdict on `result`: left out, `doc_type` confirms what it answered. `disputed` names
+// rules whose signals the user says are wrong
+pub fn feedback(
+    result: &ClassificationResult,
+    doc_type: Option<String>,
+    disputed: Vec<String>,
+    embedding: Option<Vec<f32>>,
+) -> Result<Feedback, String> {
+    let doc_type = doc_type.unwrap_or_else(|| result.doc_type.clone());
+    let known = |doc_type: &String| result.scores.contains_key(doc_type) || result.similarities.contains_key(doc_type);
+    if !known(&doc_type) && doc_type != rules().default_doc_type {
+        let known: Vec<&str> = result.scores.keys().chain(result.similarities.keys()).map(String::as_str).collect();
+        return Err(format!("unknown doc_type {}; the rules know {}", doc_type, known.join(", ")));
+    }
+    if let Some(rule) = disputed.iter().find(|rule| !result.signals.iter().any(|s| &s.rule == *rule)) {
+        return Err(format!("no signal from rule {} to dispute", rule));
+    }
+    let given_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    Ok(Feedback { doc_type, predicted: result.predicted.clone(), signals: result.signals.clone(), disputed, embedding, given_at })
+}
+
+// Multiplier for each rule's weight. A rule gains when it voted for the doc type users settled
+// on, and loses when it was disputed or voted for the one they corrected; votes for other doc
+// types are neither. CALIBRATION_PRIOR neutral votes keep a few verdicts from swinging it far
+fn calibrate(feedback: &[Feedback]) -> HashMap<String, f64> {
+    let mut votes: HashMap<&str, (f64, f64)> = HashMap::new();
+    for verdict in feedback {
+        for signal in &verdict.signals {
+            let (right, wrong) = votes.entry(signal.rule.as_str()).or_default();
+            if verdict.disputed.contains(&signal.rule) {
+                *wrong += 1.0;
+            } else if signal.contributes_to == verdict.doc_type {
+                *right += 1.0;
+            } else if signal.contributes_to == verdict.predicted {
+                *wrong += 1.0;
             }
-            if has_cpp {
-                signals.push("C++ detected -> likely library/system docs".into());
-                devdocs_score += 1.0;
+        }
+    }
+    votes.into_iter()
+        .map(|(rule, (right, wrong))| {
+            let factor = 2.0 * (right + CALIBRATION_PRIOR) / (right + wrong + 2.0 * CALIBRATION_PRIOR);
+            (rule.to_string(), factor.clamp(MIN_CALIBRATION, MAX_CALIBRATION))
+        })
+        .collect()
+}
+
+// Everything the rules can look at, from seven queries run concurrently
+async fn gather(store: &dyn GraphStore, repo_name: &str) -> Facts {
+    let mut facts = Facts::default();
+    let (counts_r, langs_r, files_r, symbols_r, deps_r, license_r, entrypoints_r) = tokio::join!(
+        store.count_by_kind(repo_name),
+        store.get_file_languages(repo_name),
+        store.get_all_files(repo_name),
+        store.get_all_symbols(repo_name),
+        store.get_declared_dependencies(repo_name),
+        store.get_license(repo_name),
+        store.get_entrypoints(repo_name),
+    );
+
+    if let Ok(counts) = counts_r {
+        if let Some(obj) = counts.as_object() {
+            for (kind, count) in obj {
+                facts.counts.insert(kind.clone(), count.as_f64().unwrap_or(0.0));
             }
         }
     }
 
+    if let Ok(langs) = langs_r {
+        if let Some(obj) = langs.as_object() {
+            facts.values.insert("language", obj.keys().cloned().collect());
+        }
+    }
+
     if let Ok(files) = files_r {
-        let paths: Vec<String> = files.iter()
+        // Test files say little about what kind of project this is
+        let paths = files.iter()
+            .filter(|f| !f.get("test_file").and_then(|t| t.as_bool()).unwrap_or(false))
             .filter_map(|f| f.get("path").and_then(|p| p.as_str()).map(|s| s.to_lowercase()))
             .collect();
+        facts.values.insert("path", paths);
+    }
 
-        let has_routes = paths.iter().any(|p| p.contains("route") || p.contains("endpoint") || p.contains("api"));
-        let has_components = paths.iter().any(|p| p.contains("component") || p.contains("pages") || p.contains("views"));
-        let has_cli = paths.iter().any(|p| p.contains("cli") || p.contains("command"));
-        let has_sdk = paths.iter().any(|p| p.contains("client") || p.contains("sdk"));
+    if let Ok(symbols) = symbols_r {
+        let callables: Vec<&serde_json::Value> = symbols.iter()
+            .filter(|s| matches!(s.get("kind").and_then(|k| k.as_str()), Some("function" | "method")))
+            .collect();
+        let async_count = callables.iter().filter(|s| s.get("is_async").and_then(|a| a.as_bool()).unwrap_or(false)).count();
+        facts.counts.insert("callables".to_string(), callables.len() as f64);
+        let percent = if callables.len() >= MIN_CALLABLES_FOR_ASYNC { async_count * 100 / callables.len() } else { 0 };
+        facts.counts.insert("async_percent".to_string(), percent as f64);
+    }
 
-        if has_routes { signals.push("route/api files found".into()); devdocs_score += 2.0; }
-        if has_components { signals.push("component/page files found".into()); consumer_score += 2.0; }
-        if has_cli { signals.push("CLI files found".into()); devdocs_score += 1.5; }
-        if has_sdk { signals.push("SDK/client files found".into()); devdocs_score += 1.5; }
+    if let Ok(deps) = deps_r {
+        // Dev tooling says nothing about what the project is
+        let runtime = deps.iter().filter(|d| d.kind != "dev").map(|d| d.name.clone()).collect();
+        facts.values.insert("dependency", runtime);
     }
 
-    if let Ok(symbols) = symbols_r {
-        let has_decorators = symbols.iter().any(|s| {
-            s.get("signature").and_then(|v| v.as_str())
-                .map(|sig| sig.contains("@app.") || sig.contains("@router.") || sig.contains("app.get") || sig.contains("app.post"))
-                .unwrap_or(false)
-        });
-        if has_decorators { signals.push("route decorators found -> API".into()); devdocs_score += 2.0; }
-    }
-
-    let total = consumer_score + devdocs_score;
-    let (doc_type, confidence) = if total == 0.0 {
-        ("devdocs".to_string(), 0.5)
-    } else if devdocs_score > consumer_score {
-        ("devdocs".to_string(), devdocs_score / total)
-    } else {
-        ("consumer".to_string(), consumer_score / total)
-    };
+    if let Ok(Some(license)) = license_r {
+        facts.values.insert("license", vec![license.category]);
+        facts.spdx = license.spdx;
+    }
+
+    if let Ok(entrypoints) = entrypoints_r {
+        facts.values.insert("entrypoint", entrypoints.iter().map(|e| e.kind.clone()).collect());
+        facts.entrypoints = entrypoints;
+    }
 
-    ClassificationResult { doc_type, confidence, signals }
+    facts
 }
@@ -0,0 +1,90 @@
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+use crate::store::GraphStore;
+
+// Symbol fields compared between the two sides; anything else (lines, clusters) is noise
+const COMPARED_FIELDS: &[&str] = &["signature", "return_type", "params", "visibility", "decorators", "docstring"];
+
+// Symbols added, removed and changed going from `base` to `head`, each a repo or snapshot key.
+// Symbols are matched by file, kind, owning class and qualified name, so a symbol that moved to
+// another file shows up as removed and added
+pub async fn diff(store: &dyn GraphStore, base: &str, head: &str) -> neo4rs::Result<Value> {
+    let before = keyed(store.get_all_symbols(base).await?);
+    let mut after = keyed(store.get_all_symbols(head).await?);
+
+    let mut removed = vec![];
+    let mut changed = vec![];
+    for (key, old) in before {
+        let Some(new) = after.remove(&key) else {
+            removed.push(summary(&old));
+            continue;
+        };
+        let mut changes = Map::new();
+        for field in COMPARED_FIELDS {
+            if old[field] != new[field] {
+                changes.insert(field.to_string(), json!({ "before": readable(field, &old[field]), "after": readable(field, &new[field]) }));
+            }
+        }
+        if !changes.is_empty() {
+            let mut entry = summary(&new);
+            entry["changes"] = Value::Object(changes);
+            changed.push(entry);
+        }
+    }
+    let added: Vec<Value> = after.values().map(summary).collect();
+
+    Ok(json!({
+        "base": base,
+        "head": head,
+        "summary": { "added": added.len(), "removed": removed.len(), "changed": changed.len() },
+        "added": added,
+        "removed": removed,
+        "changed": changed,
+    }))
+}
+
+// Symbols by identity. Same-named overloads in one scope are told apart by their order in the file
+fn keyed(mut symbols: Vec<Value>) -> BTreeMap<(String, String, String, String, usize), Value> {
+    symbols.sort_by_key(|s| (s["file"].as_str().unwrap_or_default().to_string(), s["line_start"].as_i64().unwrap_or(0)));
+    let mut out = BTreeMap::new();
+    for symbol in symbols {
+        let name = match symbol["qualified_name"].as_str().filter(|q| !q.is_empty()) {
+            Some(qualified) => qualified.to_string(),
+            None => symbol["name"].as_str().unwrap_or_default().to_string(),
+        };
+        let mut key = (
+            symbol["file"].as_str().unwrap_or_default().to_string(),
+            symbol["kind"].as_str().unwrap_or_default().to_string(),
+            symbol["parent_class"].as_str().unwrap_or_default().to_string(),
+            name,
+            0,
+        );
+        while out.contains_key(&key) {
+            key.4 += 1;
+        }
+        out.insert(key, symbol);
+    }
+    out
+}
+
+// Params are stored as JSON text; report them as the list they encode
+fn readable(field: &str, value: &Value) -> Value {
+    match (field, value.as_str()) {
+        ("params", Some(text)) => serde_json::from_str(text).unwrap_or_else(|_| value.clone()),
+        _ => value.clone(),
+    }
+}
+
+fn summary(symbol: &Value) -> Value {
+    json!({
+        "name": symbol["name"],
+        "kind": symbol["kind"],
+        "file": symbol["file"],
+        "line": symbol["line_start"],
+        "parent_class": Some(&symbol["parent_class"]).filter(|p| p.as_str().is_some_and(|p| !p.is_empty())),
+        "qualified_name": Some(&symbol["qualified_name"]).filter(|q| q.as_str().is_some_and(|q| !q.is_empty())),
+        "signature": symbol["signature"],
+        "visibility": symbol["visibility"],
+    })
+}
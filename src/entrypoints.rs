@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// A way the repo's code is started
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Entrypoint {
+    // File it was found in, relative to the repo root
+    pub file: String,
+    // 1-based; 0 when the file as a whole is the entrypoint
+    pub line: usize,
+    // "main_function", "python_main", "node_main", "bin_target", "npm_main", "npm_bin", "cargo_bin",
+    // "python_script", "docker_entrypoint" or "docker_cmd"
+    pub kind: String,
+    // What is run: the function, module, command or package
+    pub name: String,
+    // What it runs when that is somewhere else: the script of an npm bin, a Python script's
+    // module:function, a container's command line
+    pub target: Option<String>,
+}
+
+// Files read only for the entrypoints they declare: package.json, Cargo.toml and pyproject.toml
+// manifests, Dockerfiles, and anything in a bin/ directory
+pub fn is_entrypoint_file(rel_path: &str) -> bool {
+    let name = file_name(rel_path);
+    matches!(name, "package.json" | "Cargo.toml" | "pyproject.toml") || is_dockerfile(name) || in_bin_dir(rel_path)
+}
+
+// Entrypoints `content`, the file at `rel_path`, declares or defines
+pub fn detect(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let name = file_name(rel_path);
+    let mut found = match name {
+        "package.json" => npm(rel_path, content),
+        "Cargo.toml" => cargo(rel_path, content),
+        "pyproject.toml" => pyproject(rel_path, content),
+        _ if is_dockerfile(name) => docker(rel_path, content),
+        _ => source(rel_path, content),
+    };
+    if in_bin_dir(rel_path) {
+        let stem = name.split('.').next().filter(|s| !s.is_empty()).unwrap_or(name);
+        found.push(entrypoint(rel_path, 0, "bin_target", stem, None));
+    }
+    found
+}
+
+fn entrypoint(rel_path: &str, line: usize, kind: &str, name: &str, target: Option<String>) -> Entrypoint {
+    Entrypoint { file: rel_path.to_string(), line, kind: kind.to_string(), name: name.to_string(), target }
+}
+
+fn file_name(rel_path: &str) -> &str {
+    rel_path.rsplit('/').next().unwrap_or(rel_path)
+}
+
+fn is_dockerfile(name: &str) -> bool {
+    name == "Dockerfile" || name == "Containerfile" || name.starts_with("Dockerfile.") || name.ends_with(".dockerfile")
+}
+
+fn in_bin_dir(rel_path: &str) -> bool {
+    rel_path.rsplit('/').nth(1) == Some("bin")
+}
+
+// `path` as written in the manifest at `rel_path`, made relative to the repo root
+fn beside(rel_path: &str, path: &str) -> String {
+    let path = path.trim_start_matches("./");
+    match rel_path.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/{}", dir, path),
+        None => path.to_string(),
+    }
+}
+
+// "main" and "bin" of a package.json; a bin given as a plain path is named after the package
+fn npm(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let Ok(manifest) = serde_json::from_str::<Value>(content) else { return vec![] };
+    let package = manifest.get("name").and_then(Value::as_str).unwrap_or_default();
+    let mut found = vec![];
+    if let Some(main) = manifest.get("main").and_then(Value::as_str) {
+        found.push(entrypoint(rel_path, 0, "npm_main", package, Some(beside(rel_path, main))));
+    }
+    match manifest.get("bin") {
+        Some(Value::String(script)) => {
+            let command = package.rsplit('/').next().unwrap_or(package);
+            found.push(entrypoint(rel_path, 0, "npm_bin", command, Some(beside(rel_path, script))));
+        }
+        Some(Value::Object(bins)) => {
+            for (command, script) in bins {
+                let script = script.as_str().map(|s| beside(rel_path, s));
+                found.push(entrypoint(rel_path, 0, "npm_bin", command, script));
+            }
+        }
+        _ => {}
+    }
+    found
+}
+
+// Cargo's [[bin]] targets; a src/main.rs is found by its main function
+fn cargo(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let Ok(manifest) = content.parse::<toml::Table>() else { return vec![] };
+    let bins = manifest.get("bin").and_then(|b| b.as_array()).cloned().unwrap_or_default();
+    bins.iter()
+        .filter_map(|bin| {
+            let name = bin.get("name")?.as_str()?;
+            let path = bin.get("path").and_then(|p| p.as_str()).map(|p| beside(rel_path, p));
+            Some(entrypoint(rel_path, 0, "cargo_bin", name, path))
+        })
+        .collect()
+}
+
+// Console scripts from [project.scripts] or Poetry's [tool.poetry.scripts]
+fn pyproject(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let Ok(manifest) = content.parse::<toml::Table>() else { return vec![] };
+    let table = |path: &[&str]| path.iter()
+        .try_fold(&manifest, |table, key| table.get(*key)?.as_table())
+        .cloned()
+        .unwrap_or_default();
+    let mut scripts = table(&["project", "scripts"]);
+    scripts.extend(table(&["tool", "poetry", "scripts"]));
+    scripts.iter()
+        .filter_map(|(name, target)| Some(entrypoint(rel_path, 0, "python_script", name, Some(target.as_str()?.to_string()))))
+        .collect()
+}
+
+// The last ENTRYPOINT and CMD, the ones a multi-stage build's final image runs
+fn docker(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let mut last: [Option<(usize, String)>; 2] = [None, None];
+    let mut lines = content.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let mut instruction = line.trim().to_string();
+        // Continuation lines end in a backslash
+        while instruction.ends_with('\\') {
+            instruction.pop();
+            match lines.next() {
+                Some((_, next)) => instruction.push_str(next.trim()),
+                None => break,
+            }
+        }
+        let Some((keyword, args)) = instruction.split_once(char::is_whitespace) else { continue };
+        let slot = match keyword.to_ascii_uppercase().as_str() {
+            "ENTRYPOINT" => 0,
+            "CMD" => 1,
+            _ => continue,
+        };
+        let args = args.trim();
+        // The exec form is a JSON array
+        let command = serde_json::from_str::<Vec<String>>(args).map(|parts| parts.join(" ")).unwrap_or_else(|_| args.to_string());
+        last[slot] = Some((i + 1, command));
+    }
+    ["docker_entrypoint", "docker_cmd"].iter().zip(last)
+        .filter_map(|(kind, found)| {
+            let (line, command) = found?;
+            let program = command.split_whitespace().next().unwrap_or_default();
+            Some(entrypoint(rel_path, line, kind, program, Some(command.clone())))
+        })
+        .collect()
+}
+
+// main functions, Python's `if __name__ == "__main__":` and Node's `require.main === module`
+fn source(rel_path: &str, content: &str) -> Vec<Entrypoint> {
+    let extension = rel_path.rsplit_once('.').map_or("", |(_, ext)| ext);
+    let module = || rel_path.trim_end_matches(".py").trim_end_matches("/__main__").replace('/', ".");
+    if file_name(rel_path) == "__main__.py" {
+        return vec![entrypoint(rel_path, 0, "python_main", &module(), None)];
+    }
+    // Top-level definitions start in the first column; Java's main sits inside a class
+    let is_main: fn(&str) -> bool = match extension {
+        "py" => |line| line.starts_with("if __name__") && line.contains("__main__"),
+        "js" | "mjs" | "cjs" | "ts" => |line| line.contains("require.main === module"),
+        "rs" => |line| ["fn main(", "async fn main(", "pub fn main(", "pub async fn main("].iter().any(|p| line.starts_with(p)),
+        "go" => |line| line.starts_with("func main("),
+        "cpp" | "cxx" => |line| ["int main(", "int main (", "auto main("].iter().any(|p| line.starts_with(p)),
+        "java" => |line| line.trim_start().starts_with("public static void main("),
+        _ => return vec![],
+    };
+    // Only package main builds a Go program
+    if extension == "go" && !content.lines().any(|line| line.trim() == "package main") {
+        return vec![];
+    }
+    let Some(line) = content.lines().position(is_main) else { return vec![] };
+    match extension {
+        "py" => vec![entrypoint(rel_path, line + 1, "python_main", &module(), None)],
+        "js" | "mjs" | "cjs" | "ts" => vec![entrypoint(rel_path, line + 1, "node_main", file_name(rel_path), None)],
+        _ => vec![entrypoint(rel_path, line + 1, "main_function", "main", None)],
+    }
+}
@@ -0,0 +1,202 @@
+use futures::Stream;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::graph::{EXPORT_LABELS, EXPORT_SKIPPED};
+use crate::store::GraphStore;
+
+// Nodes or relationships fetched per round trip while streaming an export
+const EXPORT_PAGE: i64 = 1000;
+
+// The repo's whole graph as GraphML, written while it is read so large repos never sit in memory.
+// Every attribute is declared as a string; lists and maps are written as JSON
+pub fn graphml(store: Arc<dyn GraphStore>, repo: String) -> impl Stream<Item = Result<String, Infallible>> {
+    let (tx, rx) = mpsc::channel::<String>(16);
+    tokio::spawn(async move {
+        if let Err(e) = write_graphml(store.as_ref(), &repo, &tx).await {
+            warn!("GraphML export of {} failed: {}", repo, e);
+            // Headers are already sent, so the failure can only be reported in the body
+            let _ = tx.send(format!("<!-- export failed: {} -->\n", escape_xml(&e.to_string()))).await;
+        }
+    });
+    futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (Ok(chunk), rx)) })
+}
+
+async fn write_graphml(store: &dyn GraphStore, repo: &str, tx: &mpsc::Sender<String>) -> neo4rs::Result<()> {
+    let (node_keys, edge_keys) = store.export_keys(repo).await?;
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    for key in &node_keys {
+        out.push_str(&format!("  <key id=\"n_{0}\" for=\"node\" attr.name=\"{0}\" attr.type=\"string\"/>\n", escape_xml(key)));
+    }
+    out.push_str("  <key id=\"type\" for=\"edge\" attr.name=\"type\" attr.type=\"string\"/>\n");
+    for key in &edge_keys {
+        out.push_str(&format!("  <key id=\"e_{0}\" for=\"edge\" attr.name=\"{0}\" attr.type=\"string\"/>\n", escape_xml(key)));
+    }
+    out.push_str(&format!("  <graph id=\"{}\" edgedefault=\"directed\">\n", escape_xml(repo)));
+    if tx.send(out).await.is_err() { return Ok(()) }
+
+    for label in EXPORT_LABELS {
+        let mut skip = 0;
+        loop {
+            let nodes = store.export_nodes(repo, label, skip, EXPORT_PAGE).await?;
+            let mut out = String::new();
+            for node in &nodes {
+                out.push_str(&format!("    <node id=\"{}\">\n      <data key=\"label\">{}</data>\n", escape_xml(&node.id), node.label));
+                push_data(&mut out, "n_", &node.props);
+                out.push_str("    </node>\n");
+            }
+            // A closed channel means the client went away; stop reading
+            if tx.send(out).await.is_err() { return Ok(()) }
+            if (nodes.len() as i64) < EXPORT_PAGE { break }
+            skip += EXPORT_PAGE;
+        }
+    }
+    for label in EXPORT_LABELS {
+        let mut skip = 0;
+        loop {
+            let edges = store.export_edges(repo, label, skip, EXPORT_PAGE).await?;
+            let mut out = String::new();
+            for edge in &edges {
+                out.push_str(&format!(
+                    "    <edge id=\"{}\" source=\"{}\" target=\"{}\">\n      <data key=\"type\">{}</data>\n",
+                    escape_xml(&edge.id), escape_xml(&edge.source), escape_xml(&edge.target), edge.kind
+                ));
+                push_data(&mut out, "e_", &edge.props);
+                out.push_str("    </edge>\n");
+            }
+            if tx.send(out).await.is_err() { return Ok(()) }
+            if (edges.len() as i64) < EXPORT_PAGE { break }
+            skip += EXPORT_PAGE;
+        }
+    }
+    let _ = tx.send("  </graph>\n</graphml>\n".to_string()).await;
+    Ok(())
+}
+
+// The call graph (functions grouped by file) or the file import graph as Graphviz DOT. With a
+// root (a function id, qualified name or name; or a file path) only what it reaches within
+// `depth` hops is kept. Circular imports are drawn in red
+pub async fn dot(store: &dyn GraphStore, repo: &str, scope: &str, root: Option<&str>, depth: usize) -> neo4rs::Result<String> {
+    // id -> (label, file group)
+    let mut nodes: HashMap<String, (String, String)> = HashMap::new();
+    let mut edges: Vec<(String, String, bool)> = vec![];
+    let mut roots: Vec<String> = vec![];
+    if scope == "imports" {
+        let deps = store.get_dependencies(repo).await?;
+        for edge in deps["edges"].as_array().into_iter().flatten() {
+            let from = edge["from"].as_str().unwrap_or_default().to_string();
+            let to = edge["to"].as_str().unwrap_or_default().to_string();
+            for file in [&from, &to] {
+                nodes.entry(file.clone()).or_insert_with(|| (file.clone(), String::new()));
+            }
+            edges.push((from, to, edge["circular"].as_bool().unwrap_or(false)));
+        }
+        roots.extend(root.filter(|r| nodes.contains_key(*r)).map(str::to_string));
+    } else {
+        for (a, b) in store.get_call_edges(repo).await? {
+            for end in [&a, &b] {
+                if root.is_some_and(|r| r == end.id || r == end.qualified_name || r == end.name) && !roots.contains(&end.id) {
+                    roots.push(end.id.clone());
+                }
+                nodes.entry(end.id.clone()).or_insert_with(|| (end.name.clone(), end.file.clone()));
+            }
+            edges.push((a.id, b.id, false));
+        }
+    }
+
+    if root.is_some() {
+        let reached = reachable(&roots, &edges, depth);
+        edges.retain(|(a, b, _)| reached.get(a).is_some_and(|d| *d < depth) && reached.contains_key(b));
+        nodes.retain(|id, _| reached.contains_key(id));
+    }
+
+    let mut out = format!("digraph \"{}\" {{\n  rankdir=LR;\n  node [shape=box, fontname=\"Helvetica\"];\n", escape_dot(repo));
+    let mut groups: HashMap<&str, Vec<&String>> = HashMap::new();
+    for (id, (_, group)) in &nodes {
+        groups.entry(group.as_str()).or_default().push(id);
+    }
+    let mut group_names: Vec<&&str> = groups.keys().collect();
+    group_names.sort();
+    for (i, group) in group_names.into_iter().enumerate() {
+        let mut ids = groups[*group].clone();
+        ids.sort();
+        let indent = if group.is_empty() { "  " } else { "    " };
+        if !group.is_empty() {
+            out.push_str(&format!("  subgraph cluster_{} {{\n    label=\"{}\";\n", i, escape_dot(group)));
+        }
+        for id in ids {
+            let style = if roots.contains(id) { ", style=bold" } else { "" };
+            out.push_str(&format!("{}\"{}\" [label=\"{}\"{}];\n", indent, escape_dot(id), escape_dot(&nodes[id].0), style));
+        }
+        if !group.is_empty() {
+            out.push_str("  }\n");
+        }
+    }
+    edges.sort();
+    for (a, b, circular) in &edges {
+        let style = if *circular { " [color=red]" } else { "" };
+        out.push_str(&format!("  \"{}\" -> \"{}\"{};\n", escape_dot(a), escape_dot(b), style));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+// Hops from the nearest root to every node reachable within `depth`
+fn reachable(roots: &[String], edges: &[(String, String, bool)], depth: usize) -> HashMap<String, usize> {
+    let mut next: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (a, b, _) in edges {
+        next.entry(a.as_str()).or_default().push(b.as_str());
+    }
+    let mut reached: HashMap<String, usize> = roots.iter().map(|r| (r.clone(), 0)).collect();
+    let mut queue: VecDeque<(String, usize)> = roots.iter().map(|r| (r.clone(), 0)).collect();
+    while let Some((id, d)) = queue.pop_front() {
+        if d == depth { continue }
+        for &child in next.get(id.as_str()).into_iter().flatten() {
+            if !reached.contains_key(child) {
+                reached.insert(child.to_string(), d + 1);
+                queue.push_back((child.to_string(), d + 1));
+            }
+        }
+    }
+    reached
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn push_data(out: &mut String, prefix: &str, props: &Value) {
+    let Some(map) = props.as_object() else { return };
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    for key in keys.into_iter().filter(|k| !EXPORT_SKIPPED.contains(&k.as_str())) {
+        let text = match &map[key] {
+            Value::Null => continue,
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        out.push_str(&format!("      <data key=\"{}{}\">{}</data>\n", prefix, escape_xml(key), escape_xml(&text)));
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab and newlines are not allowed in XML 1.0
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Files that differ between an indexed commit and HEAD, relative to the indexed directory
+#[derive(Debug, Default)]
+pub struct ChangedFiles {
+    // Added, modified or retyped; renames show up as a delete plus an add
+    pub changed: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+// Authors kept per file, those with the most commits to it first
+const TOP_CONTRIBUTORS: usize = 3;
+
+// A symbol's owners wrote at least this share of its committed lines; at most MAX_OWNERS are kept
+const OWNER_SHARE: f64 = 0.25;
+const MAX_OWNERS: usize = 3;
+
+// How a file has changed over the history leading to HEAD
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileHistory {
+    // Committer date of the newest commit touching the file, in Unix seconds
+    pub last_modified: i64,
+    pub last_commit: String,
+    pub commit_count: usize,
+    // (author, commits to the file), most commits first
+    pub contributors: Vec<(String, usize)>,
+}
+
+// A submodule under the indexed directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Submodule {
+    // Relative to the indexed directory
+    pub path: String,
+    // From .gitmodules; None when it has no entry there
+    pub url: Option<String>,
+    // Commit the parent pins it at
+    pub commit: String,
+    // Repo it was indexed as, when indexed on its own rather than as part of the parent
+    pub repo: Option<String>,
+}
+
+// Commit checked out in `dir`, if it is inside a git work tree
+pub fn head_commit(dir: &str) -> Option<String> {
+    let out = git(dir, &["rev-parse", "HEAD"])?;
+    let commit = out.trim();
+    (!commit.is_empty()).then(|| commit.to_string())
+}
+
+// `git diff --name-status` from `base` to HEAD, limited to `dir`. None when git can't answer,
+// e.g. `base` is no longer in the history after a force push
+pub fn changed_files(dir: &str, base: &str) -> Option<ChangedFiles> {
+    let out = git(dir, &["diff", "--name-status", "--no-renames", "--relative", "-z", base, "HEAD"])?;
+    let mut files = ChangedFiles::default();
+    let mut fields = out.split('\0');
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        match status {
+            "D" => files.deleted.push(path.to_string()),
+            "" => break,
+            _ => files.changed.push(path.to_string()),
+        }
+    }
+    Some(files)
+}
+
+// History of every file under `dir` that some commit reachable from HEAD touched, keyed by path
+// relative to `dir`, from a single `git log` pass. Renames count as a new file, as in
+// `changed_files`
+pub fn file_history(dir: &str) -> Option<HashMap<String, FileHistory>> {
+    // A NUL can't appear in a path or a name, so it marks where each commit begins
+    // quotePath off leaves non-ASCII paths as they are, rather than octal-escaped in quotes
+    let out = git(dir, &["-c", "core.quotePath=false", "log", "--format=%x00%H %ct %aN", "--name-only", "--no-renames", "--relative", "HEAD"])?;
+    let mut files: HashMap<String, (FileHistory, HashMap<&str, usize>)> = HashMap::new();
+    for commit in out.split('\0').skip(1) {
+        let mut lines = commit.lines();
+        let mut header = lines.next().unwrap_or_default().splitn(3, ' ');
+        let (Some(hash), Some(time), Some(author)) = (header.next(), header.next(), header.next()) else { continue };
+        let time = time.parse().unwrap_or(0);
+        for path in lines.filter(|line| !line.is_empty()) {
+            // The log runs newest first, so the first commit seen is the last to touch the file
+            let (history, authors) = files.entry(path.to_string()).or_insert_with(|| (
+                FileHistory { last_modified: time, last_commit: hash.to_string(), ..Default::default() },
+                HashMap::new(),
+            ));
+            history.commit_count += 1;
+            *authors.entry(author).or_insert(0) += 1;
+        }
+    }
+    Some(files.into_iter()
+        .map(|(path, (mut history, authors))| {
+            let mut contributors: Vec<(String, usize)> = authors.into_iter().map(|(a, n)| (a.to_string(), n)).collect();
+            contributors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            contributors.truncate(TOP_CONTRIBUTORS);
+            history.contributors = contributors;
+            (path, history)
+        })
+        .collect())
+}
+
+// Check out the submodules under `dir`, and theirs, at the commits the parent pins. Fetches any
+// that were never cloned, so it can take a while
+pub fn init_submodules(dir: &str) -> bool {
+    git(dir, &["submodule", "update", "--init", "--recursive", "--", "."]).is_some()
+}
+
+// Submodules the index has under `dir` (not those nested in them), with their URLs from the
+// top level's .gitmodules
+pub fn submodules(dir: &str) -> Vec<Submodule> {
+    let Some(out) = git(dir, &["ls-files", "--stage", "-z"]) else { return vec![] };
+    let prefix = git(dir, &["rev-parse", "--show-prefix"]).unwrap_or_default().trim().to_string();
+    let toplevel = git(dir, &["rev-parse", "--show-toplevel"]).unwrap_or_default().trim().to_string();
+    // "submodule.<name>.path <path>" and "submodule.<name>.url <url>" lines
+    let config = git(&toplevel, &["config", "--file", ".gitmodules", "--get-regexp", r"^submodule\..*\.(path|url)$"]).unwrap_or_default();
+    let url = |path: &str| -> Option<String> {
+        let name = config.lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(key, value)| key.ends_with(".path") && *value == path)?
+            .0.strip_suffix(".path")?;
+        config.lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(key, _)| key.strip_suffix(".url") == Some(name))
+            .map(|(_, url)| url.to_string())
+    };
+    // Gitlinks are entries of mode 160000: "<mode> <commit> <stage>\t<path>"
+    out.split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let mut fields = info.split(' ');
+            (fields.next()? == "160000").then_some(())?;
+            Some(Submodule {
+                path: path.to_string(),
+                url: url(&format!("{}{}", prefix, path)),
+                commit: fields.next()?.to_string(),
+                repo: None,
+            })
+        })
+        .collect()
+}
+
+// Author of each line of `rel_path` as it is in the work tree, from `git blame`; None for lines
+// not committed yet. Whitespace-only changes keep the line's earlier author. Run from the file's
+// own directory, so files in submodules are blamed in theirs
+pub fn blame(dir: &str, rel_path: &str) -> Option<Vec<Option<String>>> {
+    let path = Path::new(dir).join(rel_path);
+    let (parent, name) = (path.parent()?.to_str()?, path.file_name()?.to_str()?);
+    let out = git(parent, &["blame", "--line-porcelain", "-w", "--", name])?;
+    let mut authors = vec![];
+    let (mut header, mut committed, mut author) = (true, false, None);
+    for line in out.lines() {
+        if header {
+            // "<commit> <original line> <final line> [<lines in group>]"; uncommitted lines are
+            // blamed on the all-zero commit
+            committed = line.split(' ').next().is_some_and(|commit| commit.bytes().any(|b| b != b'0'));
+            header = false;
+        } else if let Some(name) = line.strip_prefix("author ") {
+            author = Some(name.to_string());
+        } else if line.starts_with('\t') {
+            authors.push(author.take().filter(|_| committed));
+            header = true;
+        }
+    }
+    Some(authors)
+}
+
+// Authors who wrote at least OWNER_SHARE of the committed lines in `range` (1-based, inclusive)
+// of a file `blame` returned, most lines first
+pub fn owners(authors: &[Option<String>], range: (usize, usize)) -> Vec<String> {
+    let lines = authors.get(range.0.saturating_sub(1)..range.1.min(authors.len())).unwrap_or_default();
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for author in lines.iter().flatten() {
+        *counts.entry(author).or_insert(0) += 1;
+    }
+    let committed: usize = counts.values().sum();
+    let mut owners: Vec<(&str, usize)> = counts.into_iter()
+        .filter(|(_, n)| *n as f64 >= committed as f64 * OWNER_SHARE)
+        .collect();
+    owners.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    owners.into_iter().take(MAX_OWNERS).map(|(author, _)| author.to_string()).collect()
+}
+
+// Commit a branch, tag or SHA names in the repository at `dir`
+pub fn resolve_ref(dir: &str, reference: &str) -> Option<String> {
+    // A leading dash would be taken for an option
+    if reference.starts_with('-') {
+        return None;
+    }
+    let out = git(dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", reference)])?;
+    let commit = out.trim();
+    (!commit.is_empty()).then(|| commit.to_string())
+}
+
+// A commit checked out into a temporary worktree, so indexing another ref leaves the caller's
+// checkout alone. The worktree is removed on drop
+pub struct Checkout {
+    repo: String,
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl Checkout {
+    // The directory that was asked for, at the checked out commit
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for Checkout {
+    fn drop(&mut self) {
+        let root = self.root.to_string_lossy();
+        if git(&self.repo, &["worktree", "remove", "--force", &root]).is_none() {
+            let _ = std::fs::remove_dir_all(&self.root);
+            let _ = git(&self.repo, &["worktree", "prune"]);
+        }
+    }
+}
+
+// Check `commit` out into a fresh worktree. `dir` may be a subdirectory of the work tree; the
+// checkout's `dir()` is the same subdirectory
+pub fn checkout(dir: &str, commit: &str) -> Option<Checkout> {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let repo = git(dir, &["rev-parse", "--show-toplevel"])?.trim().to_string();
+    let prefix = git(dir, &["rev-parse", "--show-prefix"])?.trim().to_string();
+    let root = std::env::temp_dir().join(format!(
+        "better-docs-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed),
+    ));
+    git(&repo, &["worktree", "add", "--detach", &root.to_string_lossy(), commit])?;
+    let dir = root.join(prefix);
+    Some(Checkout { repo, root, dir })
+}
+
+fn git(dir: &str, args: &[&str]) -> Option<String> {
+    let out = Command::new("git").arg("-C").arg(dir).args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8(out.stdout).ok()
+}
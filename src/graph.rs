@@ -1,32 +1,369 @@
 use neo4rs::*;
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use serde_json::{json, Value};
-use crate::parsing::ParsingResult;
+use async_trait::async_trait;
+use crate::git::{FileHistory, Submodule};
+use crate::classifier::Feedback;
+use crate::entrypoints::Entrypoint;
+use crate::licenses::License;
+use crate::manifests::Dependency;
+use crate::parsing::{py_type_names, Implementation, Language, ParsingResult, Symbol};
+use crate::store::{unsupported, GraphStore};
+
+#[derive(serde::Deserialize)]
+pub struct SymbolEmbedding {
+    pub file: String,
+    pub name: String,
+    pub line: usize,
+    pub vector: Vec<f32>,
+}
+
+// A node or relationship as read for export, identified by its Neo4j element id
+pub struct ExportNode {
+    pub id: String,
+    pub label: String,
+    pub props: Value,
+}
+
+pub struct ExportEdge {
+    pub id: String,
+    pub kind: String,
+    pub source: String,
+    pub target: String,
+    pub props: Value,
+}
+
+// One end of a CALLS edge
+pub struct CallEnd {
+    pub id: String,
+    pub name: String,
+    pub qualified_name: String,
+    pub file: String,
+}
+
+// A kept index run. Its graph is stored under `key` (`<repo>@<id>`), beside the repo's live graph
+#[derive(Clone, serde::Serialize)]
+pub struct Snapshot {
+    pub repo: String,
+    pub id: String,
+    pub key: String,
+    // Unix seconds
+    pub created_at: i64,
+    pub files: i64,
+    pub symbols: i64,
+    // Commit the snapshot was indexed at, when the checkout was a git work tree
+    pub commit: Option<String>,
+}
+
+// Every label a repo's graph is made of, and how to find its nodes
+pub const EXPORT_LABELS: &[&str] = &[
+    "Repo", "Directory", "File", "Module", "Document", "Class", "Function", "Route", "Command", "Embedded", "Symbol", "Todo",
+    "Dependency", "Submodule", "Entrypoint",
+];
+
+// Properties too bulky to be useful in a visual export (dumps keep them)
+pub const EXPORT_SKIPPED: &[&str] = &["embedding"];
+
+// Rows for one UNWIND $batch
+type RowBatch = Vec<HashMap<String, BoltType>>;
+
+// Identifies dumps written by `dump_repo`; bump the version when their shape changes
+const DUMP_FORMAT: &str = "better-docs-graph";
+const DUMP_VERSION: i64 = 1;
+
+// Attempts per query, and the delay before the first retry (doubled each time)
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_BACKOFF_MS: u64 = 250;
+
+// A name in the docs that more symbols than this share is too ambiguous to link
+pub(crate) const MAX_MENTION_TARGETS: usize = 3;
 
 pub struct GraphClient {
-    graph: Arc<Graph>,
+    // Swapped for a fresh pool when the connection is lost (e.g. Neo4j restarted)
+    graph: RwLock<Arc<Graph>>,
+    reconnecting: tokio::sync::Mutex<()>,
+    connection: ConnectionConfig,
+    // Rows per UNWIND batch
+    batch_size: usize,
+    dialect: Dialect,
+    // Longest a query may hold a pooled connection before it is abandoned
+    query_timeout: Option<Duration>,
+}
+
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Where the server is and how to authenticate. The URI scheme picks the transport: bolt:// and
+// neo4j:// are plain, bolt+s:// and neo4j+s:// (Aura, TLS-terminated clusters) are encrypted and
+// verified against the system roots plus `ca_cert`. neo4j:// schemes send the routing context but
+// the driver has no client-side routing, so the URI should name the cluster's entry point
+#[derive(Clone)]
+pub struct ConnectionConfig {
+    pub uri: String,
+    pub user: String,
+    pub pass: String,
+    // PEM file of extra CA certificates, for servers signed by a private CA
+    pub ca_cert: Option<String>,
+    // Database to use instead of the server's default
+    pub database: Option<String>,
+    // Pool size and rows per fetch; the driver's defaults (16 and 200) when unset
+    pub max_connections: Option<usize>,
+    pub fetch_size: Option<usize>,
+}
+
+impl ConnectionConfig {
+    fn build(&self) -> Result<Config> {
+        let mut builder = ConfigBuilder::default().uri(&self.uri).user(&self.user).password(&self.pass);
+        if let Some(ca_cert) = &self.ca_cert {
+            builder = builder.with_client_certificate(ca_cert);
+        }
+        if let Some(database) = &self.database {
+            builder = builder.db(database.as_str());
+        }
+        if let Some(max_connections) = self.max_connections {
+            builder = builder.max_connections(max_connections);
+        }
+        if let Some(fetch_size) = self.fetch_size {
+            builder = builder.fetch_size(fetch_size);
+        }
+        builder.build()
+    }
+}
+
+// Bolt servers the client can talk to. Memgraph runs the same Cypher except for schema DDL and
+// element ids, and has no full-text or vector index procedures
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Neo4j,
+    Memgraph,
 }
 
 impl GraphClient {
-    pub async fn connect(uri: &str, user: &str, pass: &str) -> Result<Self> {
-        let graph = Graph::new(uri, user, pass).await?;
-        Ok(Self { graph: Arc::new(graph) })
+    pub async fn connect(connection: &ConnectionConfig) -> Result<Self> {
+        let graph = Graph::connect(connection.build()?).await?;
+        Ok(Self {
+            graph: RwLock::new(Arc::new(graph)),
+            reconnecting: tokio::sync::Mutex::new(()),
+            connection: connection.clone(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            dialect: Dialect::Neo4j,
+            query_timeout: Some(DEFAULT_QUERY_TIMEOUT),
+        })
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    // None lets queries run as long as the server allows
+    pub fn with_query_timeout(mut self, query_timeout: Option<Duration>) -> Self {
+        self.query_timeout = query_timeout;
+        self
+    }
+
+    // Expression for a node's or relationship's stable id in export queries
+    fn element_id(&self, var: &str) -> String {
+        match self.dialect {
+            Dialect::Neo4j => format!("elementId({})", var),
+            Dialect::Memgraph => format!("toString(id({}))", var),
+        }
+    }
+
+    fn current(&self) -> Arc<Graph> {
+        self.graph.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    // Retry transient failures with exponential backoff; a lost connection is replaced before retrying.
+    // Queries running past `query_timeout` are abandoned and retried once
+    async fn with_retry<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: Fn(Arc<Graph>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        let mut timed_out = false;
+        loop {
+            let graph = self.current();
+            let result = match self.query_timeout {
+                Some(limit) => match tokio::time::timeout(limit, op(graph.clone())).await {
+                    Ok(result) => result,
+                    // Dropping the query frees its connection. One retry covers a briefly busy
+                    // server; a second timeout means the query itself is too slow
+                    Err(_) if !timed_out => {
+                        timed_out = true;
+                        continue;
+                    }
+                    Err(_) => return Err(Error::UnexpectedMessage(format!("query exceeded {}s", limit.as_secs()))),
+                },
+                None => op(graph.clone()).await,
+            };
+            match result {
+                Err(e) if attempt + 1 < MAX_ATTEMPTS && is_transient(&e) => {
+                    tokio::time::sleep(Duration::from_millis(RETRY_BACKOFF_MS << attempt)).await;
+                    attempt += 1;
+                    if is_connection_lost(&e) {
+                        self.reconnect(&graph).await;
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn reconnect(&self, stale: &Arc<Graph>) {
+        let _guard = self.reconnecting.lock().await;
+        // Another request already replaced the pool while we waited
+        if !Arc::ptr_eq(stale, &self.current()) { return; }
+        let Ok(config) = self.connection.build() else { return };
+        if let Ok(graph) = Graph::connect(config).await {
+            *self.graph.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(graph);
+        }
+    }
+
+    async fn run(&self, q: Query) -> Result<()> {
+        self.with_retry(|graph| {
+            let q = q.clone();
+            async move { graph.run(q).await }
+        }).await
+    }
+
+    // Rows are read inside the retry so a connection dropped mid-stream is retried too
+    async fn execute(&self, q: Query) -> Result<Vec<Row>> {
+        self.with_retry(|graph| {
+            let q = q.clone();
+            async move {
+                let mut stream = graph.execute(q).await?;
+                let mut rows = vec![];
+                while let Some(row) = stream.next().await? {
+                    rows.push(row);
+                }
+                Ok(rows)
+            }
+        }).await
+    }
+
+    // UNWIND queries get their $batch in chunks of `batch_size` rows, keeping each Bolt message and
+    // transaction bounded for files with thousands of symbols or calls
+    async fn run_batch(&self, q: Query, batch: &[HashMap<String, BoltType>]) -> Result<()> {
+        for chunk in batch.chunks(self.batch_size.max(1)) {
+            self.run(q.clone().param("batch", chunk.to_vec())).await?;
+        }
+        Ok(())
     }
 
     pub async fn ensure_schema(&self) -> Result<()> {
+        if self.dialect == Dialect::Memgraph {
+            // Memgraph has no IF NOT EXISTS; recreating an existing constraint or index is a no-op
+            for label in ["File", "Class", "Function", "Document", "Todo", "Directory", "Dependency", "Submodule", "Entrypoint"] {
+                self.run(query(&format!("CREATE CONSTRAINT ON (n:{}) ASSERT n.id IS UNIQUE", label))).await?;
+            }
+            self.run(query("CREATE CONSTRAINT ON (r:Repo) ASSERT r.name IS UNIQUE")).await?;
+            self.run(query("CREATE CONSTRAINT ON (p:Package) ASSERT p.name IS UNIQUE")).await?;
+            self.run(query("CREATE CONSTRAINT ON (c:ClassificationFeedback) ASSERT c.repo IS UNIQUE")).await?;
+            self.run(query("CREATE INDEX ON :Node(name)")).await?;
+            self.run(query("CREATE INDEX ON :Repo(snapshot_of)")).await?;
+            return Ok(());
+        }
         for q in [
             "CREATE CONSTRAINT IF NOT EXISTS FOR (f:File) REQUIRE f.id IS UNIQUE",
             "CREATE CONSTRAINT IF NOT EXISTS FOR (c:Class) REQUIRE c.id IS UNIQUE",
             "CREATE CONSTRAINT IF NOT EXISTS FOR (fn:Function) REQUIRE fn.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (d:Document) REQUIRE d.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (t:Todo) REQUIRE t.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (r:Repo) REQUIRE r.name IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (d:Directory) REQUIRE d.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (d:Dependency) REQUIRE d.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (s:Submodule) REQUIRE s.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (e:Entrypoint) REQUIRE e.id IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (p:Package) REQUIRE p.name IS UNIQUE",
+            "CREATE CONSTRAINT IF NOT EXISTS FOR (c:ClassificationFeedback) REQUIRE c.repo IS UNIQUE",
             "CREATE INDEX IF NOT EXISTS FOR (n:Node) ON (n.name)",
+            "CREATE INDEX IF NOT EXISTS FOR (r:Repo) ON (r.snapshot_of)",
+            "CREATE FULLTEXT INDEX symbol_text IF NOT EXISTS \
+             FOR (n:Function|Class|Route|Command|Embedded|Symbol) ON EACH [n.name, n.docstring, n.signature]",
         ] {
-            self.graph.run(query(q)).await?;
+            self.run(query(q)).await?;
         }
         Ok(())
     }
 
-    pub async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()> {
+    // Summary of each function in `ids`, keyed by id
+    async fn function_info(&self, ids: Vec<String>) -> Result<HashMap<String, Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File)-[:CONTAINS]->(n:Function) WHERE n.id IN $ids \
+                   RETURN n.id AS id, n.name AS name, n.qualified_name AS qname, n.kind AS kind, \
+                          n.parent_class AS parent, f.path AS file, n.line_start AS line")
+                .param("ids", ids)
+        ).await?;
+        let mut info: HashMap<String, Value> = HashMap::new();
+        for row in rows {
+            info.insert(row.get::<String>("id").unwrap_or_default(), json!({
+                "id": row.get::<String>("id").unwrap_or_default(),
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "qualified_name": row.get::<String>("qname").ok().filter(|q| !q.is_empty()),
+                "kind": row.get::<String>("kind").unwrap_or_default(),
+                "parent_class": row.get::<String>("parent").ok().filter(|p| !p.is_empty()),
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+            }));
+        }
+        Ok(info)
+    }
+
+    // Every IMPORTS_FROM edge in the repo as (file, module, names, files the module resolves to)
+    async fn resolved_imports(&self, repo_name: &str) -> Result<Vec<(String, String, Vec<String>, Vec<String>)>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo}) RETURN f.path AS path")
+                .param("repo", repo_name)
+        ).await?;
+        let paths: HashSet<String> = rows.iter().filter_map(|row| row.get::<String>("path").ok()).collect();
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[i:IMPORTS_FROM]->(m:Module) \
+                   OPTIONAL MATCH (m)-[:HAS_FILE]->(t:File) \
+                   RETURN f.path AS file, f.language AS lang, m.name AS module, i.names AS names, \
+                          collect(t.path) AS defined_in \
+                   ORDER BY file, module")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            let file = row.get::<String>("file").unwrap_or_default();
+            let module = row.get::<String>("module").unwrap_or_default();
+            let names = row.get::<Vec<String>>("names").unwrap_or_default();
+            let mut targets = row.get::<Vec<String>>("defined_in").unwrap_or_default();
+            if targets.is_empty() {
+                let lang = row.get::<String>("lang").unwrap_or_default();
+                targets = resolve_import(&file, &lang, &module, &names, &paths);
+            }
+            out.push((file, module, names, targets));
+        }
+        Ok(out)
+    }
+}
+
+#[async_trait]
+impl GraphStore for GraphClient {
+    fn backend(&self) -> &'static str {
+        match self.dialect {
+            Dialect::Neo4j => "neo4j",
+            Dialect::Memgraph => "memgraph",
+        }
+    }
+
+    async fn ping(&self) -> bool {
+        self.execute(query("RETURN 1 AS ok")).await.is_ok()
+    }
+
+    async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()> {
         let file_id = format!("{}::{}", repo_name, file_path);
 
         // Collect raw import strings
@@ -34,57 +371,114 @@ impl GraphClient {
         let export_list: Vec<String> = result.exports.clone();
 
         // Upsert file node
-        self.graph.run(
-            query("MERGE (f:File {id: $id}) SET f.path = $path, f.repo = $repo, f.language = $lang, f.imports = $imports, f.exports = $exports")
+        self.run(
+            query("MERGE (f:File {id: $id}) SET f.path = $path, f.repo = $repo, f.language = $lang, f.imports = $imports, f.exports = $exports, \
+                   f.lines = $lines, f.loc = $loc, f.comment_lines = $comments, f.blank_lines = $blanks, \
+                   f.comment_density = $density, f.symbol_count = $symbols, f.test_file = $test_file, \
+                   f.parse_errors = $parse_errors, f.parse_error_locations = $parse_error_locations, f.references = $references, \
+                   f.content_hash = $content_hash")
                 .param("id", file_id.clone())
                 .param("path", file_path)
                 .param("repo", repo_name)
                 .param("lang", format!("{:?}", result.language))
                 .param("imports", import_raws)
                 .param("exports", export_list)
+                .param("lines", result.metrics.lines as i64)
+                .param("loc", result.metrics.code_lines as i64)
+                .param("comments", result.metrics.comment_lines as i64)
+                .param("blanks", result.metrics.blank_lines as i64)
+                .param("density", result.metrics.comment_density)
+                .param("symbols", result.metrics.symbol_count as i64)
+                .param("test_file", result.test_file)
+                .param("parse_errors", result.parse_errors.len() as i64)
+                .param("parse_error_locations", result.parse_errors.iter()
+                    .map(|e| format!("{}:{} {} {}", e.line, e.column, e.kind, e.text))
+                    .collect::<Vec<_>>())
+                .param("references", result.references.clone())
+                .param("content_hash", result.content_hash.clone())
         ).await?;
 
+        // Prose files get a Document node alongside the File
+        if let Some(doc) = &result.document {
+            self.run(
+                query("MATCH (f:File {id: $fid}) \
+                       MERGE (d:Document {id: $fid}) \
+                       SET d.repo = $repo, d.path = $path, d.kind = $kind, d.title = $title, \
+                           d.heading_count = $headings, d.word_count = $words, d.code_fences = $fences \
+                       MERGE (f)-[:DOCUMENTS]->(d)")
+                    .param("fid", file_id.clone())
+                    .param("repo", repo_name)
+                    .param("path", file_path)
+                    .param("kind", doc.kind.clone())
+                    .param("title", doc.title.clone().unwrap_or_default())
+                    .param("headings", doc.heading_count as i64)
+                    .param("words", doc.word_count as i64)
+                    .param("fences", serde_json::to_string(&doc.code_fences).unwrap_or_default())
+            ).await?;
+        }
+
+        // Rust module tree: crate -> crate::net -> crate::net::client, with the file under its module
+        if let Some(module_path) = &result.module_path {
+            let mut paths: Vec<&str> = result.modules.iter().map(|m| m.path.as_str()).collect();
+            paths.push(module_path);
+            let mut edges: Vec<HashMap<String, BoltType>> = vec![];
+            for path in paths {
+                let mut child = path;
+                while let Some((parent, _)) = child.rsplit_once("::") {
+                    let mut m: HashMap<String, BoltType> = HashMap::new();
+                    m.insert("parent".into(), parent.to_string().into());
+                    m.insert("child".into(), child.to_string().into());
+                    edges.push(m);
+                    child = parent;
+                }
+            }
+            self.run(
+                query("MATCH (f:File {id: $fid}) \
+                       MERGE (m:Module {name: $module, repo: $repo}) \
+                       MERGE (m)-[:HAS_FILE]->(f) \
+                       WITH m UNWIND $edges AS e \
+                       MERGE (p:Module {name: e.parent, repo: $repo}) \
+                       MERGE (c:Module {name: e.child, repo: $repo}) \
+                       MERGE (p)-[:HAS_MODULE]->(c)")
+                    .param("fid", file_id.clone())
+                    .param("module", module_path.clone())
+                    .param("repo", repo_name)
+                    .param("edges", edges)
+            ).await?;
+        }
+
         // Batch IMPORTS_FROM edges via UNWIND
         let import_batch: Vec<HashMap<String, BoltType>> = result.imports.iter()
             .filter_map(|imp| {
                 let source = imp.source.as_ref()?;
-                let source_clean = source.replace('.', "/");
                 let mut m: HashMap<String, BoltType> = HashMap::new();
-                m.insert("mod_name".into(), source_clean.into());
+                m.insert("mod_name".into(), module_name(result.language, source).into());
                 m.insert("names".into(), imp.names.clone().into());
                 Some(m)
             })
             .collect();
 
         if !import_batch.is_empty() {
-            self.graph.run(
+            self.run_batch(
                 query("UNWIND $batch AS imp \
                        MATCH (f:File {id: $fid}) \
                        MERGE (m:Module {name: imp.mod_name, repo: $repo}) \
                        MERGE (f)-[:IMPORTS_FROM {names: imp.names}]->(m)")
-                    .param("batch", import_batch)
                     .param("fid", file_id.clone())
-                    .param("repo", repo_name)
+                    .param("repo", repo_name),
+                &import_batch,
             ).await?;
         }
 
-        if result.symbols.is_empty() {
-            return Ok(());
-        }
-
         // Batch all symbols via UNWIND
-        for label in &["Class", "Function", "Symbol"] {
+        for label in &["Class", "Function", "Route", "Command", "Embedded", "Symbol"] {
             let batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
                 .filter(|s| {
-                    let l = match s.kind.as_str() {
-                        "class" => "Class",
-                        "function" | "method" => "Function",
-                        _ => "Symbol",
-                    };
-                    l == *label
+                    label_for_kind(&s.kind) == *label
                 })
                 .map(|s| {
                     let params_json = serde_json::to_string(&s.params).unwrap_or_default();
+                    let fields_json = serde_json::to_string(&s.fields).unwrap_or_default();
                     let mut m: HashMap<String, BoltType> = HashMap::new();
                     m.insert("id".into(), format!("{}::{}:{}", file_id, s.name, s.range.0).into());
                     m.insert("name".into(), s.name.clone().into());
@@ -97,8 +491,28 @@ impl GraphClient {
                     m.insert("parent".into(), s.parent_class.clone().unwrap_or_default().into());
                     m.insert("params".into(), params_json.into());
                     m.insert("decos".into(), s.decorators.join(", ").into());
+                    m.insert("members".into(), s.members.join(", ").into());
+                    m.insert("fields".into(), fields_json.into());
+                    m.insert("sections".into(), serde_json::to_string(&s.doc_sections).unwrap_or_default().into());
+                    m.insert("examples".into(), serde_json::to_string(&s.examples).unwrap_or_default().into());
+                    m.insert("is_test".into(), s.is_test.into());
+                    m.insert("is_async".into(), s.is_async.into());
+                    m.insert("is_generator".into(), s.is_generator.into());
+                    m.insert("is_unsafe".into(), s.is_unsafe.into());
+                    m.insert("raises".into(), s.raises.join(", ").into());
+                    m.insert("qname".into(), s.qualified_name.clone().unwrap_or_default().into());
+                    m.insert("type_params".into(), serde_json::to_string(&s.type_params).unwrap_or_default().into());
+                    m.insert("variants".into(), serde_json::to_string(&s.variants).unwrap_or_default().into());
+                    m.insert("tags".into(), s.tags.clone().into());
+                    m.insert("calls".into(), s.calls.clone().into());
+                    m.insert("mentions".into(), s.mentions.clone().into());
+                    m.insert("owners".into(), s.owners.clone().into());
                     m.insert("ls".into(), (s.range.0 as i64).into());
                     m.insert("le".into(), (s.range.1 as i64).into());
+                    m.insert("bs".into(), (s.byte_range.0 as i64).into());
+                    m.insert("be".into(), (s.byte_range.1 as i64).into());
+                    m.insert("cs".into(), (s.columns.0 as i64).into());
+                    m.insert("ce".into(), (s.columns.1 as i64).into());
                     m
                 })
                 .collect();
@@ -112,17 +526,21 @@ impl GraphClient {
                      n.docstring = s.doc, n.signature = s.sig, \
                      n.return_type = s.ret, n.visibility = s.vis, \
                      n.parent_class = s.parent, n.params = s.params, \
-                     n.decorators = s.decos, \
-                     n.line_start = s.ls, n.line_end = s.le \
+                     n.decorators = s.decos, n.members = s.members, n.fields = s.fields, \
+                     n.doc_sections = s.sections, n.examples = s.examples, n.is_test = s.is_test, \
+                     n.is_async = s.is_async, n.is_generator = s.is_generator, n.is_unsafe = s.is_unsafe, \
+                     n.raises = s.raises, n.qualified_name = s.qname, n.type_params = s.type_params, n.variants = s.variants, n.tags = s.tags, n.calls = s.calls, \
+                     n.mentions = s.mentions, n.owners = s.owners, n.line_start = s.ls, n.line_end = s.le, \
+                     n.byte_start = s.bs, n.byte_end = s.be, n.col_start = s.cs, n.col_end = s.ce \
                  WITH n, s \
                  MATCH (f:File {{id: $fid}}) \
                  MERGE (f)-[:CONTAINS]->(n)",
                 label
             );
-            self.graph.run(
+            self.run_batch(
                 query(&cypher)
-                    .param("batch", batch)
-                    .param("fid", file_id.clone())
+                    .param("fid", file_id.clone()),
+                &batch,
             ).await?;
         }
 
@@ -131,22 +549,65 @@ impl GraphClient {
             .flat_map(|sym| {
                 let caller_id = format!("{}::{}:{}", file_id, sym.name, sym.range.0);
                 sym.calls.iter().map(move |callee_name| {
+                    let target = resolve_call(result, sym, callee_name);
                     let mut m: HashMap<String, BoltType> = HashMap::new();
                     m.insert("cid".into(), caller_id.clone().into());
-                    m.insert("name".into(), callee_name.clone().into());
+                    m.insert("name".into(), target.name.into());
+                    m.insert("candidates".into(), target.candidates.into());
+                    m.insert("owner".into(), target.owner.into());
+                    m.insert("member".into(), target.member.into());
+                    m.insert("module".into(), target.module.into());
+                    m.insert("suffix".into(), target.suffix.into());
                     m
                 })
             })
             .collect();
 
         if !calls_batch.is_empty() {
-            self.graph.run(
+            self.run_batch(
                 query("UNWIND $batch AS c \
                        MATCH (caller:Function {id: c.cid}) \
                        MATCH (callee:Function {name: c.name})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       WHERE (size(c.candidates) = 0 OR coalesce(callee.qualified_name, '') = '' \
+                               OR callee.qualified_name IN c.candidates) \
+                         AND (c.owner = '' OR callee.parent_class = c.owner OR callee.parent_class ENDS WITH '::' + c.owner) \
+                         AND (NOT c.member OR coalesce(callee.parent_class, '') <> '') \
+                         AND (c.module = '' OR f.path STARTS WITH c.module + '/' OR f.path STARTS WITH c.module + '.' \
+                               OR f.path CONTAINS '/' + c.module + '/' OR f.path CONTAINS '/' + c.module + '.') \
+                         AND (c.suffix = '' OR coalesce(callee.qualified_name, '') ENDS WITH c.suffix) \
                        MERGE (caller)-[:CALLS]->(callee)")
-                    .param("batch", calls_batch)
-                    .param("repo", repo_name)
+                    .param("repo", repo_name),
+                &calls_batch,
+            ).await?;
+        }
+
+        // HAS_METHOD from each method's class: the class of that name in the same file, otherwise
+        // same-named classes elsewhere in the repo (Go receivers, Rust impls in other files)
+        let method_batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
+            .filter(|sym| label_for_kind(&sym.kind) == "Function")
+            .filter_map(|sym| {
+                let owner = owner_name(sym.parent_class.as_deref()?);
+                let local = result.symbols.iter()
+                    .find(|c| label_for_kind(&c.kind) == "Class" && c.name == owner)
+                    .map(|c| format!("{}::{}:{}", file_id, c.name, c.range.0))
+                    .unwrap_or_default();
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("mid".into(), format!("{}::{}:{}", file_id, sym.name, sym.range.0).into());
+                m.insert("owner".into(), owner.to_string().into());
+                m.insert("local".into(), local.into());
+                Some(m)
+            })
+            .collect();
+
+        if !method_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS m \
+                       MATCH (fn:Function {id: m.mid}) \
+                       MATCH (c:Class {name: m.owner})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       WHERE m.local = '' OR c.id = m.local \
+                       MERGE (c)-[:HAS_METHOD]->(fn)")
+                    .param("repo", repo_name),
+                &method_batch,
             ).await?;
         }
 
@@ -156,109 +617,2384 @@ impl GraphClient {
             .flat_map(|sym| {
                 let child_id = format!("{}::{}:{}", file_id, sym.name, sym.range.0);
                 sym.bases.iter().map(move |base| {
+                    let (name, candidates) = match result.language {
+                        Language::Php => php_candidates(base),
+                        _ => (base.clone(), vec![]),
+                    };
                     let mut m: HashMap<String, BoltType> = HashMap::new();
                     m.insert("cid".into(), child_id.clone().into());
-                    m.insert("name".into(), base.clone().into());
+                    m.insert("name".into(), name.into());
+                    m.insert("candidates".into(), candidates.into());
                     m
                 })
             })
             .collect();
 
         if !inherits_batch.is_empty() {
-            self.graph.run(
+            self.run_batch(
                 query("UNWIND $batch AS c \
                        MATCH (child:Class {id: c.cid}) \
                        MATCH (parent:Class {name: c.name})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       WHERE size(c.candidates) = 0 OR coalesce(parent.qualified_name, '') = '' \
+                           OR parent.qualified_name IN c.candidates \
                        MERGE (child)-[:INHERITS]->(parent)")
-                    .param("batch", inherits_batch)
+                    .param("repo", repo_name),
+                &inherits_batch,
+            ).await?;
+        }
+
+        // Route details, plus HANDLED_BY to the named handler (a class for Django class-based views)
+        // and CALLS from inline handlers
+        let route_batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
+            .filter_map(|sym| sym.route.as_ref().map(|route| (sym, route)))
+            .map(|(sym, route)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}:{}", file_id, sym.name, sym.range.0).into());
+                m.insert("method".into(), route.method.clone().into());
+                m.insert("path".into(), route.path.clone().into());
+                m.insert("handler".into(), route.handler.clone().unwrap_or_default().into());
+                m.insert("owner".into(), sym.parent_class.clone().unwrap_or_default().into());
+                m.insert("calls".into(), sym.calls.iter().map(|c| call_name(c).to_string()).collect::<Vec<_>>().into());
+                m
+            })
+            .collect();
+
+        if !route_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS r \
+                       MATCH (n:Route {id: r.id}) \
+                       SET n.method = r.method, n.path = r.path, n.handler = r.handler"),
+                &route_batch,
+            ).await?;
+            self.run_batch(
+                query("UNWIND $batch AS r \
+                       MATCH (n:Route {id: r.id}) \
+                       MATCH (h {name: r.handler})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       WHERE (h:Function OR h:Class) \
+                           AND (r.owner = '' OR h.parent_class = r.owner OR h.parent_class ENDS WITH '::' + r.owner) \
+                       MERGE (n)-[:HANDLED_BY]->(h)")
+                    .param("repo", repo_name),
+                &route_batch,
+            ).await?;
+            self.run_batch(
+                query("UNWIND $batch AS r \
+                       MATCH (n:Route {id: r.id}) \
+                       UNWIND r.calls AS callee \
+                       MATCH (fn:Function {name: callee})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       MERGE (n)-[:CALLS]->(fn)")
+                    .param("repo", repo_name),
+                &route_batch,
+            ).await?;
+        }
+
+        // CLI commands: options, HAS_SUBCOMMAND from the parent (matched by name or binding, possibly
+        // in another file) and HANDLED_BY to the function that runs the command
+        let command_batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
+            .filter_map(|sym| sym.command.as_ref().map(|command| (sym, command)))
+            .map(|(sym, command)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}:{}", file_id, sym.name, sym.range.0).into());
+                m.insert("options".into(), serde_json::to_string(&command.options).unwrap_or_default().into());
+                m.insert("parent".into(), command.parent.clone().unwrap_or_default().into());
+                m.insert("binding".into(), command.binding.clone().unwrap_or_default().into());
+                m.insert("handler".into(), command.handler.clone().unwrap_or_default().into());
+                m
+            })
+            .collect();
+
+        if !command_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS c \
+                       MATCH (n:Command {id: c.id}) \
+                       SET n.options = c.options, n.parent_command = c.parent, n.binding = c.binding, n.handler = c.handler"),
+                &command_batch,
+            ).await?;
+            self.run_batch(
+                query("UNWIND $batch AS c \
+                       MATCH (n:Command {id: c.id}) WHERE c.parent <> '' \
+                       MATCH (p:Command)<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       WHERE p.name = c.parent OR p.binding = c.parent \
+                       MERGE (p)-[:HAS_SUBCOMMAND]->(n)")
+                    .param("repo", repo_name),
+                &command_batch,
+            ).await?;
+            self.run_batch(
+                query("UNWIND $batch AS c \
+                       MATCH (n:Command {id: c.id}) \
+                       MATCH (h:Function {name: c.handler})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                       MERGE (n)-[:HANDLED_BY]->(h)")
+                    .param("repo", repo_name),
+                &command_batch,
+            ).await?;
+        }
+
+        // Embedded SQL/GraphQL/HTML: language, text and targets, plus EMBEDS from the enclosing function
+        let embedded_batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
+            .filter_map(|sym| sym.embedded.as_ref().map(|embedded| (sym, embedded)))
+            .map(|(sym, embedded)| {
+                let container = result.symbols.iter()
+                    .filter(|s| Some(&s.name) == embedded.container.as_ref() && label_for_kind(&s.kind) == "Function")
+                    // A setter or overload body belongs to the grouped symbol, whose span is the primary's
+                    .min_by_key(|s| (!(s.byte_range.0 <= sym.byte_range.0 && sym.byte_range.1 <= s.byte_range.1), s.byte_range.1 - s.byte_range.0))
+                    .map(|s| format!("{}::{}:{}", file_id, s.name, s.range.0))
+                    .unwrap_or_default();
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}:{}", file_id, sym.name, sym.range.0).into());
+                m.insert("language".into(), embedded.language.clone().into());
+                m.insert("text".into(), embedded.text.clone().into());
+                m.insert("targets".into(), embedded.targets.clone().into());
+                m.insert("container".into(), embedded.container.clone().unwrap_or_default().into());
+                m.insert("cid".into(), container.into());
+                m
+            })
+            .collect();
+
+        if !embedded_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS e \
+                       MATCH (n:Embedded {id: e.id}) \
+                       SET n.language = e.language, n.text = e.text, n.targets = e.targets, n.container = e.container \
+                       WITH n, e WHERE e.cid <> '' \
+                       MATCH (c:Function {id: e.cid}) \
+                       MERGE (c)-[:EMBEDS]->(n)"),
+                &embedded_batch,
+            ).await?;
+        }
+
+        // Python annotations name classes: USES_TYPE from the function, `via` the parameter or "return"
+        if result.language == Language::Python {
+            let type_batch: Vec<HashMap<String, BoltType>> = result.symbols.iter()
+                .filter(|sym| label_for_kind(&sym.kind) == "Function")
+                .flat_map(|sym| {
+                    let id = format!("{}::{}:{}", file_id, sym.name, sym.range.0);
+                    sym.params.iter()
+                        .filter_map(|p| Some((p.name.trim_start_matches('*').to_string(), p.type_annotation.as_deref()?)))
+                        .chain(sym.return_type.as_deref().map(|r| ("return".to_string(), r)))
+                        .flat_map(|(via, ty)| py_type_names(ty).into_iter().map(move |name| (via.clone(), name)))
+                        .map(move |(via, name)| {
+                            let mut m: HashMap<String, BoltType> = HashMap::new();
+                            m.insert("id".into(), id.clone().into());
+                            m.insert("via".into(), via.into());
+                            m.insert("type".into(), name.into());
+                            m
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            if !type_batch.is_empty() {
+                self.run_batch(
+                    query("UNWIND $batch AS t \
+                           MATCH (fn:Function {id: t.id}) \
+                           MATCH (c:Class {name: t.type})<-[:CONTAINS]-(f:File {repo: $repo}) \
+                           MERGE (fn)-[:USES_TYPE {via: t.via}]->(c)")
+                        .param("repo", repo_name),
+                    &type_batch,
+                ).await?;
+            }
+        }
+
+        // IMPLEMENTS edges for Rust trait impls
+        self.link_implementations(repo_name, &result.implementations).await?;
+
+        // Batch Todo nodes, linked to the File and to the innermost symbol enclosing them
+        let todo_batch: Vec<HashMap<String, BoltType>> = result.todos.iter()
+            .map(|todo| {
+                let owner = result.symbols.iter()
+                    .filter(|s| s.range.0 <= todo.line && todo.line <= s.range.1)
+                    .min_by_key(|s| s.range.1 - s.range.0)
+                    .map(|s| format!("{}::{}:{}", file_id, s.name, s.range.0))
+                    .unwrap_or_default();
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::todo:{}", file_id, todo.line).into());
+                m.insert("tag".into(), todo.tag.clone().into());
+                m.insert("text".into(), todo.text.clone().into());
+                m.insert("line".into(), (todo.line as i64).into());
+                m.insert("sid".into(), owner.into());
+                m
+            })
+            .collect();
+
+        if !todo_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS t \
+                       MATCH (f:File {id: $fid}) \
+                       MERGE (td:Todo {id: t.id}) \
+                       SET td.repo = $repo, td.path = $path, td.tag = t.tag, td.text = t.text, td.line = t.line \
+                       MERGE (f)-[:HAS_TODO]->(td) \
+                       WITH f, td, t WHERE t.sid <> '' \
+                       MATCH (f)-[:CONTAINS]->(s {id: t.sid}) \
+                       MERGE (s)-[:HAS_TODO]->(td)")
+                    .param("fid", file_id.clone())
                     .param("repo", repo_name)
+                    .param("path", file_path),
+                &todo_batch,
             ).await?;
         }
 
         Ok(())
     }
 
-    pub async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>> {
-        let mut result = self.graph.execute(
-            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(s) RETURN s.name AS name, s.kind AS kind, s.docstring AS doc, s.signature AS sig, s.return_type AS ret, s.visibility AS vis, s.parent_class AS parent, s.params AS params, s.decorators AS decos, f.path AS file, s.line_start AS ls, s.line_end AS le")
+    // (Type)-[:IMPLEMENTS]->(Trait/interface), matched by name across the repo. Also called once after a
+    // full index for Go, whose implementations are only known when every file has been parsed.
+    // (:Repo)-[:CONTAINS]->(:Directory)-[:CONTAINS]->...->(:File) for the given repo-relative paths,
+    // then file counts and line totals rolled up onto every directory and the repo
+    async fn link_hierarchy(&self, repo_name: &str, file_paths: &[String]) -> Result<()> {
+        let mut dirs: Vec<String> = vec![];
+        let mut file_batch: Vec<HashMap<String, BoltType>> = vec![];
+        for path in file_paths {
+            let path = path.replace('\\', "/");
+            let parent = path.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default();
+            let mut prefix = String::new();
+            for part in parent.split('/').filter(|p| !p.is_empty()) {
+                if !prefix.is_empty() { prefix.push('/'); }
+                prefix.push_str(part);
+                if !dirs.contains(&prefix) { dirs.push(prefix.clone()); }
+            }
+            let mut m: HashMap<String, BoltType> = HashMap::new();
+            m.insert("id".into(), format!("{}::{}", repo_name, path).into());
+            m.insert("dir".into(), if parent.is_empty() { String::new() } else { format!("{}::{}/", repo_name, parent) }.into());
+            file_batch.push(m);
+        }
+        let dir_batch: Vec<HashMap<String, BoltType>> = dirs.iter()
+            .map(|dir| {
+                let (parent, name) = dir.rsplit_once('/').unwrap_or(("", dir));
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}/", repo_name, dir).into());
+                m.insert("path".into(), dir.clone().into());
+                m.insert("name".into(), name.to_string().into());
+                m.insert("parent".into(), if parent.is_empty() { String::new() } else { format!("{}::{}/", repo_name, parent) }.into());
+                m
+            })
+            .collect();
+
+        self.run(query("MERGE (r:Repo {name: $repo})").param("repo", repo_name)).await?;
+        if !dir_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS d \
+                       MERGE (n:Directory {id: d.id}) \
+                       SET n.path = d.path, n.name = d.name, n.repo = $repo")
+                    .param("repo", repo_name),
+                &dir_batch,
+            ).await?;
+            self.run_batch(
+                query("UNWIND $batch AS d \
+                       MATCH (n:Directory {id: d.id}) \
+                       OPTIONAL MATCH (p:Directory {id: d.parent}) \
+                       MATCH (r:Repo {name: $repo}) \
+                       FOREACH (_ IN CASE WHEN d.parent = '' THEN [1] ELSE [] END | MERGE (r)-[:CONTAINS]->(n)) \
+                       FOREACH (_ IN CASE WHEN p IS NULL THEN [] ELSE [1] END | MERGE (p)-[:CONTAINS]->(n))")
+                    .param("repo", repo_name),
+                &dir_batch,
+            ).await?;
+        }
+        self.run_batch(
+            query("UNWIND $batch AS f \
+                   MATCH (file:File {id: f.id}) \
+                   OPTIONAL MATCH (d:Directory {id: f.dir}) \
+                   MATCH (r:Repo {name: $repo}) \
+                   FOREACH (_ IN CASE WHEN f.dir = '' THEN [1] ELSE [] END | MERGE (r)-[:CONTAINS]->(file)) \
+                   FOREACH (_ IN CASE WHEN d IS NULL THEN [] ELSE [1] END | MERGE (d)-[:CONTAINS]->(file))")
+                .param("repo", repo_name),
+            &file_batch,
+        ).await?;
+        self.run(
+            query("MATCH (n) WHERE (n:Directory AND n.repo = $repo) OR (n:Repo AND n.name = $repo) \
+                   OPTIONAL MATCH (n)-[:CONTAINS*]->(f:File) \
+                   WITH n, count(f) AS files, sum(coalesce(f.loc, 0)) AS loc, sum(coalesce(f.symbol_count, 0)) AS symbols \
+                   SET n.file_count = files, n.loc = loc, n.symbol_count = symbols")
                 .param("repo", repo_name)
         ).await?;
-        let mut out = vec![];
-        while let Some(row) = result.next().await? {
-            out.push(json!({
-                "name": row.get::<String>("name").unwrap_or_default(),
-                "kind": row.get::<String>("kind").unwrap_or_default(),
-                "docstring": row.get::<String>("doc").unwrap_or_default(),
-                "signature": row.get::<String>("sig").unwrap_or_default(),
-                "return_type": row.get::<String>("ret").unwrap_or_default(),
-                "visibility": row.get::<String>("vis").unwrap_or_default(),
-                "parent_class": row.get::<String>("parent").unwrap_or_default(),
-                "params": row.get::<String>("params").unwrap_or_default(),
-                "decorators": row.get::<String>("decos").unwrap_or_default(),
-                "file": row.get::<String>("file").unwrap_or_default(),
-                "line_start": row.get::<i64>("ls").unwrap_or(0),
-                "line_end": row.get::<i64>("le").unwrap_or(0),
-            }));
+        Ok(())
+    }
+
+    // Property names used on the repo's nodes and on its relationships, for formats that declare
+    // their attributes up front
+    async fn export_keys(&self, repo_name: &str) -> Result<(Vec<String>, Vec<String>)> {
+        let mut node_keys: Vec<String> = vec![];
+        let mut edge_keys: Vec<String> = vec![];
+        for label in EXPORT_LABELS {
+            for (keys, cypher) in [
+                (&mut node_keys, format!("{} UNWIND keys(n) AS k RETURN DISTINCT k", export_match(label))),
+                (&mut edge_keys, format!("{} MATCH (n)-[r]->() UNWIND keys(r) AS k RETURN DISTINCT k", export_match(label))),
+            ] {
+                let rows = self.execute(query(&cypher).param("repo", repo_name).param("prefix", format!("{}::", repo_name))).await?;
+                for key in rows.iter().filter_map(|row| row.get::<String>("k").ok()) {
+                    if !keys.contains(&key) && !EXPORT_SKIPPED.contains(&key.as_str()) {
+                        keys.push(key);
+                    }
+                }
+            }
         }
-        Ok(out)
+        node_keys.sort();
+        edge_keys.sort();
+        Ok((node_keys, edge_keys))
     }
 
-    pub async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>> {
-        let mut result = self.graph.execute(
-            query("MATCH (f:File {repo: $repo}) RETURN f.path AS path, f.language AS lang")
+    // One page of a label's nodes in the repo, in a stable order
+    async fn export_nodes(&self, repo_name: &str, label: &str, skip: i64, limit: i64) -> Result<Vec<ExportNode>> {
+        let rows = self.execute(
+            query(&format!(
+                "{} RETURN {} AS id, properties(n) AS props ORDER BY id SKIP $skip LIMIT $limit",
+                export_match(label), self.element_id("n")
+            ))
                 .param("repo", repo_name)
+                .param("prefix", format!("{}::", repo_name))
+                .param("skip", skip)
+                .param("limit", limit)
         ).await?;
-        let mut out = vec![];
-        while let Some(row) = result.next().await? {
-            out.push(json!({
-                "path": row.get::<String>("path").unwrap_or_default(),
-                "language": row.get::<String>("lang").unwrap_or_default(),
-            }));
-        }
-        Ok(out)
+        Ok(rows.into_iter()
+            .map(|row| ExportNode {
+                id: row.get::<String>("id").unwrap_or_default(),
+                label: label.to_string(),
+                props: row.get::<Value>("props").unwrap_or_default(),
+            })
+            .collect())
     }
 
-    pub async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>> {
-        let mut result = self.graph.execute(
-            query("MATCH (f:File {repo: $repo}) OPTIONAL MATCH (f)-[:CONTAINS]->(s) RETURN f.path AS path, f.language AS lang, collect({name: s.name, kind: s.kind, sig: s.signature, doc: s.docstring, ret: s.return_type, vis: s.visibility, parent: s.parent_class, params: s.params, decos: s.decorators}) AS symbols")
+    // One page of the relationships leaving a label's nodes in the repo
+    async fn export_edges(&self, repo_name: &str, label: &str, skip: i64, limit: i64) -> Result<Vec<ExportEdge>> {
+        let rows = self.execute(
+            query(&format!(
+                "{} MATCH (n)-[r]->(m) \
+                 RETURN {} AS id, type(r) AS kind, {} AS source, {} AS target, \
+                        properties(r) AS props \
+                 ORDER BY id SKIP $skip LIMIT $limit",
+                export_match(label), self.element_id("r"), self.element_id("n"), self.element_id("m")
+            ))
                 .param("repo", repo_name)
+                .param("prefix", format!("{}::", repo_name))
+                .param("skip", skip)
+                .param("limit", limit)
         ).await?;
-        let mut out = vec![];
-        while let Some(row) = result.next().await? {
-            out.push(json!({
-                "path": row.get::<String>("path").unwrap_or_default(),
-                "language": row.get::<String>("lang").unwrap_or_default(),
-                "symbols": row.get::<Vec<Value>>("symbols").unwrap_or_default(),
-            }));
-        }
-        Ok(out)
+        Ok(rows.into_iter()
+            .map(|row| ExportEdge {
+                id: row.get::<String>("id").unwrap_or_default(),
+                kind: row.get::<String>("kind").unwrap_or_default(),
+                source: row.get::<String>("source").unwrap_or_default(),
+                target: row.get::<String>("target").unwrap_or_default(),
+                props: row.get::<Value>("props").unwrap_or_default(),
+            })
+            .collect())
     }
 
-    pub async fn count_by_kind(&self, repo_name: &str) -> Result<Value> {
-        let mut result = self.graph.execute(
-            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(s) RETURN s.kind AS kind, count(s) AS cnt")
+    // Every CALLS edge between the repo's functions
+    async fn get_call_edges(&self, repo_name: &str) -> Result<Vec<(CallEnd, CallEnd)>> {
+        let rows = self.execute(
+            query("MATCH (fa:File {repo: $repo})-[:CONTAINS]->(a:Function)-[:CALLS]->(b:Function)<-[:CONTAINS]-(fb:File) \
+                   RETURN a.id AS a, a.name AS a_name, a.qualified_name AS a_qname, fa.path AS a_file, \
+                          b.id AS b, b.name AS b_name, b.qualified_name AS b_qname, fb.path AS b_file")
                 .param("repo", repo_name)
         ).await?;
-        let mut counts = serde_json::Map::new();
-        while let Some(row) = result.next().await? {
-            let kind = row.get::<String>("kind").unwrap_or_default();
-            let cnt = row.get::<i64>("cnt").unwrap_or(0);
-            counts.insert(kind, json!(cnt));
+        let end = |row: &Row, side: &str| CallEnd {
+            id: row.get::<String>(side).unwrap_or_default(),
+            name: row.get::<String>(&format!("{}_name", side)).unwrap_or_default(),
+            qualified_name: row.get::<String>(&format!("{}_qname", side)).unwrap_or_default(),
+            file: row.get::<String>(&format!("{}_file", side)).unwrap_or_default(),
+        };
+        Ok(rows.iter().map(|row| (end(row, "a"), end(row, "b"))).collect())
+    }
+
+    // Every node and relationship of the repo in the versioned dump format read by `restore_repo`.
+    // Nodes are keyed by their element id in this database; edges refer to those keys
+    async fn dump_repo(&self, repo_name: &str) -> Result<Value> {
+        const PAGE: i64 = 5000;
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        for label in EXPORT_LABELS {
+            let mut skip = 0;
+            loop {
+                let page = self.export_nodes(repo_name, label, skip, PAGE).await?;
+                let done = (page.len() as i64) < PAGE;
+                nodes.extend(page.into_iter().map(|n| json!({ "key": n.id, "label": n.label, "props": n.props })));
+                if done { break }
+                skip += PAGE;
+            }
+            let mut skip = 0;
+            loop {
+                let page = self.export_edges(repo_name, label, skip, PAGE).await?;
+                let done = (page.len() as i64) < PAGE;
+                edges.extend(page.into_iter().map(|e| json!({ "type": e.kind, "source": e.source, "target": e.target, "props": e.props })));
+                if done { break }
+                skip += PAGE;
+            }
         }
-        Ok(Value::Object(counts))
+        Ok(json!({ "format": DUMP_FORMAT, "version": DUMP_VERSION, "repo": repo_name, "nodes": nodes, "edges": edges }))
     }
 
-    pub async fn get_file_languages(&self, repo_name: &str) -> Result<Value> {
-        let mut result = self.graph.execute(
-            query("MATCH (f:File {repo: $repo}) RETURN f.language AS lang, count(f) AS cnt")
+    // Replace a repo's graph with the contents of a `dump_repo` dump. Returns (nodes, relationships) written
+    async fn restore_repo(&self, dump: &Value) -> Result<(i64, i64)> {
+        if dump["format"] != DUMP_FORMAT || dump["version"] != DUMP_VERSION {
+            return Err(Error::UnexpectedMessage(format!("expected a {} dump, version {}", DUMP_FORMAT, DUMP_VERSION)));
+        }
+        let repo_name = dump["repo"].as_str().filter(|r| !r.is_empty())
+            .ok_or_else(|| Error::UnexpectedMessage("dump has no repo".to_string()))?;
+        let prefix = format!("{}::", repo_name);
+
+        // key -> (label, identity) so edges can be matched through the constraint-backed properties
+        let mut identities: HashMap<&str, (&str, Value)> = HashMap::new();
+        let mut node_batches: HashMap<&str, RowBatch> = HashMap::new();
+        for node in dump["nodes"].as_array().into_iter().flatten() {
+            let (Some(key), Some(label)) = (node["key"].as_str(), node["label"].as_str()) else { continue };
+            let Some(label) = EXPORT_LABELS.iter().find(|l| **l == label) else {
+                return Err(Error::UnexpectedMessage(format!("unknown label `{}` in dump", label)));
+            };
+            let props = &node["props"];
+            let identity = match *label {
+                "Repo" => json!({ "name": props["name"] }),
+                "Module" => json!({ "name": props["name"], "repo": props["repo"] }),
+                _ => json!({ "id": props["id"] }),
+            };
+            // Only restore what belongs to the dumped repo
+            let owned = match *label {
+                "Repo" => props["name"] == repo_name,
+                "Directory" | "Module" => props["repo"] == repo_name,
+                _ => props["id"].as_str().is_some_and(|id| id.starts_with(&prefix)),
+            };
+            if !owned {
+                return Err(Error::UnexpectedMessage(format!("{} node `{}` does not belong to repo {}", label, key, repo_name)));
+            }
+            let mut m: HashMap<String, BoltType> = HashMap::new();
+            m.insert("identity".into(), bolt_value(&identity));
+            m.insert("props".into(), bolt_value(props));
+            node_batches.entry(label).or_default().push(m);
+            identities.insert(key, (label, identity));
+        }
+
+        // Grouped by (type, source label, target label), since none of them can be parameters
+        let mut edge_batches: HashMap<(&str, &str, &str), RowBatch> = HashMap::new();
+        for edge in dump["edges"].as_array().into_iter().flatten() {
+            let kind = edge["type"].as_str().unwrap_or_default();
+            if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(Error::UnexpectedMessage(format!("invalid relationship type `{}` in dump", kind)));
+            }
+            let source = edge["source"].as_str().and_then(|k| identities.get(k));
+            let target = edge["target"].as_str().and_then(|k| identities.get(k));
+            let (Some((source_label, source)), Some((target_label, target))) = (source, target) else {
+                return Err(Error::UnexpectedMessage(format!("{} relationship refers to a node missing from the dump", kind)));
+            };
+            let mut m: HashMap<String, BoltType> = HashMap::new();
+            m.insert("source".into(), bolt_value(source));
+            m.insert("target".into(), bolt_value(target));
+            m.insert("props".into(), bolt_value(&edge["props"]));
+            edge_batches.entry((kind, source_label, target_label)).or_default().push(m);
+        }
+
+        self.delete_repo(repo_name).await?;
+        let mut nodes = 0;
+        for (label, batch) in &node_batches {
+            self.run_batch(
+                query(&format!(
+                    "UNWIND $batch AS r MERGE (n:{} {}) SET n += r.props \
+                     WITH n, r WHERE r.props.embedding IS NOT NULL SET n:HasEmbedding",
+                    label, identity_pattern(label, "r.identity")
+                )),
+                batch,
+            ).await?;
+            nodes += batch.len() as i64;
+        }
+        let mut edges = 0;
+        for ((kind, source_label, target_label), batch) in &edge_batches {
+            let cypher = format!(
+                "UNWIND $batch AS e MATCH (a:{} {}) MATCH (b:{} {}) CREATE (a)-[r:{}]->(b) SET r = e.props",
+                source_label, identity_pattern(source_label, "e.source"),
+                target_label, identity_pattern(target_label, "e.target"),
+                kind
+            );
+            self.run_batch(query(&cypher), batch).await?;
+            edges += batch.len() as i64;
+        }
+        Ok((nodes, edges))
+    }
+
+    // Remove everything indexed for a repo, a batch per transaction so large repos don't build one
+    // huge delete. Nodes are found by their `repo::` id prefix, Modules and Directories by `repo`.
+    // Returns the number of nodes deleted
+    async fn delete_repo(&self, repo_name: &str) -> Result<i64> {
+        const BATCH: i64 = 5000;
+        let prefix = format!("{}::", repo_name);
+        let mut deleted = 0;
+        let by_prefix = ["Function", "Class", "Route", "Command", "Embedded", "Symbol", "Todo", "Document", "File", "Dependency", "Submodule", "Entrypoint"]
+            .map(|label| format!("MATCH (n:{}) WHERE n.id STARTS WITH $prefix WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted", label));
+        let by_repo = [
+            "MATCH (n:Module {repo: $repo}) WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted".to_string(),
+            "MATCH (n:Directory {repo: $repo}) WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted".to_string(),
+            "MATCH (n:Repo {name: $repo}) WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted".to_string(),
+            // Packages are shared between repos; drop the ones no other repo uses
+            "MATCH (n:Package) WHERE NOT (n)<-[:USES_PACKAGE]-() WITH n LIMIT $limit DELETE n RETURN count(*) AS deleted".to_string(),
+        ];
+        for cypher in by_prefix.iter().chain(&by_repo) {
+            loop {
+                let rows = self.execute(
+                    query(cypher).param("prefix", prefix.clone()).param("repo", repo_name).param("limit", BATCH)
+                ).await?;
+                let batch = rows.first().and_then(|row| row.get::<i64>("deleted").ok()).unwrap_or(0);
+                deleted += batch;
+                if batch < BATCH { break; }
+            }
+        }
+        Ok(deleted)
+    }
+
+    async fn remove_stale(&self, repo_name: &str, files: Option<&[String]>, seen: &HashSet<String>) -> Result<i64> {
+        let prefix = format!("{}::", repo_name);
+        let file_ids: Vec<String> = files.unwrap_or_default().iter().map(|f| format!("{}{}", prefix, f)).collect();
+        let mut removed = 0;
+        for label in ["Function", "Class", "Route", "Command", "Embedded", "Symbol", "Todo", "Document", "File"] {
+            // The file's own node and the ones under it are looked up separately: an OR of the two
+            // keeps the id index from being used, scanning the whole label for every file
+            let queries = match files {
+                Some(_) => vec![
+                    query(&format!("UNWIND $fids AS fid MATCH (n:{} {{id: fid}}) RETURN n.id AS id", label))
+                        .param("fids", file_ids.clone()),
+                    query(&format!("UNWIND $fids AS fid MATCH (n:{}) WHERE n.id STARTS WITH fid + '::' RETURN n.id AS id", label))
+                        .param("fids", file_ids.clone()),
+                ],
+                None => vec![
+                    query(&format!("MATCH (n:{}) WHERE n.id STARTS WITH $prefix RETURN n.id AS id", label))
+                        .param("prefix", prefix.clone()),
+                ],
+            };
+            let mut rows = vec![];
+            for q in queries {
+                rows.extend(self.execute(q).await?);
+            }
+            let stale: Vec<HashMap<String, BoltType>> = rows.iter()
+                .filter_map(|row| row.get::<String>("id").ok())
+                .filter(|id| !seen.contains(id))
+                .map(|id| HashMap::from([("id".to_string(), BoltType::from(id))]))
+                .collect();
+            if stale.is_empty() { continue; }
+            removed += stale.len() as i64;
+            self.run_batch(
+                query(&format!("UNWIND $batch AS s MATCH (n:{} {{id: s.id}}) DETACH DELETE n", label)),
+                &stale,
+            ).await?;
+        }
+        if removed == 0 {
+            return Ok(0);
+        }
+        // Directories left without files, then modules nothing imports, defines or nests under,
+        // innermost first
+        self.run(
+            query("MATCH (d:Directory {repo: $repo}) WHERE NOT (d)-[:CONTAINS*]->(:File) DETACH DELETE d")
                 .param("repo", repo_name)
         ).await?;
-        let mut langs = serde_json::Map::new();
-        while let Some(row) = result.next().await? {
-            let lang = row.get::<String>("lang").unwrap_or_default();
-            let cnt = row.get::<i64>("cnt").unwrap_or(0);
-            langs.insert(lang, json!(cnt));
+        loop {
+            let rows = self.execute(
+                query("MATCH (m:Module {repo: $repo}) \
+                       WHERE NOT ()-[:IMPORTS_FROM]->(m) AND NOT (m)-[:HAS_FILE]->() AND NOT (m)-[:HAS_MODULE]->() \
+                       DETACH DELETE m RETURN count(*) AS deleted")
+                    .param("repo", repo_name)
+            ).await?;
+            if rows.first().and_then(|row| row.get::<i64>("deleted").ok()).unwrap_or(0) == 0 { break; }
         }
-        Ok(Value::Object(langs))
+        Ok(removed)
+    }
+
+    // The record lives on the snapshot graph's own Repo node
+    async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        self.run(
+            query("MERGE (r:Repo {name: $key}) \
+                   SET r.snapshot_of = $repo, r.snapshot_id = $id, r.created_at = $created_at, \
+                       r.snapshot_files = $files, r.snapshot_symbols = $symbols, r.snapshot_commit = $commit")
+                .param("key", snapshot.key.clone())
+                .param("repo", snapshot.repo.clone())
+                .param("id", snapshot.id.clone())
+                .param("created_at", snapshot.created_at)
+                .param("files", snapshot.files)
+                .param("symbols", snapshot.symbols)
+                .param("commit", snapshot.commit.clone())
+        ).await
+    }
+
+    async fn list_snapshots(&self, repo_name: &str) -> Result<Vec<Snapshot>> {
+        let rows = self.execute(
+            query("MATCH (r:Repo {snapshot_of: $repo}) \
+                   RETURN r.name AS key, r.snapshot_id AS id, r.created_at AS created_at, \
+                          r.snapshot_files AS files, r.snapshot_symbols AS symbols, \
+                          r.snapshot_commit AS commit \
+                   ORDER BY created_at DESC, id DESC")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.into_iter()
+            .map(|row| Snapshot {
+                repo: repo_name.to_string(),
+                id: row.get::<String>("id").unwrap_or_default(),
+                key: row.get::<String>("key").unwrap_or_default(),
+                created_at: row.get::<i64>("created_at").unwrap_or(0),
+                files: row.get::<i64>("files").unwrap_or(0),
+                symbols: row.get::<i64>("symbols").unwrap_or(0),
+                commit: row.get::<Option<String>>("commit").ok().flatten(),
+            })
+            .collect())
+    }
+
+    async fn indexed_commit(&self, repo_name: &str) -> Result<Option<String>> {
+        let rows = self.execute(
+            query("MATCH (r:Repo {name: $repo}) RETURN r.indexed_commit AS commit").param("repo", repo_name)
+        ).await?;
+        Ok(rows.first().and_then(|row| row.get::<String>("commit").ok()))
+    }
+
+    async fn set_indexed_commit(&self, repo_name: &str, commit: &str) -> Result<()> {
+        self.run(
+            query("MERGE (r:Repo {name: $repo}) SET r.indexed_commit = $commit")
+                .param("repo", repo_name)
+                .param("commit", commit)
+        ).await
     }
+
+    async fn availability(&self, repo_name: &str) -> Result<Option<String>> {
+        let rows = self.execute(
+            query("MATCH (r:Repo {name: $repo}) RETURN r.availability AS availability").param("repo", repo_name)
+        ).await?;
+        Ok(rows.first().and_then(|row| row.get::<String>("availability").ok()))
+    }
+
+    async fn set_availability(&self, repo_name: &str, availability: &str) -> Result<()> {
+        self.run(
+            query("MERGE (r:Repo {name: $repo}) SET r.availability = $availability, r.availability_changed_at = timestamp()")
+                .param("repo", repo_name)
+                .param("availability", availability)
+        ).await
+    }
+
+    // Not attached to the Repo node, so deleting the repo leaves it
+    async fn classification_feedback(&self, repo_name: &str) -> Result<Option<Feedback>> {
+        let rows = self.execute(
+            query("MATCH (c:ClassificationFeedback {repo: $repo}) RETURN c.feedback AS feedback").param("repo", repo_name)
+        ).await?;
+        Ok(rows.first()
+            .and_then(|row| row.get::<String>("feedback").ok())
+            .and_then(|feedback| serde_json::from_str(&feedback).ok()))
+    }
+
+    async fn set_classification_feedback(&self, repo_name: &str, feedback: &Feedback) -> Result<()> {
+        self.run(
+            query("MERGE (c:ClassificationFeedback {repo: $repo}) \
+                   SET c.feedback = $feedback, c.doc_type = $doc_type, c.given_at = $given_at")
+                .param("repo", repo_name)
+                .param("feedback", serde_json::to_string(feedback).unwrap_or_default())
+                .param("doc_type", feedback.doc_type.clone())
+                .param("given_at", feedback.given_at)
+        ).await
+    }
+
+    async fn all_classification_feedback(&self) -> Result<Vec<Feedback>> {
+        let rows = self.execute(query("MATCH (c:ClassificationFeedback) RETURN c.feedback AS feedback")).await?;
+        Ok(rows.iter()
+            .filter_map(|row| row.get::<String>("feedback").ok())
+            .filter_map(|feedback| serde_json::from_str(&feedback).ok())
+            .collect())
+    }
+
+    async fn file_hashes(&self, repo_name: &str) -> Result<HashMap<String, String>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo}) RETURN f.path AS path, coalesce(f.content_hash, '') AS hash")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.iter()
+            .filter_map(|row| Some((row.get::<String>("path").ok()?, row.get::<String>("hash").unwrap_or_default())))
+            .collect())
+    }
+
+    // (:Module)-[:DEFINED_BY]->(:File) for every imported module that resolves to repo files, so
+    // imports can be followed to code. A relative module name shared by several directories links
+    // to each file it resolves to
+    async fn link_modules(&self, repo_name: &str) -> Result<()> {
+        let mut defined: Vec<(String, String)> = self.resolved_imports(repo_name).await?.into_iter()
+            .flat_map(|(_, module, _, targets)| targets.into_iter().map(move |t| (module.clone(), t)))
+            .collect();
+        defined.sort();
+        defined.dedup();
+        self.run(
+            query("MATCH (:Module {repo: $repo})-[d:DEFINED_BY]->() DELETE d").param("repo", repo_name)
+        ).await?;
+        let batch: Vec<HashMap<String, BoltType>> = defined.into_iter()
+            .map(|(module, file)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("module".into(), module.into());
+                m.insert("fid".into(), format!("{}::{}", repo_name, file).into());
+                m
+            })
+            .collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run_batch(
+            query("UNWIND $batch AS d MATCH (m:Module {name: d.module, repo: $repo}) MATCH (f:File {id: d.fid}) \
+                   MERGE (m)-[:DEFINED_BY]->(f)")
+                .param("repo", repo_name),
+            &batch,
+        ).await
+    }
+
+    // (:Repo)-[:DEPENDS_ON]->(:Dependency) for every dependency a manifest declares
+    async fn set_declared_dependencies(&self, repo_name: &str, dependencies: &[Dependency]) -> Result<()> {
+        self.run(query("MATCH (d:Dependency {repo: $repo}) DETACH DELETE d").param("repo", repo_name)).await?;
+        let batch: Vec<HashMap<String, BoltType>> = dependencies.iter()
+            .map(|d| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), dependency_id(repo_name, d).into());
+                m.insert("name".into(), d.name.clone().into());
+                m.insert("version_req".into(), d.version_req.clone().into());
+                m.insert("ecosystem".into(), d.ecosystem.clone().into());
+                m.insert("kind".into(), d.kind.clone().into());
+                m.insert("manifest".into(), d.manifest.clone().into());
+                m.insert("package".into(), d.package.clone().into());
+                m.insert("resolved_version".into(), d.resolved_version.clone().into());
+                m.insert("transitive".into(), d.transitive.map(|n| n as i64).into());
+                m
+            })
+            .collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run_batch(
+            query("MERGE (r:Repo {name: $repo}) WITH r UNWIND $batch AS d \
+                   MERGE (n:Dependency {id: d.id}) \
+                   SET n.repo = $repo, n.name = d.name, n.version_req = d.version_req, n.ecosystem = d.ecosystem, \
+                       n.kind = d.kind, n.manifest = d.manifest, n.package = d.package, \
+                       n.resolved_version = d.resolved_version, n.transitive = d.transitive \
+                   MERGE (r)-[:DEPENDS_ON]->(n)")
+                .param("repo", repo_name),
+            &batch,
+        ).await
+    }
+
+    async fn set_license(&self, repo_name: &str, license: &License) -> Result<()> {
+        self.run(
+            query("MERGE (r:Repo {name: $repo}) \
+                   SET r.license = $spdx, r.license_source = $source, r.license_category = $category, \
+                       r.license_headers = $headers")
+                .param("repo", repo_name)
+                .param("spdx", license.spdx.clone())
+                .param("source", license.source.clone())
+                .param("category", license.category.clone())
+                .param("headers", serde_json::to_string(&license.headers).unwrap_or_default())
+        ).await
+    }
+
+    // (:Repo)-[:HAS_ENTRYPOINT]->(:Entrypoint), and (:File)-[:DECLARES_ENTRYPOINT]-> it when the file
+    // it was found in is indexed
+    async fn set_entrypoints(&self, repo_name: &str, entrypoints: &[Entrypoint]) -> Result<()> {
+        self.run(query("MATCH (e:Entrypoint {repo: $repo}) DETACH DELETE e").param("repo", repo_name)).await?;
+        let batch: Vec<HashMap<String, BoltType>> = entrypoints.iter()
+            .map(|e| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::entrypoint::{}::{}::{}", repo_name, e.file, e.kind, e.name).into());
+                m.insert("fid".into(), format!("{}::{}", repo_name, e.file).into());
+                m.insert("file".into(), e.file.clone().into());
+                m.insert("line".into(), (e.line as i64).into());
+                m.insert("kind".into(), e.kind.clone().into());
+                m.insert("name".into(), e.name.clone().into());
+                m.insert("target".into(), e.target.clone().into());
+                m
+            })
+            .collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run_batch(
+            query("MERGE (r:Repo {name: $repo}) WITH r UNWIND $batch AS e \
+                   MERGE (n:Entrypoint {id: e.id}) \
+                   SET n.repo = $repo, n.file = e.file, n.line = e.line, n.kind = e.kind, n.name = e.name, n.target = e.target \
+                   MERGE (r)-[:HAS_ENTRYPOINT]->(n) \
+                   WITH n, e MATCH (f:File {id: e.fid}) \
+                   MERGE (f)-[:DECLARES_ENTRYPOINT]->(n)")
+                .param("repo", repo_name),
+            &batch,
+        ).await
+    }
+
+    // (:Repo)-[:HAS_SUBMODULE]->(:Submodule), and on to the repo a separately indexed one became
+    // through INDEXED_AS
+    async fn set_submodules(&self, repo_name: &str, submodules: &[Submodule]) -> Result<()> {
+        self.run(query("MATCH (s:Submodule {repo: $repo}) DETACH DELETE s").param("repo", repo_name)).await?;
+        let batch: Vec<HashMap<String, BoltType>> = submodules.iter()
+            .map(|s| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::submodule::{}", repo_name, s.path).into());
+                m.insert("path".into(), s.path.clone().into());
+                m.insert("url".into(), s.url.clone().into());
+                m.insert("commit".into(), s.commit.clone().into());
+                m.insert("indexed_as".into(), s.repo.clone().into());
+                m
+            })
+            .collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run_batch(
+            query("MERGE (r:Repo {name: $repo}) WITH r UNWIND $batch AS s \
+                   MERGE (n:Submodule {id: s.id}) \
+                   SET n.repo = $repo, n.path = s.path, n.url = s.url, n.commit = s.commit, n.indexed_as = s.indexed_as \
+                   MERGE (r)-[:HAS_SUBMODULE]->(n) \
+                   WITH n, s WHERE s.indexed_as IS NOT NULL \
+                   MERGE (c:Repo {name: s.indexed_as}) \
+                   MERGE (n)-[:INDEXED_AS]->(c)")
+                .param("repo", repo_name),
+            &batch,
+        ).await
+    }
+
+    async fn set_file_history(&self, repo_name: &str, history: &HashMap<String, FileHistory>) -> Result<()> {
+        let batch: Vec<HashMap<String, BoltType>> = history.iter()
+            .map(|(path, h)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}", repo_name, path).into());
+                m.insert("last_modified".into(), h.last_modified.into());
+                m.insert("last_commit".into(), h.last_commit.clone().into());
+                m.insert("commit_count".into(), (h.commit_count as i64).into());
+                m.insert("contributors".into(), h.contributors.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>().into());
+                m.insert("contributor_commits".into(), h.contributors.iter().map(|(_, n)| *n as i64).collect::<Vec<_>>().into());
+                m
+            })
+            .collect();
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.run_batch(
+            query("UNWIND $batch AS h MATCH (f:File {id: h.id}) \
+                   SET f.last_modified = h.last_modified, f.last_commit = h.last_commit, f.commit_count = h.commit_count, \
+                       f.contributors = h.contributors, f.contributor_commits = h.contributor_commits"),
+            &batch,
+        ).await
+    }
+
+    // (section)-[:MENTIONS]->(:Function|:Class) for the names a Markdown section's code spans and
+    // examples use, where the name picks out at most MAX_MENTION_TARGETS symbols
+    async fn link_mentions(&self, repo_name: &str) -> Result<()> {
+        self.run(
+            query("MATCH (:File {repo: $repo})-[:CONTAINS]->(:Symbol {kind: 'section'})-[m:MENTIONS]->() DELETE m")
+                .param("repo", repo_name)
+        ).await?;
+        self.run(
+            query("MATCH (:File {repo: $repo})-[:CONTAINS]->(s:Symbol {kind: 'section'}) \
+                   UNWIND coalesce(s.mentions, []) AS name \
+                   MATCH (:File {repo: $repo})-[:CONTAINS]->(t {name: name}) WHERE t:Function OR t:Class \
+                   WITH s, name, collect(t) AS targets WHERE size(targets) <= $max \
+                   UNWIND targets AS t \
+                   MERGE (s)-[:MENTIONS]->(t)")
+                .param("repo", repo_name)
+                .param("max", MAX_MENTION_TARGETS as i64)
+        ).await
+    }
+
+    // (:File)-[:USES_PACKAGE]->(:Package) for every external package the repo imports. Package
+    // nodes are shared by all repos, so "who uses X" is one hop from the package
+    async fn link_packages(&self, repo_name: &str) -> Result<()> {
+        let uses = package_uses(&self.resolved_imports(repo_name).await?);
+        self.run(
+            query("MATCH (:File {repo: $repo})-[u:USES_PACKAGE]->() DELETE u").param("repo", repo_name)
+        ).await?;
+        let batch: Vec<HashMap<String, BoltType>> = uses.iter()
+            .flat_map(|(package, files)| files.iter().map(move |file| (package, file)))
+            .map(|(package, file)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("fid".into(), format!("{}::{}", repo_name, file).into());
+                m.insert("package".into(), package.clone().into());
+                m
+            })
+            .collect();
+        if !batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS u MATCH (f:File {id: u.fid}) \
+                       MERGE (p:Package {name: u.package}) MERGE (f)-[:USES_PACKAGE]->(p)"),
+                &batch,
+            ).await?;
+        }
+        self.run(query("MATCH (p:Package) WHERE NOT (p)<-[:USES_PACKAGE]-() DELETE p")).await
+    }
+
+    async fn get_declared_dependencies(&self, repo_name: &str) -> Result<Vec<Dependency>> {
+        let rows = self.execute(
+            query("MATCH (d:Dependency {repo: $repo}) \
+                   RETURN d.name AS name, d.version_req AS version_req, d.ecosystem AS ecosystem, d.kind AS kind, \
+                          d.manifest AS manifest, d.package AS package, d.resolved_version AS resolved_version, \
+                          d.transitive AS transitive \
+                   ORDER BY manifest, name")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.into_iter()
+            .map(|row| Dependency {
+                name: row.get::<String>("name").unwrap_or_default(),
+                version_req: row.get::<String>("version_req").unwrap_or_default(),
+                ecosystem: row.get::<String>("ecosystem").unwrap_or_default(),
+                kind: row.get::<String>("kind").unwrap_or_default(),
+                manifest: row.get::<String>("manifest").unwrap_or_default(),
+                package: row.get::<Option<String>>("package").ok().flatten(),
+                resolved_version: row.get::<Option<String>>("resolved_version").ok().flatten(),
+                transitive: row.get::<Option<i64>>("transitive").ok().flatten().map(|n| n as usize),
+            })
+            .collect())
+    }
+
+    async fn get_submodules(&self, repo_name: &str) -> Result<Vec<Submodule>> {
+        let rows = self.execute(
+            query("MATCH (s:Submodule {repo: $repo}) \
+                   RETURN s.path AS path, s.url AS url, s.commit AS commit, s.indexed_as AS indexed_as ORDER BY path")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.into_iter()
+            .map(|row| Submodule {
+                path: row.get::<String>("path").unwrap_or_default(),
+                url: row.get::<Option<String>>("url").ok().flatten(),
+                commit: row.get::<String>("commit").unwrap_or_default(),
+                repo: row.get::<Option<String>>("indexed_as").ok().flatten(),
+            })
+            .collect())
+    }
+
+    async fn get_license(&self, repo_name: &str) -> Result<Option<License>> {
+        let rows = self.execute(
+            query("MATCH (r:Repo {name: $repo}) WHERE r.license_category IS NOT NULL \
+                   RETURN r.license AS spdx, r.license_source AS source, r.license_category AS category, \
+                          r.license_headers AS headers")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.first().map(|row| License {
+            spdx: row.get::<Option<String>>("spdx").ok().flatten(),
+            source: row.get::<Option<String>>("source").ok().flatten(),
+            category: row.get::<String>("category").unwrap_or_default(),
+            headers: row.get::<String>("headers").ok().and_then(|h| serde_json::from_str(&h).ok()).unwrap_or_default(),
+        }))
+    }
+
+    async fn get_entrypoints(&self, repo_name: &str) -> Result<Vec<Entrypoint>> {
+        let rows = self.execute(
+            query("MATCH (e:Entrypoint {repo: $repo}) \
+                   RETURN e.file AS file, e.line AS line, e.kind AS kind, e.name AS name, e.target AS target \
+                   ORDER BY file, line, kind, name")
+                .param("repo", repo_name)
+        ).await?;
+        Ok(rows.into_iter()
+            .map(|row| Entrypoint {
+                file: row.get::<String>("file").unwrap_or_default(),
+                line: row.get::<i64>("line").unwrap_or(0) as usize,
+                kind: row.get::<String>("kind").unwrap_or_default(),
+                name: row.get::<String>("name").unwrap_or_default(),
+                target: row.get::<Option<String>>("target").ok().flatten(),
+            })
+            .collect())
+    }
+
+    async fn get_packages(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:USES_PACKAGE]->(p:Package) RETURN p.name AS package, collect(f.path) AS files")
+                .param("repo", repo_name)
+        ).await?;
+        let mut uses = BTreeMap::new();
+        for row in rows {
+            let mut files = row.get::<Vec<String>>("files").unwrap_or_default();
+            files.sort();
+            uses.insert(row.get::<String>("package").unwrap_or_default(), files);
+        }
+        Ok(package_list(uses))
+    }
+
+    async fn package_users(&self, package: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File)-[:USES_PACKAGE]->(:Package {name: $package}) \
+                   RETURN f.repo AS repo, collect(f.path) AS files ORDER BY repo")
+                .param("package", package)
+        ).await?;
+        Ok(rows.into_iter()
+            .map(|row| {
+                let mut files = row.get::<Vec<String>>("files").unwrap_or_default();
+                files.sort();
+                json!({ "repo": row.get::<String>("repo").unwrap_or_default(), "files": files })
+            })
+            .collect())
+    }
+
+    async fn link_implementations(&self, repo_name: &str, implementations: &[Implementation]) -> Result<()> {
+        let implements_batch: Vec<HashMap<String, BoltType>> = implementations.iter()
+            .map(|imp| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("type".into(), imp.type_name.clone().into());
+                m.insert("trait".into(), imp.trait_name.clone().into());
+                m
+            })
+            .collect();
+
+        if !implements_batch.is_empty() {
+            self.run_batch(
+                query("UNWIND $batch AS i \
+                       MATCH (t:Class {name: i.type})<-[:CONTAINS]-(tf:File {repo: $repo}) \
+                       MATCH (tr:Class {name: i.trait})<-[:CONTAINS]-(trf:File {repo: $repo}) \
+                       MERGE (t)-[:IMPLEMENTS]->(tr)")
+                    .param("repo", repo_name),
+                &implements_batch,
+            ).await?;
+        }
+        Ok(())
+    }
+
+    // Ranked search over symbol names, docstrings and signatures; `text` uses Lucene syntax
+    // (`auth*`, `"rate limit"`, `token AND refresh`)
+    async fn search_symbols(&self, repo_name: &str, text: &str, limit: i64) -> Result<Vec<Value>> {
+        if self.dialect == Dialect::Memgraph {
+            return Err(unsupported(self.backend(), "full-text search"));
+        }
+        let rows = self.execute(
+            query("CALL db.index.fulltext.queryNodes('symbol_text', $text) YIELD node, score \
+                   MATCH (f:File {repo: $repo})-[:CONTAINS]->(node) \
+                   RETURN node.name AS name, node.kind AS kind, node.signature AS sig, node.docstring AS doc, \
+                          node.parent_class AS parent, f.path AS file, node.line_start AS line, score \
+                   ORDER BY score DESC LIMIT $limit")
+                .param("text", text)
+                .param("repo", repo_name)
+                .param("limit", limit)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "kind": row.get::<String>("kind").unwrap_or_default(),
+                "signature": row.get::<String>("sig").unwrap_or_default(),
+                "docstring": row.get::<String>("doc").unwrap_or_default(),
+                "parent_class": row.get::<String>("parent").ok().filter(|p| !p.is_empty()),
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "score": row.get::<f64>("score").unwrap_or(0.0),
+            }));
+        }
+        Ok(out)
+    }
+
+    // Store one embedding per symbol (addressed by file, name and start line) and make sure the
+    // vector index exists with the embeddings' dimension. Returns how many symbols were matched
+    async fn set_embeddings(&self, repo_name: &str, embeddings: &[SymbolEmbedding]) -> Result<i64> {
+        if self.dialect == Dialect::Memgraph {
+            return Err(unsupported(self.backend(), "embeddings"));
+        }
+        let Some(dimensions) = embeddings.first().map(|e| e.vector.len()) else { return Ok(0) };
+        self.run(query(&format!(
+            "CREATE VECTOR INDEX symbol_embedding IF NOT EXISTS FOR (n:HasEmbedding) ON n.embedding \
+             OPTIONS {{indexConfig: {{`vector.dimensions`: {}, `vector.similarity_function`: 'cosine'}}}}",
+            dimensions
+        ))).await?;
+        let batch: Vec<HashMap<String, BoltType>> = embeddings.iter()
+            .filter(|e| e.vector.len() == dimensions)
+            .map(|e| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}::{}:{}", repo_name, e.file, e.name, e.line).into());
+                m.insert("vector".into(), e.vector.iter().map(|&v| v as f64).collect::<Vec<f64>>().into());
+                m
+            })
+            .collect();
+        let mut matched = 0;
+        for chunk in batch.chunks(self.batch_size.max(1)) {
+            let rows = self.execute(
+                query("UNWIND $batch AS e \
+                       CALL { \
+                           WITH e MATCH (n:Function {id: e.id}) RETURN n \
+                           UNION WITH e MATCH (n:Class {id: e.id}) RETURN n \
+                           UNION WITH e MATCH (n:Route {id: e.id}) RETURN n \
+                           UNION WITH e MATCH (n:Command {id: e.id}) RETURN n \
+                           UNION WITH e MATCH (n:Embedded {id: e.id}) RETURN n \
+                           UNION WITH e MATCH (n:Symbol {id: e.id}) RETURN n \
+                       } \
+                       SET n:HasEmbedding, n.embedding = e.vector \
+                       RETURN count(n) AS matched")
+                    .param("batch", chunk.to_vec())
+            ).await?;
+            matched += rows.first().and_then(|row| row.get::<i64>("matched").ok()).unwrap_or(0);
+        }
+        Ok(matched)
+    }
+
+    // Nearest symbols to `embedding` in the repo by cosine similarity
+    async fn similar_symbols(&self, repo_name: &str, embedding: &[f32], k: i64) -> Result<Vec<Value>> {
+        if self.dialect == Dialect::Memgraph {
+            return Err(unsupported(self.backend(), "similarity search"));
+        }
+        let rows = self.execute(
+            // The index spans every repo, so over-fetch before filtering to this one
+            query("CALL db.index.vector.queryNodes('symbol_embedding', $fetch, $embedding) YIELD node, score \
+                   MATCH (f:File {repo: $repo})-[:CONTAINS]->(node) \
+                   RETURN node.name AS name, node.kind AS kind, node.signature AS sig, node.docstring AS doc, \
+                          f.path AS file, node.line_start AS line, score \
+                   ORDER BY score DESC LIMIT $k")
+                .param("fetch", k * 10)
+                .param("embedding", embedding.iter().map(|&v| v as f64).collect::<Vec<f64>>())
+                .param("repo", repo_name)
+                .param("k", k)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "kind": row.get::<String>("kind").unwrap_or_default(),
+                "signature": row.get::<String>("sig").unwrap_or_default(),
+                "docstring": row.get::<String>("doc").unwrap_or_default(),
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "score": row.get::<f64>("score").unwrap_or(0.0),
+            }));
+        }
+        Ok(out)
+    }
+
+    // Call tree rooted at every function matching `symbol` (id, qualified name or name), following
+    // CALLS up to `depth` hops: towards callers when `callers` is set, otherwise towards callees.
+    // A function already on the current branch is emitted with `recursive: true` and not expanded
+    async fn call_chain(&self, repo_name: &str, symbol: &str, depth: usize, callers: bool) -> Result<Vec<Value>> {
+        let hops = format!("[:CALLS*1..{}]", depth.max(1));
+        let pattern = if callers { format!("(root)<-{}-(:Function)", hops) } else { format!("(root)-{}->(:Function)", hops) };
+        let rows = self.execute(
+            query(&format!(
+                "MATCH (:File {{repo: $repo}})-[:CONTAINS]->(root:Function) \
+                 WHERE root.id = $symbol OR root.qualified_name = $symbol OR root.name = $symbol \
+                 OPTIONAL MATCH p = {} \
+                 UNWIND coalesce(relationships(p), [null]) AS r \
+                 WITH root, collect(DISTINCT r) AS rels \
+                 RETURN root.id AS root, [r IN rels | startNode(r).id] AS froms, [r IN rels | endNode(r).id] AS tos",
+                pattern
+            ))
+                .param("repo", repo_name)
+                .param("symbol", symbol)
+        ).await?;
+
+        let mut roots = vec![];
+        let mut next: HashMap<String, Vec<String>> = HashMap::new();
+        for row in rows {
+            roots.push(row.get::<String>("root").unwrap_or_default());
+            let froms = row.get::<Vec<String>>("froms").unwrap_or_default();
+            let tos = row.get::<Vec<String>>("tos").unwrap_or_default();
+            for (from, to) in froms.into_iter().zip(tos) {
+                let (parent, child) = if callers { (to, from) } else { (from, to) };
+                let children = next.entry(parent).or_default();
+                if !children.contains(&child) {
+                    children.push(child);
+                }
+            }
+        }
+        if roots.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut ids: Vec<String> = roots.clone();
+        ids.extend(next.values().flatten().cloned());
+        ids.sort();
+        ids.dedup();
+        let info = self.function_info(ids).await?;
+
+        let mut budget = MAX_CHAIN_NODES;
+        Ok(roots.iter()
+            .map(|root| chain_node(root, depth, &next, &info, &mut vec![], &mut budget))
+            .collect())
+    }
+
+    // The `limit` most depended-upon functions: PageRank over the CALLS graph, so a function
+    // called by other central functions outranks one called from many leaves. Caller and callee
+    // counts are returned alongside
+    async fn get_hotspots(&self, repo_name: &str, limit: usize) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (:File {repo: $repo})-[:CONTAINS]->(a:Function)-[:CALLS]->(b:Function) \
+                   WHERE a <> b RETURN DISTINCT a.id AS caller, b.id AS callee")
+                .param("repo", repo_name)
+        ).await?;
+        let edges: Vec<(String, String)> = rows.iter()
+            .map(|row| (row.get::<String>("caller").unwrap_or_default(), row.get::<String>("callee").unwrap_or_default()))
+            .collect();
+        let ranks = pagerank(&edges);
+
+        let mut ranked: Vec<(&String, &(f64, usize, usize))> = ranks.iter().collect();
+        ranked.sort_by(|a, b| b.1.0.total_cmp(&a.1.0).then_with(|| b.1.1.cmp(&a.1.1)).then_with(|| a.0.cmp(b.0)));
+        ranked.truncate(limit);
+        let info = self.function_info(ranked.iter().map(|(id, _)| id.to_string()).collect()).await?;
+        Ok(ranked.into_iter()
+            .map(|(id, &(score, callers, callees))| {
+                let mut node = info.get(id).cloned().unwrap_or_else(|| json!({ "id": id }));
+                node["pagerank"] = json!(score);
+                node["callers"] = json!(callers);
+                node["callees"] = json!(callees);
+                node
+            })
+            .collect())
+    }
+
+    // Group related symbols by label propagation over CALLS, INHERITS, IMPLEMENTS and USES_TYPE
+    // (treated as undirected), store the group as `cluster` on each symbol and on each file (the
+    // cluster most of its symbols fall in), and return the clusters largest first. Cluster ids are
+    // ranks by size, so 0 is always the largest; symbols with no relationships get none
+    async fn detect_clusters(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (fa:File {repo: $repo})-[:CONTAINS]->(a)-[:CALLS|INHERITS|IMPLEMENTS|USES_TYPE]->(b)<-[:CONTAINS]-(fb:File {repo: $repo}) \
+                   WHERE a <> b \
+                   RETURN a.id AS a, a.name AS a_name, [l IN labels(a) WHERE l <> 'HasEmbedding'][0] AS a_label, fa.path AS a_file, \
+                          b.id AS b, b.name AS b_name, [l IN labels(b) WHERE l <> 'HasEmbedding'][0] AS b_label, fb.path AS b_file")
+                .param("repo", repo_name)
+        ).await?;
+        // id -> (name, label, file)
+        let mut symbols: HashMap<String, (String, String, String)> = HashMap::new();
+        let mut edges: Vec<(String, String)> = vec![];
+        for row in rows {
+            let (a, b) = (row.get::<String>("a").unwrap_or_default(), row.get::<String>("b").unwrap_or_default());
+            for side in ["a", "b"] {
+                symbols.entry(row.get::<String>(side).unwrap_or_default()).or_insert_with(|| (
+                    row.get::<String>(&format!("{}_name", side)).unwrap_or_default(),
+                    row.get::<String>(&format!("{}_label", side)).unwrap_or_default(),
+                    row.get::<String>(&format!("{}_file", side)).unwrap_or_default(),
+                ));
+            }
+            edges.push((a, b));
+        }
+        let clusters = label_propagation(&edges);
+
+        // Per file, the cluster holding most of its symbols (ties to the larger cluster)
+        let mut file_votes: HashMap<&str, HashMap<usize, usize>> = HashMap::new();
+        for (id, cluster) in &clusters {
+            *file_votes.entry(symbols[id].2.as_str()).or_default().entry(*cluster).or_default() += 1;
+        }
+        let file_clusters: HashMap<&str, usize> = file_votes.iter()
+            .map(|(file, votes)| {
+                let best = votes.iter().max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0))).map(|(c, _)| *c).unwrap_or(0);
+                (*file, best)
+            })
+            .collect();
+
+        self.run(
+            query("MATCH (f:File {repo: $repo}) REMOVE f.cluster WITH f MATCH (f)-[:CONTAINS]->(n) REMOVE n.cluster")
+                .param("repo", repo_name)
+        ).await?;
+        for label in ["Function", "Class", "Route", "Command", "Embedded", "Symbol"] {
+            let batch: Vec<HashMap<String, BoltType>> = clusters.iter()
+                .filter(|(id, _)| symbols[*id].1 == label)
+                .map(|(id, cluster)| {
+                    let mut m: HashMap<String, BoltType> = HashMap::new();
+                    m.insert("id".into(), id.clone().into());
+                    m.insert("cluster".into(), (*cluster as i64).into());
+                    m
+                })
+                .collect();
+            if batch.is_empty() { continue; }
+            self.run_batch(
+                query(&format!("UNWIND $batch AS c MATCH (n:{} {{id: c.id}}) SET n.cluster = c.cluster", label)),
+                &batch,
+            ).await?;
+        }
+        let file_batch: Vec<HashMap<String, BoltType>> = file_clusters.iter()
+            .map(|(file, cluster)| {
+                let mut m: HashMap<String, BoltType> = HashMap::new();
+                m.insert("id".into(), format!("{}::{}", repo_name, file).into());
+                m.insert("cluster".into(), (*cluster as i64).into());
+                m
+            })
+            .collect();
+        if !file_batch.is_empty() {
+            self.run_batch(query("UNWIND $batch AS c MATCH (f:File {id: c.id}) SET f.cluster = c.cluster"), &file_batch).await?;
+        }
+
+        let count = clusters.values().max().map_or(0, |c| c + 1);
+        let mut out = vec![];
+        for cluster in 0..count {
+            let mut names: Vec<&str> = clusters.iter()
+                .filter(|(_, c)| **c == cluster)
+                .map(|(id, _)| symbols[id].0.as_str())
+                .collect();
+            names.sort();
+            let mut files: Vec<&str> = file_clusters.iter().filter(|(_, c)| **c == cluster).map(|(f, _)| *f).collect();
+            files.sort();
+            out.push(json!({ "cluster": cluster, "size": names.len(), "files": files, "symbols": names }));
+        }
+        Ok(out)
+    }
+
+    // User-supplied Cypher (see `readonly_violation`), with `$repo` bound to the repo. It runs in a
+    // transaction that is always rolled back, so a write the clause scan misses is still undone.
+    // Returns up to `max_rows` rows and whether more were available
+    async fn run_readonly_cypher(&self, repo_name: &str, cypher: &str, max_rows: usize, timeout: Duration) -> Result<(Vec<Value>, bool)> {
+        let mut txn = self.current().start_txn().await?;
+        let read = async {
+            let mut stream = txn.execute(query(cypher).param("repo", repo_name)).await?;
+            let mut rows = vec![];
+            while let Some(row) = stream.next(txn.handle()).await? {
+                if rows.len() == max_rows {
+                    return Ok((rows, true));
+                }
+                rows.push(row.to::<Value>().unwrap_or(Value::Null));
+            }
+            Ok((rows, false))
+        };
+        let result = tokio::time::timeout(timeout, read).await
+            .unwrap_or_else(|_| Err(Error::UnexpectedMessage(format!("query exceeded {}s", timeout.as_secs()))));
+        let _ = txn.rollback().await;
+        result
+    }
+
+    async fn get_docs(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:DOCUMENTS]->(d:Document) \
+                   OPTIONAL MATCH (f)-[:CONTAINS]->(s:Symbol {kind: 'section'}) \
+                   OPTIONAL MATCH (s)-[:MENTIONS]->(t)<-[:CONTAINS]-(tf:File) \
+                   RETURN f.path AS path, d.kind AS kind, d.title AS title, d.heading_count AS headings, \
+                          d.word_count AS words, d.code_fences AS fences, s.name AS section, s.signature AS heading, \
+                          s.parent_class AS parent, s.line_start AS line, s.docstring AS summary, \
+                          t.name AS target, t.kind AS target_kind, tf.path AS target_file, t.line_start AS target_line \
+                   ORDER BY path, line, target_file, target_line")
+                .param("repo", repo_name)
+        ).await?;
+        let mut docs: Vec<Value> = vec![];
+        for row in rows {
+            let path = row.get::<String>("path").unwrap_or_default();
+            if docs.last().is_none_or(|d| d["path"] != path.as_str()) {
+                docs.push(json!({
+                    "path": path,
+                    "kind": row.get::<String>("kind").unwrap_or_default(),
+                    "title": row.get::<String>("title").ok().filter(|t| !t.is_empty()),
+                    "heading_count": row.get::<i64>("headings").unwrap_or(0),
+                    "word_count": row.get::<i64>("words").unwrap_or(0),
+                    "code_fences": row.get::<String>("fences").ok()
+                        .and_then(|f| serde_json::from_str::<Value>(&f).ok())
+                        .unwrap_or_else(|| json!({})),
+                    "sections": [],
+                }));
+            }
+            let Some(sections) = docs.last_mut().and_then(|d| d["sections"].as_array_mut()) else { continue };
+            let Ok(name) = row.get::<String>("section") else { continue };
+            let line = row.get::<i64>("line").unwrap_or(0);
+            if sections.last().is_none_or(|s| s["line"] != line) {
+                let heading = row.get::<String>("heading").unwrap_or_default();
+                sections.push(json!({
+                    "name": name,
+                    "level": heading.chars().take_while(|c| *c == '#').count(),
+                    "parent": row.get::<String>("parent").ok().filter(|p| !p.is_empty()),
+                    "line": line,
+                    "summary": row.get::<String>("summary").ok().filter(|s| !s.is_empty()),
+                    "mentions": [],
+                }));
+            }
+            if let (Some(section), Ok(target)) = (sections.last_mut(), row.get::<String>("target")) {
+                if let Some(mentions) = section["mentions"].as_array_mut() {
+                    mentions.push(json!({
+                        "name": target,
+                        "kind": row.get::<String>("target_kind").unwrap_or_default(),
+                        "file": row.get::<String>("target_file").unwrap_or_default(),
+                        "line": row.get::<i64>("target_line").unwrap_or(0),
+                    }));
+                }
+            }
+        }
+        Ok(docs)
+    }
+
+    async fn get_todos(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:HAS_TODO]->(t:Todo) \
+                   OPTIONAL MATCH (s)-[:HAS_TODO]->(t) WHERE NOT s:File \
+                   RETURN f.path AS file, t.line AS line, t.tag AS tag, t.text AS text, s.name AS symbol \
+                   ORDER BY file, line")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "tag": row.get::<String>("tag").unwrap_or_default(),
+                "text": row.get::<String>("text").unwrap_or_default(),
+                "symbol": row.get::<String>("symbol").ok(),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_routes(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(r:Route) \
+                   OPTIONAL MATCH (r)-[:HANDLED_BY]->(h)<-[:CONTAINS]-(hf:File) \
+                   RETURN f.path AS file, r.line_start AS line, r.method AS method, r.path AS path, \
+                          r.handler AS handler, hf.path AS handler_file \
+                   ORDER BY path, method")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "method": row.get::<String>("method").unwrap_or_default(),
+                "path": row.get::<String>("path").unwrap_or_default(),
+                "handler": row.get::<String>("handler").ok().filter(|h| !h.is_empty()),
+                "handler_file": row.get::<String>("handler_file").ok(),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_embedded(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(e:Embedded) \
+                   RETURN f.path AS file, e.line_start AS line, e.name AS name, e.language AS language, \
+                          e.targets AS targets, e.container AS container, e.text AS text \
+                   ORDER BY file, line")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "language": row.get::<String>("language").unwrap_or_default(),
+                "targets": row.get::<Vec<String>>("targets").unwrap_or_default(),
+                "container": row.get::<String>("container").ok().filter(|c| !c.is_empty()),
+                "text": row.get::<String>("text").unwrap_or_default(),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_commands(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(c:Command) \
+                   OPTIONAL MATCH (p:Command)-[:HAS_SUBCOMMAND]->(c) \
+                   RETURN f.path AS file, c.line_start AS line, c.name AS name, c.docstring AS doc, \
+                          c.options AS options, c.handler AS handler, p.name AS parent \
+                   ORDER BY file, line")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line": row.get::<i64>("line").unwrap_or(0),
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "docstring": row.get::<String>("doc").unwrap_or_default(),
+                "options": row.get::<String>("options").unwrap_or_default(),
+                "handler": row.get::<String>("handler").ok().filter(|h| !h.is_empty()),
+                "parent": row.get::<String>("parent").ok(),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(s) RETURN s.name AS name, s.kind AS kind, s.docstring AS doc, s.signature AS sig, s.return_type AS ret, s.visibility AS vis, s.parent_class AS parent, s.params AS params, s.decorators AS decos, s.members AS members, s.fields AS fields, s.doc_sections AS sections, s.examples AS examples, s.is_test AS is_test, s.is_async AS is_async, s.is_generator AS is_generator, s.is_unsafe AS is_unsafe, s.raises AS raises, s.qualified_name AS qname, s.type_params AS type_params, s.variants AS variants, s.tags AS tags, s.owners AS owners, s.cluster AS cluster, f.path AS file, s.line_start AS ls, s.line_end AS le, s.byte_start AS bs, s.byte_end AS be, s.col_start AS cs, s.col_end AS ce")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "name": row.get::<String>("name").unwrap_or_default(),
+                "kind": row.get::<String>("kind").unwrap_or_default(),
+                "docstring": row.get::<String>("doc").unwrap_or_default(),
+                "signature": row.get::<String>("sig").unwrap_or_default(),
+                "return_type": row.get::<String>("ret").unwrap_or_default(),
+                "visibility": row.get::<String>("vis").unwrap_or_default(),
+                "parent_class": row.get::<String>("parent").unwrap_or_default(),
+                "params": row.get::<String>("params").unwrap_or_default(),
+                "decorators": row.get::<String>("decos").unwrap_or_default(),
+                "members": row.get::<String>("members").unwrap_or_default(),
+                "fields": row.get::<String>("fields").unwrap_or_default(),
+                "doc_sections": row.get::<String>("sections").unwrap_or_default(),
+                "examples": row.get::<String>("examples").unwrap_or_default(),
+                "is_test": row.get::<bool>("is_test").unwrap_or(false),
+                "is_async": row.get::<bool>("is_async").unwrap_or(false),
+                "is_generator": row.get::<bool>("is_generator").unwrap_or(false),
+                "is_unsafe": row.get::<bool>("is_unsafe").unwrap_or(false),
+                "raises": row.get::<String>("raises").unwrap_or_default(),
+                "qualified_name": row.get::<String>("qname").unwrap_or_default(),
+                "type_params": row.get::<String>("type_params").unwrap_or_default(),
+                "variants": row.get::<String>("variants").unwrap_or_default(),
+                "tags": row.get::<Vec<String>>("tags").unwrap_or_default(),
+                "owners": row.get::<Vec<String>>("owners").unwrap_or_default(),
+                "cluster": row.get::<i64>("cluster").ok(),
+                "file": row.get::<String>("file").unwrap_or_default(),
+                "line_start": row.get::<i64>("ls").unwrap_or(0),
+                "line_end": row.get::<i64>("le").unwrap_or(0),
+                "byte_start": row.get::<i64>("bs").unwrap_or(0),
+                "byte_end": row.get::<i64>("be").unwrap_or(0),
+                "col_start": row.get::<i64>("cs").unwrap_or(0),
+                "col_end": row.get::<i64>("ce").unwrap_or(0),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo}) RETURN f.path AS path, f.language AS lang, f.test_file AS test_file, \
+                   f.parse_errors AS parse_errors, f.parse_error_locations AS parse_error_locations, f.cluster AS cluster, \
+                   f.last_modified AS last_modified, f.last_commit AS last_commit, f.commit_count AS commit_count, \
+                   f.contributors AS contributors, f.contributor_commits AS contributor_commits, \
+                   f.lines AS lines, f.loc AS loc, f.comment_lines AS comments, f.blank_lines AS blanks, \
+                   f.comment_density AS density, f.symbol_count AS symbols")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "path": row.get::<String>("path").unwrap_or_default(),
+                "language": row.get::<String>("lang").unwrap_or_default(),
+                "test_file": row.get::<bool>("test_file").unwrap_or(false),
+                "parse_errors": row.get::<i64>("parse_errors").unwrap_or(0),
+                "parse_error_locations": row.get::<Vec<String>>("parse_error_locations").unwrap_or_default(),
+                "cluster": row.get::<i64>("cluster").ok(),
+                "last_modified": row.get::<i64>("last_modified").ok(),
+                "last_commit": row.get::<String>("last_commit").ok(),
+                "commit_count": row.get::<i64>("commit_count").ok(),
+                "contributors": row.get::<Vec<String>>("contributors").unwrap_or_default().into_iter()
+                    .zip(row.get::<Vec<i64>>("contributor_commits").unwrap_or_default())
+                    .map(|(name, commits)| json!({ "name": name, "commits": commits }))
+                    .collect::<Vec<_>>(),
+                "metrics": {
+                    "lines": row.get::<i64>("lines").unwrap_or(0),
+                    "code_lines": row.get::<i64>("loc").unwrap_or(0),
+                    "comment_lines": row.get::<i64>("comments").unwrap_or(0),
+                    "blank_lines": row.get::<i64>("blanks").unwrap_or(0),
+                    "comment_density": row.get::<f64>("density").unwrap_or(0.0),
+                    "symbol_count": row.get::<i64>("symbols").unwrap_or(0),
+                },
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_directories(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (d:Directory {repo: $repo}) \
+                   OPTIONAL MATCH (d)-[:CONTAINS]->(child) \
+                   RETURN d.path AS path, d.file_count AS files, d.loc AS loc, d.symbol_count AS symbols, \
+                          [c IN collect(child) WHERE c:Directory | c.name] AS subdirs \
+                   ORDER BY path")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "path": row.get::<String>("path").unwrap_or_default(),
+                "file_count": row.get::<i64>("files").unwrap_or(0),
+                "loc": row.get::<i64>("loc").unwrap_or(0),
+                "symbol_count": row.get::<i64>("symbols").unwrap_or(0),
+                "subdirectories": row.get::<Vec<String>>("subdirs").unwrap_or_default(),
+            }));
+        }
+        Ok(out)
+    }
+
+    // Calls that matched no function and imports that matched no file, grouped by callee name and
+    // by top-level package so the dominant externals come first. Each group keeps a few example sites
+    async fn get_unresolved(&self, repo_name: &str) -> Result<Value> {
+        const EXAMPLES: usize = 5;
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(fn:Function) WHERE size(coalesce(fn.calls, [])) > 0 \
+                   OPTIONAL MATCH (fn)-[:CALLS]->(t:Function) \
+                   RETURN f.path AS file, fn.name AS name, fn.line_start AS line, fn.calls AS calls, \
+                          collect(t.name) AS resolved")
+                .param("repo", repo_name)
+        ).await?;
+        let (mut total_calls, mut unresolved_calls) = (0, 0);
+        let mut calls: Vec<(String, usize, Vec<String>)> = vec![];
+        for row in rows {
+            let resolved = row.get::<Vec<String>>("resolved").unwrap_or_default();
+            let site = format!("{}:{} {}",
+                row.get::<String>("file").unwrap_or_default(),
+                row.get::<i64>("line").unwrap_or(0),
+                row.get::<String>("name").unwrap_or_default());
+            for call in row.get::<Vec<String>>("calls").unwrap_or_default() {
+                total_calls += 1;
+                if resolved.iter().any(|r| r == call_name(&call)) {
+                    continue;
+                }
+                unresolved_calls += 1;
+                match calls.iter_mut().find(|(c, _, _)| *c == call) {
+                    Some(group) => {
+                        group.1 += 1;
+                        if group.2.len() < EXAMPLES && !group.2.contains(&site) { group.2.push(site.clone()); }
+                    }
+                    None => calls.push((call, 1, vec![site.clone()])),
+                }
+            }
+        }
+
+        let imports = self.resolved_imports(repo_name).await?;
+        let total_imports = imports.len();
+        let mut packages: Vec<(String, usize, Vec<String>, Vec<String>)> = vec![];
+        for (file, module, _, _) in imports.into_iter().filter(|(_, _, _, t)| t.is_empty()) {
+            let package = import_package(&module);
+            match packages.iter_mut().find(|(p, _, _, _)| *p == package) {
+                Some(group) => {
+                    group.1 += 1;
+                    if !group.2.contains(&module) { group.2.push(module); }
+                    if group.3.len() < EXAMPLES && !group.3.contains(&file) { group.3.push(file); }
+                }
+                None => packages.push((package, 1, vec![module], vec![file])),
+            }
+        }
+        let unresolved_imports: usize = packages.iter().map(|p| p.1).sum();
+
+        calls.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        packages.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(json!({
+            "calls": calls.into_iter()
+                .map(|(name, count, sites)| json!({ "name": name, "count": count, "callers": sites }))
+                .collect::<Vec<_>>(),
+            "imports": packages.into_iter()
+                .map(|(package, count, modules, files)| json!({ "package": package, "count": count, "modules": modules, "files": files }))
+                .collect::<Vec<_>>(),
+            "summary": {
+                "calls": total_calls,
+                "unresolved_calls": unresolved_calls,
+                "imports": total_imports,
+                "unresolved_imports": unresolved_imports,
+            },
+        }))
+    }
+
+    // "Possibly unused" candidates: functions nothing calls, routes to or imports, and classes never
+    // instantiated, inherited, implemented or used as a type. Exported or public symbols, tests,
+    // decorated functions and entrypoints are left out since their callers may live elsewhere
+    async fn get_dead_code(&self, repo_name: &str) -> Result<Value> {
+        let rows = self.execute(
+            query("MATCH (:File {repo: $repo})-[:CONTAINS]->(n) UNWIND coalesce(n.calls, []) AS name RETURN DISTINCT name \
+                   UNION MATCH (:File {repo: $repo})-[i:IMPORTS_FROM]->() UNWIND coalesce(i.names, []) AS name RETURN name \
+                   UNION MATCH (:File {repo: $repo})-[:CONTAINS]->(c:Command) RETURN c.handler AS name \
+                   UNION MATCH (f:File {repo: $repo}) UNWIND coalesce(f.references, []) AS name RETURN name")
+                .param("repo", repo_name)
+        ).await?;
+        // Every segment counts, so `Config.load` and `new Config` both mark `Config` as used
+        let used: HashSet<String> = rows.iter()
+            .filter_map(|row| row.get::<String>("name").ok())
+            .flat_map(|name| name.split(['.', ':', '\\', '/']).filter(|s| !s.is_empty()).map(str::to_string).collect::<Vec<_>>())
+            .collect();
+
+        let candidates = |rows: Vec<Row>| -> Vec<Value> {
+            rows.into_iter()
+                .filter(|row| {
+                    let name = row.get::<String>("name").unwrap_or_default();
+                    let vis = row.get::<String>("vis").unwrap_or_default();
+                    let lang = row.get::<String>("lang").unwrap_or_default();
+                    let exports = row.get::<Vec<String>>("exports").unwrap_or_default();
+                    !used.contains(&name) && !IMPLICIT_ENTRYPOINTS.contains(&name.as_str())
+                        && !is_exported(&name, &vis, &lang, &exports)
+                })
+                .map(|row| json!({
+                    "name": row.get::<String>("name").unwrap_or_default(),
+                    "kind": row.get::<String>("kind").unwrap_or_default(),
+                    "file": row.get::<String>("file").unwrap_or_default(),
+                    "line": row.get::<i64>("line").unwrap_or(0),
+                    "parent_class": row.get::<String>("parent").ok().filter(|p| !p.is_empty()),
+                    "visibility": row.get::<String>("vis").ok().filter(|v| !v.is_empty()),
+                }))
+                .collect()
+        };
+
+        let functions = candidates(self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(n:Function) \
+                   WHERE n.kind <> 'component' AND NOT coalesce(n.is_test, false) AND NOT coalesce(f.test_file, false) \
+                     AND coalesce(n.decorators, '') = '' \
+                     AND NOT ()-[:CALLS]->(n) AND NOT ()-[:HANDLED_BY]->(n) \
+                   RETURN n.name AS name, n.kind AS kind, n.visibility AS vis, n.parent_class AS parent, \
+                          n.line_start AS line, f.path AS file, f.language AS lang, f.exports AS exports \
+                   ORDER BY file, line")
+                .param("repo", repo_name)
+        ).await?);
+        let classes = candidates(self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(n:Class) \
+                   WHERE n.kind IN ['class', 'interface'] AND NOT coalesce(n.is_test, false) AND NOT coalesce(f.test_file, false) \
+                     AND coalesce(n.decorators, '') = '' \
+                     AND NOT ()-[:INHERITS|IMPLEMENTS|USES_TYPE]->(n) \
+                   RETURN n.name AS name, n.kind AS kind, n.visibility AS vis, n.parent_class AS parent, \
+                          n.line_start AS line, f.path AS file, f.language AS lang, f.exports AS exports \
+                   ORDER BY file, line")
+                .param("repo", repo_name)
+        ).await?);
+        Ok(json!({ "functions": functions, "classes": classes }))
+    }
+
+    // File-to-file import graph: every IMPORTS_FROM Module resolved back to the repo files that
+    // define it, with strongly connected components reported as import cycles
+    async fn get_dependencies(&self, repo_name: &str) -> Result<Value> {
+        Ok(dependency_graph(self.resolved_imports(repo_name).await?))
+    }
+
+    async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo}) OPTIONAL MATCH (f)-[:CONTAINS]->(s) RETURN f.path AS path, f.language AS lang, collect({name: s.name, kind: s.kind, sig: s.signature, doc: s.docstring, ret: s.return_type, vis: s.visibility, parent: s.parent_class, params: s.params, decos: s.decorators, members: s.members, fields: s.fields, raises: s.raises, type_params: s.type_params, variants: s.variants, tags: s.tags, owners: s.owners}) AS symbols")
+                .param("repo", repo_name)
+        ).await?;
+        let mut out = vec![];
+        for row in rows {
+            out.push(json!({
+                "path": row.get::<String>("path").unwrap_or_default(),
+                "language": row.get::<String>("lang").unwrap_or_default(),
+                "symbols": row.get::<Vec<Value>>("symbols").unwrap_or_default(),
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn count_by_kind(&self, repo_name: &str) -> Result<Value> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo})-[:CONTAINS]->(s) WHERE NOT coalesce(s.is_test, false) RETURN s.kind AS kind, count(s) AS cnt")
+                .param("repo", repo_name)
+        ).await?;
+        let mut counts = serde_json::Map::new();
+        for row in rows {
+            let kind = row.get::<String>("kind").unwrap_or_default();
+            let cnt = row.get::<i64>("cnt").unwrap_or(0);
+            counts.insert(kind, json!(cnt));
+        }
+        Ok(Value::Object(counts))
+    }
+
+    async fn get_file_languages(&self, repo_name: &str) -> Result<Value> {
+        let rows = self.execute(
+            query("MATCH (f:File {repo: $repo}) RETURN f.language AS lang, count(f) AS cnt")
+                .param("repo", repo_name)
+        ).await?;
+        let mut langs = serde_json::Map::new();
+        for row in rows {
+            let lang = row.get::<String>("lang").unwrap_or_default();
+            let cnt = row.get::<i64>("cnt").unwrap_or(0);
+            langs.insert(lang, json!(cnt));
+        }
+        Ok(Value::Object(langs))
+    }
+}
+
+// Procedures user Cypher may CALL; everything else (dbms.*, apoc.*, db.create*) is refused
+const READONLY_PROCEDURES: &[&str] = &[
+    "db.index.fulltext.querynodes", "db.index.vector.querynodes", "db.labels", "db.relationshiptypes",
+    "db.propertykeys", "db.schema.visualization", "db.schema.nodetypeproperties", "db.schema.reltypeproperties",
+];
+
+// Why `cypher` can't run as a read-only query, if it can't: a write or admin clause, or a CALL to a
+// procedure outside READONLY_PROCEDURES. String literals, `quoted` names, comments and property
+// accesses (n.set) are ignored
+pub fn readonly_violation(cypher: &str) -> Option<String> {
+    let mut code = String::with_capacity(cypher.len());
+    let mut chars = cypher.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' => {
+                let mut escaped = false;
+                for n in chars.by_ref() {
+                    if n == c && !escaped { break; }
+                    escaped = n == '\\' && !escaped;
+                }
+                code.push(' ');
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for n in chars.by_ref() { if n == '\n' { break; } }
+                code.push(' ');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for n in chars.by_ref() {
+                    if prev == '*' && n == '/' { break; }
+                    prev = n;
+                }
+                code.push(' ');
+            }
+            _ => code.push(c),
+        }
+    }
+    let mut tokens: Vec<(String, bool)> = vec![];
+    let mut word = String::new();
+    let mut after_dot = false;
+    for c in code.chars().chain([' ']) {
+        if c.is_alphanumeric() || c == '_' || (c == '.' && !word.is_empty()) {
+            word.push(c);
+        } else {
+            if !word.is_empty() {
+                tokens.push((word.to_lowercase(), after_dot));
+                word.clear();
+            }
+            after_dot = c == '.';
+        }
+    }
+    for (i, (token, property)) in tokens.iter().enumerate() {
+        if *property { continue; }
+        let head = token.split('.').next().unwrap_or(token);
+        if matches!(head, "create" | "merge" | "delete" | "detach" | "set" | "remove" | "drop" | "foreach" | "load"
+            | "grant" | "revoke" | "deny" | "alter" | "rename" | "start" | "stop" | "terminate" | "enable" | "use") {
+            return Some(format!("`{}` is not allowed in read-only queries", head.to_uppercase()));
+        }
+        if head == "call" {
+            if let Some((next, _)) = tokens.get(i + 1) {
+                if next.contains('.') && !READONLY_PROCEDURES.contains(&next.as_str()) {
+                    return Some(format!("procedure `{}` is not allowed in read-only queries", next));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn is_transient(e: &Error) -> bool {
+    match e {
+        Error::Neo4j(e) => e.kind() == Neo4jErrorKind::Transient || is_connection_lost_kind(e.kind()),
+        _ => is_connection_lost(e),
+    }
+}
+
+fn is_connection_lost(e: &Error) -> bool {
+    match e {
+        Error::IOError { .. } | Error::ConnectionError => true,
+        Error::Neo4j(e) => is_connection_lost_kind(e.kind()),
+        _ => false,
+    }
+}
+
+fn is_connection_lost_kind(kind: Neo4jErrorKind) -> bool {
+    kind == Neo4jErrorKind::Client(Neo4jClientErrorKind::SessionExpired)
+}
+
+// PageRank (damping 0.85) over caller -> callee edges, with the rank of functions that call
+// nothing spread evenly. Returns (rank, in-degree, out-degree) per function
+pub(crate) fn pagerank(edges: &[(String, String)]) -> HashMap<String, (f64, usize, usize)> {
+    const DAMPING: f64 = 0.85;
+    const ITERATIONS: usize = 30;
+    let mut nodes: Vec<&str> = edges.iter().flat_map(|(a, b)| [a.as_str(), b.as_str()]).collect();
+    nodes.sort();
+    nodes.dedup();
+    let n = nodes.len();
+    if n == 0 {
+        return HashMap::new();
+    }
+    let index_of: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut out_degree = vec![0; n];
+    let mut in_degree = vec![0; n];
+    for (a, b) in edges {
+        out_degree[index_of[a.as_str()]] += 1;
+        in_degree[index_of[b.as_str()]] += 1;
+    }
+
+    let mut rank = vec![1.0 / n as f64; n];
+    for _ in 0..ITERATIONS {
+        let dangling: f64 = (0..n).filter(|&i| out_degree[i] == 0).map(|i| rank[i]).sum();
+        let mut next = vec![(1.0 - DAMPING + DAMPING * dangling) / n as f64; n];
+        for (a, b) in edges {
+            let (a, b) = (index_of[a.as_str()], index_of[b.as_str()]);
+            next[b] += DAMPING * rank[a] / out_degree[a] as f64;
+        }
+        rank = next;
+    }
+    nodes.iter().enumerate()
+        .map(|(i, id)| (id.to_string(), (rank[i], in_degree[i], out_degree[i])))
+        .collect()
+}
+
+// Label propagation over an undirected, edge-count weighted graph: every node repeatedly takes
+// the label heaviest among its neighbours until nothing changes. Nodes are visited in id order
+// and ties go to the current label, then the smallest, so the result is deterministic. Labels
+// are renumbered by community size, largest first
+pub(crate) fn label_propagation(edges: &[(String, String)]) -> HashMap<String, usize> {
+    const MAX_ROUNDS: usize = 50;
+    let mut nodes: Vec<&str> = edges.iter().flat_map(|(a, b)| [a.as_str(), b.as_str()]).collect();
+    nodes.sort();
+    nodes.dedup();
+    let index_of: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+    let mut adj: Vec<HashMap<usize, usize>> = vec![HashMap::new(); nodes.len()];
+    for (a, b) in edges {
+        let (a, b) = (index_of[a.as_str()], index_of[b.as_str()]);
+        *adj[a].entry(b).or_default() += 1;
+        *adj[b].entry(a).or_default() += 1;
+    }
+
+    let mut label: Vec<usize> = (0..nodes.len()).collect();
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        for v in 0..nodes.len() {
+            let mut weights: HashMap<usize, usize> = HashMap::new();
+            for (&w, &count) in &adj[v] {
+                *weights.entry(label[w]).or_default() += count;
+            }
+            let Some(&heaviest) = weights.values().max() else { continue };
+            if weights.get(&label[v]) == Some(&heaviest) {
+                continue;
+            }
+            let best = weights.iter().filter(|(_, &w)| w == heaviest).map(|(l, _)| *l).min().unwrap_or(label[v]);
+            label[v] = best;
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for l in &label {
+        *sizes.entry(*l).or_default() += 1;
+    }
+    let mut order: Vec<(usize, usize)> = sizes.into_iter().collect();
+    order.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let rank: HashMap<usize, usize> = order.iter().enumerate().map(|(i, (l, _))| (*l, i)).collect();
+    nodes.iter().enumerate().map(|(i, id)| (id.to_string(), rank[&label[i]])).collect()
+}
+
+// Property pattern matching a restored node by the properties its constraints are on
+// Under the declaring manifest's path; a manifest can list a name once per kind
+fn dependency_id(repo_name: &str, dependency: &Dependency) -> String {
+    format!("{}::{}::dependency::{}::{}", repo_name, dependency.manifest, dependency.kind, dependency.name)
+}
+
+fn identity_pattern(label: &str, identity: &str) -> String {
+    match label {
+        "Repo" => format!("{{name: {}.name}}", identity),
+        "Module" => format!("{{name: {0}.name, repo: {0}.repo}}", identity),
+        _ => format!("{{id: {}.id}}", identity),
+    }
+}
+
+fn bolt_value(value: &Value) -> BoltType {
+    match value {
+        Value::Null => BoltType::Null(BoltNull),
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(s) => s.clone().into(),
+        Value::Array(items) => BoltType::List(BoltList::from(items.iter().map(bolt_value).collect::<Vec<_>>())),
+        Value::Object(map) => BoltType::Map(map.iter().map(|(k, v)| (BoltString::from(k.as_str()), bolt_value(v))).collect()),
+    }
+}
+
+// Nodes of `label` that belong to the repo, bound to `n`; expects `$repo` and `$prefix` (`repo::`)
+fn export_match(label: &str) -> String {
+    match label {
+        "Repo" => "MATCH (n:Repo) WHERE n.name = $repo".to_string(),
+        "Directory" | "Module" => format!("MATCH (n:{}) WHERE n.repo = $repo", label),
+        _ => format!("MATCH (n:{}) WHERE n.id STARTS WITH $prefix", label),
+    }
+}
+
+// Run by the language or framework rather than called from the repo
+const IMPLICIT_ENTRYPOINTS: &[&str] = &["main", "init", "constructor", "__init__", "__main__"];
+
+// Whether a symbol is reachable from outside its file or package by its declaration alone
+fn is_exported(name: &str, visibility: &str, language: &str, exports: &[String]) -> bool {
+    match language {
+        // Everything is importable; only dunders are flagged, as the runtime calls them
+        "Python" => visibility == "dunder",
+        "Go" => name.starts_with(|c: char| c.is_uppercase()),
+        "TypeScript" | "JavaScript" => visibility == "export"
+            || exports.iter().any(|e| e.split(|c: char| !c.is_alphanumeric() && c != '_' && c != '$').any(|t| t == name)),
+        _ => visibility == "pub" || visibility == "export" || visibility.contains("public") || visibility.contains("protected"),
+    }
+}
+
+// Resolved imports, as (file, module, names, target files), folded into file-to-file edges with
+// the files of each import cycle
+pub(crate) fn dependency_graph(imports: Vec<(String, String, Vec<String>, Vec<String>)>) -> Value {
+    let mut edges: Vec<(String, String, String, Vec<String>)> = vec![];
+    for (file, module, names, targets) in imports {
+        for target in targets.into_iter().filter(|t| *t != file) {
+            match edges.iter_mut().find(|(from, to, _, _)| *from == file && *to == target) {
+                Some(edge) => edge.3.extend(names.iter().cloned()),
+                None => edges.push((file.clone(), target, module.clone(), names.clone())),
+            }
+        }
+    }
+
+    let cycles = import_cycles(&edges);
+    let cyclic: HashMap<&str, usize> = cycles.iter().enumerate()
+        .flat_map(|(i, cycle)| cycle.iter().map(move |f| (f.as_str(), i)))
+        .collect();
+    let edges: Vec<Value> = edges.iter()
+        .map(|(from, to, module, names)| {
+            let circular = matches!((cyclic.get(from.as_str()), cyclic.get(to.as_str())), (Some(a), Some(b)) if a == b);
+            json!({ "from": from, "to": to, "module": module, "names": names, "circular": circular })
+        })
+        .collect();
+    json!({ "edges": edges, "cycles": cycles })
+}
+
+// Module node name for an import source: dotted module names become paths; other languages
+// already import by path
+pub(crate) fn module_name(language: Language, source: &str) -> String {
+    match language {
+        Language::Python | Language::Java => source.replace('.', "/"),
+        _ => source.to_string(),
+    }
+}
+
+// Extensions tried when an import names a file without one
+const IMPORT_EXTENSIONS: &[&str] = &[
+    "ts", "tsx", "js", "jsx", "mjs", "cjs", "vue", "svelte", "py", "go", "rs", "java", "rb", "php",
+    "h", "hpp", "c", "cc", "cpp",
+];
+
+// Repo files an import's module refers to. Relative imports (`./x`, `../x`, and Python's leading
+// dots, stored as leading slashes) are joined to the importing file's directory; anything else is
+// matched as a path suffix, preferring the shallowest file. Externals resolve to nothing
+pub(crate) fn resolve_import(file: &str, language: &str, module: &str, names: &[String], paths: &HashSet<String>) -> Vec<String> {
+    let dir = file.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+    if language == "Python" && module.starts_with('/') {
+        let rest = module.trim_start_matches('/');
+        let mut base = dir.to_string();
+        for _ in 1..module.len() - rest.len() {
+            base = base.rsplit_once('/').map(|(d, _)| d.to_string()).unwrap_or_default();
+        }
+        if !rest.is_empty() {
+            return import_file(&join_path(&base, rest), paths).into_iter().collect();
+        }
+        // `from . import utils` imports sibling modules, or names from the package itself
+        let mut out: Vec<String> = names.iter().filter_map(|n| import_file(&join_path(&base, n), paths)).collect();
+        if out.len() < names.len() {
+            out.extend(import_file(&join_path(&base, "__init__"), paths));
+        }
+        return out;
+    }
+    if module.starts_with("./") || module.starts_with("../") {
+        return import_file(&join_path(dir, module), paths).into_iter().collect();
+    }
+    let module = module.trim_start_matches("@/").trim_start_matches("~/");
+    if module.is_empty() {
+        return vec![];
+    }
+    let mut found: Vec<&String> = paths.iter()
+        .filter(|p| import_candidates(module).iter().any(|c| *p == c || p.ends_with(&format!("/{}", c))))
+        .collect();
+    found.sort_by_key(|p| (p.matches('/').count(), p.len()));
+    found.first().map(|p| vec![p.to_string()]).unwrap_or_default()
+}
+
+fn import_file(base: &str, paths: &HashSet<String>) -> Option<String> {
+    import_candidates(base).into_iter().find(|c| paths.contains(c))
+}
+
+fn import_candidates(base: &str) -> Vec<String> {
+    let mut out = vec![base.to_string()];
+    out.extend(IMPORT_EXTENSIONS.iter().map(|ext| format!("{}.{}", base, ext)));
+    out.extend(IMPORT_EXTENSIONS.iter().map(|ext| format!("{}/index.{}", base, ext)));
+    out.push(format!("{}/__init__.py", base));
+    out
+}
+
+// Top-level package of an import: `@scope/pkg/sub` -> `@scope/pkg`, `os/path` -> `os`,
+// `github.com/org/pkg/sub` -> `github.com/org/pkg`, `serde::de` -> `serde`. Relative imports keep
+// their full path since they point into the repo
+fn import_package(module: &str) -> String {
+    if module.starts_with('.') || module.starts_with('/') {
+        return module.to_string();
+    }
+    if let Some((krate, _)) = module.split_once("::") {
+        return krate.to_string();
+    }
+    let first = module.split('/').next().unwrap_or_default();
+    let take = if module.starts_with('@') { 2 } else if first.contains('.') { 3 } else { 1 };
+    module.split('/').take(take).collect::<Vec<_>>().join("/")
+}
+
+// Files of the repo by the external package they import, from `resolved_imports` output. Imports
+// that resolve to repo files, relative imports and Rust's own crate paths are not packages
+pub(crate) fn package_uses(imports: &[(String, String, Vec<String>, Vec<String>)]) -> BTreeMap<String, Vec<String>> {
+    let mut uses: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (file, module, _, _) in imports.iter().filter(|(_, _, _, t)| t.is_empty()) {
+        let package = import_package(module);
+        if package.is_empty() || package.starts_with('.') || package.starts_with('/') || matches!(package.as_str(), "crate" | "self" | "super") {
+            continue;
+        }
+        let files = uses.entry(package).or_default();
+        if !files.contains(file) {
+            files.push(file.clone());
+        }
+    }
+    for files in uses.values_mut() {
+        files.sort();
+    }
+    uses
+}
+
+// `package_uses` as returned by the "packages" query, most used first
+pub(crate) fn package_list(uses: BTreeMap<String, Vec<String>>) -> Vec<Value> {
+    let mut packages: Vec<(String, Vec<String>)> = uses.into_iter().collect();
+    packages.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+    packages.into_iter()
+        .map(|(package, files)| json!({ "package": package, "count": files.len(), "files": files }))
+        .collect()
+}
+
+// `dir` joined with a relative path, with `.` and `..` segments folded
+fn join_path(dir: &str, rel: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in rel.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => { parts.pop(); }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// Files that import each other, directly or through a chain: the strongly connected components
+// with more than one file (Tarjan, iterative so deep import chains can't overflow the stack)
+fn import_cycles(edges: &[(String, String, String, Vec<String>)]) -> Vec<Vec<String>> {
+    let mut nodes: Vec<&str> = edges.iter().flat_map(|(from, to, _, _)| [from.as_str(), to.as_str()]).collect();
+    nodes.sort();
+    nodes.dedup();
+    let index_of: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (*n, i)).collect();
+    let mut adj: Vec<Vec<usize>> = vec![vec![]; nodes.len()];
+    for (from, to, _, _) in edges {
+        adj[index_of[from.as_str()]].push(index_of[to.as_str()]);
+    }
+
+    let mut index = vec![usize::MAX; nodes.len()];
+    let mut low = vec![0; nodes.len()];
+    let mut on_stack = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = vec![];
+    let mut counter = 0;
+    let mut cycles = vec![];
+    for start in 0..nodes.len() {
+        if index[start] != usize::MAX {
+            continue;
+        }
+        let mut work: Vec<(usize, usize)> = vec![(start, 0)];
+        while let Some(&(v, next)) = work.last() {
+            if next == 0 {
+                index[v] = counter;
+                low[v] = counter;
+                counter += 1;
+                stack.push(v);
+                on_stack[v] = true;
+            }
+            if let Some(&w) = adj[v].get(next) {
+                if let Some(top) = work.last_mut() {
+                    top.1 += 1;
+                }
+                if index[w] == usize::MAX {
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(parent, _)) = work.last() {
+                low[parent] = low[parent].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = vec![];
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(nodes[w].to_string());
+                    if w == v {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    component.sort();
+                    cycles.push(component);
+                }
+            }
+        }
+    }
+    cycles
+}
+
+// Cap on nodes emitted by one `call_chain`, since shared callees are expanded once per branch
+pub(crate) const MAX_CHAIN_NODES: usize = 5000;
+
+pub(crate) fn chain_node(id: &str, depth: usize, next: &HashMap<String, Vec<String>>, info: &HashMap<String, Value>,
+              path: &mut Vec<String>, budget: &mut usize) -> Value {
+    let mut node = info.get(id).cloned().unwrap_or_else(|| json!({ "id": id }));
+    *budget = budget.saturating_sub(1);
+    if path.iter().any(|p| p == id) {
+        node["recursive"] = json!(true);
+        return node;
+    }
+    let children = next.get(id).map(Vec::as_slice).unwrap_or_default();
+    if depth == 0 || children.is_empty() {
+        node["children"] = json!([]);
+        return node;
+    }
+    path.push(id.to_string());
+    let mut out = vec![];
+    for child in children {
+        if *budget == 0 {
+            node["truncated"] = json!(true);
+            break;
+        }
+        out.push(chain_node(child, depth - 1, next, info, path, budget));
+    }
+    path.pop();
+    node["children"] = json!(out);
+    node
+}
+
+pub(crate) fn label_for_kind(kind: &str) -> &'static str {
+    match kind {
+        "class" | "interface" | "type" | "input" | "message" | "service" => "Class",
+        "function" | "method" | "procedure" | "macro" | "component" | "hook" => "Function",
+        "route" => "Route",
+        "command" => "Command",
+        "embedded" => "Embedded",
+        _ => "Symbol",
+    }
+}
+
+// Ids `ingest_symbols` gives one file's nodes: the File (and its Document), symbols and todos
+pub(crate) fn node_ids(repo_name: &str, file_path: &str, result: &ParsingResult) -> Vec<String> {
+    let file_id = format!("{}::{}", repo_name, file_path);
+    let mut ids: Vec<String> = result.symbols.iter().map(|s| format!("{}::{}:{}", file_id, s.name, s.range.0)).collect();
+    ids.extend(result.todos.iter().map(|todo| format!("{}::todo:{}", file_id, todo.line)));
+    ids.push(file_id);
+    ids
+}
+
+// Class name a method's `parent_class` refers to: `Outer.Inner` -> `Inner`, `net::Client` -> `Client`
+pub(crate) fn owner_name(parent_class: &str) -> &str {
+    parent_class.rsplit("::").next().unwrap_or(parent_class).rsplit('.').next().unwrap_or(parent_class)
+}
+
+// Where a call can land, narrowed by what is known about its receiver or qualifier
+#[derive(Default)]
+pub(crate) struct CallTarget {
+    name: String,
+    // Exact qualified names to accept (C++ scope lookup, PHP FQNs)
+    candidates: Vec<String>,
+    // Callee must be a method of this type
+    owner: String,
+    // Callee must be a method of some type: obj.save() never reaches a free function
+    member: bool,
+    // Callee's file must live under this module path
+    module: String,
+    // Callee's qualified name must end with this (Rust module paths)
+    suffix: String,
+}
+
+impl CallTarget {
+    // The CALLS matching rules of `ingest_symbols`, for a function `callee` in the file at `path`
+    pub(crate) fn accepts(&self, callee: &Symbol, path: &str) -> bool {
+        let qualified = callee.qualified_name.as_deref().unwrap_or("");
+        let parent = callee.parent_class.as_deref().unwrap_or("");
+        callee.name == self.name
+            && (self.candidates.is_empty() || qualified.is_empty() || self.candidates.iter().any(|c| c == qualified))
+            && (self.owner.is_empty() || parent == self.owner || parent.ends_with(&format!("::{}", self.owner)))
+            && (!self.member || !parent.is_empty())
+            && (self.module.is_empty() || [format!("{}/", self.module), format!("{}.", self.module)].iter()
+                .any(|m| path.starts_with(m.as_str()) || path.contains(&format!("/{}", m))))
+            && (self.suffix.is_empty() || qualified.ends_with(&self.suffix))
+    }
+}
+
+pub(crate) fn resolve_call(result: &ParsingResult, caller: &Symbol, callee: &str) -> CallTarget {
+    let lang = result.language;
+    if lang == Language::Php {
+        let (name, candidates) = php_candidates(callee);
+        return CallTarget { name, candidates, ..Default::default() };
+    }
+    let class = caller.parent_class.clone().unwrap_or_default();
+    // self.save / self.db.save / utils.helper / Widget.create / obj.save
+    if let Some((receiver, method)) = callee.rsplit_once('.') {
+        let mut target = CallTarget { name: call_name(method).to_string(), member: true, ..Default::default() };
+        let segments: Vec<&str> = receiver.split('.').collect();
+        match segments.as_slice() {
+            ["self" | "this" | "cls" | "Self"] => target.owner = class,
+            ["self" | "this", field] => target.owner = field_type(result, &class, field).unwrap_or_default(),
+            _ => {
+                if let Some(module) = import_module(result, receiver) {
+                    target.member = false;
+                    target.module = module;
+                } else if segments.len() == 1 && receiver.starts_with(|c: char| c.is_ascii_uppercase()) {
+                    target.owner = receiver.to_string();
+                }
+            }
+        }
+        return target;
+    }
+    let name = callee.rsplit("::").next().unwrap_or(callee).to_string();
+    // C++ callees resolve like name lookup: `draw` called from foo::bar::Widget::resize may be
+    // foo::bar::Widget::draw, foo::bar::draw, foo::draw or ::draw
+    if let (Language::Cpp, Some(qualified)) = (lang, &caller.qualified_name) {
+        let callee = callee.trim_start_matches("::");
+        let scope: Vec<&str> = qualified.split("::").collect();
+        let scope = &scope[..scope.len().saturating_sub(1)];
+        let candidates = (0..=scope.len()).rev()
+            .map(|i| if i == 0 { callee.to_string() } else { format!("{}::{}", scope[..i].join("::"), callee) })
+            .collect();
+        return CallTarget { name, candidates, ..Default::default() };
+    }
+    // Foo::new / Self::helper / utils::parse / crate::a::b
+    let mut target = CallTarget { name, ..Default::default() };
+    if let Some((scope, _)) = callee.rsplit_once("::") {
+        let last = scope.rsplit("::").next().unwrap_or(scope);
+        if last == "Self" {
+            target.owner = class;
+        } else if last.starts_with(|c: char| c.is_ascii_uppercase()) {
+            target.owner = last.to_string();
+        } else if scope == "crate" || scope.starts_with("crate::") {
+            target.candidates = vec![callee.to_string()];
+        } else {
+            let mut path = callee;
+            while let Some(rest) = path.strip_prefix("self::").or_else(|| path.strip_prefix("super::")) { path = rest; }
+            if path.contains("::") { target.suffix = format!("::{}", path); }
+        }
+    }
+    target
+}
+
+// Bare function name of a (possibly qualified) call
+pub(crate) fn call_name(callee: &str) -> &str {
+    callee.rsplit(['.', ':', '\\']).next().unwrap_or(callee)
+}
+
+// Declared type of `field` on `class`, reduced to the type name: Optional[Database] -> Database
+fn field_type(result: &ParsingResult, class: &str, field: &str) -> Option<String> {
+    const WRAPPERS: &[&str] = &["Optional", "Option", "List", "Vec", "Box", "Arc", "Rc", "Mutex", "RwLock", "Union"];
+    let owner = result.symbols.iter().find(|s| s.name == class && label_for_kind(&s.kind) == "Class")?;
+    let ty = owner.fields.iter().find(|f| f.name == field)?.type_annotation.as_deref()?;
+    let name = ty.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .find(|t| t.starts_with(|c: char| c.is_ascii_uppercase()) && !WRAPPERS.contains(t))?;
+    Some(name.to_string())
+}
+
+// Module path an import alias refers to, as a repo path fragment: `from app import utils` -> app/utils
+fn import_module(result: &ParsingResult, alias: &str) -> Option<String> {
+    for import in &result.imports {
+        match result.language {
+            Language::Python => {
+                for entry in import.names.iter().flat_map(|n| n.split(',')) {
+                    let (target, name) = match entry.split_once(" as ") {
+                        Some((target, name)) => (target.trim(), name.trim()),
+                        None => (entry.trim(), entry.trim()),
+                    };
+                    if name != alias { continue; }
+                    let module = match &import.source {
+                        Some(source) => format!("{}.{}", source.trim_start_matches('.'), target),
+                        None => target.to_string(),
+                    };
+                    return Some(module.trim_start_matches('.').replace('.', "/"));
+                }
+            }
+            Language::TypeScript | Language::JavaScript => {
+                let source = import.source.as_deref()?;
+                if !import.names.iter().any(|n| n.trim_start_matches("* as ").trim() == alias) { continue; }
+                // Only relative imports can be files in this repo
+                if !source.starts_with('.') { return None; }
+                let module = source.trim_start_matches(['.', '/']);
+                let module = module.rsplit_once('.').filter(|(_, ext)| matches!(*ext, "js" | "ts" | "jsx" | "tsx" | "mjs" | "cjs")).map_or(module, |(stem, _)| stem);
+                return Some(module.to_string());
+            }
+            Language::Go => {
+                for line in import.raw.lines() {
+                    let Some((before, rest)) = line.split_once('"') else { continue };
+                    let path = rest.split('"').next().unwrap_or("");
+                    let last = path.rsplit('/').next().unwrap_or(path);
+                    let name = before.trim().trim_start_matches("import").trim().trim_start_matches('(').trim();
+                    if (if name.is_empty() { last } else { name }) == alias { return Some(last.to_string()); }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// App\Models\User::find matches Function {name: "find"} only where qualified_name agrees
+fn php_candidates(name: &str) -> (String, Vec<String>) {
+    if !name.contains('\\') && !name.contains("::") { return (name.to_string(), vec![]) }
+    let short = name.rsplit("::").next().unwrap_or(name);
+    let short = short.rsplit('\\').next().unwrap_or(short);
+    (short.to_string(), vec![name.to_string()])
 }
@@ -1,83 +1,918 @@
 use futures::stream::{self, StreamExt};
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_8, WINDOWS_1252};
 use ignore::WalkBuilder;
 use rayon::prelude::*;
-use std::sync::Arc;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::warn;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
-use crate::graph::GraphClient;
+use serde_json::Value;
+use crate::entrypoints::{self, Entrypoint};
+use crate::git;
+use crate::graph;
+use crate::jobs::{Job, Phase};
+use crate::licenses;
+use crate::manifests::{self, Dependency};
+use crate::store::GraphStore;
 use crate::parsing;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct IndexingStats {
     pub files_processed: usize,
     pub files_skipped: usize,
+    // Why each skipped file was left out: "too_large", "minified", "binary", "undecodable",
+    // "unreadable" or "parse_panic"
+    pub files_skipped_reason: BTreeMap<String, usize>,
+    // Files that weren't UTF-8 and were converted before parsing
+    pub files_transcoded: usize,
+    // Files in no encoding that could be recognised
+    pub undecodable_files: Vec<String>,
+    // Files whose content hash matched the stored one, left as they were
+    pub files_unchanged: usize,
     pub nodes_created: usize,
+    // Nodes of files and symbols the previous run left behind that this one no longer found
+    pub nodes_removed: usize,
+    // Line metrics summed over the repo's files, the ones this run didn't parse as last stored
+    pub lines_of_code: usize,
+    pub comment_lines: usize,
+    pub blank_lines: usize,
+    pub symbols_found: usize,
+    // Dependencies read from Cargo.toml, package.json, pyproject.toml, requirements*.txt, go.mod
+    // and pom.xml files; 0 when an incremental run touched no manifest
+    pub dependencies_declared: usize,
+    // Those a Cargo.lock, package-lock.json, pnpm-lock.yaml, poetry.lock or go.sum pinned
+    pub dependencies_resolved: usize,
+    // SPDX expression the repo is licensed under; None when nothing names one, or when an
+    // incremental run touched no license file
+    pub license: Option<String>,
+    // Entrypoints the repo's code is started from: main functions, bin/ targets, manifests'
+    // scripts and Dockerfile commands
+    pub entrypoints: usize,
+    // Files tree-sitter could only partially parse
+    pub files_with_parse_errors: usize,
+    // Whether only the files changed since the last indexed commit were parsed
+    pub incremental: bool,
+    // Files an incremental run dropped because git reports them deleted
+    pub files_deleted: usize,
+    // Entrypoints, public API files, READMEs and the code beside them, parsed ahead of the rest;
+    // the repo was marked partially available once they were in
+    pub priority_files: usize,
+    // Symlinks the walk went through, or passed over when not following them
+    pub symlinks_followed: usize,
+    pub symlinks_skipped: usize,
+    // Files left out because another path to the same file was indexed
+    pub duplicate_files: usize,
+    // Vendored directories left out, relative to the root
+    pub vendored_dirs_skipped: Vec<String>,
+    // Submodules under the root, when `submodules` is on
+    pub submodules: Vec<git::Submodule>,
+    // HEAD of the indexed checkout, when it is a git work tree
+    pub commit: Option<String>,
+    // The repo's files and their symbols by language, and by top-level directory ("." for files
+    // at the root)
+    pub languages: BTreeMap<String, Breakdown>,
+    pub directories: BTreeMap<String, Breakdown>,
 }
 
-pub async fn index_repository(repo_path: &str, repo_name: &str, graph: Option<Arc<GraphClient>>) -> IndexingStats {
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Breakdown {
+    pub files: usize,
+    pub symbols: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IndexOptions {
+    // Re-parse only what `git diff` reports since the repo's last indexed commit. Falls back to a
+    // full run when there is no recorded commit or git can't diff against it
+    pub incremental: bool,
+    // Files bigger than this many bytes are skipped without being read; None for no limit
+    pub max_file_size: Option<u64>,
+    // Parse on a pool of this many threads made for the run, instead of the global rayon pool
+    pub threads: Option<usize>,
+    // Files ingested at once
+    pub ingest_concurrency: usize,
+    pub parse_on: ParseExecutor,
+    // Walk into symlinked files and directories; a link back up the tree is not followed again
+    pub follow_symlinks: bool,
+    // Leave out VENDORED_DIRS even where no ignore file does
+    pub skip_vendored: bool,
+    // With `follow_symlinks`, index a file reachable by several paths once, preferring a path
+    // without links on the way
+    pub dedupe_links: bool,
+    pub submodules: Submodules,
+    // Set functions' and classes' owners from `git blame`. Costs a blame per parsed file, often
+    // more than parsing it, so it is off unless asked for
+    pub blame_owners: bool,
+}
+
+impl Default for IndexOptions {
+    fn default() -> Self {
+        Self {
+            incremental: false,
+            max_file_size: None,
+            threads: None,
+            ingest_concurrency: 32,
+            parse_on: ParseExecutor::default(),
+            follow_symlinks: false,
+            skip_vendored: true,
+            dedupe_links: true,
+            submodules: Submodules::default(),
+            blame_owners: false,
+        }
+    }
+}
+
+// What becomes of git submodules under the indexed directory
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Submodules {
+    // Left as they are: indexed with the parent if checked out, else not at all
+    #[default]
+    Off,
+    // Checked out and indexed as part of the parent
+    Nested,
+    // Checked out and left out of the parent, to be indexed as repos of their own named by
+    // `submodule_repo`
+    Separate,
+}
+
+impl Submodules {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Self::Off),
+            "nested" => Some(Self::Nested),
+            "separate" => Some(Self::Separate),
+            _ => None,
+        }
+    }
+}
+
+// Name a submodule indexed on its own gets
+pub fn submodule_repo(repo_name: &str, path: &str) -> String {
+    format!("{}/{}", repo_name, path)
+}
+
+// Where the walk and parse run, off the async runtime
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParseExecutor {
+    // tokio's blocking pool, shared with SQLite and Kuzu calls
+    #[default]
+    Blocking,
+    // A thread of its own, so a long parse can't hold up the blocking pool
+    Thread,
+}
+
+impl ParseExecutor {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "blocking" => Some(Self::Blocking),
+            "thread" => Some(Self::Thread),
+            _ => None,
+        }
+    }
+}
+
+// Parsing recurses as deep as the syntax tree; deeply nested files need more than the default stack
+pub const PARSE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
+// Minified bundles: anything this big whose lines average longer than MINIFIED_LINE_LENGTH
+const MINIFIED_MIN_SIZE: usize = 32 * 1024;
+const MINIFIED_LINE_LENGTH: usize = 500;
+// How much of a file is searched for NUL bytes to tell it is binary, as git does
+const BINARY_SNIFF_LENGTH: usize = 8000;
+// Directories of installed or built dependencies, left out under `skip_vendored`
+pub const VENDORED_DIRS: [&str; 5] = ["node_modules", "vendor", ".venv", "target", "dist"];
+
+// File stems of common entrypoints, and directories that hold them or a public API
+const ENTRYPOINT_STEMS: [&str; 8] = ["main", "__main__", "index", "app", "server", "cli", "lib", "manage"];
+const ENTRYPOINT_DIRS: [&str; 2] = ["cmd", "bin"];
+const API_DIRS: [&str; 6] = ["api", "apis", "public", "include", "pkg", "sdk"];
+
+// Progress through the files parsed ahead of the rest, shared by parsing and ingestion so the
+// repo is marked partially available once they are all stored
+#[derive(Default)]
+struct Priority {
+    files: AtomicUsize,
+    // Sent for ingestion and not stored yet
+    pending: AtomicUsize,
+    parsed: AtomicBool,
+    marked: AtomicBool,
+}
+
+// What the symlink and vendored-directory policies left out or went through, and the
+// submodules found
+#[derive(Default)]
+struct WalkReport {
+    symlinks_followed: usize,
+    symlinks_skipped: usize,
+    duplicate_files: usize,
+    vendored: BTreeSet<String>,
+    submodules: Vec<git::Submodule>,
+}
+
+// What became of one walked file
+enum Outcome {
+    // Sent on to ingestion
+    Parsed,
+    // Its stored hash still matches
+    Unchanged(String),
+    // Reason and relative path
+    Skipped(&'static str, String),
+}
+
+// Progress and failures are reported on `job` as the run goes
+// Fails only when parsing stops before every file was read; per-file problems end up in the
+// stats and the job's errors
+pub async fn index_repository(repo_path: &str, repo_name: &str, store: Arc<dyn GraphStore>, options: IndexOptions, job: Arc<Job>) -> Result<IndexingStats, String> {
     let repo_path_owned = repo_path.to_string();
+    job.set_phase(Phase::Walking);
+    // Until the priority files are in, what queries see is the previous run's graph or nothing
+    report(&job, "availability", store.set_availability(repo_name, "indexing").await);
+    let base = match options.incremental {
+        true => store.indexed_commit(repo_name).await.ok().flatten(),
+        false => None,
+    };
+    let stored = store.file_hashes(repo_name).await.unwrap_or_default();
 
-    // Offload blocking rayon + fs work to a dedicated thread so we don't starve the tokio runtime
-    let parsed = tokio::task::spawn_blocking(move || {
-        let files: Vec<_> = WalkBuilder::new(&repo_path_owned)
-            .hidden(false)
-            .git_ignore(true)
-            .build()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
-            .filter(|e| {
-                e.path().to_str()
-                    .map(|s| parsing::detect_language(s) != parsing::Language::Unknown)
-                    .unwrap_or(false)
-            })
-            .map(|e| e.path().to_owned())
-            .collect();
+    // Parsed files are handed to ingestion as they come and dropped once stored, so at most this
+    // many results are held at a time however big the repo is
+    let width = options.ingest_concurrency.max(1);
+    let (tx, rx) = mpsc::channel::<(String, parsing::ParsingResult, bool)>(width * 2);
+    let priority = Arc::new(Priority::default());
+    let parse_priority = priority.clone();
+
+    // Offload blocking rayon + fs work so we don't starve the tokio runtime
+    let parse_job = job.clone();
+    let parse = run_blocking(options.parse_on, move || {
+        let job = parse_job;
+        let priority = parse_priority;
+        let head = git::head_commit(&repo_path_owned);
+        let mut walked = WalkReport::default();
+        if options.submodules != Submodules::Off && head.is_some() {
+            if !git::init_submodules(&repo_path_owned) {
+                job.error("checking out submodules failed; indexing the ones already there".to_string());
+            }
+            walked.submodules = git::submodules(&repo_path_owned);
+        }
+        // git diff only sees a nested submodule's pinned commit move, not which of its files changed
+        let changes = base.and_then(|base| git::changed_files(&repo_path_owned, &base))
+            .filter(|changes| options.submodules != Submodules::Nested
+                || !changes.changed.iter().chain(&changes.deleted).any(|p| walked.submodules.iter().any(|s| s.path == *p)));
+        let is_source = |p: &Path| p.to_str().is_some_and(|s| parsing::detect_language(s) != parsing::Language::Unknown);
+        let is_dependency_file = |p: &str| manifests::is_manifest(p) || manifests::is_lockfile(p);
+        let is_manifest = |p: &Path| p.to_str().is_some_and(is_dependency_file);
+        // Files only read for the entrypoints they declare; source files are scanned as they're parsed
+        let is_entrypoint = |p: &Path| !is_source(p) && p.to_str().is_some_and(|s| entrypoints::is_entrypoint_file(&relative(&repo_path_owned, s)));
+        let mut entrypoint_paths = vec![];
+        // Manifests (and lockfiles) are None when an incremental run can keep the recorded dependencies
+        let (files, manifest_paths): (Vec<PathBuf>, Option<Vec<PathBuf>>) = match &changes {
+            Some(changes) => {
+                let mut files = vec![];
+                for rel in &changes.changed {
+                    let path = Path::new(&repo_path_owned).join(rel);
+                    // git names files the walk would have left out, so the same policies apply
+                    if let Some(dir) = vendored_dir(rel).filter(|_| options.skip_vendored) {
+                        walked.vendored.insert(dir);
+                    } else if !options.follow_symlinks && path.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) {
+                        walked.symlinks_skipped += 1;
+                    } else if path.is_file() && is_source(&path) {
+                        files.push(path);
+                    } else if path.is_file() && is_entrypoint(&path) {
+                        entrypoint_paths.push(path);
+                    }
+                }
+                // Dependencies are recorded for the repo as a whole, so one changed manifest means
+                // reading them all
+                let touched = changes.changed.iter().chain(&changes.deleted).any(|p| is_dependency_file(p));
+                (files, touched.then(|| walk(&repo_path_owned, &options, &mut walked).into_iter().filter(|p| is_manifest(p)).collect()))
+            }
+            None => {
+                let (mut files, mut found) = (vec![], vec![]);
+                for path in walk(&repo_path_owned, &options, &mut walked) {
+                    if is_manifest(&path) {
+                        found.push(path.clone());
+                    }
+                    if is_source(&path) {
+                        files.push(path);
+                    } else if is_entrypoint(&path) {
+                        entrypoint_paths.push(path);
+                    }
+                }
+                (files, Some(found))
+            }
+        };
+        let dependencies: Option<Vec<Dependency>> = manifest_paths.map(|paths| {
+            let (lockfiles, paths): (Vec<_>, Vec<_>) = paths.iter().partition(|p| p.to_str().is_some_and(manifests::is_lockfile));
+            let mut dependencies: Vec<Dependency> = paths.iter()
+                .filter_map(|path| {
+                    let (content, _) = read_file(path, options.max_file_size).ok()?;
+                    Some(manifests::parse(&relative(&repo_path_owned, path.to_str()?), &content))
+                })
+                .flatten()
+                .collect();
+            // Lockfiles are only read, never indexed, and often outgrow the file size limit
+            let lockfiles: Vec<(String, String)> = lockfiles.iter()
+                .filter_map(|path| Some((relative(&repo_path_owned, path.to_str()?), std::fs::read_to_string(path).ok()?)))
+                .collect();
+            manifests::resolve(&mut dependencies, &lockfiles);
+            dependencies
+        });
+        // Only the root's license files count; an incremental run rereads them when one changed
+        let license_changed = changes.as_ref()
+            .is_none_or(|changes| changes.changed.iter().chain(&changes.deleted).any(|p| licenses::is_license_file(p)));
+        let license_files: Option<Vec<(String, String)>> = license_changed.then(|| {
+            std::fs::read_dir(&repo_path_owned).into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_file()))
+                .filter_map(|entry| {
+                    let name = entry.file_name().into_string().ok().filter(|name| licenses::is_license_file(name))?;
+                    let (content, _) = read_file(&entry.path(), options.max_file_size).ok()?;
+                    Some((name, content))
+                })
+                .collect()
+        });
 
         let total_files = files.len();
-        let parsed: Vec<_> = files.par_iter()
+        let listed: HashSet<String> = files.iter().filter_map(|path| path.to_str()).map(|s| relative(&repo_path_owned, s)).collect();
+        job.set_total(total_files);
+        job.set_phase(Phase::Parsing);
+        let (first, rest) = prioritize(&repo_path_owned, files);
+        priority.files.store(first.len(), Ordering::Relaxed);
+        // Files whose stored hash still matches are neither parsed nor ingested again
+        let transcoded = AtomicUsize::new(0);
+        let headers = Mutex::new(BTreeMap::new());
+        let found = Mutex::new(entrypoint_paths.iter()
             .filter_map(|path| {
-                let s = path.to_str()?;
-                let content = std::fs::read_to_string(path).ok()?;
-                Some((s.to_string(), parsing::parse_content(s, &content)))
+                let (content, _) = read_file(path, options.max_file_size).ok()?;
+                Some(entrypoints::detect(&relative(&repo_path_owned, path.to_str()?), &content))
             })
-            .collect();
+            .flatten()
+            .collect::<Vec<Entrypoint>>());
+        let read = |files: &[PathBuf], first: bool| files.par_iter()
+            .map(|path| {
+                let Some(s) = path.to_str() else {
+                    return Outcome::Skipped("unreadable", path.to_string_lossy().into_owned());
+                };
+                let rel = relative(&repo_path_owned, s);
+                let outcome = read_file(path, options.max_file_size).map(|(content, converted)| {
+                    if converted {
+                        transcoded.fetch_add(1, Ordering::Relaxed);
+                    }
+                    if let Some(expression) = licenses::spdx_header(&content) {
+                        *headers.lock().unwrap_or_else(|e| e.into_inner()).entry(expression).or_insert(0) += 1;
+                    }
+                    let declared = entrypoints::detect(&rel, &content);
+                    if !declared.is_empty() {
+                        found.lock().unwrap_or_else(|e| e.into_inner()).extend(declared);
+                    }
+                    if stored.get(&rel).is_some_and(|hash| !hash.is_empty() && *hash == parsing::content_hash(&content)) {
+                        Outcome::Unchanged(rel.clone())
+                    } else {
+                        // A file the parser panics on is left out rather than taking the run down with it
+                        let mut result = match parse_guarded(s, || parsing::parse_content(s, &content)) {
+                            Ok(result) => result,
+                            Err(message) => {
+                                job.error(format!("{}: parser panicked: {}", rel, message));
+                                return Outcome::Skipped("parse_panic", rel.clone());
+                            }
+                        };
+                        if options.blame_owners && head.is_some() {
+                            attribute_owners(&repo_path_owned, &rel, &mut result);
+                        }
+                        if first {
+                            priority.pending.fetch_add(1, Ordering::Relaxed);
+                        }
+                        // Waits while ingestion is behind
+                        let _ = tx.blocking_send((s.to_string(), result, first));
+                        Outcome::Parsed
+                    }
+                });
+                job.file_parsed(&rel);
+                outcome.unwrap_or_else(|reason| Outcome::Skipped(reason, rel))
+            })
+            .collect::<Vec<Outcome>>();
+        let read_all = || {
+            let mut outcomes = read(&first, true);
+            priority.parsed.store(true, Ordering::Relaxed);
+            outcomes.extend(read(&rest, false));
+            outcomes
+        };
+        let pool = options.threads.and_then(|threads| {
+            rayon::ThreadPoolBuilder::new().num_threads(threads).stack_size(PARSE_STACK_SIZE).build().ok()
+        });
+        let outcomes = match pool {
+            Some(pool) => pool.install(read_all),
+            None => read_all(),
+        };
+        // Closes the channel, ending ingestion once it has caught up
+        drop(tx);
+        let mut unchanged = HashSet::new();
+        let mut skipped = BTreeMap::new();
+        let mut undecodable = vec![];
+        for outcome in outcomes {
+            match outcome {
+                Outcome::Parsed => {}
+                Outcome::Unchanged(path) => { unchanged.insert(path); }
+                Outcome::Skipped(reason, path) => {
+                    *skipped.entry(reason.to_string()).or_insert(0) += 1;
+                    if reason == "undecodable" {
+                        undecodable.push(path);
+                    }
+                }
+            }
+        }
+        undecodable.sort();
+        // Go interfaces are satisfied across files, so a run that parsed or deleted Go files infers
+        // over all of them; the ones it left alone are parsed again for their outline
+        let is_go = |p: &str| parsing::detect_language(p) == parsing::Language::Go;
+        let go_touched = listed.iter().any(|rel| is_go(rel) && !unchanged.contains(rel))
+            || changes.as_ref().is_some_and(|changes| changes.deleted.iter().any(|p| is_go(p)));
+        let carried_go: Vec<(String, parsing::ParsingResult)> = match go_touched {
+            true => walk(&repo_path_owned, &options, &mut WalkReport::default()).par_iter()
+                .filter_map(|path| {
+                    let s = path.to_str().filter(|s| is_go(s))?;
+                    let rel = relative(&repo_path_owned, s);
+                    if listed.contains(&rel) && !unchanged.contains(&rel) {
+                        return None;
+                    }
+                    let (content, _) = read_file(path, options.max_file_size).ok()?;
+                    let result = parse_guarded(s, || parsing::parse_content(s, &content)).ok()?;
+                    parsing::go_outline(&result).map(|outline| (s.to_string(), outline))
+                })
+                .collect(),
+            false => vec![],
+        };
+        job.set_phase(Phase::Ingesting);
+        // Files that weren't read or didn't change count as processed straight away
+        job.files_skipped(unchanged.len() + skipped.values().sum::<usize>());
+        let skips = (skipped, undecodable, transcoded.into_inner());
 
-        (parsed, total_files)
-    }).await.unwrap_or_default();
+        let licensing = (license_files, headers.into_inner().unwrap_or_else(|e| e.into_inner()));
+        let found = found.into_inner().unwrap_or_else(|e| e.into_inner());
+        let history = head.as_ref().and_then(|_| git::file_history(&repo_path_owned)).map(|mut history| {
+            // The parent's log stops at submodules; nested ones have histories of their own
+            if options.submodules == Submodules::Nested {
+                for submodule in &walked.submodules {
+                    let dir = Path::new(&repo_path_owned).join(&submodule.path);
+                    let nested = dir.to_str().and_then(git::file_history).unwrap_or_default();
+                    history.extend(nested.into_iter().map(|(path, h)| (format!("{}/{}", submodule.path, path), h)));
+                }
+            }
+            history
+        });
 
-    let (parsed, total_walked) = parsed;
+        (unchanged, skips, total_files, head, changes, stored, dependencies, licensing, found, history, walked, carried_go)
+    });
 
-    let mut stats = IndexingStats {
-        files_processed: parsed.len(),
-        files_skipped: total_walked - parsed.len(),
-        ..Default::default()
-    };
+    let mut stats = IndexingStats::default();
+    let mut rel_paths = vec![];
+    let mut seen = HashSet::new();
+    // Go interface satisfaction needs every file's method sets, so it is linked after ingestion;
+    // only the symbols it looks at are kept. Files this run didn't parse are added once it's over
+    let mut go_outlines = vec![];
+    let repo_name_arc: Arc<str> = repo_name.into();
+    let received = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|file| (file, rx)) });
 
-    if let Some(client) = graph {
-        let repo_name_arc: Arc<str> = repo_name.into();
-
-        // Ingest files concurrently (up to 32 at a time) instead of sequentially
-        let results: Vec<usize> = stream::iter(parsed.into_iter())
-            .map(|(path, result)| {
-                let client = client.clone();
-                let rn = repo_name_arc.clone();
-                let rel = Path::new(&path).strip_prefix(repo_path).unwrap_or(Path::new(&path))
-                    .to_str().unwrap_or(&path).to_string();
-                let sym_count = result.symbols.len() + 1;
-                async move {
-                    if client.ingest_symbols(&rn, &rel, &result).await.is_ok() {
-                        sym_count
-                    } else {
+    // Ingest files concurrently (up to `ingest_concurrency` at a time) instead of sequentially
+    let ingest = received
+        .map(|(path, result, first)| {
+            let rel = relative(repo_path, &path);
+            tally(&mut stats, &rel, &result);
+            seen.extend(graph::node_ids(repo_name, &rel, &result));
+            go_outlines.extend(parsing::go_outline(&result).map(|outline| (path, outline)));
+            rel_paths.push(rel.clone());
+
+            let store = store.clone();
+            let job = job.clone();
+            let rn = repo_name_arc.clone();
+            let priority = priority.clone();
+            let sym_count = result.symbols.len() + 1;
+            async move {
+                let ingested = store.ingest_symbols(&rn, &rel, &result).await;
+                job.file_ingested(&rel);
+                if first {
+                    priority.pending.fetch_sub(1, Ordering::Relaxed);
+                }
+                let caught_up = priority.parsed.load(Ordering::Relaxed) && priority.pending.load(Ordering::Relaxed) == 0;
+                if caught_up && !priority.marked.swap(true, Ordering::Relaxed) {
+                    report(&job, "availability", store.set_availability(&rn, "partial").await);
+                    job.available("partial");
+                }
+                match ingested {
+                    Ok(()) => sym_count,
+                    Err(e) => {
+                        job.error(format!("{}: {}", rel, e));
                         0
                     }
                 }
-            })
-            .buffer_unordered(32)
-            .collect()
-            .await;
+            }
+        })
+        .buffer_unordered(width)
+        .fold(0, |created, nodes| async move { created + nodes });
+    let (parsed, nodes_created) = tokio::join!(parse, ingest);
+    let (unchanged, (skipped, undecodable, transcoded), total_walked, head, changes, stored, dependencies, (license_files, headers), found, history, walked, carried_go) =
+        parsed.ok_or_else(|| "parsing stopped before every file was read".to_string())?;
+
+    let processed = rel_paths.len();
+    go_outlines.extend(carried_go);
+    let go_implementations = parsing::infer_go_implementations(&go_outlines);
+    stats = IndexingStats {
+        files_processed: processed,
+        files_skipped: total_walked.saturating_sub(processed).saturating_sub(unchanged.len()),
+        files_skipped_reason: skipped,
+        files_transcoded: transcoded,
+        undecodable_files: undecodable,
+        files_unchanged: unchanged.len(),
+        nodes_created,
+        incremental: changes.is_some(),
+        files_deleted: changes.as_ref().map_or(0, |c| c.deleted.len()),
+        priority_files: priority.files.load(Ordering::Relaxed),
+        symlinks_followed: walked.symlinks_followed,
+        symlinks_skipped: walked.symlinks_skipped,
+        duplicate_files: walked.duplicate_files,
+        vendored_dirs_skipped: walked.vendored.into_iter().collect(),
+        submodules: walked.submodules,
+        commit: head.clone(),
+        ..stats
+    };
+    job.set_phase(Phase::Linking);
+    // Unchanged files weren't parsed, so their nodes aren't in `seen` and must be left out
+    let removed = match &changes {
+        // Only the files git named can have gone stale
+        Some(changes) => {
+            let touched: Vec<String> = changes.changed.iter().chain(&changes.deleted)
+                .filter(|path| !unchanged.contains(*path))
+                .cloned()
+                .collect();
+            store.remove_stale(repo_name, Some(&touched), &seen).await
+        }
+        // An empty walk more likely means the checkout is unreadable than that every file was deleted
+        None if total_walked == 0 => Ok(0),
+        None if unchanged.is_empty() => store.remove_stale(repo_name, None, &seen).await,
+        None => {
+            let touched: Vec<String> = stored.into_keys().filter(|path| !unchanged.contains(path)).collect();
+            store.remove_stale(repo_name, Some(&touched), &seen).await
+        }
+    };
+    stats.nodes_removed = report(&job, "stale cleanup", removed).unwrap_or(0) as usize;
+    // Files this run didn't parse count with the metrics stored for them, so the totals and
+    // breakdowns cover the whole repo
+    if changes.is_some() || !unchanged.is_empty() {
+        let parsed: HashSet<&str> = rel_paths.iter().map(String::as_str).collect();
+        for file in report(&job, "stored files", store.get_all_files(repo_name).await).unwrap_or_default() {
+            let Some(rel) = file.get("path").and_then(Value::as_str).filter(|rel| !parsed.contains(rel)) else { continue };
+            let metrics: parsing::FileMetrics = file.get("metrics").cloned()
+                .and_then(|metrics| serde_json::from_value(metrics).ok())
+                .unwrap_or_default();
+            let language = file.get("language").and_then(Value::as_str).unwrap_or("Unknown");
+            let has_errors = file.get("parse_errors").and_then(Value::as_i64).unwrap_or(0) > 0;
+            add_metrics(&mut stats, rel, language.to_string(), &metrics, has_errors);
+        }
+    }
+    report(&job, "implementations", store.link_implementations(repo_name, &go_implementations).await);
+    report(&job, "directories", store.link_hierarchy(repo_name, &rel_paths).await);
+    report(&job, "modules", store.link_modules(repo_name).await);
+    report(&job, "packages", store.link_packages(repo_name).await);
+    report(&job, "mentions", store.link_mentions(repo_name).await);
+    if options.submodules != Submodules::Off {
+        if options.submodules == Submodules::Separate {
+            for submodule in &mut stats.submodules {
+                submodule.repo = Some(submodule_repo(repo_name, &submodule.path));
+            }
+        }
+        report(&job, "submodules", store.set_submodules(repo_name, &stats.submodules).await);
+    }
+    // Files this run walked; the history of the rest can't have moved without them changing
+    if let Some(mut history) = history {
+        let walked: HashSet<&String> = rel_paths.iter().chain(&unchanged).collect();
+        history.retain(|path, _| walked.contains(path));
+        report(&job, "history", store.set_file_history(repo_name, &history).await);
+    }
+    // Like stale removal, a walk that found nothing at all is left alone
+    if let Some(dependencies) = dependencies.filter(|d| !d.is_empty() || total_walked > 0 || changes.is_some()) {
+        stats.dependencies_declared = dependencies.len();
+        stats.dependencies_resolved = dependencies.iter().filter(|d| d.resolved_version.is_some()).count();
+        report(&job, "dependencies", store.set_declared_dependencies(repo_name, &dependencies).await);
+    }
+    if let Some(files) = license_files.filter(|f| !f.is_empty() || total_walked > 0 || changes.is_some()) {
+        // Headers are only counted across the whole repo on a full run; an incremental one keeps
+        // that count
+        let headers = if changes.is_some() {
+            store.get_license(repo_name).await.ok().flatten().map(|l| l.headers).unwrap_or_default()
+        } else {
+            headers
+        };
+        let license = licenses::detect(&files, headers);
+        stats.license = license.spdx.clone();
+        report(&job, "license", store.set_license(repo_name, &license).await);
+    }
+    // Every file is scanned on a full run; an incremental one keeps what the files git didn't name
+    // declared
+    let entrypoints = match &changes {
+        Some(changes) => store.get_entrypoints(repo_name).await.map(|stored| {
+            let touched: HashSet<&String> = changes.changed.iter().chain(&changes.deleted).collect();
+            stored.into_iter().filter(|e| !touched.contains(&e.file)).chain(found).collect::<Vec<_>>()
+        }),
+        None => Ok(found),
+    };
+    if let Some(mut entrypoints) = report(&job, "entrypoints", entrypoints).filter(|e| !e.is_empty() || total_walked > 0 || changes.is_some()) {
+        entrypoints.sort();
+        entrypoints.dedup();
+        stats.entrypoints = entrypoints.len();
+        report(&job, "entrypoints", store.set_entrypoints(repo_name, &entrypoints).await);
+    }
+    // Clustering is optional, and unsupported off Neo4j
+    let _ = store.detect_clusters(repo_name).await;
+    if let Some(commit) = &head {
+        report(&job, "commit", store.set_indexed_commit(repo_name, commit).await);
+    }
+    report(&job, "availability", store.set_availability(repo_name, "complete").await);
+    job.available("complete");
+
+    Ok(stats)
+}
+
+// `files` split into those parsed first, then the rest: entrypoints, then public API directories,
+// then READMEs and the code beside them; tests come last of all
+fn prioritize(root: &str, files: Vec<PathBuf>) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let rels: Vec<String> = files.iter().map(|path| relative(root, &path.to_string_lossy())).collect();
+    let dir = |rel: &str| rel.rsplit_once('/').map_or("", |(dir, _)| dir).to_string();
+    let readme_dirs: HashSet<String> = rels.iter().filter(|rel| parsing::document_kind(rel) == "readme").map(|rel| dir(rel)).collect();
+    let rank = |rel: &str| -> u8 {
+        let name = rel.rsplit('/').next().unwrap_or(rel);
+        let stem = name.split('.').next().unwrap_or(name);
+        let dirs: Vec<&str> = rel.split('/').rev().skip(1).collect();
+        if parsing::named_like_test(rel, parsing::detect_language(rel)) {
+            4
+        } else if ENTRYPOINT_STEMS.contains(&stem) || dirs.iter().any(|d| ENTRYPOINT_DIRS.contains(d)) {
+            0
+        } else if dirs.iter().any(|d| API_DIRS.contains(d)) {
+            1
+        } else if readme_dirs.contains(&dir(rel)) {
+            2
+        } else {
+            3
+        }
+    };
+    let mut ranked: Vec<(u8, PathBuf)> = rels.iter().map(|rel| rank(rel)).zip(files).collect();
+    ranked.sort();
+    let rest = ranked.split_off(ranked.partition_point(|(rank, _)| *rank < 3));
+    let paths = |ranked: Vec<(u8, PathBuf)>| ranked.into_iter().map(|(_, path)| path).collect();
+    (paths(ranked), paths(rest))
+}
+
+// Files under `root`, minus what .gitignore, the symlink and vendored-directory policies in
+// `options`, and submodules indexed separately leave out
+fn walk(root: &str, options: &IndexOptions, report: &mut WalkReport) -> Vec<PathBuf> {
+    let vendored = Arc::new(Mutex::new(BTreeSet::new()));
+    let mut builder = WalkBuilder::new(root);
+    builder.hidden(false).git_ignore(true).follow_links(options.follow_symlinks);
+    let separate: HashSet<String> = match options.submodules {
+        Submodules::Separate => report.submodules.iter().map(|s| s.path.clone()).collect(),
+        _ => HashSet::new(),
+    };
+    if options.skip_vendored || !separate.is_empty() {
+        let (vendored, root, skip_vendored) = (vendored.clone(), root.to_string(), options.skip_vendored);
+        builder.filter_entry(move |entry| {
+            if entry.depth() == 0 || !entry.file_type().is_some_and(|t| t.is_dir()) {
+                return true;
+            }
+            let dir = relative(&root, &entry.path().to_string_lossy());
+            let is_vendored = skip_vendored && entry.file_name().to_str().is_some_and(|name| VENDORED_DIRS.contains(&name));
+            if is_vendored {
+                vendored.lock().unwrap_or_else(|e| e.into_inner()).insert(dir.clone());
+            }
+            !is_vendored && !separate.contains(&dir)
+        });
+    }
+    let mut files = vec![];
+    for entry in builder.build().filter_map(|e| e.ok()) {
+        if entry.path_is_symlink() {
+            match options.follow_symlinks {
+                true => report.symlinks_followed += 1,
+                false => report.symlinks_skipped += 1,
+            }
+        }
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            files.push(entry.into_path());
+        }
+    }
+    report.vendored.extend(std::mem::take(&mut *vendored.lock().unwrap_or_else(|e| e.into_inner())));
+    if options.follow_symlinks && options.dedupe_links {
+        files = dedupe(root, files, report);
+    }
+    files
+}
+
+// One path per file the paths resolve to: one with no symlink on the way if there is one, else
+// the first in order
+fn dedupe(root: &str, files: Vec<PathBuf>, report: &mut WalkReport) -> Vec<PathBuf> {
+    let real_root = std::fs::canonicalize(root).ok();
+    let mut by_target: HashMap<PathBuf, (bool, PathBuf)> = HashMap::new();
+    let mut unresolved = vec![];
+    for path in files {
+        let Ok(target) = std::fs::canonicalize(&path) else {
+            unresolved.push(path);
+            continue;
+        };
+        let direct = real_root.as_ref()
+            .zip(path.strip_prefix(root).ok())
+            .is_some_and(|(real_root, rel)| real_root.join(rel) == target);
+        let candidate = (!direct, path);
+        match by_target.get_mut(&target) {
+            Some(kept) => {
+                report.duplicate_files += 1;
+                if candidate < *kept {
+                    *kept = candidate;
+                }
+            }
+            None => { by_target.insert(target, candidate); }
+        }
+    }
+    let mut files: Vec<PathBuf> = by_target.into_values().map(|(_, path)| path).chain(unresolved).collect();
+    files.sort();
+    files
+}
+
+// The vendored directory `rel_path` lies in, if any
+fn vendored_dir(rel_path: &str) -> Option<String> {
+    let mut dir = PathBuf::new();
+    for part in Path::new(rel_path).parent()?.components() {
+        dir.push(part);
+        if part.as_os_str().to_str().is_some_and(|name| VENDORED_DIRS.contains(&name)) {
+            return dir.to_str().map(str::to_string);
+        }
+    }
+    None
+}
 
-        stats.nodes_created = results.iter().sum();
+// Set `owners` on the file's functions and classes from `git blame`. Files git doesn't track
+// are left without
+fn attribute_owners(dir: &str, rel_path: &str, result: &mut parsing::ParsingResult) {
+    let owned = |kind: &str| matches!(graph::label_for_kind(kind), "Function" | "Class");
+    if !result.symbols.iter().any(|sym| owned(&sym.kind)) {
+        return;
     }
+    let Some(authors) = git::blame(dir, rel_path) else { return };
+    for sym in result.symbols.iter_mut().filter(|sym| owned(&sym.kind)) {
+        sym.owners = git::owners(&authors, sym.range);
+    }
+}
+
+// A file's content as UTF-8 and whether it had to be converted, or why it isn't worth parsing
+fn read_file(path: &Path, max_size: Option<u64>) -> Result<(String, bool), &'static str> {
+    let size = std::fs::metadata(path).map_err(|_| "unreadable")?.len();
+    if max_size.is_some_and(|max| size > max) {
+        return Err("too_large");
+    }
+    let bytes = std::fs::read(path).map_err(|_| "unreadable")?;
+    // UTF-16 is full of NULs, so the byte order mark is looked for first
+    let bom = Encoding::for_bom(&bytes);
+    if bom.is_none() && bytes[..bytes.len().min(BINARY_SNIFF_LENGTH)].contains(&0) {
+        return Err("binary");
+    }
+    let (content, converted) = decode(&bytes, bom.map(|(encoding, _)| encoding)).ok_or("undecodable")?;
+    if is_minified(path, &content) {
+        return Err("minified");
+    }
+    Ok((content, converted))
+}
 
-    stats
+// The encoding a byte order mark names, else UTF-8, else Shift-JIS when the result reads as
+// Japanese, else Windows-1252 (a superset of Latin-1) when the result has no stray control
+// characters. The bool is whether anything but plain UTF-8 was decoded
+fn decode(bytes: &[u8], bom: Option<&'static Encoding>) -> Option<(String, bool)> {
+    if let Some(encoding) = bom {
+        let (text, malformed) = encoding.decode_with_bom_removal(bytes);
+        return (!malformed).then(|| (text.into_owned(), encoding != UTF_8));
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Some((text.to_string(), false));
+    }
+    let japanese = SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)
+        .filter(|text| text.chars().any(|c| ('\u{3040}'..='\u{30ff}').contains(&c)));
+    if let Some(text) = japanese {
+        return Some((text.into_owned(), true));
+    }
+    let text = WINDOWS_1252.decode_without_bom_handling_and_without_replacement(bytes)?;
+    let plausible = text.chars().all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c'));
+    plausible.then(|| (text.into_owned(), true))
+}
+
+// `parse`, with a panic logged and returned as its message. Parsing only reads the file's content;
+// what it changes is this thread's parsers, dropped after a panic so a half-done parse isn't
+// resumed, and query caches that are only ever set whole. That is what AssertUnwindSafe relies on
+fn parse_guarded<T>(path: &str, parse: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(parse)).map_err(|payload| {
+        parsing::reset_parsers();
+        let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "no message".to_string());
+        warn!("Parsing {} panicked: {}", path, message);
+        message
+    })
+}
+
+fn is_minified(path: &Path, content: &str) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if name.contains(".min.") {
+        return true;
+    }
+    content.len() >= MINIFIED_MIN_SIZE && content.len() / content.lines().count().max(1) > MINIFIED_LINE_LENGTH
+}
+
+async fn run_blocking<T, F>(executor: ParseExecutor, f: F) -> Option<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    match executor {
+        ParseExecutor::Blocking => tokio::task::spawn_blocking(f).await.ok(),
+        ParseExecutor::Thread => {
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            std::thread::Builder::new()
+                .name("index-parse".to_string())
+                .stack_size(PARSE_STACK_SIZE)
+                .spawn(move || { let _ = tx.send(f()); })
+                .ok()?;
+            rx.await.ok()
+        }
+    }
+}
+
+// Add a processed file to the totals and breakdowns
+fn tally(stats: &mut IndexingStats, rel: &str, result: &parsing::ParsingResult) {
+    add_metrics(stats, rel, format!("{:?}", result.language), &result.metrics, !result.parse_errors.is_empty());
+}
+
+fn add_metrics(stats: &mut IndexingStats, rel: &str, language: String, metrics: &parsing::FileMetrics, has_errors: bool) {
+    let top = match rel.split_once('/') {
+        Some((dir, _)) => dir.to_string(),
+        None => ".".to_string(),
+    };
+    for breakdown in [
+        stats.languages.entry(language).or_default(),
+        stats.directories.entry(top).or_default(),
+    ] {
+        breakdown.files += 1;
+        breakdown.symbols += metrics.symbol_count;
+    }
+    stats.lines_of_code += metrics.code_lines;
+    stats.comment_lines += metrics.comment_lines;
+    stats.blank_lines += metrics.blank_lines;
+    stats.symbols_found += metrics.symbol_count;
+    if has_errors {
+        stats.files_with_parse_errors += 1;
+    }
+}
+
+fn report<T>(job: &Job, step: &str, result: neo4rs::Result<T>) -> Option<T> {
+    result.map_err(|e| job.error(format!("{}: {}", step, e))).ok()
+}
+
+fn relative(root: &str, path: &str) -> String {
+    Path::new(path).strip_prefix(root).unwrap_or(Path::new(path)).to_str().unwrap_or(path).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::Jobs;
+    use crate::memory::MemoryGraph;
+
+    #[tokio::test]
+    async fn reindex_links_go_types_to_unchanged_interfaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().to_str().unwrap();
+        std::fs::write(dir.path().join("shape.go"), "package geo\n\ntype Shape interface {\n\tArea() float64\n}\n").unwrap();
+        std::fs::write(dir.path().join("square.go"), "package geo\n\ntype Square struct {\n\tside float64\n}\n").unwrap();
+        let (store, jobs) = (Arc::new(MemoryGraph::new()), Jobs::default());
+        index_repository(root, "r", store.clone(), IndexOptions::default(), jobs.start("r")).await.unwrap();
+        assert!(store.implementations("r").is_empty());
+
+        std::fs::write(
+            dir.path().join("square.go"),
+            "package geo\n\ntype Square struct {\n\tside float64\n}\n\nfunc (s Square) Area() float64 {\n\treturn s.side * s.side\n}\n",
+        ).unwrap();
+        let stats = index_repository(root, "r", store.clone(), IndexOptions::default(), jobs.start("r")).await.unwrap();
+        assert_eq!((stats.files_processed, stats.files_unchanged), (1, 1));
+        assert_eq!(stats.languages["Go"].files, 2);
+        assert!(store.implementations("r").iter().any(|i| i.type_name == "Square" && i.trait_name == "Shape"));
+    }
+
+    #[test]
+    fn parse_panic_is_caught_and_the_thread_parses_on() {
+        let source = "def area(side):\n    return side * side\n";
+        parsing::parse_content("warm.py", source);
+        let caught = parse_guarded("broken.py", || -> parsing::ParsingResult { panic!("grammar gave up on {}", "broken.py") });
+        assert_eq!(caught.err().as_deref(), Some("grammar gave up on broken.py"));
+        let result = parse_guarded("next.py", || parsing::parse_content("next.py", source)).unwrap();
+        assert_eq!(result.symbols[0].name, "area");
+    }
 }
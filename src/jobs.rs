@@ -0,0 +1,231 @@
+use futures::Stream;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// Finished jobs kept for status lookups; older ones are forgotten first
+const FINISHED_JOBS_KEPT: usize = 200;
+// Progress events buffered per job; a listener further behind than this skips ahead
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Queued,
+    Walking,
+    Parsing,
+    Ingesting,
+    Linking,
+    Done,
+    Failed,
+}
+
+// One background index run. Counters are atomics so rayon workers and ingest tasks can report
+// without taking the lock
+pub struct Job {
+    pub id: String,
+    pub repo: String,
+    files_total: AtomicUsize,
+    files_parsed: AtomicUsize,
+    files_ingested: AtomicUsize,
+    state: Mutex<JobState>,
+    events: broadcast::Sender<Value>,
+}
+
+struct JobState {
+    phase: Phase,
+    errors: Vec<String>,
+    started_at: u64,
+    finished_at: Option<u64>,
+    result: Option<Value>,
+}
+
+impl Job {
+    fn new(id: String, repo: &str) -> Self {
+        Self {
+            id,
+            repo: repo.to_string(),
+            files_total: AtomicUsize::new(0),
+            files_parsed: AtomicUsize::new(0),
+            files_ingested: AtomicUsize::new(0),
+            state: Mutex::new(JobState { phase: Phase::Queued, errors: vec![], started_at: now(), finished_at: None, result: None }),
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    pub fn set_phase(&self, phase: Phase) {
+        self.lock().phase = phase;
+        self.emit(json!({ "type": "phase", "phase": phase }));
+    }
+
+    pub fn set_total(&self, files: usize) {
+        self.files_total.store(files, Ordering::Relaxed);
+    }
+
+    pub fn file_parsed(&self, path: &str) {
+        self.files_parsed.fetch_add(1, Ordering::Relaxed);
+        self.emit_file("parsed", path);
+    }
+
+    pub fn file_ingested(&self, path: &str) {
+        self.files_ingested.fetch_add(1, Ordering::Relaxed);
+        self.emit_file("ingested", path);
+    }
+
+    // Files that need no ingesting
+    pub fn files_skipped(&self, files: usize) {
+        self.files_ingested.fetch_add(files, Ordering::Relaxed);
+    }
+
+    // The repo's graph has become usable as far as `availability` says, before the run is over
+    pub fn available(&self, availability: &str) {
+        self.emit(json!({ "type": "availability", "availability": availability }));
+    }
+
+    pub fn error(&self, message: String) {
+        self.emit(json!({ "type": "error", "message": message }));
+        self.lock().errors.push(message);
+    }
+
+    // `result` is what a blocking /index would have answered
+    pub fn finish(&self, result: Value) {
+        {
+            let mut state = self.lock();
+            state.phase = if result.get("error").is_some() { Phase::Failed } else { Phase::Done };
+            state.finished_at = Some(now());
+            state.result = Some(result);
+        }
+        let mut done = self.status();
+        done["type"] = json!("done");
+        self.emit(done);
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.lock().finished_at.is_some()
+    }
+
+    pub fn status(&self) -> Value {
+        let state = self.lock();
+        let mut status = self.progress(state.phase);
+        status["job_id"] = json!(self.id);
+        status["repo"] = json!(self.repo);
+        status["phase"] = json!(state.phase);
+        status["errors"] = json!(state.errors);
+        status["started_at"] = json!(state.started_at);
+        status["finished_at"] = json!(state.finished_at);
+        status["result"] = json!(state.result);
+        status
+    }
+
+    // Everything that happens to the job from now on, starting with its current status (or the
+    // final one, when it is already over). Ends after the "done" event
+    pub fn events(&self) -> impl Stream<Item = Value> {
+        let rx = self.events.subscribe();
+        let mut first = self.status();
+        let finished = self.is_finished();
+        first["type"] = json!(if finished { "done" } else { "status" });
+        let rx = (!finished).then_some(rx);
+        futures::stream::unfold((Some(first), rx), |(first, rx)| async move {
+            if let Some(first) = first {
+                return Some((first, (None, rx)));
+            }
+            let mut rx = rx?;
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        let done = event["type"] == "done";
+                        return Some((event, (None, (!done).then_some(rx))));
+                    }
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    // Counters and percentage. Parsing is the first half of the work and ingestion most of the
+    // second; linking the rest
+    fn progress(&self, phase: Phase) -> Value {
+        let total = self.files_total.load(Ordering::Relaxed);
+        let parsed = self.files_parsed.load(Ordering::Relaxed);
+        let ingested = self.files_ingested.load(Ordering::Relaxed);
+        let share = |done: usize| if total == 0 { 1.0 } else { done.min(total) as f64 / total as f64 };
+        let percent = match phase {
+            Phase::Queued | Phase::Walking => 0.0,
+            Phase::Parsing => 50.0 * share(parsed),
+            Phase::Ingesting => 50.0 + 45.0 * share(ingested),
+            Phase::Linking => 95.0,
+            Phase::Done | Phase::Failed => 100.0,
+        };
+        json!({
+            "files_total": total,
+            "files_parsed": parsed,
+            "files_processed": ingested,
+            "percent": (percent * 10.0).round() / 10.0,
+        })
+    }
+
+    fn emit_file(&self, stage: &str, path: &str) {
+        // Nobody listening is the common case; skip building the event
+        if self.events.receiver_count() == 0 {
+            return;
+        }
+        let mut event = self.progress(self.lock().phase);
+        event["type"] = json!("file");
+        event["stage"] = json!(stage);
+        event["path"] = json!(path);
+        self.emit(event);
+    }
+
+    fn emit(&self, event: Value) {
+        let _ = self.events.send(event);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+// In-process registry of index jobs. Nothing survives a restart; a job that was running then is
+// simply unknown afterwards
+#[derive(Default)]
+pub struct Jobs {
+    jobs: RwLock<HashMap<String, Arc<Job>>>,
+    order: Mutex<VecDeque<String>>,
+    next: AtomicUsize,
+}
+
+impl Jobs {
+    pub fn start(&self, repo: &str) -> Arc<Job> {
+        let id = format!("{}-{}", now(), self.next.fetch_add(1, Ordering::Relaxed));
+        let job = Arc::new(Job::new(id.clone(), repo));
+        let mut jobs = self.jobs.write().unwrap_or_else(|e| e.into_inner());
+        let mut order = self.order.lock().unwrap_or_else(|e| e.into_inner());
+        // Forget the oldest finished jobs; running ones are never dropped
+        let finished = order.iter().filter(|id| jobs.get(*id).is_some_and(|j| j.is_finished())).count();
+        let mut excess = finished.saturating_sub(FINISHED_JOBS_KEPT);
+        order.retain(|id| {
+            if excess > 0 && jobs.get(id).is_some_and(|j| j.is_finished()) {
+                jobs.remove(id);
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        order.push_back(id.clone());
+        jobs.insert(id, job.clone());
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Job>> {
+        self.jobs.read().unwrap_or_else(|e| e.into_inner()).get(id).cloned()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
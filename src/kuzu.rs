@@ -0,0 +1,734 @@
+use async_trait::async_trait;
+use kuzu::{Connection, Database, SystemConfig};
+use neo4rs::{Error, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::git::{FileHistory, Submodule};
+use crate::graph::{self, CallEnd, Snapshot};
+use crate::classifier::Feedback;
+use crate::entrypoints::Entrypoint;
+use crate::licenses::License;
+use crate::manifests::Dependency;
+use crate::memory::MemoryGraph;
+use crate::parsing::{Implementation, ParsingResult, Symbol};
+use crate::store::GraphStore;
+
+const SCHEMA: [&str; 15] = [
+    "CREATE NODE TABLE IF NOT EXISTS File(id STRING, repo STRING, path STRING, language STRING, result STRING, content_hash STRING, \
+     PRIMARY KEY (id))",
+    "CREATE NODE TABLE IF NOT EXISTS Symbol(id STRING, repo STRING, file STRING, label STRING, name STRING, kind STRING, \
+     qualified_name STRING, parent_class STRING, line_start INT64, PRIMARY KEY (id))",
+    "CREATE REL TABLE IF NOT EXISTS CALLS(FROM Symbol TO Symbol)",
+    "CREATE REL TABLE IF NOT EXISTS INHERITS(FROM Symbol TO Symbol)",
+    "CREATE REL TABLE IF NOT EXISTS IMPLEMENTS(FROM Symbol TO Symbol)",
+    "CREATE REL TABLE IF NOT EXISTS HAS_METHOD(FROM Symbol TO Symbol)",
+    "CREATE NODE TABLE IF NOT EXISTS Snapshot(key STRING, repo STRING, id STRING, created_at INT64, files INT64, symbols INT64, \
+     git_commit STRING, PRIMARY KEY (key))",
+    "CREATE NODE TABLE IF NOT EXISTS RepoState(name STRING, indexed_commit STRING, PRIMARY KEY (name))",
+    "CREATE NODE TABLE IF NOT EXISTS Dependency(id SERIAL, repo STRING, manifest STRING, name STRING, kind STRING, \
+     ecosystem STRING, version_req STRING, package STRING, resolved_version STRING, transitive INT64, PRIMARY KEY (id))",
+    "CREATE NODE TABLE IF NOT EXISTS License(repo STRING, spdx STRING, source STRING, category STRING, headers STRING, \
+     PRIMARY KEY (repo))",
+    "CREATE NODE TABLE IF NOT EXISTS Submodule(id STRING, repo STRING, path STRING, url STRING, git_commit STRING, \
+     indexed_as STRING, PRIMARY KEY (id))",
+    "CREATE NODE TABLE IF NOT EXISTS Entrypoint(id SERIAL, repo STRING, file STRING, line INT64, kind STRING, name STRING, \
+     target STRING, PRIMARY KEY (id))",
+    "CREATE NODE TABLE IF NOT EXISTS Availability(repo STRING, availability STRING, PRIMARY KEY (repo))",
+    "CREATE NODE TABLE IF NOT EXISTS FileHistory(id STRING, repo STRING, path STRING, history STRING, PRIMARY KEY (id))",
+    "CREATE NODE TABLE IF NOT EXISTS ClassificationFeedback(repo STRING, feedback STRING, PRIMARY KEY (repo))",
+];
+
+type KuzuResult<T> = std::result::Result<T, kuzu::Error>;
+
+// Embedded Kuzu storage: the same layout as `SqliteGraph` (whole parse results on File nodes,
+// symbols with CALLS, INHERITS, IMPLEMENTS and HAS_METHOD between them) in a graph database
+// directory. Everything other than the call graph is answered from the parse results through
+// `MemoryGraph`
+pub struct KuzuGraph {
+    db: Arc<Mutex<Database>>,
+}
+
+impl KuzuGraph {
+    pub fn open(path: &str) -> Result<Self> {
+        let db = Database::new(path, SystemConfig::default()).map_err(kuzu_error)?;
+        {
+            let conn = Connection::new(&db).map_err(kuzu_error)?;
+            for q in SCHEMA {
+                conn.query(q).map_err(kuzu_error)?;
+            }
+        }
+        Ok(Self { db: Arc::new(Mutex::new(db)) })
+    }
+
+    // Run `f` on a fresh connection off the async runtime. Kuzu allows one write transaction at a
+    // time, so calls are serialized like the SQLite backend's
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> KuzuResult<T> + Send + 'static,
+    {
+        let db = self.db.clone();
+        tokio::task::spawn_blocking(move || {
+            let db = db.lock().unwrap_or_else(|e| e.into_inner());
+            let conn = Connection::new(&db)?;
+            f(&conn)
+        })
+            .await
+            .map_err(|e| Error::UnexpectedMessage(format!("kuzu task failed: {}", e)))?
+            .map_err(kuzu_error)
+    }
+
+    // The repo's stored parse results as an in-memory graph
+    pub(crate) async fn load(&self, repo_name: &str) -> Result<MemoryGraph> {
+        let repo = repo_name.to_string();
+        let (stored, histories) = self.with_conn(move |conn| {
+            let params = || vec![("repo", repo.as_str().into())];
+            Ok((
+                rows(conn, "MATCH (f:File) WHERE f.repo = $repo RETURN f.path, f.result", params())?,
+                rows(conn, "MATCH (h:FileHistory) WHERE h.repo = $repo RETURN h.path, h.history", params())?,
+            ))
+        }).await?;
+        let mut files = BTreeMap::new();
+        for row in stored {
+            let path = text(&row[0]);
+            let result: ParsingResult = serde_json::from_str(&text(&row[1]))
+                .map_err(|e| Error::UnexpectedMessage(format!("stored parse result for {} is unreadable: {}", path, e)))?;
+            files.insert(path, result);
+        }
+        let history = histories.iter()
+            .filter_map(|row| Some((text(&row[0]), serde_json::from_str::<FileHistory>(&text(&row[1])).ok()?)))
+            .collect();
+        Ok(MemoryGraph::from_files(repo_name, files, history))
+    }
+
+    // Every CALLS edge in the repo as (caller id, callee id)
+    async fn call_pairs(&self, repo_name: &str) -> Result<Vec<(String, String)>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (a:Symbol)-[:CALLS]->(b:Symbol) WHERE a.repo = $repo RETURN a.id, b.id", vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter().map(|row| (text(&row[0]), text(&row[1]))).collect())
+    }
+}
+
+#[async_trait]
+impl GraphStore for KuzuGraph {
+    fn backend(&self) -> &'static str {
+        "kuzu"
+    }
+
+    // Replace the file's node and symbols, then link its calls and bases against the repo's
+    // symbols with the same rules as the Neo4j ingest
+    async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()> {
+        let (repo, path, parsed) = (repo_name.to_string(), file_path.to_string(), result.clone());
+        let json = serde_json::to_string(result).map_err(|e| Error::UnexpectedMessage(e.to_string()))?;
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            let result = parsed;
+            let file_id = format!("{}::{}", repo, path);
+            rows(conn, "MATCH (s:Symbol) WHERE s.repo = $repo AND s.file = $path DETACH DELETE s",
+                 vec![("repo", repo.as_str().into()), ("path", path.as_str().into())])?;
+            rows(conn,
+                 "MERGE (f:File {id: $id}) SET f.repo = $repo, f.path = $path, f.language = $language, f.result = $result, \
+                  f.content_hash = $hash",
+                 vec![
+                     ("id", file_id.as_str().into()), ("repo", repo.as_str().into()), ("path", path.as_str().into()),
+                     ("language", format!("{:?}", result.language).into()), ("result", json.into()),
+                     ("hash", result.content_hash.as_str().into()),
+                 ])?;
+
+            let mut insert = conn.prepare(
+                "MERGE (s:Symbol {id: $id}) SET s.repo = $repo, s.file = $file, s.label = $label, s.name = $name, \
+                 s.kind = $kind, s.qualified_name = $qname, s.parent_class = $parent, s.line_start = $line",
+            )?;
+            for s in &result.symbols {
+                conn.execute(&mut insert, vec![
+                    ("id", symbol_id(&file_id, s).into()), ("repo", repo.as_str().into()), ("file", path.as_str().into()),
+                    ("label", graph::label_for_kind(&s.kind).into()), ("name", s.name.as_str().into()), ("kind", s.kind.as_str().into()),
+                    ("qname", s.qualified_name.clone().unwrap_or_default().into()),
+                    ("parent", s.parent_class.clone().unwrap_or_default().into()),
+                    ("line", (s.range.0 as i64).into()),
+                ])?;
+            }
+
+            let mut candidates = conn.prepare(
+                "MATCH (s:Symbol) WHERE s.repo = $repo AND s.name = $name AND s.label = $label \
+                 RETURN s.id, s.file, s.name, s.qualified_name, s.parent_class",
+            )?;
+            let mut links: Vec<(&str, String, String)> = vec![];
+            for s in result.symbols.iter().filter(|s| graph::label_for_kind(&s.kind) == "Function") {
+                let caller = symbol_id(&file_id, s);
+                for callee in &s.calls {
+                    let target = graph::resolve_call(&result, s, callee);
+                    let found = conn.execute(&mut candidates, vec![
+                        ("repo", repo.as_str().into()), ("name", graph::call_name(callee).into()), ("label", "Function".into()),
+                    ])?;
+                    for row in found {
+                        let (id, file, symbol) = stored_symbol(&row);
+                        if target.accepts(&symbol, &file) {
+                            links.push(("CALLS", caller.clone(), id));
+                        }
+                    }
+                }
+            }
+            for s in result.symbols.iter().filter(|s| s.kind == "class") {
+                let child = symbol_id(&file_id, s);
+                for base in &s.bases {
+                    let found = conn.execute(&mut candidates, vec![
+                        ("repo", repo.as_str().into()), ("name", base.as_str().into()), ("label", "Class".into()),
+                    ])?;
+                    for row in found {
+                        links.push(("INHERITS", child.clone(), text(&row[0])));
+                    }
+                }
+            }
+            // Same rule as `GraphClient::ingest_symbols`: the class in this file, else any in the repo
+            for s in result.symbols.iter().filter(|s| graph::label_for_kind(&s.kind) == "Function") {
+                let Some(owner) = s.parent_class.as_deref().map(graph::owner_name) else { continue };
+                let method = symbol_id(&file_id, s);
+                match result.symbols.iter().find(|c| graph::label_for_kind(&c.kind) == "Class" && c.name == owner) {
+                    Some(class) => links.push(("HAS_METHOD", symbol_id(&file_id, class), method)),
+                    None => {
+                        let found = conn.execute(&mut candidates, vec![
+                            ("repo", repo.as_str().into()), ("name", owner.into()), ("label", "Class".into()),
+                        ])?;
+                        for row in found {
+                            links.push(("HAS_METHOD", text(&row[0]), method.clone()));
+                        }
+                    }
+                }
+            }
+            for (kind, source, target) in links {
+                rows(conn,
+                     &format!("MATCH (a:Symbol {{id: $source}}), (b:Symbol {{id: $target}}) MERGE (a)-[:{}]->(b)", kind),
+                     vec![("source", source.into()), ("target", target.into())])?;
+            }
+            Ok(())
+        })).await?;
+        self.link_implementations(repo_name, &result.implementations).await
+    }
+
+    async fn link_implementations(&self, repo_name: &str, implementations: &[Implementation]) -> Result<()> {
+        let (repo, implementations) = (repo_name.to_string(), implementations.to_vec());
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            let mut link = conn.prepare(
+                "MATCH (t:Symbol), (tr:Symbol) \
+                 WHERE t.repo = $repo AND t.name = $type AND t.label = 'Class' \
+                   AND tr.repo = $repo AND tr.name = $trait AND tr.label = 'Class' \
+                 MERGE (t)-[:IMPLEMENTS]->(tr)",
+            )?;
+            for imp in &implementations {
+                conn.execute(&mut link, vec![
+                    ("repo", repo.as_str().into()), ("type", imp.type_name.as_str().into()), ("trait", imp.trait_name.as_str().into()),
+                ])?;
+            }
+            Ok(())
+        })).await
+    }
+
+    async fn set_declared_dependencies(&self, repo_name: &str, dependencies: &[Dependency]) -> Result<()> {
+        let (repo, dependencies) = (repo_name.to_string(), dependencies.to_vec());
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            rows(conn, "MATCH (n:Dependency) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            for d in dependencies {
+                rows(conn,
+                    "CREATE (:Dependency {repo: $repo, manifest: $manifest, name: $name, kind: $kind, ecosystem: $ecosystem, \
+                     version_req: $version_req, package: $package, resolved_version: $resolved_version, transitive: $transitive})",
+                    vec![
+                        ("repo", repo.as_str().into()), ("manifest", d.manifest.into()), ("name", d.name.into()),
+                        ("kind", d.kind.into()), ("ecosystem", d.ecosystem.into()), ("version_req", d.version_req.into()),
+                        ("package", d.package.unwrap_or_default().into()),
+                        ("resolved_version", d.resolved_version.unwrap_or_default().into()),
+                        ("transitive", d.transitive.map_or(kuzu::Value::Null(kuzu::LogicalType::Int64), |n| (n as i64).into())),
+                    ])?;
+            }
+            Ok(())
+        })).await
+    }
+
+    async fn set_license(&self, repo_name: &str, license: &License) -> Result<()> {
+        let (repo, license) = (repo_name.to_string(), license.clone());
+        let headers = serde_json::to_string(&license.headers).unwrap_or_default();
+        self.with_conn(move |conn| {
+            rows(conn,
+                "MERGE (l:License {repo: $repo}) SET l.spdx = $spdx, l.source = $source, l.category = $category, l.headers = $headers",
+                vec![
+                    ("repo", repo.into()), ("spdx", license.spdx.unwrap_or_default().into()),
+                    ("source", license.source.unwrap_or_default().into()), ("category", license.category.into()),
+                    ("headers", headers.into()),
+                ])?;
+            Ok(())
+        }).await
+    }
+
+    async fn set_entrypoints(&self, repo_name: &str, entrypoints: &[Entrypoint]) -> Result<()> {
+        let (repo, entrypoints) = (repo_name.to_string(), entrypoints.to_vec());
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            rows(conn, "MATCH (n:Entrypoint) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            for e in entrypoints {
+                rows(conn,
+                    "CREATE (:Entrypoint {repo: $repo, file: $file, line: $line, kind: $kind, name: $name, target: $target})",
+                    vec![
+                        ("repo", repo.as_str().into()), ("file", e.file.into()), ("line", (e.line as i64).into()),
+                        ("kind", e.kind.into()), ("name", e.name.into()), ("target", e.target.unwrap_or_default().into()),
+                    ])?;
+            }
+            Ok(())
+        })).await
+    }
+
+    async fn set_submodules(&self, repo_name: &str, submodules: &[Submodule]) -> Result<()> {
+        let (repo, submodules) = (repo_name.to_string(), submodules.to_vec());
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            rows(conn, "MATCH (n:Submodule) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            for s in submodules.iter().cloned() {
+                rows(conn,
+                    "CREATE (:Submodule {id: $id, repo: $repo, path: $path, url: $url, git_commit: $commit, indexed_as: $indexed_as})",
+                    vec![
+                        ("id", format!("{}::{}", repo, s.path).into()), ("repo", repo.as_str().into()),
+                        ("path", s.path.into()), ("url", s.url.unwrap_or_default().into()),
+                        ("commit", s.commit.into()), ("indexed_as", s.repo.unwrap_or_default().into()),
+                    ])?;
+            }
+            Ok(())
+        })).await
+    }
+
+    async fn set_file_history(&self, repo_name: &str, history: &HashMap<String, FileHistory>) -> Result<()> {
+        let repo = repo_name.to_string();
+        let entries: Vec<(String, String)> = history.iter()
+            .map(|(path, h)| (path.clone(), serde_json::to_string(h).unwrap_or_default()))
+            .collect();
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            for (path, history) in &entries {
+                rows(conn,
+                    "MERGE (h:FileHistory {id: $id}) SET h.repo = $repo, h.path = $path, h.history = $history",
+                    vec![
+                        ("id", format!("{}::{}", repo, path).into()), ("repo", repo.as_str().into()),
+                        ("path", path.as_str().into()), ("history", history.as_str().into()),
+                    ])?;
+            }
+            Ok(())
+        })).await
+    }
+
+    async fn delete_repo(&self, repo_name: &str) -> Result<i64> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            let mut deleted = 0;
+            for label in ["File", "Symbol"] {
+                let params = || vec![("repo", repo.as_str().into())];
+                let count = rows(conn, &format!("MATCH (n:{}) WHERE n.repo = $repo RETURN count(n)", label), params())?;
+                deleted += count.first().map_or(0, |row| int(&row[0]));
+                rows(conn, &format!("MATCH (n:{}) WHERE n.repo = $repo DETACH DELETE n", label), params())?;
+            }
+            rows(conn, "MATCH (n:Snapshot) WHERE n.key = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:RepoState) WHERE n.name = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:Dependency) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:License) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:FileHistory) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:Submodule) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:Entrypoint) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            rows(conn, "MATCH (n:Availability) WHERE n.repo = $repo DELETE n", vec![("repo", repo.as_str().into())])?;
+            Ok(deleted)
+        })).await
+    }
+
+    // Symbols are replaced with their file, so only files that are gone can be stale
+    async fn remove_stale(&self, repo_name: &str, files: Option<&[String]>, seen: &HashSet<String>) -> Result<i64> {
+        let (repo, files, seen) = (repo_name.to_string(), files.map(<[String]>::to_vec), seen.clone());
+        self.with_conn(move |conn| in_transaction(conn, |conn| {
+            let stored = rows(conn, "MATCH (f:File) WHERE f.repo = $repo RETURN f.id, f.path", vec![("repo", repo.as_str().into())])?;
+            let mut removed = 0;
+            let stale = stored.iter()
+                .filter(|row| files.as_ref().is_none_or(|f| f.contains(&text(&row[1]))))
+                .filter(|row| !seen.contains(&text(&row[0])));
+            for row in stale {
+                let params = || vec![("repo", repo.as_str().into()), ("path", text(&row[1]).into())];
+                let count = rows(conn, "MATCH (s:Symbol) WHERE s.repo = $repo AND s.file = $path RETURN count(s)", params())?;
+                removed += count.first().map_or(0, |row| int(&row[0])) + 1;
+                rows(conn, "MATCH (s:Symbol) WHERE s.repo = $repo AND s.file = $path DETACH DELETE s", params())?;
+                rows(conn, "MATCH (f:File) WHERE f.repo = $repo AND f.path = $path DELETE f", params())?;
+                rows(conn, "MATCH (h:FileHistory) WHERE h.repo = $repo AND h.path = $path DELETE h", params())?;
+            }
+            Ok(removed)
+        })).await
+    }
+
+    async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let snapshot = snapshot.clone();
+        self.with_conn(move |conn| {
+            rows(conn,
+                "MERGE (s:Snapshot {key: $key}) SET s.repo = $repo, s.id = $id, s.created_at = $created_at, \
+                 s.files = $files, s.symbols = $symbols, s.git_commit = $commit",
+                vec![
+                    ("key", snapshot.key.into()), ("repo", snapshot.repo.into()), ("id", snapshot.id.into()),
+                    ("created_at", snapshot.created_at.into()), ("files", snapshot.files.into()), ("symbols", snapshot.symbols.into()),
+                    ("commit", snapshot.commit.unwrap_or_default().into()),
+                ])?;
+            Ok(())
+        }).await
+    }
+
+    async fn list_snapshots(&self, repo_name: &str) -> Result<Vec<Snapshot>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn,
+                "MATCH (s:Snapshot) WHERE s.repo = $repo \
+                 RETURN s.repo, s.id, s.key, s.created_at, s.files, s.symbols, s.git_commit ORDER BY s.created_at DESC, s.id DESC",
+                vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter()
+            .map(|row| Snapshot {
+                repo: text(&row[0]),
+                id: text(&row[1]),
+                key: text(&row[2]),
+                created_at: int(&row[3]),
+                files: int(&row[4]),
+                symbols: int(&row[5]),
+                commit: Some(text(&row[6])).filter(|c| !c.is_empty()),
+            })
+            .collect())
+    }
+
+    async fn indexed_commit(&self, repo_name: &str) -> Result<Option<String>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (r:RepoState) WHERE r.name = $repo RETURN r.indexed_commit", vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.first().map(|row| text(&row[0])))
+    }
+
+    async fn set_indexed_commit(&self, repo_name: &str, commit: &str) -> Result<()> {
+        let (repo, commit) = (repo_name.to_string(), commit.to_string());
+        self.with_conn(move |conn| {
+            rows(conn, "MERGE (r:RepoState {name: $repo}) SET r.indexed_commit = $commit",
+                 vec![("repo", repo.into()), ("commit", commit.into())])?;
+            Ok(())
+        }).await
+    }
+
+    async fn availability(&self, repo_name: &str) -> Result<Option<String>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (a:Availability) WHERE a.repo = $repo RETURN a.availability", vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.first().map(|row| text(&row[0])))
+    }
+
+    async fn set_availability(&self, repo_name: &str, availability: &str) -> Result<()> {
+        let (repo, availability) = (repo_name.to_string(), availability.to_string());
+        self.with_conn(move |conn| {
+            rows(conn, "MERGE (a:Availability {repo: $repo}) SET a.availability = $availability",
+                 vec![("repo", repo.into()), ("availability", availability.into())])?;
+            Ok(())
+        }).await
+    }
+
+    // Left alone by delete_repo
+    async fn classification_feedback(&self, repo_name: &str) -> Result<Option<Feedback>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (c:ClassificationFeedback) WHERE c.repo = $repo RETURN c.feedback", vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.first().and_then(|row| serde_json::from_str(&text(&row[0])).ok()))
+    }
+
+    async fn set_classification_feedback(&self, repo_name: &str, feedback: &Feedback) -> Result<()> {
+        let (repo, feedback) = (repo_name.to_string(), serde_json::to_string(feedback).unwrap_or_default());
+        self.with_conn(move |conn| {
+            rows(conn, "MERGE (c:ClassificationFeedback {repo: $repo}) SET c.feedback = $feedback",
+                 vec![("repo", repo.into()), ("feedback", feedback.into())])?;
+            Ok(())
+        }).await
+    }
+
+    async fn all_classification_feedback(&self) -> Result<Vec<Feedback>> {
+        let rows = self.with_conn(move |conn| rows(conn, "MATCH (c:ClassificationFeedback) RETURN c.feedback", vec![])).await?;
+        Ok(rows.iter().filter_map(|row| serde_json::from_str(&text(&row[0])).ok()).collect())
+    }
+
+    async fn file_hashes(&self, repo_name: &str) -> Result<HashMap<String, String>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (f:File) WHERE f.repo = $repo RETURN f.path, f.content_hash", vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter().map(|row| (text(&row[0]), text(&row[1]))).collect())
+    }
+
+    async fn call_chain(&self, repo_name: &str, symbol: &str, depth: usize, callers: bool) -> Result<Vec<Value>> {
+        let mut next: HashMap<String, Vec<String>> = HashMap::new();
+        for (a, b) in self.call_pairs(repo_name).await? {
+            // Walk CALLS backwards for callers
+            let (parent, child) = if callers { (b, a) } else { (a, b) };
+            next.entry(parent).or_default().push(child);
+        }
+        for children in next.values_mut() {
+            children.sort();
+            children.dedup();
+        }
+        let (repo, symbol) = (repo_name.to_string(), symbol.to_string());
+        let ids: Vec<String> = next.iter().flat_map(|(parent, children)| std::iter::once(parent).chain(children)).cloned().collect();
+        let (roots, info) = self.with_conn(move |conn| {
+            let roots = rows(conn,
+                "MATCH (s:Symbol) WHERE s.repo = $repo AND s.label = 'Function' \
+                 AND (s.id = $symbol OR s.qualified_name = $symbol OR s.name = $symbol) RETURN s.id ORDER BY s.id",
+                vec![("repo", repo.into()), ("symbol", symbol.into())])?;
+            let roots: Vec<String> = roots.iter().map(|row| text(&row[0])).collect();
+            let mut ids: Vec<&String> = roots.iter().chain(&ids).collect();
+            ids.sort();
+            ids.dedup();
+            let info = function_info(conn, &ids)?;
+            Ok((roots, info))
+        }).await?;
+
+        let mut budget = graph::MAX_CHAIN_NODES;
+        Ok(roots.iter()
+            .map(|root| graph::chain_node(root, depth, &next, &info, &mut vec![], &mut budget))
+            .collect())
+    }
+
+    async fn get_hotspots(&self, repo_name: &str, limit: usize) -> Result<Vec<Value>> {
+        let edges: Vec<(String, String)> = self.call_pairs(repo_name).await?.into_iter().filter(|(a, b)| a != b).collect();
+        let ranks = graph::pagerank(&edges);
+        let mut ranked: Vec<(String, (f64, usize, usize))> = ranks.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.0.total_cmp(&a.1.0).then_with(|| b.1.1.cmp(&a.1.1)).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        let ids: Vec<String> = ranked.iter().map(|(id, _)| id.clone()).collect();
+        let info = self.with_conn(move |conn| function_info(conn, &ids.iter().collect::<Vec<_>>())).await?;
+        Ok(ranked.into_iter()
+            .map(|(id, (score, callers, callees))| {
+                let mut node = info.get(&id).cloned().unwrap_or_else(|| json!({ "id": id }));
+                node["pagerank"] = json!(score);
+                node["callers"] = json!(callers);
+                node["callees"] = json!(callees);
+                node
+            })
+            .collect())
+    }
+
+    async fn get_call_edges(&self, repo_name: &str) -> Result<Vec<(CallEnd, CallEnd)>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn,
+                "MATCH (a:Symbol)-[:CALLS]->(b:Symbol) WHERE a.repo = $repo \
+                 RETURN a.id, a.name, a.qualified_name, a.file, b.id, b.name, b.qualified_name, b.file",
+                vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter()
+            .map(|row| (
+                CallEnd { id: text(&row[0]), name: text(&row[1]), qualified_name: text(&row[2]), file: text(&row[3]) },
+                CallEnd { id: text(&row[4]), name: text(&row[5]), qualified_name: text(&row[6]), file: text(&row[7]) },
+            ))
+            .collect())
+    }
+
+    async fn count_by_kind(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.count_by_kind(repo_name).await
+    }
+
+    async fn get_file_languages(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.get_file_languages(repo_name).await
+    }
+
+    async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_all_files(repo_name).await
+    }
+
+    async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_all_symbols(repo_name).await
+    }
+
+    async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_repo_structure(repo_name).await
+    }
+
+    async fn get_docs(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_docs(repo_name).await
+    }
+
+    async fn get_todos(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_todos(repo_name).await
+    }
+
+    async fn get_routes(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_routes(repo_name).await
+    }
+
+    async fn get_commands(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_commands(repo_name).await
+    }
+
+    async fn get_embedded(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_embedded(repo_name).await
+    }
+
+    async fn get_directories(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_directories(repo_name).await
+    }
+
+    async fn get_dependencies(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.get_dependencies(repo_name).await
+    }
+
+    async fn get_packages(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_packages(repo_name).await
+    }
+
+    async fn get_declared_dependencies(&self, repo_name: &str) -> Result<Vec<Dependency>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn,
+                "MATCH (d:Dependency) WHERE d.repo = $repo \
+                 RETURN d.name, d.version_req, d.ecosystem, d.kind, d.manifest, d.package, d.resolved_version, d.transitive \
+                 ORDER BY d.manifest, d.name",
+                vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter()
+            .map(|row| Dependency {
+                name: text(&row[0]),
+                version_req: text(&row[1]),
+                ecosystem: text(&row[2]),
+                kind: text(&row[3]),
+                manifest: text(&row[4]),
+                package: Some(text(&row[5])).filter(|p| !p.is_empty()),
+                resolved_version: Some(text(&row[6])).filter(|v| !v.is_empty()),
+                transitive: matches!(row[7], kuzu::Value::Int64(_)).then(|| int(&row[7]) as usize),
+            })
+            .collect())
+    }
+
+    async fn get_submodules(&self, repo_name: &str) -> Result<Vec<Submodule>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn,
+                "MATCH (s:Submodule) WHERE s.repo = $repo RETURN s.path, s.url, s.git_commit, s.indexed_as ORDER BY s.path",
+                vec![("repo", repo.into())])
+        }).await?;
+        let optional = |value: &kuzu::Value| Some(text(value)).filter(|s| !s.is_empty());
+        Ok(rows.iter()
+            .map(|row| Submodule { path: text(&row[0]), url: optional(&row[1]), commit: text(&row[2]), repo: optional(&row[3]) })
+            .collect())
+    }
+
+    async fn get_license(&self, repo_name: &str) -> Result<Option<License>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn, "MATCH (l:License) WHERE l.repo = $repo RETURN l.spdx, l.source, l.category, l.headers",
+                 vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.first().map(|row| License {
+            spdx: Some(text(&row[0])).filter(|s| !s.is_empty()),
+            source: Some(text(&row[1])).filter(|s| !s.is_empty()),
+            category: text(&row[2]),
+            headers: serde_json::from_str(&text(&row[3])).unwrap_or_default(),
+        }))
+    }
+
+    async fn get_entrypoints(&self, repo_name: &str) -> Result<Vec<Entrypoint>> {
+        let repo = repo_name.to_string();
+        let rows = self.with_conn(move |conn| {
+            rows(conn,
+                "MATCH (e:Entrypoint) WHERE e.repo = $repo RETURN e.file, e.line, e.kind, e.name, e.target \
+                 ORDER BY e.file, e.line, e.kind, e.name",
+                vec![("repo", repo.into())])
+        }).await?;
+        Ok(rows.iter()
+            .map(|row| Entrypoint {
+                file: text(&row[0]),
+                line: int(&row[1]) as usize,
+                kind: text(&row[2]),
+                name: text(&row[3]),
+                target: Some(text(&row[4])).filter(|t| !t.is_empty()),
+            })
+            .collect())
+    }
+
+    async fn package_users(&self, package: &str) -> Result<Vec<Value>> {
+        let repos = self.with_conn(|conn| rows(conn, "MATCH (f:File) RETURN DISTINCT f.repo ORDER BY f.repo", vec![])).await?;
+        let mut out = vec![];
+        for row in repos {
+            let repo = text(&row[0]);
+            out.extend(self.load(&repo).await?.package_users(package).await?);
+        }
+        Ok(out)
+    }
+}
+
+// Run `f` in an explicit transaction, rolled back if it fails
+fn in_transaction<T>(conn: &Connection, f: impl FnOnce(&Connection) -> KuzuResult<T>) -> KuzuResult<T> {
+    conn.query("BEGIN TRANSACTION")?;
+    match f(conn) {
+        Ok(value) => {
+            conn.query("COMMIT")?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = conn.query("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+// All rows of a parameterized query
+fn rows(conn: &Connection, q: &str, params: Vec<(&str, kuzu::Value)>) -> KuzuResult<Vec<Vec<kuzu::Value>>> {
+    let mut stmt = conn.prepare(q)?;
+    Ok(conn.execute(&mut stmt, params)?.collect())
+}
+
+fn text(value: &kuzu::Value) -> String {
+    match value {
+        kuzu::Value::String(s) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+fn int(value: &kuzu::Value) -> i64 {
+    match value {
+        kuzu::Value::Int64(n) => *n,
+        _ => 0,
+    }
+}
+
+fn symbol_id(file_id: &str, s: &Symbol) -> String {
+    format!("{}::{}:{}", file_id, s.name, s.range.0)
+}
+
+// (id, file, symbol) for a `s.id, s.file, s.name, s.qualified_name, s.parent_class` row, with
+// the fields CALLS matching looks at
+fn stored_symbol(row: &[kuzu::Value]) -> (String, String, Symbol) {
+    let symbol = Symbol {
+        name: text(&row[2]),
+        qualified_name: Some(text(&row[3])).filter(|q| !q.is_empty()),
+        parent_class: Some(text(&row[4])).filter(|p| !p.is_empty()),
+        ..Default::default()
+    };
+    (text(&row[0]), text(&row[1]), symbol)
+}
+
+// Same summary as the Neo4j `function_info`, keyed by id
+fn function_info(conn: &Connection, ids: &[&String]) -> KuzuResult<HashMap<String, Value>> {
+    let mut stmt = conn.prepare(
+        "MATCH (s:Symbol) WHERE s.id = $id RETURN s.id, s.name, s.qualified_name, s.kind, s.parent_class, s.file, s.line_start",
+    )?;
+    let mut info = HashMap::new();
+    for id in ids {
+        if let Some(row) = conn.execute(&mut stmt, vec![("id", id.as_str().into())])?.next() {
+            info.insert(id.to_string(), json!({
+                "id": text(&row[0]),
+                "name": text(&row[1]),
+                "qualified_name": Some(text(&row[2])).filter(|q| !q.is_empty()),
+                "kind": text(&row[3]),
+                "parent_class": Some(text(&row[4])).filter(|p| !p.is_empty()),
+                "file": text(&row[5]),
+                "line": int(&row[6]),
+            }));
+        }
+    }
+    Ok(info)
+}
+
+fn kuzu_error(e: kuzu::Error) -> Error {
+    Error::UnexpectedMessage(format!("kuzu: {}", e))
+}
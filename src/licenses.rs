@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Lines at the top of a file searched for an SPDX-License-Identifier header
+const HEADER_LINES: usize = 20;
+
+// What a repo is licensed under
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct License {
+    // SPDX expression ("MIT", "Apache-2.0 OR MIT"); None when nothing names one
+    pub spdx: Option<String>,
+    // License files it was read from, or "headers" when only SPDX headers named it
+    pub source: Option<String>,
+    // "permissive", "weak_copyleft", "copyleft", "unknown" (a license file nothing matched,
+    // proprietary ones included) or "none"
+    pub category: String,
+    // Expressions in SPDX-License-Identifier headers, and how many files carry each
+    pub headers: BTreeMap<String, usize>,
+}
+
+// Whether `rel_path` (relative to the repo root) is a license file for the repo as a whole.
+// Nested ones usually belong to vendored code
+pub fn is_license_file(rel_path: &str) -> bool {
+    let name = rel_path.to_ascii_uppercase();
+    !name.contains('/') && ["LICENSE", "LICENCE", "COPYING", "UNLICENSE"].iter().any(|prefix| name.starts_with(prefix))
+}
+
+// The expression in an SPDX-License-Identifier header near the top of `content`
+pub fn spdx_header(content: &str) -> Option<String> {
+    content.lines().take(HEADER_LINES).find_map(|line| {
+        let (_, expression) = line.split_once("SPDX-License-Identifier:")?;
+        let expression = expression.trim().trim_end_matches("*/").trim_end_matches("-->").trim();
+        (!expression.is_empty()).then(|| expression.to_string())
+    })
+}
+
+// The repo's license from its license files, (path, content) pairs, falling back to the
+// expression most files' headers carry
+pub fn detect(files: &[(String, String)], headers: BTreeMap<String, usize>) -> License {
+    let mut ids: Vec<&str> = files.iter().filter_map(|(_, content)| identify(content)).collect();
+    ids.sort();
+    ids.dedup();
+    // LGPL projects ship the GPL text alongside, since the LGPL is written as exceptions to it
+    if ids.iter().any(|id| id.starts_with("LGPL")) {
+        ids.retain(|id| !id.starts_with("GPL"));
+    }
+    let (spdx, source) = if !ids.is_empty() {
+        let mut paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+        paths.sort();
+        (Some(ids.join(" OR ")), Some(paths.join(", ")))
+    } else if let Some((expression, _)) = headers.iter().max_by_key(|(_, files)| **files) {
+        (Some(expression.clone()), Some("headers".to_string()))
+    } else {
+        (None, None)
+    };
+    let category = match &spdx {
+        Some(expression) => category(expression),
+        None if files.is_empty() => "none",
+        None => "unknown",
+    };
+    License { spdx, source, category: category.to_string(), headers }
+}
+
+// SPDX identifier of a license text, by phrases that only its text contains
+fn identify(content: &str) -> Option<&'static str> {
+    let text = content.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let has = |phrase: &str| text.contains(phrase);
+    // The GPL family's texts mention each other, so the title that comes first is the license
+    let gnu = ["gnu affero general public license", "gnu lesser general public license", "gnu general public license"].iter()
+        .filter_map(|title| text.find(title).map(|at| (at, *title)))
+        .min();
+    // Version numbers follow the title
+    let titled = |title: &str, version: &str| text.find(title).is_some_and(|at| text[at..].chars().take(200).collect::<String>().contains(version));
+    let id = if let Some((_, title)) = gnu {
+        match (title, titled(title, "version 3")) {
+            ("gnu affero general public license", _) => "AGPL-3.0",
+            ("gnu lesser general public license", true) => "LGPL-3.0",
+            ("gnu lesser general public license", false) => "LGPL-2.1",
+            (_, true) => "GPL-3.0",
+            (_, false) => "GPL-2.0",
+        }
+    } else if titled("apache license", "version 2.0") {
+        "Apache-2.0"
+    } else if titled("mozilla public license", "2.0") {
+        "MPL-2.0"
+    } else if titled("eclipse public license", "") {
+        if titled("eclipse public license", "2.0") { "EPL-2.0" } else { "EPL-1.0" }
+    } else if has("permission is hereby granted, free of charge") {
+        "MIT"
+    } else if has("permission to use, copy, modify, and/or distribute this software for any purpose") {
+        "ISC"
+    } else if has("redistribution and use in source and binary forms") {
+        if has("endorse or promote") { "BSD-3-Clause" } else { "BSD-2-Clause" }
+    } else if has("free and unencumbered software released into the public domain") {
+        "Unlicense"
+    } else if has("boost software license") {
+        "BSL-1.0"
+    } else if has("cc0 1.0 universal") {
+        "CC0-1.0"
+    } else {
+        return None;
+    };
+    Some(id)
+}
+
+// How much an expression asks of those who use the code. Under OR the user picks, so the
+// least demanding alternative counts; under AND every part applies, so the most demanding does
+fn category(expression: &str) -> &'static str {
+    const ORDER: [&str; 4] = ["permissive", "weak_copyleft", "copyleft", "unknown"];
+    let rank = |id: &str| -> usize {
+        let id = id.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace());
+        let id = id.split(" WITH ").next().unwrap_or(id);
+        let family = |prefixes: &[&str]| prefixes.iter().any(|prefix| id.starts_with(prefix));
+        if family(&["AGPL", "GPL", "EUPL", "OSL", "SSPL"]) {
+            2
+        } else if family(&["LGPL", "MPL", "EPL", "CDDL", "CPL"]) {
+            1
+        } else if family(&["MIT", "Apache", "BSD", "0BSD", "ISC", "Unlicense", "BSL", "CC0", "Zlib", "PSF", "Python", "X11"]) {
+            0
+        } else {
+            3
+        }
+    };
+    let least = expression.split(" OR ")
+        .map(|alternative| alternative.split(" AND ").map(rank).max().unwrap_or(3))
+        .min()
+        .unwrap_or(3);
+    ORDER[least]
+}
@@ -1,7 +1,10 @@
-use axum::{routing::{get, post}, Router, response::Json, extract::State};
+use axum::{routing::{delete, get, post}, Router, body::{Body, Bytes}, extract::DefaultBodyLimit, http::header, response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Json, Response}, extract::{FromRequest, Multipart, Path, Query, Request, State}};
 use serde_json::{json, Value};
+use futures::StreamExt;
+use std::convert::Infallible;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tower_http::cors::{CorsLayer, Any};
 use tracing::{info, warn, error, debug};
 
@@ -9,11 +12,46 @@ mod parsing;
 mod graph;
 mod indexing;
 mod classifier;
+mod scanners;
+mod export;
+mod diff;
+mod archive;
+mod git;
+mod jobs;
+mod entrypoints;
+mod licenses;
+mod manifests;
+mod memory;
+mod sqlite;
+mod store;
+#[cfg(feature = "kuzu")]
+mod kuzu;
 
-use graph::GraphClient;
+use graph::{Dialect, GraphClient};
+use memory::MemoryGraph;
+use sqlite::SqliteGraph;
+use store::GraphStore;
 
 struct AppState {
-    graph: Option<Arc<GraphClient>>,
+    store: Arc<dyn GraphStore>,
+    // The store, when it is the in-memory stand-in for an unreachable Neo4j (see /flush)
+    memory: Option<Arc<MemoryGraph>>,
+    neo4j: Neo4jConfig,
+    // Background /index runs, for /index/status
+    jobs: jobs::Jobs,
+    // What an /index request doesn't override: MAX_FILE_SIZE (0 for no limit),
+    // INGEST_CONCURRENCY and PARSE_EXECUTOR ("blocking" or "thread")
+    index_defaults: indexing::IndexOptions,
+}
+
+// Big enough for any hand-written source file; generated bundles and data dumps go over
+const DEFAULT_MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+struct Neo4jConfig {
+    connection: graph::ConnectionConfig,
+    batch_size: usize,
+    query_timeout: Option<std::time::Duration>,
+    dialect: Dialect,
 }
 
 #[tokio::main]
@@ -23,42 +61,165 @@ async fn main() {
         .with_timer(tracing_subscriber::fmt::time::uptime())
         .init();
 
-    // Set rayon thread stack size to 8MB to prevent stack overflow on deeply nested files
+    // Set rayon thread stack size to 8MB to prevent stack overflow on deeply nested files.
+    // INDEX_THREADS sizes the pool; rayon picks one thread per core when it is unset or 0
     rayon::ThreadPoolBuilder::new()
-        .stack_size(8 * 1024 * 1024)
+        .num_threads(std::env::var("INDEX_THREADS").ok().and_then(|n| n.parse().ok()).unwrap_or(0))
+        .stack_size(indexing::PARSE_STACK_SIZE)
         .build_global()
         .ok();
 
-    let uri = std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string());
-    let user = std::env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string());
-    let pass = std::env::var("NEO4J_PASSWORD").unwrap_or_else(|_| "betterdocs".to_string());
+    // Extra extraction rules: $QUERIES_DIR/<language>/*.scm
+    if let Ok(dir) = std::env::var("QUERIES_DIR") {
+        let (loaded, errors) = parsing::load_custom_queries(std::path::Path::new(&dir));
+        for e in &errors {
+            warn!("Custom query skipped: {}", e);
+        }
+        info!("Loaded {} custom queries from {}", loaded, dir);
+    }
+    // Classifier rules in place of the built-in ones, re-read whenever the file changes
+    if let Ok(path) = std::env::var("CLASSIFIER_RULES") {
+        match classifier::load_rules(std::path::Path::new(&path)) {
+            Ok(count) => info!("Loaded {} classifier rules from {}", count, path),
+            Err(e) => warn!("Classifier rules not loaded, using the built-in ones: {}", e),
+        }
+    }
+    // Doc type centroids for the embedding stage, until users label embedded repos
+    if let Ok(path) = std::env::var("CLASSIFIER_CENTROIDS") {
+        match classifier::load_centroids(std::path::Path::new(&path)) {
+            Ok(count) => info!("Loaded {} classifier centroids from {}", count, path),
+            Err(e) => warn!("Classifier centroids not loaded: {}", e),
+        }
+    }
+    parsing::preload_languages();
+    info!("Tree-sitter grammars and queries loaded");
+
+    // NEO4J_URI may use bolt+s:// or neo4j+s:// for TLS (Aura); NEO4J_CA_CERT adds a PEM CA bundle
+    let connection = graph::ConnectionConfig {
+        uri: std::env::var("NEO4J_URI").unwrap_or_else(|_| "bolt://localhost:7687".to_string()),
+        user: std::env::var("NEO4J_USER").unwrap_or_else(|_| "neo4j".to_string()),
+        pass: std::env::var("NEO4J_PASSWORD").unwrap_or_else(|_| "betterdocs".to_string()),
+        ca_cert: std::env::var("NEO4J_CA_CERT").ok().filter(|p| !p.is_empty()),
+        database: std::env::var("NEO4J_DATABASE").ok().filter(|d| !d.is_empty()),
+        max_connections: std::env::var("NEO4J_MAX_CONNECTIONS").ok().and_then(|n| n.parse().ok()),
+        fetch_size: std::env::var("NEO4J_FETCH_SIZE").ok().and_then(|n| n.parse().ok()),
+    };
 
-    info!("Connecting to Neo4j at {} as {}", uri, user);
+    let batch_size = std::env::var("NEO4J_BATCH_SIZE").ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(graph::DEFAULT_BATCH_SIZE);
+    // Seconds a single query may run; 0 disables the limit
+    let query_timeout = match std::env::var("NEO4J_QUERY_TIMEOUT_SECS").ok().and_then(|n| n.parse::<u64>().ok()) {
+        Some(0) => None,
+        Some(secs) => Some(std::time::Duration::from_secs(secs)),
+        None => Some(graph::DEFAULT_QUERY_TIMEOUT),
+    };
 
-    let graph_client = match GraphClient::connect(&uri, &user, &pass).await {
-        Ok(client) => {
-            info!("Neo4j connected successfully");
-            match client.ensure_schema().await {
-                Ok(_) => info!("Neo4j schema ready"),
-                Err(e) => error!("Neo4j schema setup failed: {}", e),
+    // STORAGE_BACKEND=sqlite keeps everything in one file (SQLITE_PATH) and STORAGE_BACKEND=kuzu in
+    // an embedded Kuzu database (KUZU_PATH) instead of Neo4j. STORAGE_BACKEND=memgraph talks to a
+    // Memgraph server over Bolt using the NEO4J_* settings
+    let storage = std::env::var("STORAGE_BACKEND").unwrap_or_else(|_| "neo4j".to_string());
+    let dialect = if storage == "memgraph" { Dialect::Memgraph } else { Dialect::Neo4j };
+    let embedded: Option<Arc<dyn GraphStore>> = match storage.as_str() {
+        "sqlite" => {
+            let path = std::env::var("SQLITE_PATH").unwrap_or_else(|_| "better-docs.db".to_string());
+            match SqliteGraph::open(&path) {
+                Ok(store) => {
+                    info!("Using SQLite storage at {}", path);
+                    Some(Arc::new(store))
+                }
+                Err(e) => {
+                    error!("SQLite open FAILED for {}: {} -- engine will keep the graph in memory", path, e);
+                    None
+                }
             }
-            Some(Arc::new(client))
         }
-        Err(e) => {
-            error!("Neo4j connection FAILED: {} -- engine will run without database", e);
-            None
+        "kuzu" => open_kuzu(),
+        _ => None,
+    };
+
+    let graph_client = if matches!(storage.as_str(), "sqlite" | "kuzu") {
+        None
+    } else {
+        info!("Connecting to {:?} at {} as {}", dialect, connection.uri, connection.user);
+        match GraphClient::connect(&connection).await {
+            Ok(client) => {
+                let client = client.with_batch_size(batch_size).with_dialect(dialect).with_query_timeout(query_timeout);
+                info!("{:?} connected successfully", dialect);
+                match client.ensure_schema().await {
+                    Ok(_) => info!("{:?} schema ready", dialect),
+                    Err(e) => error!("{:?} schema setup failed: {}", dialect, e),
+                }
+                Some(Arc::new(client))
+            }
+            Err(e) => {
+                error!("{:?} connection FAILED: {} -- engine will keep the graph in memory", dialect, e);
+                None
+            }
+        }
+    };
+    let (store, memory): (Arc<dyn GraphStore>, _) = match (embedded, graph_client) {
+        (Some(embedded), _) => (embedded, None),
+        (None, Some(client)) => (client, None),
+        (None, None) => {
+            let memory = Arc::new(MemoryGraph::new());
+            (memory.clone(), Some(memory))
         }
     };
 
-    let shared_state = Arc::new(AppState { graph: graph_client });
+    let neo4j = Neo4jConfig { connection, batch_size, query_timeout, dialect };
+    let mut index_defaults = indexing::IndexOptions {
+        max_file_size: match std::env::var("MAX_FILE_SIZE").ok().and_then(|n| n.parse::<u64>().ok()) {
+            Some(0) => None,
+            Some(max) => Some(max),
+            None => Some(DEFAULT_MAX_FILE_SIZE),
+        },
+        ..Default::default()
+    };
+    if let Some(width) = std::env::var("INGEST_CONCURRENCY").ok().and_then(|n| n.parse().ok()).filter(|&n: &usize| n > 0) {
+        index_defaults.ingest_concurrency = width;
+    }
+    if let Ok(name) = std::env::var("PARSE_EXECUTOR") {
+        match indexing::ParseExecutor::from_name(&name) {
+            Some(executor) => index_defaults.parse_on = executor,
+            None => warn!("Unknown PARSE_EXECUTOR {}, parsing on the blocking pool", name),
+        }
+    }
+    let flag = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<bool>().ok());
+    index_defaults.follow_symlinks = flag("FOLLOW_SYMLINKS").unwrap_or(index_defaults.follow_symlinks);
+    index_defaults.skip_vendored = flag("SKIP_VENDORED").unwrap_or(index_defaults.skip_vendored);
+    index_defaults.dedupe_links = flag("DEDUPE_LINKS").unwrap_or(index_defaults.dedupe_links);
+    index_defaults.blame_owners = flag("BLAME_OWNERS").unwrap_or(index_defaults.blame_owners);
+    if let Ok(name) = std::env::var("SUBMODULES") {
+        match indexing::Submodules::from_name(&name) {
+            Some(mode) => index_defaults.submodules = mode,
+            None => warn!("Unknown SUBMODULES {}, leaving submodules as they are", name),
+        }
+    }
+    let shared_state = Arc::new(AppState { store, memory, neo4j, jobs: jobs::Jobs::default(), index_defaults });
     let cors = CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any);
 
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/index", post(index_repo))
+        .route("/index/upload", post(index_upload).layer(DefaultBodyLimit::max(UPLOAD_BODY_LIMIT)))
+        .route("/index/status/:job_id", get(index_status))
+        .route("/index/progress/:job_id", get(index_progress))
         .route("/parse", post(parse_file))
         .route("/classify", post(classify_repo))
+        .route("/classify/feedback", post(classify_feedback))
         .route("/graph/query", post(query_graph))
+        .route("/search", post(search_symbols))
+        .route("/embeddings", post(store_embeddings))
+        .route("/similar", post(similar_symbols))
+        .route("/repo/:name", delete(delete_repo))
+        .route("/repo/:name/dump", get(dump_repo))
+        .route("/repo/:name/snapshots", get(list_snapshots))
+        .route("/restore", post(restore_repo).layer(DefaultBodyLimit::max(RESTORE_BODY_LIMIT)))
+        .route("/graph/export", get(export_graph))
+        .route("/diff", get(diff_repos))
+        .route("/packages/:name", get(package_users))
+        .route("/flush", post(flush_memory))
         .layer(cors)
         .with_state(shared_state);
 
@@ -72,25 +233,411 @@ async fn main() {
     axum::serve(listener, app).await.unwrap();
 }
 
+#[cfg(feature = "kuzu")]
+fn open_kuzu() -> Option<Arc<dyn GraphStore>> {
+    let path = std::env::var("KUZU_PATH").unwrap_or_else(|_| "better-docs.kuzu".to_string());
+    match kuzu::KuzuGraph::open(&path) {
+        Ok(store) => {
+            info!("Using Kuzu storage at {}", path);
+            Some(Arc::new(store))
+        }
+        Err(e) => {
+            error!("Kuzu open FAILED for {}: {} -- engine will keep the graph in memory", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "kuzu"))]
+fn open_kuzu() -> Option<Arc<dyn GraphStore>> {
+    error!("STORAGE_BACKEND=kuzu needs a build with `--features kuzu` -- engine will keep the graph in memory");
+    None
+}
+
 async fn health_check(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let db = if state.graph.is_some() { "connected" } else { "disconnected" };
-    Json(json!({ "status": "ok", "service": "better-docs", "database": db }))
+    // A failed ping also makes the client reconnect, so /health doubles as a recovery probe
+    let backend = state.store.backend();
+    let db = match backend {
+        "memory" => "disconnected",
+        _ if state.store.ping().await => "connected",
+        _ => "unreachable",
+    };
+    Json(json!({ "status": "ok", "service": "better-docs", "database": db, "backend": backend }))
 }
 
 #[derive(serde::Deserialize)]
 struct IndexRequest {
     repo_path: String,
     repo_name: String,
+    // Keep this run as a snapshot (`<repo_name>@<id>`) instead of updating the repo's live graph
+    #[serde(default)]
+    snapshot: bool,
+    // Re-parse only the files changed in git since the last indexed commit
+    #[serde(default)]
+    incremental: bool,
+    // Branch, tag or commit to index instead of what is checked out at `repo_path`
+    #[serde(default, rename = "ref")]
+    git_ref: Option<String>,
+    // Overrides MAX_FILE_SIZE for this run; 0 lifts the limit
+    max_file_size: Option<u64>,
+    // Parse on a pool of this many threads rather than the shared one
+    threads: Option<usize>,
+    // Override INGEST_CONCURRENCY and PARSE_EXECUTOR for this run
+    ingest_concurrency: Option<usize>,
+    parse_executor: Option<indexing::ParseExecutor>,
+    // Override FOLLOW_SYMLINKS, SKIP_VENDORED and DEDUPE_LINKS for this run
+    follow_symlinks: Option<bool>,
+    skip_vendored: Option<bool>,
+    dedupe_links: Option<bool>,
+    // Override SUBMODULES: "off", "nested" or "separate"
+    submodules: Option<indexing::Submodules>,
+    // Override BLAME_OWNERS for this run
+    blame_owners: Option<bool>,
 }
 
+// Starts the run in the background and answers with its job id right away; large repos take
+// longer than proxies wait. Progress and the final stats come from /index/status
 async fn index_repo(State(state): State<Arc<AppState>>, Json(payload): Json<IndexRequest>) -> Json<Value> {
-    info!("POST /index -- repo={} path={}", payload.repo_name, payload.repo_path);
+    info!("POST /index -- repo={} path={} snapshot={} incremental={} ref={:?}",
+        payload.repo_name, payload.repo_path, payload.snapshot, payload.incremental, payload.git_ref);
+    Json(start_index(state, payload, None))
+}
+
+// `upload` holds an extracted archive being indexed; it is deleted once the run is over
+fn start_index(state: Arc<AppState>, payload: IndexRequest, upload: Option<tempfile::TempDir>) -> Value {
+    let job = state.jobs.start(&payload.repo_name);
+    info!("  Started job {}", job.id);
+    let out = json!({ "job_id": job.id, "status": job.status() });
+    tokio::spawn(async move {
+        let result = run_index(&state, &payload, job.clone()).await;
+        job.finish(result);
+        drop(upload);
+    });
+    out
+}
+
+// Uploads are build artifacts rather than whole histories, but can still be large
+const UPLOAD_BODY_LIMIT: usize = 512 * 1024 * 1024;
+
+#[derive(serde::Deserialize)]
+struct UploadParams {
+    repo_name: Option<String>,
+    #[serde(default)]
+    snapshot: bool,
+}
+
+// Index a zip or tar.gz sent as the raw body, or as the `file` field of a multipart form. The
+// repo name and snapshot flag come from the query string or, for forms, from fields of the same
+// name. Answers like /index once the archive is unpacked
+async fn index_upload(State(state): State<Arc<AppState>>, Query(params): Query<UploadParams>, request: Request) -> Json<Value> {
+    let UploadParams { mut repo_name, mut snapshot } = params;
+    let multipart = request.headers().get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    let archive = if multipart {
+        let mut form = match Multipart::from_request(request, &state).await {
+            Ok(form) => form,
+            Err(e) => return Json(json!({ "error": e.body_text() })),
+        };
+        let mut archive = None;
+        loop {
+            let field = match form.next_field().await {
+                Ok(Some(field)) => field,
+                Ok(None) => break,
+                Err(e) => return Json(json!({ "error": e.body_text() })),
+            };
+            let name = field.name().unwrap_or_default().to_string();
+            let value = match field.bytes().await {
+                Ok(value) => value,
+                Err(e) => return Json(json!({ "error": e.body_text() })),
+            };
+            match name.as_str() {
+                "file" => archive = Some(value),
+                "repo_name" => repo_name = Some(String::from_utf8_lossy(&value).into_owned()),
+                "snapshot" => snapshot = &value[..] == b"true",
+                _ => {}
+            }
+        }
+        archive
+    } else {
+        match Bytes::from_request(request, &state).await {
+            Ok(body) => Some(body),
+            Err(e) => return Json(json!({ "error": e.body_text() })),
+        }
+    };
+    let Some(repo_name) = repo_name.filter(|name| !name.is_empty()) else {
+        return Json(json!({ "error": "repo_name is required" }));
+    };
+    let Some(archive) = archive.filter(|archive| !archive.is_empty()) else {
+        return Json(json!({ "repo": repo_name, "error": "no archive uploaded" }));
+    };
+    info!("POST /index/upload -- repo={} bytes={} snapshot={}", repo_name, archive.len(), snapshot);
+
+    let extracted = tokio::task::spawn_blocking(move || {
+        let dir = tempfile::Builder::new().prefix("better-docs-upload-").tempdir()?;
+        let root = archive::extract(&archive, dir.path())?;
+        Ok::<_, std::io::Error>((dir, root))
+    }).await;
+    let (dir, root) = match extracted {
+        Ok(Ok(extracted)) => extracted,
+        Ok(Err(e)) => {
+            warn!("  Unpacking the upload for {} failed: {}", repo_name, e);
+            return Json(json!({ "repo": repo_name, "error": e.to_string() }));
+        }
+        Err(e) => return Json(json!({ "repo": repo_name, "error": e.to_string() })),
+    };
+    let payload = IndexRequest {
+        repo_path: root.to_string_lossy().into_owned(),
+        repo_name,
+        snapshot,
+        incremental: false,
+        git_ref: None,
+        max_file_size: None,
+        threads: None,
+        ingest_concurrency: None,
+        parse_executor: None,
+        follow_symlinks: None,
+        skip_vendored: None,
+        dedupe_links: None,
+        submodules: None,
+        blame_owners: None,
+    };
+    Json(start_index(state, payload, Some(dir)))
+}
+
+async fn index_status(State(state): State<Arc<AppState>>, Path(job_id): Path<String>) -> Json<Value> {
+    debug!("GET /index/status/{}", job_id);
+    match state.jobs.get(&job_id) {
+        Some(job) => Json(job.status()),
+        None => Json(json!({ "job_id": job_id, "error": "unknown job" })),
+    }
+}
+
+// Server-sent events for one job: its status, then phase, per-file, error and finally "done"
+// events, each named by its `type`
+async fn index_progress(State(state): State<Arc<AppState>>, Path(job_id): Path<String>) -> Response {
+    info!("GET /index/progress/{}", job_id);
+    let Some(job) = state.jobs.get(&job_id) else {
+        return Json(json!({ "job_id": job_id, "error": "unknown job" })).into_response();
+    };
+    let events = job.events().map(|event| {
+        let name = event["type"].as_str().unwrap_or("progress").to_string();
+        Ok::<_, Infallible>(Event::default().event(name).data(event.to_string()))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+async fn run_index(state: &AppState, payload: &IndexRequest, job: Arc<jobs::Job>) -> Value {
     let start = std::time::Instant::now();
-    let stats = indexing::index_repository(&payload.repo_path, &payload.repo_name, state.graph.clone()).await;
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    // Ids lead with the millisecond so they sort in run order; the process id and a count of this
+    // server's snapshots keep runs started in the same millisecond apart, and the key adds the repo
+    static NEXT_SNAPSHOT: AtomicUsize = AtomicUsize::new(0);
+    let snapshot_id = payload.snapshot.then(|| format!(
+        "{}-{}-{}", now.as_millis(), std::process::id(), NEXT_SNAPSHOT.fetch_add(1, Ordering::Relaxed),
+    ));
+    let target = match &snapshot_id {
+        Some(id) => format!("{}@{}", payload.repo_name, id),
+        None => payload.repo_name.clone(),
+    };
+    // A ref is indexed from its own temporary worktree, dropped once the run is over
+    let checkout = match &payload.git_ref {
+        Some(reference) => match check_out(&payload.repo_path, reference).await {
+            Ok(checkout) => Some(checkout),
+            Err(e) => {
+                error!("  Job {}: {}", job.id, e);
+                job.error(e.clone());
+                return json!({ "error": e });
+            }
+        },
+        None => None,
+    };
+    let repo_path = match &checkout {
+        Some(checkout) => checkout.dir().to_string_lossy().into_owned(),
+        None => payload.repo_path.clone(),
+    };
+    let defaults = state.index_defaults;
+    let options = indexing::IndexOptions {
+        incremental: payload.incremental,
+        max_file_size: payload.max_file_size.map_or(defaults.max_file_size, |max| (max > 0).then_some(max)),
+        threads: payload.threads.filter(|&threads| threads > 0).or(defaults.threads),
+        ingest_concurrency: payload.ingest_concurrency.filter(|&width| width > 0).unwrap_or(defaults.ingest_concurrency),
+        parse_on: payload.parse_executor.unwrap_or(defaults.parse_on),
+        follow_symlinks: payload.follow_symlinks.unwrap_or(defaults.follow_symlinks),
+        skip_vendored: payload.skip_vendored.unwrap_or(defaults.skip_vendored),
+        dedupe_links: payload.dedupe_links.unwrap_or(defaults.dedupe_links),
+        submodules: payload.submodules.unwrap_or(defaults.submodules),
+        blame_owners: payload.blame_owners.unwrap_or(defaults.blame_owners),
+    };
+    debug!("  Job {} options: {:?}", job.id, options);
+    let stats = match indexing::index_repository(&repo_path, &target, state.store.clone(), options, job.clone()).await {
+        Ok(stats) => stats,
+        Err(e) => {
+            error!("  Job {}: {}", job.id, e);
+            job.error(e.clone());
+            return json!({ "error": e });
+        }
+    };
+    // Submodules indexed separately go into the repos the parent's record names, with their own
+    // submodules nested
+    let mut submodule_runs = serde_json::Map::new();
+    for submodule in &stats.submodules {
+        let Some(name) = &submodule.repo else { continue };
+        info!("  Job {}: indexing submodule {} as {}", job.id, submodule.path, name);
+        let dir = std::path::Path::new(&repo_path).join(&submodule.path);
+        let options = indexing::IndexOptions { submodules: indexing::Submodules::Nested, ..options };
+        let sub = match indexing::index_repository(&dir.to_string_lossy(), name, state.store.clone(), options, job.clone()).await {
+            Ok(sub) => json!(sub),
+            Err(e) => {
+                job.error(format!("{}: {}", submodule.path, e));
+                json!({ "error": e })
+            }
+        };
+        submodule_runs.insert(name.clone(), sub);
+    }
+    drop(checkout);
     let elapsed = start.elapsed();
-    info!("  Indexed {} files ({} skipped), {} nodes created in {:.1}s",
-        stats.files_processed, stats.files_skipped, stats.nodes_created, elapsed.as_secs_f64());
-    Json(json!(stats))
+    info!("  Job {}: indexed {} files ({} skipped), {} nodes created, {} stale removed in {:.1}s",
+        job.id, stats.files_processed, stats.files_skipped, stats.nodes_created, stats.nodes_removed, elapsed.as_secs_f64());
+    if stats.incremental {
+        info!("  Incremental run: {} changed, {} deleted since the last indexed commit", stats.files_processed, stats.files_deleted);
+    }
+    let languages: Vec<String> = stats.languages.iter().map(|(lang, b)| format!("{} {}", lang, b.files)).collect();
+    debug!("  Files by language: {}", languages.join(", "));
+    if !stats.files_skipped_reason.is_empty() {
+        info!("  Skipped files by reason: {:?}", stats.files_skipped_reason);
+    }
+    let mut out = json!(stats);
+    if !submodule_runs.is_empty() {
+        out["submodule_runs"] = Value::Object(submodule_runs);
+    }
+    if let Some(reference) = &payload.git_ref {
+        out["ref"] = json!(reference);
+    }
+    if let Some(id) = snapshot_id {
+        let snapshot = graph::Snapshot {
+            repo: payload.repo_name.clone(),
+            id,
+            key: target,
+            created_at: now.as_secs() as i64,
+            files: stats.files_processed as i64,
+            symbols: stats.symbols_found as i64,
+            commit: stats.commit.clone(),
+        };
+        match state.store.record_snapshot(&snapshot).await {
+            Ok(()) => {
+                info!("  Recorded snapshot {}", snapshot.key);
+                out["snapshot"] = json!(snapshot);
+            }
+            Err(e) => {
+                error!("  Recording snapshot {} failed: {}", snapshot.key, e);
+                out["error"] = json!(e.to_string());
+            }
+        }
+    }
+    out
+}
+
+// Resolve `reference` and check it out beside the repo at `repo_path`. Git runs off the runtime
+async fn check_out(repo_path: &str, reference: &str) -> Result<git::Checkout, String> {
+    let (repo_path, reference) = (repo_path.to_string(), reference.to_string());
+    tokio::task::spawn_blocking(move || {
+        let commit = git::resolve_ref(&repo_path, &reference)
+            .ok_or_else(|| format!("Unknown ref {} in {}", reference, repo_path))?;
+        info!("  Checking out {} ({})", reference, commit);
+        git::checkout(&repo_path, &commit).ok_or_else(|| format!("Checking out {} in {} failed", reference, repo_path))
+    }).await.map_err(|e| e.to_string())?
+}
+
+async fn list_snapshots(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Json<Value> {
+    info!("GET /repo/{}/snapshots", name);
+    match state.store.list_snapshots(&name).await {
+        Ok(snapshots) => Json(json!({ "repo": name, "snapshots": snapshots })),
+        Err(e) => {
+            warn!("  Listing snapshots failed: {}", e);
+            Json(json!({ "repo": name, "snapshots": [], "error": e.to_string() }))
+        }
+    }
+}
+
+// Every indexed repo importing the external package, with the files that import it. Snapshots
+// are left out so each repo is counted once
+async fn package_users(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Json<Value> {
+    info!("GET /packages/{}", name);
+    match state.store.package_users(&name).await {
+        Ok(users) => {
+            let repos: Vec<Value> = users.into_iter()
+                .filter(|u| !u["repo"].as_str().unwrap_or_default().contains('@'))
+                .collect();
+            debug!("  Used by {} repos", repos.len());
+            Json(json!({ "package": name, "repos": repos }))
+        }
+        Err(e) => {
+            warn!("  Package lookup failed: {}", e);
+            Json(json!({ "package": name, "repos": [], "error": e.to_string() }))
+        }
+    }
+}
+
+// `<repo>@latest` names the repo's newest snapshot; any other name (including `<repo>@<id>`) is
+// used as given
+async fn resolve_repo(store: &dyn GraphStore, name: &str) -> String {
+    let Some(repo) = name.strip_suffix("@latest") else { return name.to_string() };
+    match store.list_snapshots(repo).await {
+        Ok(snapshots) => snapshots.first().map_or_else(|| name.to_string(), |s| s.key.clone()),
+        Err(e) => {
+            warn!("  Listing snapshots of {} failed: {}", repo, e);
+            name.to_string()
+        }
+    }
+}
+
+async fn delete_repo(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Json<Value> {
+    info!("DELETE /repo/{}", name);
+    match state.store.delete_repo(&name).await {
+        Ok(deleted) => {
+            info!("  Deleted {} nodes", deleted);
+            Json(json!({ "repo": name, "deleted": deleted }))
+        }
+        Err(e) => {
+            error!("  Delete failed for {}: {}", name, e);
+            Json(json!({ "repo": name, "deleted": 0, "error": e.to_string() }))
+        }
+    }
+}
+
+async fn dump_repo(State(state): State<Arc<AppState>>, Path(name): Path<String>) -> Json<Value> {
+    info!("GET /repo/{}/dump", name);
+    let repo = resolve_repo(state.store.as_ref(), &name).await;
+    match state.store.dump_repo(&repo).await {
+        Ok(dump) => {
+            info!("  Dumped {} nodes and {} relationships",
+                dump["nodes"].as_array().map_or(0, Vec::len), dump["edges"].as_array().map_or(0, Vec::len));
+            Json(dump)
+        }
+        Err(e) => {
+            error!("  Dump failed for {}: {}", name, e);
+            Json(json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+// Dumps of large repos are far beyond axum's 2 MB default
+const RESTORE_BODY_LIMIT: usize = 512 * 1024 * 1024;
+
+async fn restore_repo(State(state): State<Arc<AppState>>, Json(dump): Json<Value>) -> Json<Value> {
+    let repo = dump["repo"].as_str().unwrap_or_default().to_string();
+    info!("POST /restore -- repo={}", repo);
+    match state.store.restore_repo(&dump).await {
+        Ok((nodes, edges)) => {
+            info!("  Restored {} nodes and {} relationships", nodes, edges);
+            Json(json!({ "repo": repo, "nodes": nodes, "relationships": edges }))
+        }
+        Err(e) => {
+            error!("  Restore failed for {}: {}", repo, e);
+            Json(json!({ "repo": repo, "error": e.to_string() }))
+        }
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -98,37 +645,225 @@ struct ParseRequest {
     filename: String,
     content: String,
     repo_name: Option<String>,
+    // Hash returned by a previous /parse of this file, plus the edits made since
+    previous_hash: Option<String>,
+    #[serde(default)]
+    edits: Vec<parsing::TextEdit>,
 }
 
 async fn parse_file(State(state): State<Arc<AppState>>, Json(payload): Json<ParseRequest>) -> Json<Value> {
     debug!("POST /parse -- file={}", payload.filename);
-    let result = parsing::parse_content(&payload.filename, &payload.content);
-    debug!("  Parsed: {} symbols, {} imports", result.symbols.len(), result.imports.len());
-    let ingested = if let (Some(client), Some(repo)) = (&state.graph, &payload.repo_name) {
-        match client.ingest_symbols(repo, &payload.filename, &result).await {
-            Ok(_) => { true }
-            Err(e) => { error!("  Neo4j ingest failed for {}: {}", payload.filename, e); false }
+    let (result, content_hash, incremental) = parsing::parse_content_incremental(
+        &payload.filename, &payload.content, payload.previous_hash.as_deref(), &payload.edits);
+    debug!("  Parsed: {} symbols, {} imports (incremental: {})", result.symbols.len(), result.imports.len(), incremental);
+    let ingested = if let Some(repo) = &payload.repo_name {
+        match state.store.ingest_symbols(repo, &payload.filename, &result).await {
+            Ok(_) => {
+                if let Err(e) = state.store.link_hierarchy(repo, std::slice::from_ref(&payload.filename)).await {
+                    warn!("  Directory linking failed for {}: {}", payload.filename, e);
+                }
+                if let Err(e) = state.store.link_modules(repo).await {
+                    warn!("  Module linking failed for {}: {}", repo, e);
+                }
+                if let Err(e) = state.store.link_packages(repo).await {
+                    warn!("  Package linking failed for {}: {}", repo, e);
+                }
+                if let Err(e) = state.store.link_mentions(repo).await {
+                    warn!("  Mention linking failed for {}: {}", repo, e);
+                }
+                true
+            }
+            Err(e) => { error!("  Ingest failed for {}: {}", payload.filename, e); false }
         }
     } else {
         false
     };
-    Json(json!({ "parsing": result, "ingested": ingested }))
+    Json(json!({ "parsing": &*result, "ingested": ingested, "content_hash": content_hash, "incremental": incremental }))
 }
 
 #[derive(serde::Deserialize)]
 struct ClassifyRequest {
     repo_name: String,
+    // Embedding of the repo's summary (query_type "summary"), for the embedding stage
+    embedding: Option<Vec<f32>>,
 }
 
 async fn classify_repo(State(state): State<Arc<AppState>>, Json(payload): Json<ClassifyRequest>) -> Json<Value> {
     info!("POST /classify -- repo={}", payload.repo_name);
-    if let Some(client) = &state.graph {
-        let result = classifier::classify(client, &payload.repo_name).await;
-        info!("  Classified as {} (confidence: {:.2}), signals: {:?}", result.doc_type, result.confidence, result.signals);
-        Json(json!(result))
-    } else {
-        warn!("  No database -- defaulting to devdocs");
-        Json(json!({ "doc_type": "devdocs", "confidence": 0.0, "signals": [] }))
+    let repo = resolve_repo(state.store.as_ref(), &payload.repo_name).await;
+    let result = classifier::classify(state.store.as_ref(), &repo, payload.embedding.as_deref()).await;
+    let signals: Vec<&str> = result.signals.iter().map(|s| s.signal.as_str()).collect();
+    info!("  Classified as {} (confidence: {:.2}), signals: {:?}", result.doc_type, result.confidence, signals);
+    Json(json!(result))
+}
+
+#[derive(serde::Deserialize)]
+struct FeedbackRequest {
+    repo_name: String,
+    // The right doc type; left out to confirm the current one
+    doc_type: Option<String>,
+    // Rules whose signals are wrong for this repo
+    #[serde(default)]
+    disputed: Vec<String>,
+    // As for /classify; kept as an example of the doc type
+    embedding: Option<Vec<f32>>,
+}
+
+// A user's verdict on /classify for a repo: it answers /classify from then on, and recalibrates
+// the rule weights for every repo
+async fn classify_feedback(State(state): State<Arc<AppState>>, Json(payload): Json<FeedbackRequest>) -> Json<Value> {
+    info!("POST /classify/feedback -- repo={} doc_type={:?} disputed={:?}", payload.repo_name, payload.doc_type, payload.disputed);
+    let repo = resolve_repo(state.store.as_ref(), &payload.repo_name).await;
+    let result = classifier::classify(state.store.as_ref(), &repo, payload.embedding.as_deref()).await;
+    let feedback = match classifier::feedback(&result, payload.doc_type, payload.disputed, payload.embedding) {
+        Ok(feedback) => feedback,
+        Err(e) => return Json(json!({ "repo": repo, "error": e })),
+    };
+    match state.store.set_classification_feedback(&repo, &feedback).await {
+        Ok(()) => {
+            info!("  Recorded {} for {} (rules picked {})", feedback.doc_type, repo, feedback.predicted);
+            Json(json!({ "repo": repo, "feedback": feedback }))
+        }
+        Err(e) => {
+            error!("  Feedback failed for {}: {}", repo, e);
+            Json(json!({ "repo": repo, "error": e.to_string() }))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SearchRequest {
+    repo_name: String,
+    query: String,
+    #[serde(default = "default_search_limit")]
+    limit: i64,
+}
+
+fn default_search_limit() -> i64 { 20 }
+
+async fn search_symbols(State(state): State<Arc<AppState>>, Json(payload): Json<SearchRequest>) -> Json<Value> {
+    info!("POST /search -- repo={} query={:?}", payload.repo_name, payload.query);
+    let repo = resolve_repo(state.store.as_ref(), &payload.repo_name).await;
+    match state.store.search_symbols(&repo, &payload.query, payload.limit).await {
+        Ok(results) => {
+            debug!("  Returning {} results", results.len());
+            Json(json!({ "results": results }))
+        }
+        Err(e) => {
+            warn!("  Search failed: {}", e);
+            Json(json!({ "results": [], "error": e.to_string() }))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingsRequest {
+    repo_name: String,
+    embeddings: Vec<graph::SymbolEmbedding>,
+}
+
+async fn store_embeddings(State(state): State<Arc<AppState>>, Json(payload): Json<EmbeddingsRequest>) -> Json<Value> {
+    info!("POST /embeddings -- repo={} count={}", payload.repo_name, payload.embeddings.len());
+    match state.store.set_embeddings(&payload.repo_name, &payload.embeddings).await {
+        Ok(stored) => {
+            debug!("  Stored {} embeddings", stored);
+            Json(json!({ "stored": stored }))
+        }
+        Err(e) => {
+            error!("  Storing embeddings failed: {}", e);
+            Json(json!({ "stored": 0, "error": e.to_string() }))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct SimilarRequest {
+    repo_name: String,
+    embedding: Vec<f32>,
+    #[serde(default = "default_search_limit")]
+    k: i64,
+}
+
+async fn similar_symbols(State(state): State<Arc<AppState>>, Json(payload): Json<SimilarRequest>) -> Json<Value> {
+    info!("POST /similar -- repo={} k={}", payload.repo_name, payload.k);
+    let repo = resolve_repo(state.store.as_ref(), &payload.repo_name).await;
+    match state.store.similar_symbols(&repo, &payload.embedding, payload.k).await {
+        Ok(results) => {
+            debug!("  Returning {} results", results.len());
+            Json(json!({ "results": results }))
+        }
+        Err(e) => {
+            warn!("  Similarity search failed: {}", e);
+            Json(json!({ "results": [], "error": e.to_string() }))
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct ExportParams {
+    repo: String,
+    #[serde(default = "default_export_format")]
+    format: String,
+    // format "dot": "calls" (default) or "imports", optionally limited to what `root` reaches in `depth` hops
+    #[serde(default)]
+    scope: Option<String>,
+    #[serde(default)]
+    root: Option<String>,
+    #[serde(default)]
+    depth: Option<usize>,
+}
+
+fn default_export_format() -> String { "graphml".to_string() }
+
+async fn export_graph(State(state): State<Arc<AppState>>, Query(params): Query<ExportParams>) -> Response {
+    info!("GET /graph/export -- repo={} format={}", params.repo, params.format);
+    let repo = resolve_repo(state.store.as_ref(), &params.repo).await;
+    match params.format.as_str() {
+        "graphml" => (
+            [(header::CONTENT_TYPE, "application/graphml+xml")],
+            Body::from_stream(export::graphml(state.store.clone(), repo)),
+        ).into_response(),
+        "dot" => {
+            let scope = params.scope.as_deref().unwrap_or("calls");
+            if scope != "calls" && scope != "imports" {
+                return Json(json!({ "error": "unknown scope" })).into_response();
+            }
+            let depth = params.depth.unwrap_or(DEFAULT_CALL_DEPTH).clamp(1, MAX_CALL_DEPTH);
+            match export::dot(state.store.as_ref(), &repo, scope, params.root.as_deref(), depth).await {
+                Ok(dot) => ([(header::CONTENT_TYPE, "text/vnd.graphviz")], dot).into_response(),
+                Err(e) => {
+                    warn!("  DOT export failed: {}", e);
+                    Json(json!({ "error": e.to_string() })).into_response()
+                }
+            }
+        }
+        _ => {
+            warn!("  Unknown export format: {}", params.format);
+            Json(json!({ "error": "unknown format" })).into_response()
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct DiffParams {
+    // Repo names or snapshot keys (`<repo>@<id>`, `<repo>@latest`)
+    base: String,
+    head: String,
+}
+
+async fn diff_repos(State(state): State<Arc<AppState>>, Query(params): Query<DiffParams>) -> Json<Value> {
+    info!("GET /diff -- base={} head={}", params.base, params.head);
+    let base = resolve_repo(state.store.as_ref(), &params.base).await;
+    let head = resolve_repo(state.store.as_ref(), &params.head).await;
+    match diff::diff(state.store.as_ref(), &base, &head).await {
+        Ok(diff) => {
+            debug!("  {}", diff["summary"]);
+            Json(diff)
+        }
+        Err(e) => {
+            warn!("  Diff failed: {}", e);
+            Json(json!({ "base": base, "head": head, "error": e.to_string() }))
+        }
     }
 }
 
@@ -136,34 +871,263 @@ async fn classify_repo(State(state): State<Arc<AppState>>, Json(payload): Json<C
 struct GraphQueryRequest {
     repo_name: String,
     query_type: String,
+    // query_type "cypher": the statement, and an optional row cap (default and maximum 1000).
+    // `limit` is also the result count for "hotspots" (default 20)
+    #[serde(default)]
+    cypher: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+    // query_type "callers" / "callees": a function id, qualified name or name, and how many hops to follow
+    #[serde(default)]
+    symbol: Option<String>,
+    #[serde(default)]
+    depth: Option<usize>,
 }
 
+const CYPHER_MAX_ROWS: usize = 1000;
+const CYPHER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const DEFAULT_CALL_DEPTH: usize = 3;
+const MAX_CALL_DEPTH: usize = 10;
+const DEFAULT_HOTSPOTS: usize = 20;
+
 async fn query_graph(State(state): State<Arc<AppState>>, Json(payload): Json<GraphQueryRequest>) -> Json<Value> {
     info!("POST /graph/query -- repo={} type={}", payload.repo_name, payload.query_type);
-    if let Some(client) = &state.graph {
-        match payload.query_type.as_str() {
-            "symbols" => {
-                let symbols = client.get_all_symbols(&payload.repo_name).await.unwrap_or_default();
-                debug!("  Returning {} symbols", symbols.len());
-                Json(json!({ "symbols": symbols }))
+    let repo = resolve_repo(state.store.as_ref(), &payload.repo_name).await;
+    match payload.query_type.as_str() {
+        "symbols" => {
+            let symbols = state.store.get_all_symbols(&repo).await.unwrap_or_default();
+            debug!("  Returning {} symbols", symbols.len());
+            Json(json!({ "symbols": symbols }))
+        }
+        "files" => {
+            let files = state.store.get_all_files(&repo).await.unwrap_or_default();
+            debug!("  Returning {} files", files.len());
+            Json(json!({ "files": files }))
+        }
+        "structure" => {
+            let structure = state.store.get_repo_structure(&repo).await.unwrap_or_default();
+            debug!("  Returning structure for {} files", structure.len());
+            Json(json!({ "structure": structure }))
+        }
+        "docs" => {
+            let docs = state.store.get_docs(&repo).await.unwrap_or_default();
+            debug!("  Returning {} documents", docs.len());
+            Json(json!({ "docs": docs }))
+        }
+        "todos" => {
+            let todos = state.store.get_todos(&repo).await.unwrap_or_default();
+            debug!("  Returning {} todos", todos.len());
+            Json(json!({ "todos": todos }))
+        }
+        "routes" => {
+            let routes = state.store.get_routes(&repo).await.unwrap_or_default();
+            debug!("  Returning {} routes", routes.len());
+            Json(json!({ "routes": routes }))
+        }
+        "commands" => {
+            let commands = state.store.get_commands(&repo).await.unwrap_or_default();
+            debug!("  Returning {} commands", commands.len());
+            Json(json!({ "commands": commands }))
+        }
+        "cypher" => {
+            let cypher = payload.cypher.as_deref().unwrap_or("").trim();
+            if cypher.is_empty() {
+                return Json(json!({ "error": "cypher is required" }));
             }
-            "files" => {
-                let files = client.get_all_files(&payload.repo_name).await.unwrap_or_default();
-                debug!("  Returning {} files", files.len());
-                Json(json!({ "files": files }))
+            if let Some(reason) = graph::readonly_violation(cypher) {
+                warn!("  Rejected cypher: {}", reason);
+                return Json(json!({ "error": reason }));
             }
-            "structure" => {
-                let structure = client.get_repo_structure(&payload.repo_name).await.unwrap_or_default();
-                debug!("  Returning structure for {} files", structure.len());
-                Json(json!({ "structure": structure }))
+            let limit = payload.limit.unwrap_or(CYPHER_MAX_ROWS).min(CYPHER_MAX_ROWS);
+            match state.store.run_readonly_cypher(&repo, cypher, limit, CYPHER_TIMEOUT).await {
+                Ok((rows, truncated)) => {
+                    debug!("  Returning {} rows (truncated: {})", rows.len(), truncated);
+                    Json(json!({ "rows": rows, "truncated": truncated }))
+                }
+                Err(e) => {
+                    warn!("  Cypher failed: {}", e);
+                    Json(json!({ "error": e.to_string() }))
+                }
             }
-            _ => {
-                warn!("  Unknown query_type: {}", payload.query_type);
-                Json(json!({ "error": "unknown query_type" }))
+        }
+        "callers" | "callees" => {
+            let symbol = payload.symbol.as_deref().unwrap_or("").trim();
+            if symbol.is_empty() {
+                return Json(json!({ "error": "symbol is required" }));
+            }
+            let depth = payload.depth.unwrap_or(DEFAULT_CALL_DEPTH).clamp(1, MAX_CALL_DEPTH);
+            let callers = payload.query_type == "callers";
+            match state.store.call_chain(&repo, symbol, depth, callers).await {
+                Ok(chains) => {
+                    debug!("  Returning {} call chains for {}", chains.len(), symbol);
+                    Json(json!({ "symbol": symbol, "direction": payload.query_type, "depth": depth, "chains": chains }))
+                }
+                Err(e) => {
+                    warn!("  Call chain query failed: {}", e);
+                    Json(json!({ "error": e.to_string() }))
+                }
             }
         }
-    } else {
-        error!("  No database connection");
-        Json(json!({ "error": "no database connection" }))
+        "packages" => {
+            let packages = state.store.get_packages(&repo).await.unwrap_or_default();
+            debug!("  Returning {} external packages", packages.len());
+            Json(json!({ "packages": packages }))
+        }
+        "declared_dependencies" => {
+            match state.store.get_declared_dependencies(&repo).await {
+                Ok(dependencies) => {
+                    debug!("  Returning {} declared dependencies", dependencies.len());
+                    Json(json!({ "dependencies": dependencies }))
+                }
+                Err(e) => {
+                    warn!("  Declared dependency query failed: {}", e);
+                    Json(json!({ "dependencies": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "license" => {
+            match state.store.get_license(&repo).await {
+                Ok(license) => Json(json!({ "license": license })),
+                Err(e) => {
+                    warn!("  License query failed: {}", e);
+                    Json(json!({ "license": null, "error": e.to_string() }))
+                }
+            }
+        }
+        "submodules" => {
+            match state.store.get_submodules(&repo).await {
+                Ok(submodules) => Json(json!({ "submodules": submodules })),
+                Err(e) => {
+                    warn!("  Submodules query failed: {}", e);
+                    Json(json!({ "submodules": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "entrypoints" => {
+            match state.store.get_entrypoints(&repo).await {
+                Ok(entrypoints) => Json(json!({ "entrypoints": entrypoints })),
+                Err(e) => {
+                    warn!("  Entrypoints query failed: {}", e);
+                    Json(json!({ "entrypoints": [], "error": e.to_string() }))
+                }
+            }
+        }
+        // What to embed for /classify's embedding stage
+        "summary" => Json(json!({ "summary": classifier::summary(state.store.as_ref(), &repo).await })),
+        "availability" => {
+            match state.store.availability(&repo).await {
+                Ok(availability) => Json(json!({ "availability": availability })),
+                Err(e) => {
+                    warn!("  Availability query failed: {}", e);
+                    Json(json!({ "availability": null, "error": e.to_string() }))
+                }
+            }
+        }
+        "dependencies" => {
+            match state.store.get_dependencies(&repo).await {
+                Ok(deps) => {
+                    debug!("  Returning {} dependency edges", deps["edges"].as_array().map_or(0, Vec::len));
+                    Json(deps)
+                }
+                Err(e) => {
+                    warn!("  Dependency query failed: {}", e);
+                    Json(json!({ "edges": [], "cycles": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "unresolved" => {
+            match state.store.get_unresolved(&repo).await {
+                Ok(unresolved) => {
+                    debug!("  Returning unresolved calls and imports: {}", unresolved["summary"]);
+                    Json(unresolved)
+                }
+                Err(e) => {
+                    warn!("  Unresolved query failed: {}", e);
+                    Json(json!({ "calls": [], "imports": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "dead_code" => {
+            match state.store.get_dead_code(&repo).await {
+                Ok(dead) => {
+                    debug!("  Returning {} functions and {} classes with no users",
+                        dead["functions"].as_array().map_or(0, Vec::len), dead["classes"].as_array().map_or(0, Vec::len));
+                    Json(dead)
+                }
+                Err(e) => {
+                    warn!("  Dead code query failed: {}", e);
+                    Json(json!({ "functions": [], "classes": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "hotspots" => {
+            let limit = payload.limit.unwrap_or(DEFAULT_HOTSPOTS);
+            match state.store.get_hotspots(&repo, limit).await {
+                Ok(hotspots) => {
+                    debug!("  Returning {} hotspots", hotspots.len());
+                    Json(json!({ "hotspots": hotspots }))
+                }
+                Err(e) => {
+                    warn!("  Hotspot query failed: {}", e);
+                    Json(json!({ "hotspots": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "clusters" => {
+            match state.store.detect_clusters(&repo).await {
+                Ok(clusters) => {
+                    debug!("  Returning {} clusters", clusters.len());
+                    Json(json!({ "clusters": clusters }))
+                }
+                Err(e) => {
+                    warn!("  Clustering failed: {}", e);
+                    Json(json!({ "clusters": [], "error": e.to_string() }))
+                }
+            }
+        }
+        "directories" => {
+            let directories = state.store.get_directories(&repo).await.unwrap_or_default();
+            debug!("  Returning {} directories", directories.len());
+            Json(json!({ "directories": directories }))
+        }
+        "embedded" => {
+            let embedded = state.store.get_embedded(&repo).await.unwrap_or_default();
+            debug!("  Returning {} embedded snippets", embedded.len());
+            Json(json!({ "embedded": embedded }))
+        }
+        _ => {
+            warn!("  Unknown query_type: {}", payload.query_type);
+            Json(json!({ "error": "unknown query_type" }))
+        }
+    }
+}
+
+// Write the in-memory graph to Neo4j once it is reachable. Queries keep being served from memory
+// until the engine restarts
+async fn flush_memory(State(state): State<Arc<AppState>>) -> Json<Value> {
+    info!("POST /flush");
+    let Some(memory) = &state.memory else {
+        return Json(json!({ "flushed": 0, "error": "engine is already using the database" }));
+    };
+    let cfg = &state.neo4j;
+    let client = match GraphClient::connect(&cfg.connection).await {
+        Ok(client) => client.with_batch_size(cfg.batch_size).with_dialect(cfg.dialect).with_query_timeout(cfg.query_timeout),
+        Err(e) => {
+            warn!("  Neo4j still unreachable: {}", e);
+            return Json(json!({ "flushed": 0, "error": e.to_string() }));
+        }
+    };
+    if let Err(e) = client.ensure_schema().await {
+        error!("  Neo4j schema setup failed: {}", e);
+    }
+    match memory.flush(&client).await {
+        Ok(files) => {
+            info!("  Flushed {} files to Neo4j", files);
+            Json(json!({ "flushed": files }))
+        }
+        Err(e) => {
+            error!("  Flush failed: {}", e);
+            Json(json!({ "flushed": 0, "error": e.to_string() }))
+        }
     }
 }
@@ -0,0 +1,612 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// One dependency a manifest declares
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    // As written in the manifest ("^1.2", ">=2,<3", "v1.4.0"); empty when unconstrained
+    pub version_req: String,
+    // "cargo", "npm", "pypi", "go" or "maven"
+    pub ecosystem: String,
+    // "runtime", "dev", "build", "optional", "peer" or "indirect"
+    pub kind: String,
+    // Manifest declaring it, relative to the repo root
+    pub manifest: String,
+    // Package the manifest describes, when it names one
+    pub package: Option<String>,
+    // Exact version the lockfile next to (or above) the manifest pinned
+    pub resolved_version: Option<String>,
+    // Packages the lockfile pulled in through this one, itself excluded; None when the lockfile
+    // records no dependency graph (go.sum)
+    pub transitive: Option<usize>,
+}
+
+// Whether `path` names a manifest `parse` understands
+pub fn is_manifest(path: &str) -> bool {
+    let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    matches!(name, "Cargo.toml" | "package.json" | "pyproject.toml" | "go.mod" | "pom.xml")
+        || (name.starts_with("requirements") && name.ends_with(".txt"))
+}
+
+// Dependencies declared in the manifest at `rel_path` (relative to the repo root). Unparseable
+// manifests declare nothing
+pub fn parse(rel_path: &str, content: &str) -> Vec<Dependency> {
+    let name = Path::new(rel_path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let (package, deps) = match name {
+        "Cargo.toml" => cargo(content),
+        "package.json" => npm(content),
+        "pyproject.toml" => pyproject(content),
+        "go.mod" => go_mod(content),
+        "pom.xml" => pom(content),
+        _ if is_manifest(name) => (None, requirements(content, name.contains("dev") || name.contains("test"))),
+        _ => (None, vec![]),
+    };
+    deps.into_iter()
+        .map(|(ecosystem, name, version_req, kind)| Dependency {
+            name,
+            version_req,
+            ecosystem: ecosystem.to_string(),
+            kind: kind.to_string(),
+            manifest: rel_path.to_string(),
+            package: package.clone(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+// (ecosystem, name, version requirement, kind)
+type Declared = (&'static str, String, String, &'static str);
+
+fn cargo(content: &str) -> (Option<String>, Vec<Declared>) {
+    let Ok(manifest) = content.parse::<toml::Table>() else { return (None, vec![]) };
+    let package = manifest.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(str::to_string);
+    let mut deps = vec![];
+    let mut tables = vec![(&manifest, false)];
+    // [target.'cfg(windows)'.dependencies] and friends
+    if let Some(targets) = manifest.get("target").and_then(|t| t.as_table()) {
+        tables.extend(targets.values().filter_map(|t| t.as_table()).map(|t| (t, false)));
+    }
+    if let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) {
+        tables.push((workspace, true));
+    }
+    for (table, workspace) in tables {
+        for (section, kind) in [("dependencies", "runtime"), ("dev-dependencies", "dev"), ("build-dependencies", "build")] {
+            let Some(section) = table.get(section).and_then(|s| s.as_table()) else { continue };
+            for (key, spec) in section {
+                let (version, optional) = match spec {
+                    toml::Value::String(version) => (version.clone(), false),
+                    toml::Value::Table(spec) => (
+                        spec.get("version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                        spec.get("optional").and_then(|o| o.as_bool()).unwrap_or(false),
+                    ),
+                    _ => continue,
+                };
+                // `package = "..."` renames; the registry knows it by that name
+                let name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                let kind = if optional && !workspace { "optional" } else { kind };
+                deps.push(("cargo", name.to_string(), version, kind));
+            }
+        }
+    }
+    (package, deps)
+}
+
+fn npm(content: &str) -> (Option<String>, Vec<Declared>) {
+    let Ok(manifest) = serde_json::from_str::<Value>(content) else { return (None, vec![]) };
+    let package = manifest["name"].as_str().map(str::to_string);
+    let mut deps = vec![];
+    for (section, kind) in [
+        ("dependencies", "runtime"), ("devDependencies", "dev"), ("peerDependencies", "peer"), ("optionalDependencies", "optional"),
+    ] {
+        let Some(section) = manifest[section].as_object() else { continue };
+        for (name, version) in section {
+            deps.push(("npm", name.clone(), version.as_str().unwrap_or_default().to_string(), kind));
+        }
+    }
+    (package, deps)
+}
+
+fn pyproject(content: &str) -> (Option<String>, Vec<Declared>) {
+    let Ok(manifest) = content.parse::<toml::Table>() else { return (None, vec![]) };
+    let project = manifest.get("project");
+    let poetry = manifest.get("tool").and_then(|t| t.get("poetry"));
+    let package = project.or(poetry).and_then(|p| p.get("name")).and_then(|n| n.as_str()).map(str::to_string);
+    let mut deps = vec![];
+    // PEP 621: lists of requirement strings
+    let requirements = |value: Option<&toml::Value>| -> Vec<String> {
+        value.and_then(|v| v.as_array()).into_iter().flatten().filter_map(|r| r.as_str()).map(str::to_string).collect()
+    };
+    for requirement in requirements(project.and_then(|p| p.get("dependencies"))) {
+        deps.extend(pep508(&requirement).map(|(name, version)| ("pypi", name, version, "runtime")));
+    }
+    if let Some(extras) = project.and_then(|p| p.get("optional-dependencies")).and_then(|o| o.as_table()) {
+        for requirement in extras.values().flat_map(|group| requirements(Some(group))) {
+            deps.extend(pep508(&requirement).map(|(name, version)| ("pypi", name, version, "optional")));
+        }
+    }
+    // Poetry: tables of name = version or name = { version = ... }
+    if let Some(poetry) = poetry {
+        let mut sections = vec![(poetry.get("dependencies"), "runtime"), (poetry.get("dev-dependencies"), "dev")];
+        if let Some(groups) = poetry.get("group").and_then(|g| g.as_table()) {
+            sections.extend(groups.values().map(|group| (group.get("dependencies"), "dev")));
+        }
+        for (section, kind) in sections {
+            let Some(section) = section.and_then(|s| s.as_table()) else { continue };
+            for (name, spec) in section.iter().filter(|(name, _)| *name != "python") {
+                let version = match spec {
+                    toml::Value::String(version) => version.clone(),
+                    toml::Value::Table(spec) => spec.get("version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+                    _ => String::new(),
+                };
+                deps.push(("pypi", name.clone(), version, kind));
+            }
+        }
+    }
+    (package, deps)
+}
+
+fn requirements(content: &str, dev: bool) -> Vec<Declared> {
+    let kind = if dev { "dev" } else { "runtime" };
+    content.lines()
+        .map(|line| line.split(" #").next().unwrap_or_default().trim())
+        // Options (-r other.txt, -e ., --index-url) and comments declare no package
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with('-'))
+        .filter_map(pep508)
+        .map(|(name, version)| ("pypi", name, version, kind))
+        .collect()
+}
+
+// Name and version specifier of a PEP 508 requirement ("requests[socks]>=2.0; python_version<'3.8'")
+fn pep508(requirement: &str) -> Option<(String, String)> {
+    let requirement = requirement.split(';').next().unwrap_or_default().trim();
+    let end = requirement.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))).unwrap_or(requirement.len());
+    let name = &requirement[..end];
+    if name.is_empty() || requirement.contains("://") {
+        return None;
+    }
+    let mut rest = requirement[end..].trim_start();
+    if rest.starts_with('[') {
+        rest = rest.split_once(']').map_or("", |(_, after)| after);
+    }
+    Some((name.to_string(), rest.trim().to_string()))
+}
+
+fn go_mod(content: &str) -> (Option<String>, Vec<Declared>) {
+    let mut package = None;
+    let mut deps = vec![];
+    let mut in_require = false;
+    for line in content.lines() {
+        let (line, comment) = line.split_once("//").unwrap_or((line, ""));
+        let line = line.trim();
+        let spec = if in_require {
+            if line == ")" {
+                in_require = false;
+                continue;
+            }
+            line
+        } else if let Some(module) = line.strip_prefix("module ") {
+            package = Some(module.trim().trim_matches('"').to_string());
+            continue;
+        } else if let Some(require) = line.strip_prefix("require") {
+            let require = require.trim();
+            if require == "(" {
+                in_require = true;
+                continue;
+            }
+            require
+        } else {
+            continue;
+        };
+        let mut parts = spec.split_whitespace();
+        if let (Some(path), Some(version)) = (parts.next(), parts.next()) {
+            let kind = if comment.trim() == "indirect" { "indirect" } else { "runtime" };
+            deps.push(("go", path.to_string(), version.to_string(), kind));
+        }
+    }
+    (package, deps)
+}
+
+fn pom(content: &str) -> (Option<String>, Vec<Declared>) {
+    // Managed versions, plugins and the parent aren't dependencies of this project
+    let mut project = content.to_string();
+    for section in ["dependencyManagement", "build", "parent", "profiles"] {
+        project = strip_element(&project, section);
+    }
+    let package = element(project.split("<dependencies>").next().unwrap_or_default(), "artifactId");
+    let mut deps = vec![];
+    let mut rest = project.as_str();
+    while let Some(start) = rest.find("<dependency>") {
+        let block = &rest[start..];
+        let end = block.find("</dependency>").unwrap_or(block.len());
+        let dependency = &block[..end];
+        rest = &block[end..];
+        let (Some(group), Some(artifact)) = (element(dependency, "groupId"), element(dependency, "artifactId")) else { continue };
+        let kind = match element(dependency, "scope").as_deref() {
+            Some("test") => "dev",
+            Some("provided") => "build",
+            _ if element(dependency, "optional").as_deref() == Some("true") => "optional",
+            _ => "runtime",
+        };
+        deps.push(("maven", format!("{}:{}", group, artifact), element(dependency, "version").unwrap_or_default(), kind));
+    }
+    (package, deps)
+}
+
+// Text of the first <tag>...</tag> in `xml`
+fn element(xml: &str, tag: &str) -> Option<String> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim().to_string())
+}
+
+// `xml` without any <tag>...</tag> elements
+fn strip_element(xml: &str, tag: &str) -> String {
+    let (open, close) = (format!("<{}>", tag), format!("</{}>", tag));
+    let mut out = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        out.push_str(&rest[..start]);
+        rest = match rest[start..].find(&close) {
+            Some(end) => &rest[start + end + close.len()..],
+            None => "",
+        };
+    }
+    out.push_str(rest);
+    out
+}
+
+// Whether `path` names a lockfile `resolve` understands
+pub fn is_lockfile(path: &str) -> bool {
+    let name = Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    matches!(name, "Cargo.lock" | "package-lock.json" | "pnpm-lock.yaml" | "poetry.lock" | "go.sum")
+}
+
+// Fill in resolved versions and transitive counts from `lockfiles`, (path relative to the repo
+// root, content) pairs. Each dependency is looked up in the nearest lockfile of its ecosystem at
+// or above its manifest; those no lockfile covers are left as declared
+pub fn resolve(dependencies: &mut [Dependency], lockfiles: &[(String, String)]) {
+    let locks: Vec<Lock> = lockfiles.iter().filter_map(|(path, content)| lock(path, content)).collect();
+    for dependency in dependencies.iter_mut() {
+        let dir = parent(&dependency.manifest);
+        let Some((lock, member)) = locks.iter()
+            .filter(|lock| lock.ecosystem == dependency.ecosystem)
+            .filter_map(|lock| member_of(&lock.dir, dir).map(|member| (lock, member)))
+            .max_by_key(|(lock, _)| lock.dir.len()) else { continue };
+        let Some(index) = lock.find(dependency, member) else { continue };
+        dependency.resolved_version = Some(lock.packages[index].1.clone());
+        dependency.transitive = lock.graph.then(|| lock.reachable(index));
+    }
+}
+
+// (name, version, dependencies with the version they resolved to when the lockfile says)
+type Locked = (String, String, Vec<(String, Option<String>)>);
+// Versions pinned for the direct dependencies of a workspace member, keyed by (member, dependency)
+type Pinned = HashMap<(String, String), String>;
+
+struct Lock {
+    ecosystem: &'static str,
+    // Directory holding the lockfile, relative to the repo root
+    dir: String,
+    // (name, version) of every locked package, and the packages each depends on
+    packages: Vec<(String, String)>,
+    edges: Vec<Vec<usize>>,
+    by_name: HashMap<String, Vec<usize>>,
+    // Members are directories relative to `dir` ("" for its own), or package names for Cargo
+    pinned: Pinned,
+    // Whether the lockfile records which package needs which
+    graph: bool,
+}
+
+impl Lock {
+    fn new(ecosystem: &'static str, dir: String, locked: Vec<Locked>, pinned: Pinned, graph: bool) -> Self {
+        let mut index: HashMap<(String, String), usize> = HashMap::new();
+        let (mut packages, mut requires) = (vec![], vec![]);
+        for (name, version, dependencies) in locked {
+            let name = normalize(ecosystem, &name);
+            // The same package can appear more than once (installed at several paths, or once
+            // per set of peers); they are one node
+            let i = *index.entry((name.clone(), version.clone())).or_insert_with(|| {
+                packages.push((name, version));
+                requires.push(vec![]);
+                packages.len() - 1
+            });
+            requires[i].extend(dependencies);
+        }
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, (name, _)) in packages.iter().enumerate() {
+            by_name.entry(name.clone()).or_default().push(i);
+        }
+        let mut lock = Self { ecosystem, dir, packages, edges: vec![], by_name, pinned, graph };
+        lock.edges = requires.into_iter()
+            .map(|dependencies: Vec<(String, Option<String>)>| dependencies.iter()
+                .filter_map(|(name, version)| lock.lookup(name, version.as_deref().unwrap_or_default()))
+                .collect())
+            .collect();
+        lock
+    }
+
+    // Where `dependency`, declared by a manifest in `member`, landed
+    fn find(&self, dependency: &Dependency, member: &str) -> Option<usize> {
+        let name = normalize(self.ecosystem, &dependency.name);
+        let pinned = [Some(member), dependency.package.as_deref()].into_iter()
+            .flatten()
+            .find_map(|member| self.pinned.get(&(member.to_string(), name.clone())));
+        self.lookup(&name, pinned.unwrap_or(&dependency.version_req))
+    }
+
+    // The locked `name` whose version `requirement` picks
+    fn lookup(&self, name: &str, requirement: &str) -> Option<usize> {
+        let candidates = self.by_name.get(&normalize(self.ecosystem, name))?;
+        let versions: Vec<&str> = candidates.iter().map(|&i| self.packages[i].1.as_str()).collect();
+        let version = pick(requirement, &versions)?;
+        candidates.iter().copied().find(|&i| self.packages[i].1 == version)
+    }
+
+    // Packages reachable from `start`, not counting itself
+    fn reachable(&self, start: usize) -> usize {
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(i) = stack.pop() {
+            for &next in &self.edges[i] {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen.len() - 1
+    }
+}
+
+fn lock(rel_path: &str, content: &str) -> Option<Lock> {
+    let name = Path::new(rel_path).file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let (ecosystem, (locked, pinned)) = match name {
+        "Cargo.lock" => ("cargo", cargo_lock(content)?),
+        "package-lock.json" => ("npm", npm_lock(content)?),
+        "pnpm-lock.yaml" => ("npm", pnpm_lock(content)?),
+        "poetry.lock" => ("pypi", poetry_lock(content)?),
+        "go.sum" => ("go", (go_sum(content), HashMap::new())),
+        _ => return None,
+    };
+    Some(Lock::new(ecosystem, parent(rel_path).to_string(), locked, pinned, ecosystem != "go"))
+}
+
+fn cargo_lock(content: &str) -> Option<(Vec<Locked>, Pinned)> {
+    let lock = content.parse::<toml::Table>().ok()?;
+    let packages = lock.get("package")?.as_array()?;
+    let field = |package: &toml::Value, key: &str| package.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let mut versions: HashMap<String, String> = HashMap::new();
+    for package in packages {
+        versions.insert(field(package, "name"), field(package, "version"));
+    }
+    let (mut locked, mut pinned) = (vec![], HashMap::new());
+    for package in packages {
+        let name = field(package, "name");
+        // "serde", or "serde 1.0.1 (registry+...)" when more than one version is locked
+        let dependencies: Vec<(String, Option<String>)> = package.get("dependencies").and_then(|d| d.as_array()).into_iter()
+            .flatten()
+            .filter_map(|d| d.as_str())
+            .filter_map(|d| {
+                let mut parts = d.split_whitespace();
+                let dependency = parts.next()?.to_string();
+                let version = parts.next().map(str::to_string).or_else(|| versions.get(&dependency).cloned());
+                Some((dependency, version))
+            })
+            .collect();
+        // Packages without a source are the workspace's own; what they list is what they declared
+        if package.get("source").is_none() {
+            for (dependency, version) in dependencies.iter().filter_map(|(d, v)| Some((d, v.as_ref()?))) {
+                pinned.insert((name.clone(), dependency.clone()), version.clone());
+            }
+        }
+        locked.push((name, field(package, "version"), dependencies));
+    }
+    Some((locked, pinned))
+}
+
+const NPM_SECTIONS: [&str; 4] = ["dependencies", "devDependencies", "optionalDependencies", "peerDependencies"];
+
+fn npm_lock(content: &str) -> Option<(Vec<Locked>, Pinned)> {
+    let lock = serde_json::from_str::<Value>(content).ok()?;
+    if let Some(packages) = lock["packages"].as_object() {
+        return Some(npm_packages(packages));
+    }
+    let mut out = (vec![], HashMap::new());
+    npm_tree(lock["dependencies"].as_object()?, &mut vec![], &mut out);
+    Some(out)
+}
+
+// lockfileVersion 2 and 3: packages keyed by where they are installed ("node_modules/a/node_modules/b"),
+// workspace members by their directory ("" for the root)
+fn npm_packages(packages: &serde_json::Map<String, Value>) -> (Vec<Locked>, Pinned) {
+    // What Node loads for `name` required from `from`: the nearest node_modules/name going up
+    let find = |from: &str, name: &str| -> Option<&Value> {
+        let mut base = from;
+        loop {
+            let path = if base.is_empty() { format!("node_modules/{}", name) } else { format!("{}/node_modules/{}", base, name) };
+            if let Some(found) = packages.get(&path) {
+                // Workspace members are symlinked into node_modules
+                return match found["resolved"].as_str().filter(|_| found["link"] == true) {
+                    Some(target) => packages.get(target),
+                    None => Some(found),
+                };
+            }
+            if base.is_empty() {
+                return None;
+            }
+            base = base.rfind("/node_modules/").map_or("", |end| &base[..end]);
+        }
+    };
+    let (mut locked, mut pinned) = (vec![], HashMap::new());
+    for (path, package) in packages.iter().filter(|(_, p)| p["link"] != true) {
+        let dependencies: Vec<(String, String)> = NPM_SECTIONS.iter()
+            .filter_map(|section| package[*section].as_object())
+            .flat_map(|section| section.keys())
+            .filter_map(|name| Some((name.clone(), find(path, name)?["version"].as_str()?.to_string())))
+            .collect();
+        if !path.contains("node_modules/") {
+            for (name, version) in &dependencies {
+                pinned.insert((path.clone(), name.clone()), version.clone());
+            }
+        }
+        // Aliased installs carry their real name
+        let Some(name) = package["name"].as_str().or_else(|| path.rsplit_once("node_modules/").map(|(_, name)| name)) else { continue };
+        let version = package["version"].as_str().unwrap_or_default().to_string();
+        locked.push((name.to_string(), version, dependencies.into_iter().map(|(name, version)| (name, Some(version))).collect()));
+    }
+    (locked, pinned)
+}
+
+// lockfileVersion 1: nested "dependencies", each listing what it "requires"; a requirement is met
+// by the nearest package of that name, looking in its own dependencies and then outwards
+fn npm_tree<'a>(dependencies: &'a serde_json::Map<String, Value>, scopes: &mut Vec<&'a serde_json::Map<String, Value>>, out: &mut (Vec<Locked>, Pinned)) {
+    scopes.push(dependencies);
+    for (name, package) in dependencies {
+        let nested = package["dependencies"].as_object();
+        let requires = package["requires"].as_object().into_iter()
+            .flat_map(|requires| requires.keys())
+            .map(|dependency| {
+                let version = nested.and_then(|n| n.get(dependency))
+                    .or_else(|| scopes.iter().rev().find_map(|scope| scope.get(dependency)))
+                    .and_then(|d| d["version"].as_str())
+                    .map(str::to_string);
+                (dependency.clone(), version)
+            })
+            .collect();
+        let version = package["version"].as_str().unwrap_or_default().to_string();
+        if scopes.len() == 1 {
+            out.1.insert((String::new(), name.clone()), version.clone());
+        }
+        out.0.push((name.clone(), version, requires));
+        if let Some(nested) = nested {
+            npm_tree(nested, scopes, out);
+        }
+    }
+    scopes.pop();
+}
+
+// pnpm-lock.yaml, lockfileVersion 5 to 9. Importers (workspace members, "." the root) pin their
+// direct dependencies; packages, and from version 9 snapshots, list what each package needs
+fn pnpm_lock(content: &str) -> Option<(Vec<Locked>, Pinned)> {
+    let lock = serde_yaml::from_str::<serde_yaml::Value>(content).ok()?;
+    // Unquoted versions like 1.0 come back as numbers
+    let text = |value: &serde_yaml::Value| match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    // Before workspaces, version 5 kept the root's dependencies at the top level
+    let root = [(serde_yaml::Value::from("."), lock.clone())];
+    let importers: Vec<(&serde_yaml::Value, &serde_yaml::Value)> = match lock.get("importers").and_then(|i| i.as_mapping()) {
+        Some(importers) => importers.iter().collect(),
+        None => root.iter().map(|(dir, importer)| (dir, importer)).collect(),
+    };
+    let mut pinned = HashMap::new();
+    for (dir, importer) in importers {
+        let Some(dir) = text(dir).map(|dir| if dir == "." { String::new() } else { dir }) else { continue };
+        for section in ["dependencies", "devDependencies", "optionalDependencies"] {
+            let Some(section) = importer.get(section).and_then(|s| s.as_mapping()) else { continue };
+            for (name, spec) in section {
+                // Version 6 on: { specifier, version }; before: the version alone
+                let (Some(name), Some(version)) = (text(name), text(spec.get("version").unwrap_or(spec))) else { continue };
+                if !version.starts_with("link:") {
+                    pinned.insert((dir.clone(), name), pnpm_version(&version));
+                }
+            }
+        }
+    }
+    let mut locked = vec![];
+    for section in ["packages", "snapshots"] {
+        let Some(packages) = lock.get(section).and_then(|p| p.as_mapping()) else { continue };
+        for (key, package) in packages {
+            let Some((name, version)) = text(key).as_deref().and_then(pnpm_key) else { continue };
+            let dependencies = ["dependencies", "optionalDependencies"].iter()
+                .filter_map(|section| package.get(*section).and_then(|d| d.as_mapping()))
+                .flatten()
+                .filter_map(|(name, version)| Some((text(name)?, Some(pnpm_version(&text(version)?)))))
+                .collect();
+            locked.push((name, version, dependencies));
+        }
+    }
+    Some((locked, pinned))
+}
+
+// Name and version of a pnpm package key: "/name@1.0.0(peer@2.0.0)" (version 6), "name@1.0.0"
+// (version 9) or "/name/1.0.0_peer@2.0.0" (version 5)
+fn pnpm_key(key: &str) -> Option<(String, String)> {
+    let key = key.strip_prefix('/').unwrap_or(key).split('(').next()?;
+    if let Some((name, version)) = key.rsplit_once('/').filter(|(_, version)| version.starts_with(|c: char| c.is_ascii_digit())) {
+        return Some((name.to_string(), pnpm_version(version)));
+    }
+    // A scope's @ comes first
+    let at = key.get(1..)?.find('@')? + 1;
+    Some((key[..at].to_string(), key[at + 1..].to_string()))
+}
+
+// A pnpm version without the peers it was installed against
+fn pnpm_version(version: &str) -> String {
+    version.split(['(', '_']).next().unwrap_or_default().to_string()
+}
+
+fn poetry_lock(content: &str) -> Option<(Vec<Locked>, Pinned)> {
+    let lock = content.parse::<toml::Table>().ok()?;
+    let locked = lock.get("package")?.as_array()?.iter()
+        .map(|package| {
+            let field = |key: &str| package.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+            let dependencies = package.get("dependencies").and_then(|d| d.as_table()).into_iter()
+                .flatten()
+                .map(|(name, _)| (name.clone(), None))
+                .collect();
+            (field("name"), field("version"), dependencies)
+        })
+        .collect();
+    Some((locked, HashMap::new()))
+}
+
+// go.sum lists every module version the build looked at, but not which needs which
+fn go_sum(content: &str) -> Vec<Locked> {
+    content.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let (module, version) = (parts.next()?, parts.next()?);
+            Some((module.to_string(), version.trim_end_matches("/go.mod").to_string(), vec![]))
+        })
+        .collect()
+}
+
+// The version `requirement` picks out of `versions`: the one it names exactly, else the highest
+// it allows, else the highest
+fn pick<'a>(requirement: &str, versions: &[&'a str]) -> Option<&'a str> {
+    let requirement = requirement.trim();
+    let exact = requirement.trim_start_matches(['=', 'v']);
+    if let Some(version) = versions.iter().find(|v| v.trim_start_matches('v') == exact) {
+        return Some(version);
+    }
+    let semver = |v: &str| semver::Version::parse(v.trim_start_matches('v')).ok();
+    let highest = |allowed: &dyn Fn(&str) -> bool| versions.iter().copied().filter(|v| allowed(v)).max_by_key(|v| semver(v));
+    semver::VersionReq::parse(requirement).ok()
+        .and_then(|req| highest(&|v| semver(v).is_some_and(|v| req.matches(&v))))
+        .or_else(|| highest(&|_| true))
+}
+
+// Python package names compare case-insensitively, with -, _ and . interchangeable
+fn normalize(ecosystem: &str, name: &str) -> String {
+    if ecosystem == "pypi" { name.to_lowercase().replace(['_', '.'], "-") } else { name.to_string() }
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+// `dir` relative to `lock_dir`, when it is at or below it
+fn member_of<'a>(lock_dir: &str, dir: &'a str) -> Option<&'a str> {
+    if lock_dir.is_empty() {
+        return Some(dir);
+    }
+    match dir.strip_prefix(lock_dir)? {
+        "" => Some(""),
+        rest => rest.strip_prefix('/'),
+    }
+}
@@ -0,0 +1,695 @@
+use async_trait::async_trait;
+use neo4rs::Result;
+use petgraph::graph::{DiGraph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::git::{FileHistory, Submodule};
+use crate::graph::{self, CallEnd, GraphClient, Snapshot};
+use crate::classifier::Feedback;
+use crate::entrypoints::Entrypoint;
+use crate::licenses::License;
+use crate::manifests::Dependency;
+use crate::parsing::{Implementation, ParsingResult, Symbol};
+use crate::store::GraphStore;
+
+// Parsed repos held in process when Neo4j is unreachable, answering the same queries as
+// `GraphClient` from the parse results. Calls, imports and directories are resolved when queried,
+// with the rules `ingest_symbols` and `link_hierarchy` use
+#[derive(Default)]
+pub struct MemoryGraph {
+    // Shared with queries that are still reading them; writes copy a repo only while it is being read
+    repos: RwLock<HashMap<String, Arc<MemoryRepo>>>,
+    // Outlives the repos it is about, as in the other backends
+    feedback: RwLock<HashMap<String, Feedback>>,
+}
+
+#[derive(Default, Clone)]
+struct MemoryRepo {
+    files: BTreeMap<String, ParsingResult>,
+    implementations: Vec<Implementation>,
+    // Set when the repo is a kept index run
+    snapshot: Option<Snapshot>,
+    indexed_commit: Option<String>,
+    availability: Option<String>,
+    dependencies: Vec<Dependency>,
+    license: Option<License>,
+    entrypoints: Vec<Entrypoint>,
+    history: HashMap<String, FileHistory>,
+    submodules: Vec<Submodule>,
+}
+
+// Functions and the CALLS edges between them, by node id
+struct CallGraph<'a> {
+    graph: DiGraph<String, ()>,
+    index: HashMap<String, NodeIndex>,
+    functions: HashMap<String, (&'a str, &'a Symbol)>,
+}
+
+impl MemoryGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A graph holding one repo's parsed files, for backends that store parse results elsewhere
+    pub(crate) fn from_files(
+        repo_name: &str,
+        files: BTreeMap<String, ParsingResult>,
+        history: HashMap<String, FileHistory>,
+    ) -> Self {
+        let repo = MemoryRepo { files, history, ..Default::default() };
+        Self { repos: RwLock::new(HashMap::from([(repo_name.to_string(), Arc::new(repo))])), ..Default::default() }
+    }
+
+    // Write every held repo to Neo4j, as indexing would have. Returns the number of files written
+    pub async fn flush(&self, client: &GraphClient) -> Result<usize> {
+        let repos: Vec<(String, Arc<MemoryRepo>)> = self.repos.read().unwrap_or_else(|e| e.into_inner())
+            .iter().map(|(name, repo)| (name.clone(), repo.clone())).collect();
+        let mut written = 0;
+        for (name, repo) in repos {
+            for (path, result) in &repo.files {
+                client.ingest_symbols(&name, path, result).await?;
+                written += 1;
+            }
+            client.link_implementations(&name, &repo.implementations).await?;
+            client.link_hierarchy(&name, &repo.files.keys().cloned().collect::<Vec<_>>()).await?;
+            client.link_modules(&name).await?;
+            client.link_packages(&name).await?;
+            client.link_mentions(&name).await?;
+            client.set_file_history(&name, &repo.history).await?;
+            client.set_submodules(&name, &repo.submodules).await?;
+            client.set_declared_dependencies(&name, &repo.dependencies).await?;
+            if let Some(license) = &repo.license {
+                client.set_license(&name, license).await?;
+            }
+            client.set_entrypoints(&name, &repo.entrypoints).await?;
+            client.detect_clusters(&name).await?;
+            if let Some(snapshot) = &repo.snapshot {
+                client.record_snapshot(snapshot).await?;
+            }
+            if let Some(commit) = &repo.indexed_commit {
+                client.set_indexed_commit(&name, commit).await?;
+            }
+            if let Some(availability) = &repo.availability {
+                client.set_availability(&name, availability).await?;
+            }
+        }
+        let feedback: Vec<(String, Feedback)> = self.feedback.read().unwrap_or_else(|e| e.into_inner())
+            .iter().map(|(name, feedback)| (name.clone(), feedback.clone())).collect();
+        for (name, feedback) in feedback {
+            client.set_classification_feedback(&name, &feedback).await?;
+        }
+        Ok(written)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn implementations(&self, repo_name: &str) -> Vec<Implementation> {
+        self.repo(repo_name).implementations.clone()
+    }
+
+    fn repo(&self, repo_name: &str) -> Arc<MemoryRepo> {
+        self.repos.read().unwrap_or_else(|e| e.into_inner()).get(repo_name).cloned().unwrap_or_default()
+    }
+}
+
+fn repo_mut<'a>(repos: &'a mut HashMap<String, Arc<MemoryRepo>>, repo_name: &str) -> &'a mut MemoryRepo {
+    Arc::make_mut(repos.entry(repo_name.to_string()).or_default())
+}
+
+#[async_trait]
+impl GraphStore for MemoryGraph {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).files.insert(file_path.to_string(), result.clone());
+        Ok(())
+    }
+
+    async fn link_implementations(&self, repo_name: &str, implementations: &[Implementation]) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        let held = &mut repo_mut(&mut repos, repo_name).implementations;
+        // Go's are inferred again over the whole repo whenever a Go file changes
+        for imp in implementations {
+            if !held.iter().any(|h| h.type_name == imp.type_name && h.trait_name == imp.trait_name) {
+                held.push(imp.clone());
+            }
+        }
+        Ok(())
+    }
+
+    async fn set_declared_dependencies(&self, repo_name: &str, dependencies: &[Dependency]) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).dependencies = dependencies.to_vec();
+        Ok(())
+    }
+
+    async fn set_license(&self, repo_name: &str, license: &License) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).license = Some(license.clone());
+        Ok(())
+    }
+
+    async fn set_entrypoints(&self, repo_name: &str, entrypoints: &[Entrypoint]) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).entrypoints = entrypoints.to_vec();
+        Ok(())
+    }
+
+    async fn set_submodules(&self, repo_name: &str, submodules: &[Submodule]) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).submodules = submodules.to_vec();
+        Ok(())
+    }
+
+    async fn set_file_history(&self, repo_name: &str, history: &HashMap<String, FileHistory>) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).history.extend(history.iter().map(|(p, h)| (p.clone(), h.clone())));
+        Ok(())
+    }
+
+    async fn delete_repo(&self, repo_name: &str) -> Result<i64> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        Ok(repos.remove(repo_name)
+            .map(|repo| repo.files.values().map(|r| r.symbols.len() as i64 + 1).sum())
+            .unwrap_or(0))
+    }
+
+    // Files are stored whole, so only files that are gone can be stale
+    async fn remove_stale(&self, repo_name: &str, files: Option<&[String]>, seen: &HashSet<String>) -> Result<i64> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        let Some(repo) = repos.get_mut(repo_name).map(Arc::make_mut) else { return Ok(0) };
+        let mut removed = 0;
+        repo.files.retain(|path, result| {
+            let keep = files.is_some_and(|f| !f.contains(path)) || seen.contains(&format!("{}::{}", repo_name, path));
+            if !keep { removed += result.symbols.len() as i64 + 1; }
+            keep
+        });
+        let files = &repo.files;
+        repo.history.retain(|path, _| files.contains_key(path));
+        Ok(removed)
+    }
+
+    async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, &snapshot.key).snapshot = Some(snapshot.clone());
+        Ok(())
+    }
+
+    async fn list_snapshots(&self, repo_name: &str) -> Result<Vec<Snapshot>> {
+        let mut snapshots: Vec<Snapshot> = self.repos.read().unwrap_or_else(|e| e.into_inner()).values()
+            .filter_map(|repo| repo.snapshot.clone())
+            .filter(|s| s.repo == repo_name)
+            .collect();
+        snapshots.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| b.id.cmp(&a.id)));
+        Ok(snapshots)
+    }
+
+    async fn indexed_commit(&self, repo_name: &str) -> Result<Option<String>> {
+        Ok(self.repos.read().unwrap_or_else(|e| e.into_inner()).get(repo_name).and_then(|repo| repo.indexed_commit.clone()))
+    }
+
+    async fn set_indexed_commit(&self, repo_name: &str, commit: &str) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).indexed_commit = Some(commit.to_string());
+        Ok(())
+    }
+
+    async fn availability(&self, repo_name: &str) -> Result<Option<String>> {
+        Ok(self.repos.read().unwrap_or_else(|e| e.into_inner()).get(repo_name).and_then(|repo| repo.availability.clone()))
+    }
+
+    async fn set_availability(&self, repo_name: &str, availability: &str) -> Result<()> {
+        let mut repos = self.repos.write().unwrap_or_else(|e| e.into_inner());
+        repo_mut(&mut repos, repo_name).availability = Some(availability.to_string());
+        Ok(())
+    }
+
+    async fn classification_feedback(&self, repo_name: &str) -> Result<Option<Feedback>> {
+        Ok(self.feedback.read().unwrap_or_else(|e| e.into_inner()).get(repo_name).cloned())
+    }
+
+    async fn set_classification_feedback(&self, repo_name: &str, feedback: &Feedback) -> Result<()> {
+        self.feedback.write().unwrap_or_else(|e| e.into_inner()).insert(repo_name.to_string(), feedback.clone());
+        Ok(())
+    }
+
+    async fn all_classification_feedback(&self) -> Result<Vec<Feedback>> {
+        Ok(self.feedback.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect())
+    }
+
+    async fn file_hashes(&self, repo_name: &str) -> Result<HashMap<String, String>> {
+        Ok(self.repo(repo_name).files.iter().map(|(path, result)| (path.clone(), result.content_hash.clone())).collect())
+    }
+
+    async fn count_by_kind(&self, repo_name: &str) -> Result<Value> {
+        let mut counts = serde_json::Map::new();
+        for sym in self.repo(repo_name).files.values().flat_map(|r| &r.symbols).filter(|s| !s.is_test) {
+            let count = counts.get(&sym.kind).and_then(Value::as_i64).unwrap_or(0);
+            counts.insert(sym.kind.clone(), json!(count + 1));
+        }
+        Ok(Value::Object(counts))
+    }
+
+    async fn get_file_languages(&self, repo_name: &str) -> Result<Value> {
+        let mut langs = serde_json::Map::new();
+        for result in self.repo(repo_name).files.values() {
+            let lang = format!("{:?}", result.language);
+            let count = langs.get(&lang).and_then(Value::as_i64).unwrap_or(0);
+            langs.insert(lang, json!(count + 1));
+        }
+        Ok(Value::Object(langs))
+    }
+
+    async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        Ok(repo.files.iter()
+            .map(|(path, result)| {
+                let history = repo.history.get(path);
+                json!({
+                    "path": path,
+                    "language": format!("{:?}", result.language),
+                    "test_file": result.test_file,
+                    "parse_errors": result.parse_errors.len(),
+                    "parse_error_locations": result.parse_errors.iter()
+                        .map(|e| format!("{}:{} {} {}", e.line, e.column, e.kind, e.text))
+                        .collect::<Vec<_>>(),
+                    "cluster": null,
+                    "last_modified": history.map(|h| h.last_modified),
+                    "last_commit": history.map(|h| &h.last_commit),
+                    "commit_count": history.map(|h| h.commit_count),
+                    "contributors": history.map_or(vec![], |h| h.contributors.iter()
+                        .map(|(name, commits)| json!({ "name": name, "commits": commits }))
+                        .collect()),
+                    "metrics": result.metrics,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        let mut out = vec![];
+        for (path, result) in &repo.files {
+            for sym in &result.symbols {
+                let mut value = symbol_json(sym);
+                value["is_test"] = json!(sym.is_test);
+                value["is_async"] = json!(sym.is_async);
+                value["is_generator"] = json!(sym.is_generator);
+                value["is_unsafe"] = json!(sym.is_unsafe);
+                value["qualified_name"] = json!(sym.qualified_name.clone().unwrap_or_default());
+                value["doc_sections"] = json!(serde_json::to_string(&sym.doc_sections).unwrap_or_default());
+                value["examples"] = json!(serde_json::to_string(&sym.examples).unwrap_or_default());
+                value["cluster"] = Value::Null;
+                value["file"] = json!(path);
+                value["line_start"] = json!(sym.range.0);
+                value["line_end"] = json!(sym.range.1);
+                value["byte_start"] = json!(sym.byte_range.0);
+                value["byte_end"] = json!(sym.byte_range.1);
+                value["col_start"] = json!(sym.columns.0);
+                value["col_end"] = json!(sym.columns.1);
+                out.push(value);
+            }
+        }
+        Ok(out)
+    }
+
+    async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>> {
+        Ok(self.repo(repo_name).files.iter()
+            .map(|(path, result)| {
+                // Same short keys as the Neo4j structure query
+                let symbols: Vec<Value> = result.symbols.iter()
+                    .map(|s| {
+                        let v = symbol_json(s);
+                        json!({
+                            "name": v["name"], "kind": v["kind"], "sig": v["signature"], "doc": v["docstring"],
+                            "ret": v["return_type"], "vis": v["visibility"], "parent": v["parent_class"],
+                            "params": v["params"], "decos": v["decorators"], "members": v["members"],
+                            "fields": v["fields"], "raises": v["raises"], "type_params": v["type_params"],
+                            "variants": v["variants"], "tags": v["tags"], "owners": v["owners"],
+                        })
+                    })
+                    .collect();
+                json!({ "path": path, "language": format!("{:?}", result.language), "symbols": symbols })
+            })
+            .collect())
+    }
+
+    async fn get_docs(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        // Functions and classes by name, for the sections that mention them
+        let mut by_name: HashMap<&str, Vec<(&str, &Symbol)>> = HashMap::new();
+        for (path, result) in &repo.files {
+            for sym in result.symbols.iter().filter(|s| matches!(graph::label_for_kind(&s.kind), "Function" | "Class")) {
+                by_name.entry(sym.name.as_str()).or_default().push((path.as_str(), sym));
+            }
+        }
+        let mut out = vec![];
+        for (path, result) in &repo.files {
+            let Some(doc) = &result.document else { continue };
+            let sections: Vec<Value> = result.symbols.iter()
+                .filter(|s| s.kind == "section")
+                .map(|s| {
+                    let mut mentions: Vec<(&str, &Symbol)> = s.mentions.iter()
+                        .filter_map(|name| by_name.get(name.as_str()))
+                        .filter(|targets| targets.len() <= graph::MAX_MENTION_TARGETS)
+                        .flatten()
+                        .copied()
+                        .collect();
+                    mentions.sort_by_key(|(file, t)| (*file, t.range.0));
+                    json!({
+                        "name": s.name,
+                        "level": s.signature.as_deref().unwrap_or_default().chars().take_while(|c| *c == '#').count(),
+                        "parent": s.parent_class,
+                        "line": s.range.0,
+                        "summary": s.docstring,
+                        "mentions": mentions.iter()
+                            .map(|(file, t)| json!({ "name": t.name, "kind": t.kind, "file": file, "line": t.range.0 }))
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            out.push(json!({
+                "path": path,
+                "kind": doc.kind,
+                "title": doc.title,
+                "heading_count": doc.heading_count,
+                "word_count": doc.word_count,
+                "code_fences": doc.code_fences,
+                "sections": sections,
+            }));
+        }
+        Ok(out)
+    }
+
+    async fn get_todos(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let mut out = vec![];
+        for (path, result) in &self.repo(repo_name).files {
+            for todo in &result.todos {
+                let symbol = result.symbols.iter()
+                    .filter(|s| s.range.0 <= todo.line && todo.line <= s.range.1)
+                    .min_by_key(|s| s.range.1 - s.range.0)
+                    .map(|s| s.name.clone());
+                out.push(json!({ "file": path, "line": todo.line, "tag": todo.tag, "text": todo.text, "symbol": symbol }));
+            }
+        }
+        out.sort_by_key(|t| (t["file"].as_str().unwrap_or_default().to_string(), t["line"].as_i64()));
+        Ok(out)
+    }
+
+    async fn get_routes(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        let mut out = vec![];
+        for (path, result) in &repo.files {
+            for (sym, route) in result.symbols.iter().filter_map(|s| s.route.as_ref().map(|r| (s, r))) {
+                let handler = route.handler.clone().filter(|h| !h.is_empty());
+                let handler_file = handler.as_ref().and_then(|h| {
+                    let owner = sym.parent_class.as_deref().unwrap_or("");
+                    repo.files.iter().find(|(_, r)| r.symbols.iter().any(|s| {
+                        &s.name == h && matches!(graph::label_for_kind(&s.kind), "Function" | "Class")
+                            && (owner.is_empty() || s.parent_class.as_deref().is_some_and(|p| p == owner || p.ends_with(&format!("::{}", owner))))
+                    })).map(|(p, _)| p.clone())
+                });
+                out.push(json!({
+                    "file": path, "line": sym.range.0, "method": route.method, "path": route.path,
+                    "handler": handler, "handler_file": handler_file,
+                }));
+            }
+        }
+        out.sort_by_key(|r| (r["path"].as_str().unwrap_or_default().to_string(), r["method"].as_str().unwrap_or_default().to_string()));
+        Ok(out)
+    }
+
+    async fn get_commands(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        let commands: Vec<(&String, &Symbol)> = repo.files.iter()
+            .flat_map(|(path, r)| r.symbols.iter().filter(|s| s.command.is_some()).map(move |s| (path, s)))
+            .collect();
+        Ok(commands.iter()
+            .map(|(path, sym)| {
+                let command = sym.command.as_ref().expect("filtered on command");
+                let parent = command.parent.as_ref().and_then(|p| commands.iter()
+                    .find(|(_, c)| &c.name == p || c.command.as_ref().and_then(|c| c.binding.as_ref()) == Some(p))
+                    .map(|(_, c)| c.name.clone()));
+                json!({
+                    "file": path, "line": sym.range.0, "name": sym.name,
+                    "docstring": sym.docstring.clone().unwrap_or_default(),
+                    "options": serde_json::to_string(&command.options).unwrap_or_default(),
+                    "handler": command.handler.clone().filter(|h| !h.is_empty()),
+                    "parent": parent,
+                })
+            })
+            .collect())
+    }
+
+    async fn get_embedded(&self, repo_name: &str) -> Result<Vec<Value>> {
+        let mut out = vec![];
+        for (path, result) in &self.repo(repo_name).files {
+            for (sym, embedded) in result.symbols.iter().filter_map(|s| s.embedded.as_ref().map(|e| (s, e))) {
+                out.push(json!({
+                    "file": path, "line": sym.range.0, "name": sym.name, "language": embedded.language,
+                    "targets": embedded.targets, "container": embedded.container.clone().filter(|c| !c.is_empty()),
+                    "text": embedded.text,
+                }));
+            }
+        }
+        Ok(out)
+    }
+
+    async fn get_directories(&self, repo_name: &str) -> Result<Vec<Value>> {
+        // path -> (files, loc, symbols, subdirectories)
+        let mut dirs: BTreeMap<String, (i64, i64, i64, Vec<String>)> = BTreeMap::new();
+        for (path, result) in &self.repo(repo_name).files {
+            let parent = path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+            let mut prefix = String::new();
+            for part in parent.split('/').filter(|p| !p.is_empty()) {
+                let parent_dir = prefix.clone();
+                if !prefix.is_empty() { prefix.push('/'); }
+                prefix.push_str(part);
+                let entry = dirs.entry(prefix.clone()).or_default();
+                entry.0 += 1;
+                entry.1 += result.metrics.code_lines as i64;
+                entry.2 += result.metrics.symbol_count as i64;
+                if !parent_dir.is_empty() {
+                    let siblings = &mut dirs.entry(parent_dir).or_default().3;
+                    if !siblings.iter().any(|s| s == part) { siblings.push(part.to_string()); }
+                }
+            }
+        }
+        Ok(dirs.into_iter()
+            .map(|(path, (files, loc, symbols, subdirs))| json!({
+                "path": path, "file_count": files, "loc": loc, "symbol_count": symbols, "subdirectories": subdirs,
+            }))
+            .collect())
+    }
+
+    async fn call_chain(&self, repo_name: &str, symbol: &str, depth: usize, callers: bool) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        let calls = call_graph(repo_name, &repo);
+        let roots: Vec<&String> = calls.functions.iter()
+            .filter(|(id, (_, s))| *id == symbol || s.qualified_name.as_deref() == Some(symbol) || s.name == symbol)
+            .map(|(id, _)| id)
+            .collect();
+        let direction = if callers { Direction::Incoming } else { Direction::Outgoing };
+        let mut next: HashMap<String, Vec<String>> = HashMap::new();
+        for root in &roots {
+            let mut frontier = vec![calls.index[*root]];
+            let mut seen: HashSet<NodeIndex> = frontier.iter().copied().collect();
+            for _ in 0..depth {
+                let mut reached = vec![];
+                for node in frontier {
+                    for neighbour in calls.graph.neighbors_directed(node, direction) {
+                        let children = next.entry(calls.graph[node].clone()).or_default();
+                        if !children.contains(&calls.graph[neighbour]) {
+                            children.push(calls.graph[neighbour].clone());
+                        }
+                        if seen.insert(neighbour) { reached.push(neighbour); }
+                    }
+                }
+                frontier = reached;
+            }
+        }
+        let info: HashMap<String, Value> = calls.functions.iter()
+            .map(|(id, (path, sym))| (id.clone(), function_json(id, path, sym)))
+            .collect();
+        let mut budget = graph::MAX_CHAIN_NODES;
+        let mut roots: Vec<&String> = roots;
+        roots.sort();
+        Ok(roots.into_iter()
+            .map(|root| graph::chain_node(root, depth, &next, &info, &mut vec![], &mut budget))
+            .collect())
+    }
+
+    async fn get_hotspots(&self, repo_name: &str, limit: usize) -> Result<Vec<Value>> {
+        let repo = self.repo(repo_name);
+        let calls = call_graph(repo_name, &repo);
+        let edges: Vec<(String, String)> = calls.graph.edge_references()
+            .filter(|e| e.source() != e.target())
+            .map(|e| (calls.graph[e.source()].clone(), calls.graph[e.target()].clone()))
+            .collect();
+        let ranks = graph::pagerank(&edges);
+        let mut ranked: Vec<(&String, &(f64, usize, usize))> = ranks.iter().collect();
+        ranked.sort_by(|a, b| b.1.0.total_cmp(&a.1.0).then_with(|| b.1.1.cmp(&a.1.1)).then_with(|| a.0.cmp(b.0)));
+        ranked.truncate(limit);
+        Ok(ranked.into_iter()
+            .map(|(id, &(score, callers, callees))| {
+                let (path, sym) = calls.functions[id];
+                let mut node = function_json(id, path, sym);
+                node["pagerank"] = json!(score);
+                node["callers"] = json!(callers);
+                node["callees"] = json!(callees);
+                node
+            })
+            .collect())
+    }
+
+    async fn get_call_edges(&self, repo_name: &str) -> Result<Vec<(CallEnd, CallEnd)>> {
+        let repo = self.repo(repo_name);
+        let calls = call_graph(repo_name, &repo);
+        let end = |id: &String| {
+            let (path, sym) = calls.functions[id];
+            CallEnd {
+                id: id.clone(),
+                name: sym.name.clone(),
+                qualified_name: sym.qualified_name.clone().unwrap_or_default(),
+                file: path.to_string(),
+            }
+        };
+        Ok(calls.graph.edge_references()
+            .map(|e| (end(&calls.graph[e.source()]), end(&calls.graph[e.target()])))
+            .collect())
+    }
+
+    async fn get_dependencies(&self, repo_name: &str) -> Result<Value> {
+        Ok(graph::dependency_graph(resolved_imports(&self.repo(repo_name))))
+    }
+
+    async fn get_packages(&self, repo_name: &str) -> Result<Vec<Value>> {
+        Ok(graph::package_list(graph::package_uses(&resolved_imports(&self.repo(repo_name)))))
+    }
+
+    async fn get_declared_dependencies(&self, repo_name: &str) -> Result<Vec<Dependency>> {
+        let mut dependencies = self.repo(repo_name).dependencies.clone();
+        dependencies.sort_by(|a, b| (&a.manifest, &a.name).cmp(&(&b.manifest, &b.name)));
+        Ok(dependencies)
+    }
+
+    async fn get_submodules(&self, repo_name: &str) -> Result<Vec<Submodule>> {
+        let mut submodules = self.repo(repo_name).submodules.clone();
+        submodules.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(submodules)
+    }
+
+    async fn get_license(&self, repo_name: &str) -> Result<Option<License>> {
+        Ok(self.repo(repo_name).license.clone())
+    }
+
+    async fn get_entrypoints(&self, repo_name: &str) -> Result<Vec<Entrypoint>> {
+        let mut entrypoints = self.repo(repo_name).entrypoints.clone();
+        entrypoints.sort();
+        Ok(entrypoints)
+    }
+
+    async fn package_users(&self, package: &str) -> Result<Vec<Value>> {
+        let repos = self.repos.read().unwrap_or_else(|e| e.into_inner());
+        let mut users: Vec<(&String, Vec<String>)> = repos.iter()
+            .filter_map(|(name, repo)| graph::package_uses(&resolved_imports(repo)).remove(package).map(|files| (name, files)))
+            .collect();
+        users.sort();
+        Ok(users.into_iter().map(|(repo, files)| json!({ "repo": repo, "files": files })).collect())
+    }
+}
+
+// Every import in the repo as (file, module, names, files the module resolves to), like
+// `GraphClient::resolved_imports`
+fn resolved_imports(repo: &MemoryRepo) -> Vec<(String, String, Vec<String>, Vec<String>)> {
+    let paths: HashSet<String> = repo.files.keys().cloned().collect();
+    let mut imports = vec![];
+    for (path, result) in &repo.files {
+        for import in &result.imports {
+            let Some(source) = &import.source else { continue };
+            let module = graph::module_name(result.language, source);
+            // Rust files are attached to the module they define
+            let mut targets: Vec<String> = repo.files.iter()
+                .filter(|(_, r)| r.module_path.as_deref() == Some(module.as_str()))
+                .map(|(p, _)| p.clone())
+                .collect();
+            if targets.is_empty() {
+                let lang = format!("{:?}", result.language);
+                targets = graph::resolve_import(path, &lang, &module, &import.names, &paths);
+            }
+            imports.push((path.clone(), module, import.names.clone(), targets));
+        }
+    }
+    imports.sort();
+    imports
+}
+
+// Resolve every call in the repo against its functions with the Neo4j CALLS rules
+fn call_graph<'a>(repo_name: &str, repo: &'a MemoryRepo) -> CallGraph<'a> {
+    let mut calls = CallGraph { graph: DiGraph::new(), index: HashMap::new(), functions: HashMap::new() };
+    let mut by_name: HashMap<&str, Vec<(&str, &Symbol, NodeIndex)>> = HashMap::new();
+    for (path, result) in &repo.files {
+        for sym in result.symbols.iter().filter(|s| graph::label_for_kind(&s.kind) == "Function") {
+            let id = format!("{}::{}::{}:{}", repo_name, path, sym.name, sym.range.0);
+            let node = calls.graph.add_node(id.clone());
+            calls.index.insert(id.clone(), node);
+            calls.functions.insert(id, (path.as_str(), sym));
+            by_name.entry(sym.name.as_str()).or_default().push((path.as_str(), sym, node));
+        }
+    }
+    for (path, result) in &repo.files {
+        for sym in result.symbols.iter().filter(|s| graph::label_for_kind(&s.kind) == "Function") {
+            let caller = calls.index[&format!("{}::{}::{}:{}", repo_name, path, sym.name, sym.range.0)];
+            for callee in &sym.calls {
+                let target = graph::resolve_call(result, sym, callee);
+                let Some(candidates) = by_name.get(graph::call_name(callee)) else { continue };
+                for (callee_path, callee_sym, node) in candidates {
+                    if target.accepts(callee_sym, callee_path) {
+                        calls.graph.update_edge(caller, *node, ());
+                    }
+                }
+            }
+        }
+    }
+    calls
+}
+
+fn function_json(id: &str, path: &str, sym: &Symbol) -> Value {
+    json!({
+        "id": id,
+        "name": sym.name,
+        "qualified_name": sym.qualified_name.clone().filter(|q| !q.is_empty()),
+        "kind": sym.kind,
+        "parent_class": sym.parent_class.clone().filter(|p| !p.is_empty()),
+        "file": path,
+        "line": sym.range.0,
+    })
+}
+
+// Symbol properties encoded the way they are stored on Neo4j nodes
+fn symbol_json(sym: &Symbol) -> Value {
+    json!({
+        "name": sym.name,
+        "kind": sym.kind,
+        "docstring": sym.docstring.clone().unwrap_or_default(),
+        "signature": sym.signature.clone().unwrap_or_default(),
+        "return_type": sym.return_type.clone().unwrap_or_default(),
+        "visibility": sym.visibility.clone().unwrap_or_default(),
+        "parent_class": sym.parent_class.clone().unwrap_or_default(),
+        "params": serde_json::to_string(&sym.params).unwrap_or_default(),
+        "decorators": sym.decorators.join(", "),
+        "members": sym.members.join(", "),
+        "fields": serde_json::to_string(&sym.fields).unwrap_or_default(),
+        "raises": sym.raises.join(", "),
+        "type_params": serde_json::to_string(&sym.type_params).unwrap_or_default(),
+        "variants": serde_json::to_string(&sym.variants).unwrap_or_default(),
+        "tags": sym.tags,
+        "owners": sym.owners,
+    })
+}
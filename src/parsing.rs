@@ -1,17 +1,29 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
-use tree_sitter::{Parser, Query, QueryCursor, Node};
+use std::cell::RefCell;
+use lru::LruCache;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use tree_sitter::{InputEdit, Parser, Point, Query, QueryCursor, Node, Tree};
+use crate::scanners;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum Language {
-    Python, TypeScript, JavaScript, Rust, Go, Java, Cpp, Ruby, Php, Unknown,
+    Python, TypeScript, JavaScript, Rust, Go, Java, Cpp, Ruby, Php, Shell, Sql, Hcl, Protobuf, GraphQl, Svelte, Notebook, Markdown, ObjC, R, Julia, Erlang, Perl,
+    #[default]
+    Unknown,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Symbol {
     pub name: String,
     pub kind: String,
     pub range: (usize, usize),
+    // Exact extent in the source: byte offsets, and 0-based byte columns on the first/last line
+    pub byte_range: (usize, usize),
+    pub columns: (usize, usize),
     pub content_preview: String,
     pub docstring: Option<String>,
     pub signature: Option<String>,
@@ -22,34 +34,208 @@ pub struct Symbol {
     pub decorators: Vec<String>,
     pub calls: Vec<String>,
     pub bases: Vec<String>,
+    // Variant names for enums, method names for Go interfaces
+    pub members: Vec<String>,
+    // Data members of structs/classes/interfaces, props of React components
+    pub fields: Vec<Param>,
+    // Rust: `# Heading` sections of the doc comment and its fenced code examples
+    pub doc_sections: Vec<DocSection>,
+    pub examples: Vec<String>,
+    pub is_test: bool,
+    pub is_async: bool,
+    pub is_generator: bool,
+    pub is_unsafe: bool,
+    // Exception/error types raised, thrown or returned as Err(..)
+    pub raises: Vec<String>,
+    // Rust: crate::net::client::Client::connect, C++: foo::bar::Widget::draw
+    pub qualified_name: Option<String>,
+    // TypeScript generics: <T extends Foo = Bar>
+    pub type_params: Vec<TypeParam>,
+    // HTTP endpoint registered by a web framework (kind "route")
+    pub route: Option<Route>,
+    // CLI (sub)command defined with argparse/click/clap/cobra (kind "command")
+    pub command: Option<Command>,
+    // SQL/GraphQL/HTML held in a string literal or tagged template (kind "embedded")
+    pub embedded: Option<Embedded>,
+    // Every definition of a grouped property or overload set, this one included
+    pub variants: Vec<Variant>,
+    // Kinds from user-defined queries that matched this symbol, e.g. "celery_task"
+    pub tags: Vec<String>,
+    // Markdown sections: names the prose's `code spans` and fenced examples refer to
+    #[serde(default)]
+    pub mentions: Vec<String>,
+    // Functions and classes: the authors `git blame` credits with most of their lines, filled in
+    // while indexing a git work tree with `blame_owners` on
+    #[serde(default)]
+    pub owners: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variant {
+    // "getter", "setter", "deleter", "overload" or "implementation"
+    pub role: String,
+    pub signature: Option<String>,
+    pub params: Vec<Param>,
+    pub return_type: Option<String>,
+    pub range: (usize, usize),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embedded {
+    // "sql", "graphql" or "html"
+    pub language: String,
+    pub text: String,
+    // SQL: tables read or written; GraphQL: root fields selected
+    pub targets: Vec<String>,
+    // Innermost function or method containing the literal
+    pub container: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Command {
+    // Name or binding of the parent command; None for a root command
+    pub parent: Option<String>,
+    // Identifier the command is bound to: Go var, Python function/parser, Rust type
+    pub binding: Option<String>,
+    pub handler: Option<String>,
+    pub options: Vec<CliOption>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CliOption {
+    pub name: String,
+    pub short: Option<String>,
+    pub positional: bool,
+    pub type_annotation: Option<String>,
+    pub default: Option<String>,
+    pub help: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub method: String,
+    pub path: String,
+    // Name of the function serving the route; None for inline handlers
+    pub handler: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeParam {
+    pub name: String,
+    pub constraint: Option<String>,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocSection {
+    pub heading: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Param {
     pub name: String,
     pub type_annotation: Option<String>,
     pub default: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Import {
     pub raw: String,
     pub source: Option<String>,
     pub names: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ParsingResult {
     pub language: Language,
     pub symbols: Vec<Symbol>,
     pub imports: Vec<Import>,
     pub exports: Vec<String>,
+    pub document: Option<Document>,
+    pub metrics: FileMetrics,
+    pub todos: Vec<Todo>,
+    // Named or placed like a test file; test symbols elsewhere are marked on their own
+    pub test_file: bool,
+    pub implementations: Vec<Implementation>,
+    // Rust: the module this file defines (crate::net::client) and the `mod` items inside it
+    pub module_path: Option<String>,
+    pub modules: Vec<ModuleDecl>,
+    // Syntax errors tree-sitter recovered from; symbols inside them may be missing
+    pub parse_errors: Vec<ParseError>,
+    // Everything the file calls or instantiates, module-level code and field initializers included,
+    // so a class that is only ever constructed still counts as used
+    #[serde(default)]
+    pub references: Vec<String>,
+    // `content_hash` of the parsed text, so re-indexing can skip files that haven't changed
+    #[serde(default)]
+    pub content_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseError {
+    // "error" for unparseable text, "missing" for a token the parser had to insert
+    pub kind: String,
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModuleDecl {
+    pub path: String,
+    pub line: usize,
+    // `mod foo { ... }` rather than `mod foo;`
+    pub inline: bool,
+}
+
+// `impl Trait for Type` (or an inferred Go interface): becomes an IMPLEMENTS edge between the two Class nodes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Implementation {
+    pub type_name: String,
+    pub trait_name: String,
+    pub line: usize,
+}
+
+// TODO/FIXME/HACK annotation found in a comment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Todo {
+    pub tag: String,
+    pub text: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileMetrics {
+    pub lines: usize,
+    pub code_lines: usize,
+    pub comment_lines: usize,
+    pub blank_lines: usize,
+    // comment_lines / (code_lines + comment_lines)
+    pub comment_density: f64,
+    pub symbol_count: usize,
+}
+
+// Summary of a prose file (Markdown); its headings are emitted as "section" symbols
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Document {
+    // "readme", "changelog", "docs" (under a docs/ directory) or "other"
+    #[serde(default)]
+    pub kind: String,
+    pub title: Option<String>,
+    pub heading_count: usize,
+    pub word_count: usize,
+    pub code_fences: std::collections::BTreeMap<String, usize>,
 }
 
 pub fn detect_language(filename: &str) -> Language {
+    // README, CHANGELOG.txt and the like are prose whether or not they say so
+    if matches!(document_kind(filename), "readme" | "changelog") {
+        return Language::Markdown;
+    }
     match Path::new(filename).extension().and_then(|e| e.to_str()) {
         Some("py" | "pyw") => Language::Python,
-        Some("ts" | "tsx") => Language::TypeScript,
+        Some("ts" | "tsx" | "mts" | "cts") => Language::TypeScript,
         Some("js" | "jsx" | "mjs" | "cjs") => Language::JavaScript,
         Some("rs") => Language::Rust,
         Some("go") => Language::Go,
@@ -57,13 +243,33 @@ pub fn detect_language(filename: &str) -> Language {
         Some("cpp" | "cxx" | "hpp" | "h") => Language::Cpp,
         Some("rb") => Language::Ruby,
         Some("php") => Language::Php,
+        Some("sh" | "bash" | "zsh") => Language::Shell,
+        Some("sql") => Language::Sql,
+        Some("tf" | "tfvars" | "hcl") => Language::Hcl,
+        Some("proto") => Language::Protobuf,
+        Some("graphql" | "graphqls" | "gql") => Language::GraphQl,
+        Some("svelte") => Language::Svelte,
+        Some("ipynb") => Language::Notebook,
+        Some("md" | "mdx" | "markdown") => Language::Markdown,
+        Some("m" | "mm") => Language::ObjC,
+        Some("R" | "r") => Language::R,
+        Some("jl") => Language::Julia,
+        Some("erl" | "hrl") => Language::Erlang,
+        Some("pl" | "pm") => Language::Perl,
         _ => Language::Unknown,
     }
 }
 
-fn get_ts_language(lang: Language) -> tree_sitter::Language {
+// JSX only parses under the TSX grammar, which in turn rejects the `<T>expr` casts plain
+// TypeScript allows, so .tsx files get their own
+fn is_tsx(filename: &str) -> bool {
+    filename.ends_with(".tsx")
+}
+
+fn get_ts_language(lang: Language, tsx: bool) -> tree_sitter::Language {
     match lang {
         Language::Python => tree_sitter_python::language(),
+        Language::TypeScript if tsx => tree_sitter_typescript::language_tsx(),
         Language::TypeScript => tree_sitter_typescript::language_typescript(),
         Language::JavaScript => tree_sitter_javascript::language(),
         Language::Rust => tree_sitter_rust::language(),
@@ -72,20 +278,406 @@ fn get_ts_language(lang: Language) -> tree_sitter::Language {
         Language::Cpp => tree_sitter_cpp::language(),
         Language::Ruby => tree_sitter_ruby::language(),
         Language::Php => tree_sitter_php::language_php(),
-        Language::Unknown => unreachable!(),
+        Language::Shell => tree_sitter_bash::language(),
+        // Handled by the line-based scanners instead
+        Language::Sql | Language::Hcl | Language::Protobuf | Language::GraphQl | Language::Markdown
+        | Language::ObjC | Language::R | Language::Julia | Language::Erlang | Language::Perl => unreachable!(),
+        // Svelte script blocks and notebook code cells are parsed with the JS/TS and Python grammars
+        Language::Svelte | Language::Notebook | Language::Unknown => unreachable!(),
     }
 }
 
 pub fn parse_content(filename: &str, content: &str) -> ParsingResult {
+    let mut result = analyze_content(filename, content);
+    finish_result(&mut result, filename, content_hash(content));
+    result
+}
+
+// What the file's path decides rather than its content: test marking, the Rust module, the kind of
+// document
+fn finish_result(result: &mut ParsingResult, filename: &str, hash: String) {
+    mark_tests(result, filename);
+    resolve_rust_module(result, filename);
+    if let Some(document) = &mut result.document {
+        document.kind = document_kind(filename).to_string();
+    }
+    result.content_hash = hash;
+}
+
+// What a prose file is to the project, by its path. README and CHANGELOG files count with no
+// extension or a plain-text one too
+pub fn document_kind(filename: &str) -> &'static str {
+    let path = filename.replace('\\', "/");
+    let name = path.rsplit('/').next().unwrap_or(&path).to_ascii_uppercase();
+    let (stem, extension) = name.rsplit_once('.').unwrap_or((&name, ""));
+    if matches!(extension, "" | "MD" | "MDX" | "MARKDOWN" | "TXT" | "RST") {
+        if stem.starts_with("README") {
+            return "readme";
+        }
+        if stem.starts_with("CHANGELOG") || stem == "CHANGES" || stem == "HISTORY" {
+            return "changelog";
+        }
+    }
+    let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+    if dir.split('/').any(|dir| dir == "docs" || dir == "doc") {
+        "docs"
+    } else {
+        "other"
+    }
+}
+
+// Results of recent /parse calls, keyed by filename and content hash since the path decides part
+// of the analysis; least recently used entries go first. Indexing reads every file once and
+// doesn't go through it
+const RESULT_CACHE_SIZE: usize = 1024;
+static RESULT_CACHE: OnceLock<Mutex<LruCache<String, Arc<ParsingResult>>>> = OnceLock::new();
+
+fn result_cache() -> &'static Mutex<LruCache<String, Arc<ParsingResult>>> {
+    RESULT_CACHE.get_or_init(|| Mutex::new(LruCache::new(NonZeroUsize::new(RESULT_CACHE_SIZE).unwrap_or(NonZeroUsize::MIN))))
+}
+
+fn cache_key(filename: &str, hash: &str) -> String {
+    format!("{}:{}", filename, hash)
+}
+
+// A panic while the cache was held poisons it; it is emptied and used again rather than trusted
+// or left unusable for the rest of the process
+fn lock_cache<V>(cache: &Mutex<LruCache<String, V>>) -> MutexGuard<'_, LruCache<String, V>> {
+    cache.lock().unwrap_or_else(|poisoned| {
+        cache.clear_poison();
+        let mut guard = poisoned.into_inner();
+        guard.clear();
+        guard
+    })
+}
+
+fn cached_result(key: &str) -> Option<Arc<ParsingResult>> {
+    lock_cache(result_cache()).get(key).cloned()
+}
+
+fn store_result(key: String, result: &Arc<ParsingResult>) {
+    lock_cache(result_cache()).put(key, result.clone());
+}
+
+fn analyze_content(filename: &str, content: &str) -> ParsingResult {
     let language = detect_language(filename);
+    let mut result = parse_language(filename, language, content);
+    // Notebooks are measured on their code cells, not the JSON
+    if language != Language::Notebook {
+        result.metrics = measure(language, content);
+        result.todos = extract_todos(language, content);
+    }
+    result.metrics.symbol_count = result.symbols.len();
+    // Notebook positions refer to the concatenated code cells, as their line ranges do
+    if language != Language::Notebook {
+        fill_line_spans(&mut result, content);
+    }
+    result
+}
+
+fn parse_language(filename: &str, language: Language, content: &str) -> ParsingResult {
     if language == Language::Unknown {
-        return ParsingResult { language, symbols: vec![], imports: vec![], exports: vec![] };
+        return ParsingResult { language, ..Default::default() };
+    }
+    if let Some(result) = scanners::scan(language, content) {
+        return result;
+    }
+    if language == Language::Svelte {
+        return parse_svelte(filename, content);
+    }
+    if language == Language::Notebook {
+        return parse_notebook(content);
+    }
+    parse_tree(language, is_tsx(filename), content)
+}
+
+// Flag test functions/classes by per-language convention, and the file if it is named or placed like a test
+fn mark_tests(result: &mut ParsingResult, filename: &str) {
+    let lang = result.language;
+    let path = filename.replace('\\', "/").to_lowercase();
+    let base = path.rsplit('/').next().unwrap_or(&path).to_string();
+    for sym in result.symbols.iter_mut() {
+        let name = sym.name.as_str();
+        sym.is_test = sym.is_test || match lang {
+            Language::Python | Language::Notebook => match sym.kind.as_str() {
+                "class" => name.starts_with("Test"),
+                "function" | "method" => name.starts_with("test"),
+                _ => false,
+            },
+            Language::Rust => sym.decorators.iter().any(|d| {
+                let attr = d.trim_start_matches("#[").trim_end_matches(']');
+                let path = attr.split('(').next().unwrap_or(attr).trim();
+                matches!(path.rsplit("::").next(), Some("test" | "rstest"))
+            }),
+            Language::Go => base.ends_with("_test.go") && ["Test", "Benchmark", "Fuzz", "Example"].iter().any(|p| {
+                name.strip_prefix(p).is_some_and(|rest| !rest.starts_with(|c: char| c.is_lowercase()))
+            }),
+            Language::Java => sym.decorators.iter().any(|d| {
+                let ann = d.trim_start_matches('@');
+                let ann = ann.split('(').next().unwrap_or(ann);
+                matches!(ann.rsplit('.').next(), Some("Test" | "ParameterizedTest" | "RepeatedTest" | "TestFactory"))
+            }),
+            _ => false,
+        };
+    }
+    // Rust's inline test modules and Python's test helpers live in ordinary source files, so a test
+    // symbol alone doesn't make the file a test file
+    result.test_file = named_like_test(filename, lang);
+}
+
+// Whether the path alone says the file holds tests
+pub fn named_like_test(filename: &str, language: Language) -> bool {
+    let path = filename.replace('\\', "/").to_lowercase();
+    let base = path.rsplit('/').next().unwrap_or(&path);
+    path.contains("/tests/") || path.contains("/test/") || path.contains("/__tests__/")
+        || path.starts_with("tests/") || path.starts_with("test/")
+        || base.starts_with("test_") || base.contains("_test.") || base.contains(".test.") || base.contains(".spec.")
+        || (language == Language::Java && base.ends_with("test.java"))
+}
+
+const TODO_TAGS: &[&str] = &["TODO", "FIXME", "HACK"];
+
+fn extract_todos(lang: Language, content: &str) -> Vec<Todo> {
+    let (line_prefixes, blocks) = comment_syntax(lang);
+    let mut todos = vec![];
+    let mut block_end: Option<&str> = None;
+    for (idx, line) in content.lines().enumerate() {
+        // The part of the line that is comment text, if any
+        let comment = match block_end {
+            Some(end) => {
+                if line.contains(end) { block_end = None; }
+                line
+            }
+            None => {
+                let line_start = line_prefixes.iter().filter_map(|p| line.find(p)).min();
+                let block_start = blocks.iter().filter_map(|(open, close)| line.find(open).map(|i| (i, *open, *close))).min();
+                match (line_start, block_start) {
+                    (Some(l), Some((b, _, _))) if l < b => &line[l..],
+                    (_, Some((b, open, close))) => {
+                        if !line[b + open.len()..].contains(close) { block_end = Some(close); }
+                        &line[b..]
+                    }
+                    (Some(l), None) => &line[l..],
+                    (None, None) => continue,
+                }
+            }
+        };
+        for tag in TODO_TAGS {
+            let Some(pos) = comment.find(tag) else { continue };
+            let before = comment[..pos].chars().next_back();
+            let after = comment[pos + tag.len()..].chars().next();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_') || after.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            // TODO(owner): text / TODO: text / TODO - text
+            let mut rest = comment[pos + tag.len()..].trim_start();
+            if rest.starts_with('(') {
+                rest = rest.split_once(')').map(|(_, r)| r).unwrap_or(rest);
+            }
+            let text = rest.trim_start_matches([':', '-', ' ']).trim()
+                .trim_end_matches("*/").trim_end_matches("-->").trim_end_matches("=#").trim();
+            todos.push(Todo { tag: tag.to_string(), text: text.to_string(), line: idx + 1 });
+            break;
+        }
+    }
+    todos
+}
+
+// Line-comment prefixes and block-comment delimiters used for line metrics and TODO extraction
+fn comment_syntax(lang: Language) -> (&'static [&'static str], &'static [(&'static str, &'static str)]) {
+    match lang {
+        Language::TypeScript | Language::JavaScript | Language::Rust | Language::Go | Language::Java
+        | Language::Cpp | Language::ObjC | Language::Protobuf => (&["//"], &[("/*", "*/")]),
+        Language::Php => (&["//", "#"], &[("/*", "*/")]),
+        Language::Hcl => (&["#", "//"], &[("/*", "*/")]),
+        Language::Python | Language::Notebook | Language::Shell | Language::R | Language::GraphQl => (&["#"], &[]),
+        Language::Ruby => (&["#"], &[("=begin", "=end")]),
+        Language::Perl => (&["#"], &[("=pod", "=cut"), ("=head", "=cut")]),
+        Language::Julia => (&["#"], &[("#=", "=#")]),
+        Language::Sql => (&["--"], &[("/*", "*/")]),
+        Language::Erlang => (&["%"], &[]),
+        Language::Svelte => (&["//"], &[("<!--", "-->"), ("/*", "*/")]),
+        Language::Markdown => (&[], &[("<!--", "-->")]),
+        Language::Unknown => (&[], &[]),
+    }
+}
+
+// Classify each line as blank, comment-only or code. Lines mixing code and a trailing comment count as code.
+fn measure(lang: Language, content: &str) -> FileMetrics {
+    let (line_prefixes, blocks) = comment_syntax(lang);
+    let mut m = FileMetrics::default();
+    let mut block_end: Option<&str> = None;
+    for line in content.lines() {
+        m.lines += 1;
+        let t = line.trim();
+        if let Some(end) = block_end {
+            m.comment_lines += 1;
+            if t.contains(end) { block_end = None; }
+        } else if t.is_empty() {
+            m.blank_lines += 1;
+        } else if let Some((open, close)) = blocks.iter().find(|(open, _)| t.starts_with(open)) {
+            m.comment_lines += 1;
+            if !t[open.len()..].contains(close) { block_end = Some(close); }
+        } else if line_prefixes.iter().any(|p| t.starts_with(p)) {
+            m.comment_lines += 1;
+        } else {
+            m.code_lines += 1;
+        }
+    }
+    let counted = m.code_lines + m.comment_lines;
+    m.comment_density = if counted == 0 { 0.0 } else { m.comment_lines as f64 / counted as f64 };
+    m
+}
+
+fn parse_tree(language: Language, tsx: bool, content: &str) -> ParsingResult {
+    let tree = build_tree(language, tsx, content, None);
+    analyze_tree(&tree, language, content)
+}
+
+const LANGUAGE_COUNT: usize = Language::Unknown as usize + 1;
+
+// Parsers and queries are kept per grammar: one for each language, then TypeScript's TSX dialect
+const GRAMMAR_COUNT: usize = LANGUAGE_COUNT + 1;
+const TSX_GRAMMAR: usize = LANGUAGE_COUNT;
+
+fn grammar(language: Language, tsx: bool) -> usize {
+    if language == Language::TypeScript && tsx { TSX_GRAMMAR } else { language as usize }
+}
+
+// Grammar of a tree parsed as `lang`
+fn tree_grammar(root: Node, lang: Language) -> usize {
+    grammar(lang, lang == Language::TypeScript && *root.language() == tree_sitter_typescript::language_tsx())
+}
+
+thread_local! {
+    // One parser per grammar per thread, reused across files instead of rebuilt for each
+    static PARSERS: RefCell<Vec<Option<Parser>>> = RefCell::new((0..GRAMMAR_COUNT).map(|_| None).collect());
+}
+
+// Drop this thread's parsers after a panic, so none resumes the parse it cut short on the next file
+pub fn reset_parsers() {
+    PARSERS.with(|parsers| parsers.borrow_mut().iter_mut().for_each(|parser| *parser = None));
+}
+
+fn build_tree(language: Language, tsx: bool, content: &str, old_tree: Option<&Tree>) -> Tree {
+    PARSERS.with(|parsers| {
+        let mut parsers = parsers.borrow_mut();
+        let parser = parsers[grammar(language, tsx)].get_or_insert_with(|| {
+            let mut parser = Parser::new();
+            parser.set_language(&get_ts_language(language, tsx)).expect("lang load failed");
+            parser
+        });
+        parser.parse(content, old_tree).expect("parse failed")
+    })
+}
+
+// Compiled queries for one call site, indexed by grammar; each is built on first use
+type QueryCache = [OnceLock<Option<Query>>; GRAMMAR_COUNT];
+
+// `source` compiled for the grammar `root`'s tree was parsed with
+fn cached_query(cache: &'static QueryCache, lang: Language, root: Node, source: &str) -> Option<&'static Query> {
+    let grammar = tree_grammar(root, lang);
+    cache[grammar].get_or_init(|| Query::new(&get_ts_language(lang, grammar == TSX_GRAMMAR), source).ok()).as_ref()
+}
+
+const TREE_SITTER_LANGUAGES: [Language; 10] = [
+    Language::Python, Language::TypeScript, Language::JavaScript, Language::Rust, Language::Go,
+    Language::Java, Language::Cpp, Language::Ruby, Language::Php, Language::Shell,
+];
+
+// Load every grammar and compile its queries up front so the first requests don't pay for it
+pub fn preload_languages() {
+    for lang in TREE_SITTER_LANGUAGES {
+        parse_tree(lang, false, "");
+    }
+    parse_tree(Language::TypeScript, true, "");
+}
+
+// Operator-supplied queries, indexed by language; set once at startup
+static CUSTOM_QUERIES: OnceLock<Vec<Vec<Query>>> = OnceLock::new();
+
+// Compile `<dir>/<language>/*.scm` (python, typescript, cpp, ...). Returns the number of queries
+// loaded and a message for each file that could not be read or compiled
+pub fn load_custom_queries(dir: &Path) -> (usize, Vec<String>) {
+    let mut queries: Vec<Vec<Query>> = (0..GRAMMAR_COUNT).map(|_| vec![]).collect();
+    let mut errors = vec![];
+    for lang in TREE_SITTER_LANGUAGES {
+        let Ok(entries) = std::fs::read_dir(dir.join(format!("{:?}", lang).to_lowercase())) else { continue };
+        let mut paths: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "scm"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let src = match std::fs::read_to_string(&path) {
+                Ok(src) => src,
+                Err(e) => {
+                    errors.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            // TypeScript queries also run on .tsx files
+            let grammars: &[bool] = if lang == Language::TypeScript { &[false, true] } else { &[false] };
+            for &tsx in grammars {
+                match Query::new(&get_ts_language(lang, tsx), &src) {
+                    Ok(query) => queries[grammar(lang, tsx)].push(query),
+                    Err(e) => errors.push(format!("{}{}: {}", path.display(), if tsx { " (tsx)" } else { "" }, e)),
+                }
+            }
+        }
+    }
+    let loaded = queries[..LANGUAGE_COUNT].iter().map(Vec::len).sum();
+    CUSTOM_QUERIES.set(queries).ok();
+    (loaded, errors)
+}
+
+// Custom queries capture `@kind` on a definition and `@kind.name` on its name; captures starting
+// with `_` are only there for predicates. A match on an already extracted symbol tags it with the
+// kind, anything else becomes a new symbol of that kind
+fn apply_custom_queries(symbols: &mut Vec<Symbol>, root: Node, source: &str, lang: Language) {
+    let Some(queries) = CUSTOM_QUERIES.get().map(|q| &q[tree_grammar(root, lang)]) else { return };
+    for query in queries {
+        let capture_names = query.capture_names();
+        let mut cursor = QueryCursor::new();
+        for m in cursor.matches(query, root, source.as_bytes()) {
+            let mut definitions: Vec<(&str, Node)> = vec![];
+            let mut names: HashMap<&str, Node> = HashMap::new();
+            for capture in m.captures {
+                let capture_name = capture_names[capture.index as usize];
+                if capture_name.starts_with('_') { continue; }
+                match capture_name.strip_suffix(".name") {
+                    Some(kind) => { names.insert(kind, capture.node); }
+                    None => definitions.push((capture_name, capture.node)),
+                }
+            }
+            for (kind, node) in definitions {
+                let text = node.utf8_text(source.as_bytes()).unwrap_or("");
+                let first_line = text.lines().next().unwrap_or("").trim();
+                let name = names.get(kind).and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| first_line.chars().take(60).collect());
+                let existing = symbols.iter_mut().find(|s| s.name == name && s.byte_range.0 < node.end_byte() && node.start_byte() < s.byte_range.1);
+                match existing {
+                    Some(sym) => {
+                        if !sym.tags.iter().any(|t| t == kind) { sym.tags.push(kind.to_string()); }
+                    }
+                    None => symbols.push(Symbol {
+                        name,
+                        kind: kind.to_string(),
+                        range: (node.start_position().row + 1, node.end_position().row + 1),
+                        byte_range: byte_span(node, node),
+                        columns: column_span(node, node),
+                        content_preview: first_line.chars().take(120).collect(),
+                        signature: Some(first_line.to_string()),
+                        tags: vec![kind.to_string()],
+                        ..Default::default()
+                    }),
+                }
+            }
+        }
     }
+}
 
-    let mut parser = Parser::new();
-    let ts_lang = get_ts_language(language);
-    parser.set_language(&ts_lang).expect("lang load failed");
-    let tree = parser.parse(content, None).expect("parse failed");
+fn analyze_tree(tree: &Tree, language: Language, content: &str) -> ParsingResult {
     let root = tree.root_node();
 
     let symbols = extract_symbols(root, content, language);
@@ -101,136 +693,800 @@ pub fn parse_content(filename: &str, content: &str) -> ParsingResult {
         s
     }).collect();
 
-    ParsingResult { language, symbols, imports, exports }
+    let references = collect_calls_in_node(root, content);
+    let mut result = ParsingResult { language, symbols, imports, exports, references, ..Default::default() };
+    if root.has_error() {
+        collect_parse_errors(root, content, &mut result.parse_errors);
+    }
+    if language == Language::Rust {
+        result.implementations = extract_trait_impls(root, content);
+        qualify_rust_symbols(&mut result, root, content);
+    }
+    if language == Language::Cpp {
+        qualify_cpp_symbols(&mut result, root, content);
+    }
+    if language == Language::Php {
+        qualify_php_symbols(&mut result, root, content);
+    }
+    if language == Language::Ruby {
+        result.symbols.extend(rails_routes(root, content));
+    }
+    match language {
+        Language::Python => {
+            normalize_python_hints(&mut result.symbols);
+            result.symbols.extend(click_commands(root, content));
+            result.symbols.extend(argparse_commands(root, content));
+        }
+        Language::Rust => result.symbols.extend(clap_commands(root, content)),
+        Language::Go => result.symbols.extend(cobra_commands(root, content)),
+        _ => {}
+    }
+    let embedded = embedded_languages(root, content, &result.symbols);
+    result.symbols.extend(embedded);
+    apply_custom_queries(&mut result.symbols, root, content, language);
+    group_variants(&mut result.symbols, language);
+    result
 }
 
-fn extract_imports(root: Node, source: &str, lang: Language) -> Vec<Import> {
-    let query_str = match lang {
-        Language::Python => "(import_statement) @imp\n(import_from_statement) @imp",
-        Language::TypeScript | Language::JavaScript => "(import_statement) @imp",
-        Language::Rust => "(use_declaration) @imp",
-        Language::Go => "(import_declaration) @imp",
-        Language::Java => "(import_declaration) @imp",
-        Language::Cpp => "(preproc_include) @imp",
-        Language::Ruby => "(call method: (identifier) @method (#eq? @method \"require\")) @imp",
-        Language::Php => "(namespace_use_declaration) @imp",
-        Language::Unknown => return vec![],
-    };
-
-    let ts_lang = get_ts_language(lang);
-    let Ok(query) = Query::new(&ts_lang, query_str) else { return vec![] };
-    let mut cursor = QueryCursor::new();
-    cursor.matches(&query, root, source.as_bytes())
-        .filter_map(|m| {
-            m.captures.first().and_then(|c| {
-                let raw = c.node.utf8_text(source.as_bytes()).ok()?.to_string();
-                let (source_mod, names) = parse_import_details(&raw, lang);
-                Some(Import { raw, source: source_mod, names })
-            })
-        })
-        .collect()
+// Python @property/setter pairs and @overload stubs, TS/JS get/set accessors and Java/C++ overloads
+// collapse into one symbol that lists each definition as a variant
+fn group_variants(symbols: &mut Vec<Symbol>, lang: Language) {
+    let mut groups: HashMap<(Option<String>, Option<String>, String), Vec<usize>> = HashMap::new();
+    for (i, sym) in symbols.iter().enumerate() {
+        if matches!(sym.kind.as_str(), "function" | "method") {
+            groups.entry((sym.parent_class.clone(), sym.qualified_name.clone(), sym.name.clone())).or_default().push(i);
+        }
+    }
+    let mut merged = std::collections::HashSet::new();
+    for mut group in groups.into_values().filter(|g| g.len() > 1) {
+        group.sort_unstable();
+        let roles: Vec<&str> = group.iter().map(|&i| variant_role(&symbols[i], lang)).collect();
+        // Plain redefinitions (a def under if/else) stay separate
+        let grouped = match lang {
+            Language::Java | Language::Cpp => true,
+            Language::Python | Language::TypeScript | Language::JavaScript => roles.iter().any(|r| *r != "implementation"),
+            _ => false,
+        };
+        if !grouped { continue; }
+        let primary = roles.iter().position(|r| *r == "getter")
+            .or_else(|| roles.iter().position(|r| *r == "implementation"))
+            .map(|p| group[p])
+            .unwrap_or(group[0]);
+        let variants = group.iter().zip(&roles).map(|(&i, role)| Variant {
+            role: role.to_string(),
+            signature: symbols[i].signature.clone(),
+            params: symbols[i].params.clone(),
+            return_type: symbols[i].return_type.clone(),
+            range: symbols[i].range,
+        }).collect();
+        let docstring = symbols[primary].docstring.clone()
+            .or_else(|| group.iter().find_map(|&i| symbols[i].docstring.clone()));
+        symbols[primary].variants = variants;
+        symbols[primary].docstring = docstring;
+        merged.extend(group.into_iter().filter(|&i| i != primary));
+    }
+    let mut i = 0;
+    symbols.retain(|_| {
+        i += 1;
+        !merged.contains(&(i - 1))
+    });
 }
 
-fn parse_import_details(raw: &str, lang: Language) -> (Option<String>, Vec<String>) {
+fn variant_role(sym: &Symbol, lang: Language) -> &'static str {
     match lang {
         Language::Python => {
-            // "from foo.bar import baz, qux" or "import foo.bar"
-            if raw.starts_with("from ") {
-                let parts: Vec<&str> = raw.splitn(2, " import ").collect();
-                let source = parts.first().map(|s| s.trim_start_matches("from ").trim().to_string());
-                let names = parts.get(1).map(|s| s.split(',').map(|n| n.trim().to_string()).collect()).unwrap_or_default();
-                (source, names)
-            } else {
-                let name = raw.trim_start_matches("import ").trim().to_string();
-                (None, vec![name])
+            for deco in &sym.decorators {
+                let deco = deco.trim_start_matches('@');
+                let deco = deco.split('(').next().unwrap_or(deco);
+                if matches!(deco, "property" | "cached_property" | "functools.cached_property") { return "getter"; }
+                if deco.ends_with(".setter") { return "setter"; }
+                if deco.ends_with(".deleter") { return "deleter"; }
+                if matches!(deco, "overload" | "typing.overload") { return "overload"; }
             }
+            "implementation"
         }
         Language::TypeScript | Language::JavaScript => {
-            // "import { X, Y } from 'module'" or "import X from 'module'"
-            if let Some(from_idx) = raw.find(" from ") {
-                let source = raw[from_idx+6..].trim().trim_matches(|c| c == '\'' || c == '"' || c == ';').to_string();
-                let names_part = &raw[..from_idx];
-                let names: Vec<String> = names_part.replace("import", "").replace('{', "").replace('}', "")
-                    .split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
-                (Some(source), names)
-            } else {
-                (None, vec![raw.to_string()])
+            // Overload signatures have no body
+            if sym.range.0 == sym.range.1 && !sym.content_preview.contains('{') && !sym.content_preview.contains("=>") {
+                return "overload";
             }
+            let sig = sym.signature.as_deref().unwrap_or("");
+            let sig = sig.trim_start_matches("static ").trim_start_matches("public ").trim_start_matches("private ").trim_start_matches("protected ");
+            if sig.starts_with("get ") { "getter" } else if sig.starts_with("set ") { "setter" } else { "implementation" }
         }
-        _ => (None, vec![raw.to_string()]),
+        _ => "overload",
     }
 }
 
-fn extract_exports(root: Node, source: &str, lang: Language) -> Vec<String> {
-    let query_str = match lang {
-        Language::TypeScript | Language::JavaScript => "(export_statement) @exp",
-        Language::Rust => "(visibility_modifier) @exp",
-        Language::Go => return extract_go_exports(root, source),
-        _ => return vec![],
-    };
-    let ts_lang = get_ts_language(lang);
-    let Ok(query) = Query::new(&ts_lang, query_str) else { return vec![] };
-    let mut cursor = QueryCursor::new();
-    let mut exports = vec![];
-    for m in cursor.matches(&query, root, source.as_bytes()) {
-        if let Some(c) = m.captures.first() {
-            if let Ok(text) = c.node.utf8_text(source.as_bytes()) {
-                exports.push(text.to_string());
-            }
+// Outermost ERROR nodes and every MISSING token; subtrees without errors are skipped
+fn collect_parse_errors(node: Node, source: &str, out: &mut Vec<ParseError>) {
+    let kind = if node.is_missing() { "missing" } else if node.is_error() { "error" } else { "" };
+    if !kind.is_empty() {
+        let text = if node.is_missing() {
+            node.kind().to_string()
+        } else {
+            node.utf8_text(source.as_bytes()).unwrap_or("").lines().next().unwrap_or("").trim().chars().take(80).collect()
+        };
+        out.push(ParseError { kind: kind.to_string(), line: node.start_position().row + 1, column: node.start_position().column, text });
+        if node.is_error() { return; }
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.has_error() {
+            collect_parse_errors(child, source, out);
         }
     }
-    exports
 }
 
-fn extract_go_exports(root: Node, source: &str) -> Vec<String> {
-    // In Go, exported symbols start with uppercase
-    let mut exports = vec![];
+// PHP: resolve namespaces and `use` aliases so classes, bases and calls carry their
+// fully qualified names, e.g. App\Models\User and App\Models\User::find
+fn qualify_php_symbols(result: &mut ParsingResult, root: Node, source: &str) {
+    let mut namespaces: Vec<(String, usize, usize)> = vec![];
+    let mut aliases: HashMap<String, String> = HashMap::new();
     let mut walk = root.walk();
-    for node in root.children(&mut walk) {
-        if let Some(name_node) = node.child_by_field_name("name") {
-            if let Ok(name) = name_node.utf8_text(source.as_bytes()) {
-                if name.starts_with(|c: char| c.is_uppercase()) {
-                    exports.push(name.to_string());
+    for child in root.named_children(&mut walk) {
+        match child.kind() {
+            "namespace_definition" => {
+                let name = child.child_by_field_name("name")
+                    .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                    .unwrap_or("").to_string();
+                let start = child.start_position().row + 1;
+                match child.child_by_field_name("body") {
+                    Some(body) => {
+                        namespaces.push((name, start, child.end_position().row + 1));
+                        let mut body_walk = body.walk();
+                        for stmt in body.named_children(&mut body_walk) {
+                            if stmt.kind() == "namespace_use_declaration" {
+                                collect_php_aliases(stmt, source, &mut aliases);
+                            }
+                        }
+                    }
+                    // `namespace Foo;` applies until the next namespace statement
+                    None => {
+                        if let Some(last) = namespaces.last_mut() {
+                            if last.2 == usize::MAX { last.2 = start - 1; }
+                        }
+                        namespaces.push((name, start, usize::MAX));
+                    }
                 }
             }
+            "namespace_use_declaration" => collect_php_aliases(child, source, &mut aliases),
+            _ => {}
         }
     }
-    exports
-}
 
-fn extract_docstring(node: Node, source: &str, lang: Language) -> Option<String> {
-    match lang {
-        Language::Python => {
-            // Python: docstring is the first expression_statement > string in the function body
-            let body = node.child_by_field_name("body")?;
-            let first = body.named_child(0)?;
-            if first.kind() == "expression_statement" {
-                let inner = first.named_child(0)?;
-                if inner.kind() == "string" {
-                    return inner.utf8_text(source.as_bytes()).ok()
-                        .map(|s| s.trim_matches('"').trim_matches('\'').trim().to_string());
-                }
-            }
-            // Fallback: check preceding comment
-            let prev = node.prev_named_sibling()?;
-            if prev.kind() == "comment" {
-                return prev.utf8_text(source.as_bytes()).ok()
-                    .map(|s| s.trim_start_matches('#').trim().to_string());
+    let classes: HashMap<String, (String, Vec<String>)> = result.symbols.iter()
+        .filter(|s| s.kind == "class")
+        .map(|s| (s.name.clone(), (php_namespace_at(&namespaces, s.range.0).to_string(), s.bases.clone())))
+        .collect();
+    for sym in result.symbols.iter_mut() {
+        let ns = php_namespace_at(&namespaces, sym.range.0);
+        let class = sym.parent_class.clone().or_else(|| (sym.kind == "class").then(|| sym.name.clone()));
+        let class_fq = class.as_ref().map(|c| php_join(ns, c));
+        let parent_fq = class.as_ref()
+            .and_then(|c| classes.get(c))
+            .and_then(|(class_ns, bases)| bases.first().map(|b| resolve_php_name(b, class_ns, &aliases, None, None)));
+        sym.qualified_name = Some(match &sym.parent_class {
+            Some(owner) => format!("{}::{}", php_join(ns, owner), sym.name),
+            None => php_join(ns, &sym.name),
+        });
+        sym.bases = sym.bases.iter().map(|b| resolve_php_name(b, ns, &aliases, None, None)).collect();
+        sym.calls = sym.calls.iter().map(|call| match call.split_once("::") {
+            Some((scope, method)) => {
+                let scope = resolve_php_name(scope, ns, &aliases, class_fq.as_deref(), parent_fq.as_deref());
+                format!("{}::{}", scope, method)
             }
-            None
-        }
-        Language::JavaScript | Language::TypeScript | Language::Java | Language::Cpp | Language::Php => {
-            let prev = node.prev_named_sibling()?;
-            if prev.kind() == "comment" {
-                Some(prev.utf8_text(source.as_bytes()).ok()?
-                    .trim_start_matches("//").trim_start_matches("/*").trim_end_matches("*/").trim().to_string())
-            } else { None }
-        }
-        Language::Rust => {
-            // Collect consecutive doc comments above the node
-            let mut docs = vec![];
-            let mut sibling = node.prev_named_sibling();
-            while let Some(s) = sibling {
+            // Unqualified function calls fall back to the global function at runtime, so leave them bare
+            None if call.contains('\\') => resolve_php_name(call, ns, &aliases, None, None),
+            None => aliases.get(call).cloned().unwrap_or_else(|| call.clone()),
+        }).collect();
+    }
+}
+
+fn php_namespace_at(namespaces: &[(String, usize, usize)], line: usize) -> &str {
+    namespaces.iter()
+        .find(|(_, start, end)| *start <= line && line <= *end)
+        .map(|(name, _, _)| name.as_str())
+        .unwrap_or("")
+}
+
+fn php_join(ns: &str, name: &str) -> String {
+    if ns.is_empty() { name.to_string() } else { format!("{}\\{}", ns, name) }
+}
+
+fn resolve_php_name(name: &str, ns: &str, aliases: &HashMap<String, String>, class_fq: Option<&str>, parent_fq: Option<&str>) -> String {
+    if let Some(absolute) = name.strip_prefix('\\') {
+        return absolute.to_string();
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "self" | "static" => if let Some(c) = class_fq { return c.to_string() },
+        "parent" => if let Some(p) = parent_fq { return p.to_string() },
+        _ => {}
+    }
+    let (head, rest) = match name.split_once('\\') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (name, None),
+    };
+    match (aliases.get(head), rest) {
+        (Some(fq), Some(rest)) => format!("{}\\{}", fq, rest),
+        (Some(fq), None) => fq.clone(),
+        (None, _) if head.eq_ignore_ascii_case("namespace") => php_join(ns, rest.unwrap_or("")),
+        (None, _) => php_join(ns, name),
+    }
+}
+
+fn collect_php_aliases(decl: Node, source: &str, aliases: &mut HashMap<String, String>) {
+    if let Ok(raw) = decl.utf8_text(source.as_bytes()) {
+        aliases.extend(php_use_aliases(raw));
+    }
+}
+
+// "use A\B\{C, D as E};" -> [(C, A\B\C), (E, A\B\D)]
+fn php_use_aliases(raw: &str) -> Vec<(String, String)> {
+    let body = raw.trim().trim_start_matches("use").trim().trim_end_matches(';').trim();
+    let strip_kind = |s: &str| -> String {
+        let s = s.trim();
+        s.strip_prefix("function ").or_else(|| s.strip_prefix("const ")).unwrap_or(s).trim().to_string()
+    };
+    let body = strip_kind(body);
+    let items: Vec<String> = match body.split_once('{') {
+        Some((prefix, group)) => {
+            let prefix = prefix.trim().trim_end_matches('\\');
+            group.trim_end_matches('}').split(',')
+                .map(strip_kind)
+                .filter(|item| !item.is_empty())
+                .map(|item| format!("{}\\{}", prefix, item))
+                .collect()
+        }
+        None => body.split(',').map(strip_kind).filter(|item| !item.is_empty()).collect(),
+    };
+    items.into_iter().map(|item| {
+        let (path, alias) = match item.split_once(" as ") {
+            Some((path, alias)) => (path.trim().to_string(), alias.trim().to_string()),
+            None => (item.clone(), item.rsplit('\\').next().unwrap_or(&item).to_string()),
+        };
+        (alias, path.trim_start_matches('\\').to_string())
+    }).collect()
+}
+
+// C++: prefix symbols with their enclosing namespaces, e.g. foo::bar::Widget::draw
+fn qualify_cpp_symbols(result: &mut ParsingResult, root: Node, source: &str) {
+    let mut namespaces = vec![];
+    collect_cpp_namespaces(root, source, "", &mut namespaces);
+    for sym in result.symbols.iter_mut() {
+        let ns = namespaces.iter()
+            .filter(|(_, start, end)| *start <= sym.range.0 && sym.range.1 <= *end)
+            .max_by_key(|(path, _, _)| path.len())
+            .map(|(path, _, _)| path.as_str());
+        let parts: Vec<&str> = ns.into_iter().chain(sym.parent_class.as_deref()).chain([sym.name.as_str()]).collect();
+        sym.qualified_name = Some(parts.join("::"));
+    }
+}
+
+fn collect_cpp_namespaces(node: Node, source: &str, prefix: &str, out: &mut Vec<(String, usize, usize)>) {
+    let mut walk = node.walk();
+    for child in node.named_children(&mut walk) {
+        if child.kind() != "namespace_definition" {
+            // Namespaces only nest inside namespaces, linkage specs and preprocessor blocks
+            if matches!(child.kind(), "declaration_list" | "linkage_specification" | "preproc_if" | "preproc_ifdef" | "preproc_else") {
+                collect_cpp_namespaces(child, source, prefix, out);
+            }
+            continue;
+        }
+        // Anonymous namespaces don't add a qualifier
+        let name = child.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()).unwrap_or("");
+        let path = match (prefix.is_empty(), name.is_empty()) {
+            (_, true) => prefix.to_string(),
+            (true, false) => name.to_string(),
+            (false, false) => format!("{}::{}", prefix, name),
+        };
+        if !path.is_empty() {
+            out.push((path.clone(), child.start_position().row + 1, child.end_position().row + 1));
+        }
+        if let Some(body) = child.child_by_field_name("body") {
+            collect_cpp_namespaces(body, source, &path, out);
+        }
+    }
+}
+
+// Name in a C++ function definition's declarator chain (through pointer/reference declarators)
+fn cpp_declarator_name(node: Node, source: &str) -> Option<String> {
+    let mut decl = node.child_by_field_name("declarator");
+    while let Some(d) = decl {
+        if d.kind() == "function_declarator" {
+            return d.child_by_field_name("declarator")?.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+        }
+        decl = d.child_by_field_name("declarator");
+    }
+    None
+}
+
+// Module paths here are relative to the file; resolve_rust_module prefixes the file's own module later
+fn qualify_rust_symbols(result: &mut ParsingResult, root: Node, source: &str) {
+    let mut inline_ranges = vec![];
+    collect_rust_modules(root, source, "", &mut result.modules, &mut inline_ranges);
+    for sym in result.symbols.iter_mut() {
+        // Innermost inline module enclosing the symbol
+        let module = inline_ranges.iter()
+            .filter(|(_, start, end)| *start <= sym.range.0 && sym.range.1 <= *end)
+            .max_by_key(|(path, _, _)| path.len())
+            .map(|(path, _, _)| path.as_str());
+        let mut parts: Vec<String> = module.into_iter().map(|m| m.to_string()).collect();
+        if let Some(parent) = &sym.parent_class { parts.push(bare_type_name(parent)); }
+        parts.push(sym.name.clone());
+        sym.qualified_name = Some(parts.join("::"));
+    }
+}
+
+fn collect_rust_modules(node: Node, source: &str, prefix: &str, out: &mut Vec<ModuleDecl>, inline_ranges: &mut Vec<(String, usize, usize)>) {
+    let mut walk = node.walk();
+    for child in node.named_children(&mut walk) {
+        if child.kind() != "mod_item" {
+            if child.kind() == "declaration_list" || child.kind() == "source_file" {
+                collect_rust_modules(child, source, prefix, out, inline_ranges);
+            }
+            continue;
+        }
+        let Some(name) = child.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()) else { continue };
+        let path = if prefix.is_empty() { name.to_string() } else { format!("{}::{}", prefix, name) };
+        let body = child.child_by_field_name("body");
+        out.push(ModuleDecl { path: path.clone(), line: child.start_position().row + 1, inline: body.is_some() });
+        if let Some(body) = body {
+            inline_ranges.push((path.clone(), child.start_position().row + 1, child.end_position().row + 1));
+            collect_rust_modules(body, source, &path, out, inline_ranges);
+        }
+    }
+}
+
+// The module a Rust file defines, from its place under src/: lib.rs/main.rs are the crate root,
+// net/mod.rs and net.rs are crate::net. Files outside src/ (tests, examples, build.rs) are their own crate roots.
+fn rust_file_module(filename: &str) -> String {
+    let path = filename.replace('\\', "/");
+    let rel = match path.rfind("/src/") {
+        Some(i) => &path[i + 5..],
+        None => match path.strip_prefix("src/") {
+            Some(rel) => rel,
+            None => return "crate".to_string(),
+        },
+    };
+    if rel.starts_with("bin/") { return "crate".to_string(); }
+    let mut parts: Vec<&str> = rel.trim_end_matches(".rs").split('/').collect();
+    if matches!(parts.last(), Some(&"mod") | Some(&"lib") | Some(&"main")) { parts.pop(); }
+    std::iter::once("crate").chain(parts).collect::<Vec<_>>().join("::")
+}
+
+fn resolve_rust_module(result: &mut ParsingResult, filename: &str) {
+    if result.language != Language::Rust { return; }
+    let module = rust_file_module(filename);
+    for sym in result.symbols.iter_mut() {
+        if let Some(q) = &sym.qualified_name { sym.qualified_name = Some(format!("{}::{}", module, q)); }
+    }
+    for decl in result.modules.iter_mut() {
+        decl.path = format!("{}::{}", module, decl.path);
+    }
+    result.module_path = Some(module);
+}
+
+fn extract_trait_impls(root: Node, source: &str) -> Vec<Implementation> {
+    static QUERIES: QueryCache = [const { OnceLock::new() }; GRAMMAR_COUNT];
+    let Some(query) = cached_query(&QUERIES, Language::Rust, root, "(impl_item trait: (_) @trait type: (_) @type) @impl") else { return vec![] };
+    let mut cursor = QueryCursor::new();
+    let mut impls = vec![];
+    for m in cursor.matches(query, root, source.as_bytes()) {
+        let mut trait_name = None;
+        let mut type_name = None;
+        let mut line = 0;
+        for capture in m.captures {
+            let text = capture.node.utf8_text(source.as_bytes()).ok().map(bare_type_name);
+            match query.capture_names()[capture.index as usize] {
+                "trait" => trait_name = text,
+                "type" => type_name = text,
+                _ => line = capture.node.start_position().row + 1,
+            }
+        }
+        if let (Some(type_name), Some(trait_name)) = (type_name, trait_name) {
+            if !type_name.is_empty() && !trait_name.is_empty() {
+                impls.push(Implementation { type_name, trait_name, line });
+            }
+        }
+    }
+    impls
+}
+
+// `&'a mut crate::net::Client<T>` -> `Client`
+fn bare_type_name(ty: &str) -> String {
+    let ty = ty.trim_start_matches('&').trim_start();
+    let ty = if ty.starts_with('\'') { ty.split_once(' ').map(|(_, t)| t).unwrap_or(ty) } else { ty };
+    let ty = ty.trim_start_matches("mut ").trim_start_matches("dyn ");
+    let ty = ty.split('<').next().unwrap_or(ty);
+    ty.rsplit("::").next().unwrap_or(ty).trim().to_string()
+}
+
+// One edit from an editor, relative to the text as it was after the previous edit
+#[derive(Debug, Deserialize)]
+pub struct TextEdit {
+    pub start_byte: usize,
+    pub old_end_byte: usize,
+    pub text: String,
+}
+
+// Both are cheap to clone, so an entry is copied out and edited without holding the cache
+#[derive(Clone)]
+struct CachedTree {
+    content: Arc<str>,
+    tree: Tree,
+}
+
+// Trees from recent /parse calls keyed by filename and content hash, so follow-up edits to the same
+// file can reparse incrementally
+const TREE_CACHE_SIZE: usize = 256;
+static TREE_CACHE: OnceLock<Mutex<LruCache<String, CachedTree>>> = OnceLock::new();
+
+pub fn content_hash(content: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn point_at(text: &str, byte: usize) -> Point {
+    let before = &text[..byte];
+    let row = before.matches('\n').count();
+    let column = byte - before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    Point { row, column }
+}
+
+// Replay `edits` on the cached text and tree; None if they don't reproduce `content`
+fn apply_edits(cached: CachedTree, edits: &[TextEdit], content: &str) -> Option<Tree> {
+    let mut text = cached.content.to_string();
+    let mut tree = cached.tree;
+    for e in edits {
+        if e.start_byte > e.old_end_byte || e.old_end_byte > text.len()
+            || !text.is_char_boundary(e.start_byte) || !text.is_char_boundary(e.old_end_byte) {
+            return None;
+        }
+        let start_position = point_at(&text, e.start_byte);
+        let old_end_position = point_at(&text, e.old_end_byte);
+        text.replace_range(e.start_byte..e.old_end_byte, &e.text);
+        let new_end_byte = e.start_byte + e.text.len();
+        tree.edit(&InputEdit {
+            start_byte: e.start_byte,
+            old_end_byte: e.old_end_byte,
+            new_end_byte,
+            start_position,
+            old_end_position,
+            new_end_position: point_at(&text, new_end_byte),
+        });
+    }
+    if text == content { Some(tree) } else { None }
+}
+
+// Parse for /parse: reuses the tree cached under `previous_hash` when `edits` are given.
+// Returns the result, the new content hash, and whether the parse was incremental.
+pub fn parse_content_incremental(filename: &str, content: &str, previous_hash: Option<&str>, edits: &[TextEdit]) -> (Arc<ParsingResult>, String, bool) {
+    let hash = content_hash(content);
+    let key = cache_key(filename, &hash);
+    let language = detect_language(filename);
+    let cache = TREE_CACHE.get_or_init(|| Mutex::new(LruCache::new(NonZeroUsize::new(TREE_CACHE_SIZE).unwrap_or(NonZeroUsize::MIN))));
+    // Unchanged content needs neither parsing nor analysis; for tree-sitter languages only while
+    // its tree is still cached for later edits
+    let tree_cached = || lock_cache(cache).contains(&key);
+    if edits.is_empty() && (!uses_tree_sitter(language) || tree_cached()) {
+        if let Some(result) = cached_result(&key) {
+            return (result, hash, false);
+        }
+    }
+    if !uses_tree_sitter(language) {
+        let result = Arc::new(parse_content(filename, content));
+        store_result(key, &result);
+        return (result, hash, false);
+    }
+    let tsx = is_tsx(filename);
+
+    let old_tree = previous_hash.filter(|_| !edits.is_empty()).and_then(|prev| {
+        let cached = lock_cache(cache).get(&cache_key(filename, prev)).cloned()?;
+        apply_edits(cached, edits, content)
+    });
+    let incremental = old_tree.is_some();
+    let tree = build_tree(language, tsx, content, old_tree.as_ref());
+    let mut result = analyze_tree(&tree, language, content);
+    result.metrics = measure(language, content);
+    result.metrics.symbol_count = result.symbols.len();
+    result.todos = extract_todos(language, content);
+    finish_result(&mut result, filename, hash.clone());
+    let result = Arc::new(result);
+    lock_cache(cache).put(key.clone(), CachedTree { content: content.into(), tree });
+    store_result(key, &result);
+    (result, hash, incremental)
+}
+
+fn uses_tree_sitter(lang: Language) -> bool {
+    TREE_SITTER_LANGUAGES.contains(&lang)
+}
+
+fn parse_svelte(filename: &str, content: &str) -> ParsingResult {
+    // Blank out everything outside <script> blocks so byte offsets and line numbers stay intact
+    let mut blocks = vec![];
+    let mut script_lang = Language::JavaScript;
+    let mut pos = 0;
+    while let Some(open) = content[pos..].find("<script").map(|i| pos + i) {
+        let Some(tag_end) = content[open..].find('>').map(|i| open + i + 1) else { break };
+        let tag = &content[open..tag_end];
+        if tag.contains("lang=\"ts\"") || tag.contains("lang='ts'") || tag.contains("typescript") {
+            script_lang = Language::TypeScript;
+        }
+        let close = content[tag_end..].find("</script>").map(|i| tag_end + i).unwrap_or(content.len());
+        blocks.push((tag_end, close));
+        pos = close;
+    }
+    let masked: String = content.char_indices()
+        .flat_map(|(i, c)| {
+            let keep = c == '\n' || blocks.iter().any(|&(s, e)| i >= s && i < e);
+            let n = if keep { 1 } else { c.len_utf8() };
+            std::iter::repeat_n(if keep { c } else { ' ' }, n)
+        })
+        .collect();
+
+    let mut result = parse_tree(script_lang, false, &masked);
+    result.language = Language::Svelte;
+
+    // The file itself is the component; `export let` (Svelte 4) and `$props()` (Svelte 5) are its props
+    let mut props = vec![];
+    for &(s, e) in &blocks {
+        for line in content[s..e].lines().map(str::trim) {
+            if let Some(decl) = line.strip_prefix("export let ") {
+                let decl = decl.trim_end_matches(';');
+                let (lhs, default) = match decl.split_once('=') {
+                    Some((l, d)) => (l, Some(d.trim().to_string())),
+                    None => (decl, None),
+                };
+                let (name, ty) = match lhs.split_once(':') {
+                    Some((n, t)) => (n.trim(), Some(t.trim().to_string())),
+                    None => (lhs.trim(), None),
+                };
+                props.push(Param { name: name.to_string(), type_annotation: ty, default });
+            } else if line.starts_with("let {") && line.contains("$props()") {
+                let inner = &line[line.find('{').unwrap_or(0) + 1..line.find('}').unwrap_or(line.len())];
+                for field in inner.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+                    let (name, default) = match field.split_once('=') {
+                        Some((n, d)) => (n.trim(), Some(d.trim().to_string())),
+                        None => (field, None),
+                    };
+                    props.push(Param { name: name.to_string(), type_annotation: None, default });
+                }
+            }
+        }
+    }
+    let name = Path::new(filename).file_stem().and_then(|s| s.to_str()).unwrap_or("Component").to_string();
+    let docstring = content.find("<!-- @component").map(|i| {
+        let rest = &content[i + 15..];
+        rest[..rest.find("-->").unwrap_or(rest.len())].trim().to_string()
+    });
+    result.symbols.insert(0, Symbol {
+        name: name.clone(),
+        kind: "component".to_string(),
+        range: (1, content.lines().count().max(1)),
+        content_preview: format!("<{} />", name),
+        docstring,
+        signature: Some(format!("<{} {}/>", name, props.iter().map(|p| format!("{} ", p.name)).collect::<String>())),
+        params: props,
+        visibility: Some("export".to_string()),
+        ..Default::default()
+    });
+    result
+}
+
+fn parse_notebook(content: &str) -> ParsingResult {
+    let empty = ParsingResult { language: Language::Notebook, ..Default::default() };
+    let Ok(nb) = serde_json::from_str::<serde_json::Value>(content) else { return empty };
+    let Some(cells) = nb.get("cells").and_then(|c| c.as_array()) else { return empty };
+
+    // Concatenate code cells into one Python module, remembering where each starts and the
+    // markdown that preceded it
+    let mut code = String::new();
+    let mut cell_starts: Vec<(usize, Option<String>)> = vec![];
+    let mut markdown: Option<String> = None;
+    for cell in cells {
+        let source = match cell.get("source") {
+            Some(serde_json::Value::Array(parts)) => parts.iter().filter_map(|p| p.as_str()).collect::<String>(),
+            Some(serde_json::Value::String(s)) => s.clone(),
+            _ => continue,
+        };
+        match cell.get("cell_type").and_then(|t| t.as_str()) {
+            Some("markdown") => {
+                let text = source.trim();
+                if !text.is_empty() { markdown = Some(text.to_string()); }
+            }
+            Some("code") => {
+                cell_starts.push((code.lines().count() + 1, markdown.take()));
+                for line in source.lines() {
+                    // IPython magics and shell escapes aren't Python; keep the line count stable
+                    let t = line.trim_start();
+                    if t.starts_with('%') || t.starts_with('!') {
+                        code.push_str(&format!("# {}", t));
+                    } else {
+                        code.push_str(line);
+                    }
+                    code.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut result = parse_tree(Language::Python, false, &code);
+    result.language = Language::Notebook;
+    result.metrics = measure(Language::Notebook, &code);
+    result.todos = extract_todos(Language::Notebook, &code);
+    for sym in result.symbols.iter_mut().filter(|s| s.parent_class.is_none() && s.docstring.is_none()) {
+        let cell = cell_starts.iter().rev().find(|(start, _)| *start <= sym.range.0);
+        if let Some((_, Some(md))) = cell {
+            sym.docstring = Some(md.clone());
+        }
+    }
+    result
+}
+
+fn extract_imports(root: Node, source: &str, lang: Language) -> Vec<Import> {
+    let query_str = match lang {
+        Language::Python => "(import_statement) @imp\n(import_from_statement) @imp",
+        Language::TypeScript | Language::JavaScript => "(import_statement) @imp",
+        Language::Rust => "(use_declaration) @imp",
+        Language::Go => "(import_declaration) @imp",
+        Language::Java => "(import_declaration) @imp",
+        Language::Cpp => "(preproc_include) @imp",
+        Language::Ruby => "(call method: (identifier) @method (#eq? @method \"require\")) @imp",
+        Language::Php => "(namespace_use_declaration) @imp",
+        Language::Shell => "(command name: (command_name) @cmd (#match? @cmd \"^(source|\\.)$\")) @imp",
+        _ => return vec![],
+    };
+
+    static QUERIES: QueryCache = [const { OnceLock::new() }; GRAMMAR_COUNT];
+    let Some(query) = cached_query(&QUERIES, lang, root, query_str) else { return vec![] };
+    let mut cursor = QueryCursor::new();
+    cursor.matches(query, root, source.as_bytes())
+        .filter_map(|m| {
+            m.captures.iter().find(|c| query.capture_names()[c.index as usize] == "imp").and_then(|c| {
+                let raw = c.node.utf8_text(source.as_bytes()).ok()?.to_string();
+                let (source_mod, names) = parse_import_details(&raw, lang);
+                Some(Import { raw, source: source_mod, names })
+            })
+        })
+        .collect()
+}
+
+fn parse_import_details(raw: &str, lang: Language) -> (Option<String>, Vec<String>) {
+    match lang {
+        Language::Python => {
+            // "from foo.bar import baz, qux" or "import foo.bar"
+            if raw.starts_with("from ") {
+                let parts: Vec<&str> = raw.splitn(2, " import ").collect();
+                let source = parts.first().map(|s| s.trim_start_matches("from ").trim().to_string());
+                let names = parts.get(1).map(|s| s.split(',').map(|n| n.trim().to_string()).collect()).unwrap_or_default();
+                (source, names)
+            } else {
+                let name = raw.trim_start_matches("import ").trim().to_string();
+                (None, vec![name])
+            }
+        }
+        Language::TypeScript | Language::JavaScript => {
+            // "import { X, Y } from 'module'" or "import X from 'module'"
+            if let Some(from_idx) = raw.find(" from ") {
+                let source = raw[from_idx+6..].trim().trim_matches(|c| c == '\'' || c == '"' || c == ';').to_string();
+                let names_part = &raw[..from_idx];
+                let names: Vec<String> = names_part.replace("import", "").replace(['{', '}'], "")
+                    .split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+                (Some(source), names)
+            } else {
+                (None, vec![raw.to_string()])
+            }
+        }
+        Language::Php => {
+            // "use App\Models\{User, Post as Article};"
+            let pairs = php_use_aliases(raw);
+            let source = pairs.first().map(|(_, fq)| fq.clone());
+            (source, pairs.into_iter().map(|(alias, _)| alias).collect())
+        }
+        Language::Shell => {
+            // "source ./lib/common.sh" or ". ~/.bashrc"
+            let source = raw.split_whitespace().nth(1)
+                .map(|s| s.trim_matches(|c| c == '\'' || c == '"').to_string());
+            (source.clone(), source.into_iter().collect())
+        }
+        _ => (None, vec![raw.to_string()]),
+    }
+}
+
+fn extract_exports(root: Node, source: &str, lang: Language) -> Vec<String> {
+    let query_str = match lang {
+        Language::TypeScript | Language::JavaScript => "(export_statement) @exp",
+        Language::Rust => "(visibility_modifier) @exp",
+        Language::Go => return extract_go_exports(root, source),
+        _ => return vec![],
+    };
+    static QUERIES: QueryCache = [const { OnceLock::new() }; GRAMMAR_COUNT];
+    let Some(query) = cached_query(&QUERIES, lang, root, query_str) else { return vec![] };
+    let mut cursor = QueryCursor::new();
+    let mut exports = vec![];
+    for m in cursor.matches(query, root, source.as_bytes()) {
+        if let Some(c) = m.captures.first() {
+            if let Ok(text) = c.node.utf8_text(source.as_bytes()) {
+                exports.push(text.to_string());
+            }
+        }
+    }
+    exports
+}
+
+fn extract_go_exports(root: Node, source: &str) -> Vec<String> {
+    // In Go, exported symbols start with uppercase
+    let mut exports = vec![];
+    let mut walk = root.walk();
+    for node in root.children(&mut walk) {
+        if node.kind() == "type_declaration" {
+            let mut walk2 = node.walk();
+            for spec in node.named_children(&mut walk2).filter(|n| n.kind() == "type_spec") {
+                if let Some(name) = spec.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()) {
+                    if name.starts_with(|c: char| c.is_uppercase()) { exports.push(name.to_string()); }
+                }
+            }
+        }
+        if node.kind() == "const_declaration" || node.kind() == "var_declaration" {
+            for spec in go_value_specs(node) {
+                let mut walk2 = spec.walk();
+                for name_node in spec.children_by_field_name("name", &mut walk2).filter(|n| n.kind() == "identifier") {
+                    if let Ok(name) = name_node.utf8_text(source.as_bytes()) {
+                        if name.starts_with(|c: char| c.is_uppercase()) { exports.push(name.to_string()); }
+                    }
+                }
+            }
+        }
+        if let Some(name_node) = node.child_by_field_name("name") {
+            if let Ok(name) = name_node.utf8_text(source.as_bytes()) {
+                if name.starts_with(|c: char| c.is_uppercase()) {
+                    exports.push(name.to_string());
+                }
+            }
+        }
+    }
+    exports
+}
+
+fn extract_docstring(node: Node, source: &str, lang: Language) -> Option<String> {
+    match lang {
+        Language::Python => {
+            // Python: docstring is the first expression_statement > string in the function body
+            let body = node.child_by_field_name("body")?;
+            let first = body.named_child(0)?;
+            if first.kind() == "expression_statement" {
+                let inner = first.named_child(0)?;
+                if inner.kind() == "string" {
+                    return inner.utf8_text(source.as_bytes()).ok()
+                        .map(|s| s.trim_matches('"').trim_matches('\'').trim().to_string());
+                }
+            }
+            // Fallback: check preceding comment
+            let prev = node.prev_named_sibling()?;
+            if prev.kind() == "comment" {
+                return prev.utf8_text(source.as_bytes()).ok()
+                    .map(|s| s.trim_start_matches('#').trim().to_string());
+            }
+            None
+        }
+        Language::JavaScript | Language::TypeScript | Language::Java | Language::Cpp | Language::Php => {
+            let prev = node.prev_named_sibling()?;
+            if prev.kind() == "comment" {
+                Some(prev.utf8_text(source.as_bytes()).ok()?
+                    .trim_start_matches("//").trim_start_matches("/*").trim_end_matches("*/").trim().to_string())
+            } else { None }
+        }
+        Language::Rust => {
+            // Collect consecutive doc comments above the node
+            let mut docs = vec![];
+            let mut sibling = node.prev_named_sibling();
+            while sibling.is_some_and(|s| s.kind() == "attribute_item") {
+                sibling = sibling.and_then(|s| s.prev_named_sibling());
+            }
+            while let Some(s) = sibling {
                 if s.kind() == "line_comment" || s.kind() == "block_comment" {
                     if let Ok(text) = s.utf8_text(source.as_bytes()) {
                         docs.push(text.trim_start_matches("///").trim_start_matches("//!").trim_start_matches("//").trim().to_string());
@@ -250,6 +1506,22 @@ fn extract_docstring(node: Node, source: &str, lang: Language) -> Option<String>
                     .trim_start_matches("//").trim().to_string())
             } else { None }
         }
+        Language::Shell => {
+            // Collect the comment block directly above the function, skipping shebangs
+            let mut docs = vec![];
+            let mut sibling = node.prev_named_sibling();
+            while let Some(s) = sibling {
+                if s.kind() != "comment" { break; }
+                if let Ok(text) = s.utf8_text(source.as_bytes()) {
+                    if !text.starts_with("#!") {
+                        docs.push(text.trim_start_matches('#').trim().to_string());
+                    }
+                }
+                sibling = s.prev_named_sibling();
+            }
+            docs.reverse();
+            if docs.is_empty() { None } else { Some(docs.join("\n")) }
+        }
         _ => None,
     }
 }
@@ -269,7 +1541,8 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
     for child in node.children(&mut cursor) {
         match (lang, child.kind()) {
             // Python
-            (Language::Python, "function_definition") | (Language::Python, "decorated_definition") => {
+            (Language::Python, "function_definition") | (Language::Python, "decorated_definition")
+                if child.child_by_field_name("definition").is_none_or(|d| d.kind() != "class_definition") => {
                 let (def_node, decorators) = if child.kind() == "decorated_definition" {
                     let decos = extract_decorators(child, source);
                     (child.child_by_field_name("definition").unwrap_or(child), decos)
@@ -277,15 +1550,44 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                     (child, vec![])
                 };
                 if let Some(sym) = build_symbol(def_node, source, lang, if parent.is_some() { "method" } else { "function" }, parent, decorators) {
+                    // @app.get("/users/{id}") / @bp.route("/x", methods=["POST"])
+                    let routes = if child.kind() == "decorated_definition" { python_routes(child, &sym, source) } else { vec![] };
                     out.push(sym);
+                    out.extend(routes);
                 }
             }
-            (Language::Python, "class_definition") => {
-                let name = child.child_by_field_name("name")
-                    .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-                    .unwrap_or("").to_string();
+            (Language::Python, "expression_statement") if parent.is_none() && node.kind() == "module" => {
+                // Module-level assignment: NAME = value / NAME: type = value
+                if let Some(extend) = child.named_child(0).filter(|a| a.kind() == "augmented_assignment") {
+                    // urlpatterns += [...]
+                    if extend.child_by_field_name("left").is_some_and(|l| l.utf8_text(source.as_bytes()) == Ok("urlpatterns")) {
+                        out.extend(django_routes(extend, source));
+                    }
+                    continue;
+                }
+                let Some(assign) = child.named_child(0).filter(|a| a.kind() == "assignment") else { continue };
+                let Some(name_node) = assign.child_by_field_name("left").filter(|n| n.kind() == "identifier") else { continue };
+                let name = name_node.utf8_text(source.as_bytes()).unwrap_or("");
+                let vis = if name.starts_with('_') { "private" } else { "public" };
+                let routes = if name == "urlpatterns" { django_routes(assign, source) } else { vec![] };
+                out.push(build_constant(assign, child, name_node, assign.child_by_field_name("type"), source, lang, Some(vis.to_string())));
+                out.extend(routes);
+            }
+            (Language::Python, "class_definition" | "decorated_definition") => {
+                // Decorated classes (e.g. @dataclass) keep their decorators
+                let (child, decorators) = match child.child_by_field_name("definition") {
+                    Some(def) => (def, extract_decorators(child, source)),
+                    None => (child, vec![]),
+                };
+                let name = child.child_by_field_name("name")
+                    .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                    .unwrap_or("").to_string();
                 let bases = extract_bases(child, source, lang);
-                if let Some(mut sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
+                if let Some(mut sym) = build_symbol(child, source, lang, "class", parent, decorators) {
+                    if bases.iter().any(|b| is_python_enum_base(b)) {
+                        sym.members = python_enum_members(child, source);
+                        sym.fields.clear();
+                    }
                     sym.bases = bases;
                     out.push(sym);
                 }
@@ -295,8 +1597,9 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
             }
 
             // TypeScript / JavaScript
-            (Language::TypeScript | Language::JavaScript, "function_declaration") => {
-                if let Some(sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+            (Language::TypeScript | Language::JavaScript, "function_declaration" | "generator_function_declaration") => {
+                if let Some(mut sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                    tag_react(&mut sym, child, None, source, lang);
                     out.push(sym);
                 }
             }
@@ -313,31 +1616,84 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                     collect_symbols(child, source, lang, Some(&name), out, depth + 1);
                 }
             }
+            // Overload signatures, folded into the implementation by group_variants
+            (Language::TypeScript, "function_signature") => {
+                if let Some(mut sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                    sym.signature = sym.signature.map(|s| s.trim_end_matches(';').to_string());
+                    out.push(sym);
+                }
+            }
+            (Language::TypeScript, "method_signature") if node.kind() == "class_body" => {
+                out.extend(build_symbol(child, source, lang, "method", parent, vec![]));
+            }
             (Language::TypeScript | Language::JavaScript, "method_definition") => {
                 if let Some(sym) = build_symbol(child, source, lang, "method", parent, vec![]) {
                     out.push(sym);
                 }
             }
             (Language::TypeScript | Language::JavaScript, "lexical_declaration" | "variable_declaration") => {
-                // const foo = () => {} or const foo = function() {}
+                // const foo = () => {} or const foo = function() {}; other module-level consts are constants
+                let stmt = child.parent().filter(|p| p.kind() == "export_statement").unwrap_or(child);
+                let top_level = parent.is_none() && stmt.parent().is_some_and(|p| p.kind() == "program");
+                let is_const = child.child(0).is_some_and(|k| k.kind() == "const");
                 let mut walk2 = child.walk();
                 for decl in child.children(&mut walk2) {
                     if decl.kind() == "variable_declarator" {
-                        if let Some(value) = decl.child_by_field_name("value") {
-                            if value.kind() == "arrow_function" || value.kind() == "function_expression" {
-                                if let Some(sym) = build_symbol(decl, source, lang, "function", parent, vec![]) {
-                                    out.push(sym);
-                                }
+                        let value = decl.child_by_field_name("value");
+                        if let Some(func) = value.filter(|v| matches!(v.kind(), "arrow_function" | "function_expression" | "function" | "generator_function")) {
+                            if let Some(mut sym) = build_symbol(decl, source, lang, "function", parent, vec![]) {
+                                tag_react(&mut sym, func, decl.child_by_field_name("type"), source, lang);
+                                out.push(sym);
                             }
+                        } else if top_level && is_const {
+                            let Some(name_node) = decl.child_by_field_name("name").filter(|n| n.kind() == "identifier") else { continue };
+                            let vis = (stmt.kind() == "export_statement").then(|| "export".to_string());
+                            out.push(build_constant(decl, stmt, name_node, decl.child_by_field_name("type"), source, lang, vis));
                         }
                     }
                 }
             }
+            (Language::TypeScript | Language::JavaScript, "expression_statement") if js_test_block(child, source).is_some() => {
+                // describe("suite", () => { it("case", ...) })
+                let Some((func, title, callback)) = js_test_block(child, source) else { continue };
+                let text = child.utf8_text(source.as_bytes()).unwrap_or("");
+                let first_line = text.lines().next().unwrap_or("");
+                out.push(Symbol {
+                    name: title.clone(),
+                    kind: if func == "describe" { "test_suite" } else { "test" }.to_string(),
+                    range: (child.start_position().row + 1, child.end_position().row + 1),
+                    byte_range: byte_span(child, child),
+                    columns: column_span(child, child),
+                    content_preview: first_line.chars().take(120).collect(),
+                    signature: Some(first_line.trim_end_matches('{').trim().to_string()),
+                    parent_class: parent.map(|s| s.to_string()),
+                    is_test: true,
+                    ..Default::default()
+                });
+                if func == "describe" {
+                    if let Some(cb) = callback {
+                        collect_symbols(cb, source, lang, Some(&title), out, depth + 1);
+                    }
+                }
+            }
+            (Language::TypeScript | Language::JavaScript, "expression_statement") => {
+                // app.get('/users/:id', auth, getUser) in Express, Fastify, Koa router
+                let routes = js_routes(child, source, parent);
+                if routes.is_empty() {
+                    collect_symbols(child, source, lang, parent, out, depth + 1);
+                }
+                out.extend(routes);
+            }
             (Language::TypeScript, "interface_declaration") => {
                 if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
                     out.push(sym);
                 }
             }
+            (Language::TypeScript, "enum_declaration") => {
+                if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
+                    out.push(sym);
+                }
+            }
             (Language::TypeScript, "type_alias_declaration") => {
                 if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
                     out.push(sym);
@@ -346,7 +1702,26 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
 
             // Rust
             (Language::Rust, "function_item") => {
-                if let Some(sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                if let Some(mut sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                    // #[proc_macro], #[proc_macro_derive(..)], #[proc_macro_attribute]
+                    if sym.decorators.iter().any(|d| d.starts_with("#[proc_macro")) {
+                        sym.kind = "macro".to_string();
+                    }
+                    out.push(sym);
+                }
+            }
+            (Language::Rust, "macro_definition") => {
+                if let Some(mut sym) = build_symbol(child, source, lang, "macro", parent, vec![]) {
+                    // One matcher per rule: macro_rules! name { ($x:expr) => ...; (...) => ... }
+                    let mut walk2 = child.walk();
+                    let matchers: Vec<&str> = child.named_children(&mut walk2)
+                        .filter(|r| r.kind() == "macro_rule")
+                        .filter_map(|r| r.child_by_field_name("left")?.utf8_text(source.as_bytes()).ok())
+                        .collect();
+                    sym.signature = Some(format!("macro_rules! {} {{ {} }}", sym.name, matchers.join("; ")));
+                    if sym.decorators.iter().any(|d| d == "#[macro_export]") {
+                        sym.visibility = Some("pub".to_string());
+                    }
                     out.push(sym);
                 }
             }
@@ -355,6 +1730,12 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                     out.push(sym);
                 }
             }
+            (Language::Rust, "const_item" | "static_item") if parent.is_none() => {
+                if let Some(name_node) = child.child_by_field_name("name") {
+                    let vis = extract_visibility(child, source, lang);
+                    out.push(build_constant(child, child, name_node, child.child_by_field_name("type"), source, lang, vis));
+                }
+            }
             (Language::Rust, "impl_item") => {
                 let type_name = child.child_by_field_name("type")
                     .and_then(|n| n.utf8_text(source.as_bytes()).ok())
@@ -371,28 +1752,70 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                 }
             }
             (Language::Go, "method_declaration") => {
-                if let Some(sym) = build_symbol(child, source, lang, "method", parent, vec![]) {
+                // The receiver type is the method's owner: func (s *Server) Start()
+                let receiver = child.child_by_field_name("receiver")
+                    .and_then(|r| r.named_child(0))
+                    .and_then(|p| p.child_by_field_name("type"))
+                    .and_then(|t| t.utf8_text(source.as_bytes()).ok())
+                    .map(|t| t.trim_start_matches('*').split('[').next().unwrap_or(t).to_string());
+                if let Some(sym) = build_symbol(child, source, lang, "method", receiver.as_deref().or(parent), vec![]) {
                     out.push(sym);
                 }
             }
             (Language::Go, "type_declaration") => {
-                if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
-                    out.push(sym);
+                // `type X struct{}` or a `type ( ... )` group; the names live on the type_spec nodes
+                let mut walk2 = child.walk();
+                let specs: Vec<Node> = child.named_children(&mut walk2).filter(|n| n.kind() == "type_spec").collect();
+                for &spec in &specs {
+                    if let Some(mut sym) = build_symbol(spec, source, lang, "class", parent, vec![]) {
+                        if let Some(iface) = spec.child_by_field_name("type").filter(|t| t.kind() == "interface_type") {
+                            sym.kind = "interface".to_string();
+                            (sym.members, sym.bases) = go_interface_elements(iface, source);
+                        }
+                        if specs.len() == 1 {
+                            sym.range = (child.start_position().row + 1, child.end_position().row + 1);
+                            sym.byte_range = byte_span(child, child);
+                            sym.columns = column_span(child, child);
+                            sym.docstring = extract_docstring(child, source, lang);
+                        }
+                        out.push(sym);
+                    }
+                }
+            }
+
+            (Language::Go, "const_declaration" | "var_declaration") => {
+                let specs = go_value_specs(child);
+                for &spec in &specs {
+                    // Specs inside a ( ... ) block carry their own range and comments
+                    let owner = if specs.len() > 1 { spec } else { child };
+                    let mut walk2 = spec.walk();
+                    for name_node in spec.children_by_field_name("name", &mut walk2).filter(|n| n.kind() == "identifier") {
+                        out.push(build_constant(spec, owner, name_node, spec.child_by_field_name("type"), source, lang, None));
+                    }
                 }
             }
 
             // Java
-            (Language::Java, "class_declaration" | "interface_declaration") => {
+            (Language::Java, "class_declaration" | "interface_declaration" | "enum_declaration") => {
                 let name = child.child_by_field_name("name")
                     .and_then(|n| n.utf8_text(source.as_bytes()).ok())
                     .unwrap_or("").to_string();
                 let bases = extract_bases(child, source, lang);
+                let mut prefix = String::new();
                 if let Some(mut sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
                     sym.bases = bases;
+                    // Class-level @RequestMapping("/api") / @Path("/api") prefixes every method route
+                    prefix = sym.decorators.iter().filter_map(|a| java_mapping(a)).find_map(|(_, paths)| paths.into_iter().next()).unwrap_or_default();
                     out.push(sym);
                 }
                 if !name.is_empty() {
+                    let start = out.len();
                     collect_symbols(child, source, lang, Some(&name), out, depth + 1);
+                    let routes: Vec<Symbol> = out[start..].iter()
+                        .filter(|m| m.kind == "method" && m.parent_class.as_deref() == Some(name.as_str()))
+                        .flat_map(|m| java_routes(m, &prefix))
+                        .collect();
+                    out.extend(routes);
                 }
             }
             (Language::Java, "method_declaration" | "constructor_declaration") => {
@@ -403,10 +1826,36 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
 
             // C++
             (Language::Cpp, "function_definition") => {
-                if let Some(sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                if let Some(mut sym) = build_symbol(child, source, lang, if parent.is_some() { "method" } else { "function" }, parent, vec![]) {
+                    // Out-of-line definitions: void Widget::draw() -> draw owned by Widget
+                    if let Some(full) = cpp_declarator_name(child, source) {
+                        match full.rsplit_once("::") {
+                            Some((owner, name)) if !owner.is_empty() => {
+                                sym.name = name.to_string();
+                                sym.kind = "method".to_string();
+                                if sym.parent_class.is_none() { sym.parent_class = Some(owner.to_string()); }
+                            }
+                            _ => sym.name = full,
+                        }
+                    }
+                    if let Some(body) = child.child_by_field_name("body") {
+                        sym.calls = collect_calls_in_node(body, source);
+                    }
                     out.push(sym);
                 }
             }
+            (Language::Cpp, "template_declaration") => {
+                // template <typename T> ...: the templated definition keeps its parameter list in the signature
+                let first = out.len();
+                collect_symbols(child, source, lang, parent, out, depth + 1);
+                let params = child.child_by_field_name("parameters").and_then(|p| p.utf8_text(source.as_bytes()).ok());
+                if let (Some(sym), Some(params)) = (out.get_mut(first), params) {
+                    sym.signature = Some(format!("template {} {}", params, sym.signature.as_deref().unwrap_or("")).trim().to_string());
+                    sym.range.0 = child.start_position().row + 1;
+                    sym.byte_range.0 = child.start_byte();
+                    sym.columns.0 = child.start_position().column;
+                }
+            }
             (Language::Cpp, "class_specifier") => {
                 let name = child.child_by_field_name("name")
                     .and_then(|n| n.utf8_text(source.as_bytes()).ok())
@@ -429,11 +1878,19 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                 let name = child.child_by_field_name("name")
                     .and_then(|n| n.utf8_text(source.as_bytes()).ok())
                     .unwrap_or("").to_string();
-                if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
+                if let Some(mut sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
+                    sym.bases = extract_bases(child, source, lang);
                     out.push(sym);
                 }
                 if !name.is_empty() {
+                    let start = out.len();
                     collect_symbols(child, source, lang, Some(&name), out, depth + 1);
+                    // Bare `private`/`protected`/`public` apply to every def after them
+                    let modifiers = ruby_visibility_modifiers(child, source);
+                    for sym in out[start..].iter_mut().filter(|s| s.parent_class.as_deref() == Some(name.as_str())) {
+                        let vis = modifiers.iter().rfind(|(line, _)| *line < sym.range.0).map(|(_, v)| v.as_str()).unwrap_or("public");
+                        sym.visibility = Some(vis.to_string());
+                    }
                 }
             }
 
@@ -443,177 +1900,1859 @@ fn collect_symbols(node: Node, source: &str, lang: Language, parent: Option<&str
                     out.push(sym);
                 }
             }
-            (Language::Php, "method_declaration") => {
-                if let Some(sym) = build_symbol(child, source, lang, "method", parent, vec![]) {
-                    out.push(sym);
-                }
+            (Language::Php, "method_declaration") => {
+                if let Some(sym) = build_symbol(child, source, lang, "method", parent, vec![]) {
+                    out.push(sym);
+                }
+            }
+            (Language::Php, "class_declaration") => {
+                let name = child.child_by_field_name("name")
+                    .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                    .unwrap_or("").to_string();
+                if let Some(mut sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
+                    sym.bases = extract_bases(child, source, lang);
+                    out.push(sym);
+                }
+                if !name.is_empty() {
+                    collect_symbols(child, source, lang, Some(&name), out, depth + 1);
+                }
+            }
+
+            // Shell
+            (Language::Shell, "function_definition") => {
+                if let Some(sym) = build_symbol(child, source, lang, "function", parent, vec![]) {
+                    out.push(sym);
+                }
+            }
+
+            _ => {
+                // Recurse into other nodes to find nested definitions
+                collect_symbols(child, source, lang, parent, out, depth + 1);
+            }
+        }
+    }
+}
+
+fn build_symbol(node: Node, source: &str, lang: Language, kind: &str, parent: Option<&str>, decorators: Vec<String>) -> Option<Symbol> {
+    let name_node = node.child_by_field_name("name")
+        .or_else(|| node.child_by_field_name("declarator")); // C++ function_declarator
+    let name = match name_node {
+        Some(n) => {
+            // For C++ nested declarators
+            let actual = n.child_by_field_name("declarator").unwrap_or(n);
+            actual.utf8_text(source.as_bytes()).ok()?.to_string()
+        }
+        None => return None,
+    };
+    if name.is_empty() { return None; }
+
+    let range = (node.start_position().row + 1, node.end_position().row + 1);
+
+    // Full signature: everything up to the body
+    let sig = extract_full_signature(node, source, lang);
+
+    let docstring = extract_docstring(node, source, lang);
+    // `const f = <T>(x: T): R => ..` keeps its parameters and types on the function value
+    let func = node.child_by_field_name("value")
+        .filter(|v| node.kind() == "variable_declarator" && matches!(v.kind(), "arrow_function" | "function_expression" | "function" | "generator_function"))
+        .unwrap_or(node);
+    let params = extract_params(func, source, lang);
+    let return_type = extract_return_type(func, source, lang);
+    let visibility = extract_visibility(node, source, lang);
+
+    let preview = {
+        let start = node.start_byte();
+        let mut end = std::cmp::min(start + 120, node.end_byte());
+        while end < source.len() && !source.is_char_boundary(end) { end += 1; }
+        source[start..end].lines().next().unwrap_or("").to_string()
+    };
+
+    // Java annotations live in the modifiers
+    let decorators = if lang == Language::Java && decorators.is_empty() { extract_java_annotations(node, source) } else { decorators };
+
+    let mut sym = Symbol {
+        name,
+        kind: kind.to_string(),
+        range,
+        byte_range: byte_span(node, node),
+        columns: column_span(node, node),
+        content_preview: preview,
+        docstring,
+        signature: sig,
+        params,
+        return_type,
+        visibility,
+        parent_class: parent.map(|s| s.to_string()),
+        decorators,
+        calls: vec![],
+        bases: vec![],
+        members: extract_enum_members(node, source, lang),
+        fields: extract_fields(node, source, lang),
+        type_params: extract_type_params(func, source, lang),
+        ..Default::default()
+    };
+    (sym.is_async, sym.is_generator, sym.is_unsafe) = function_flags(node, lang);
+    if kind == "function" || kind == "method" { sym.raises = extract_raises(node, source, lang); }
+    if lang == Language::Rust { apply_rustdoc(&mut sym, node, source); }
+    Some(sym)
+}
+
+// React: PascalCase functions that render JSX are components, use* functions are hooks.
+// `annotation` is the declarator type for `const Card: React.FC<CardProps> = ...`
+fn tag_react(sym: &mut Symbol, func: Node, annotation: Option<Node>, source: &str, lang: Language) {
+    if sym.parent_class.is_some() { return; }
+    if sym.name.strip_prefix("use").is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase())) {
+        sym.kind = "hook".to_string();
+        return;
+    }
+    if !sym.name.starts_with(|c: char| c.is_ascii_uppercase()) || !contains_jsx(func) { return; }
+    sym.kind = "component".to_string();
+
+    let text = |n: Node| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+    let first = func.child_by_field_name("parameters").and_then(|p| p.named_child(0));
+    let pattern = first.map(|p| p.child_by_field_name("pattern").unwrap_or(p)).filter(|p| p.kind() == "object_pattern");
+    let mut props: Vec<Param> = vec![];
+    if let Some(pattern) = pattern {
+        // function Card({ title, size = "md", ...rest }: CardProps)
+        let mut walk = pattern.walk();
+        for prop in pattern.named_children(&mut walk) {
+            let (name, default) = match prop.kind() {
+                "shorthand_property_identifier_pattern" => (text(prop), None),
+                "object_assignment_pattern" => (prop.child_by_field_name("left").and_then(text), prop.child_by_field_name("right").and_then(text)),
+                "pair_pattern" => (prop.child_by_field_name("key").and_then(text), None),
+                _ => (None, None),
+            };
+            if let Some(name) = name { props.push(Param { name, type_annotation: None, default }); }
+        }
+    }
+
+    // Types come from the props interface/type alias when it's declared in the same file
+    let props_type = first.and_then(|p| p.child_by_field_name("type")).and_then(text)
+        .map(|t| t.trim_start_matches(':').trim().to_string())
+        .or_else(|| annotation.and_then(text).and_then(|t| {
+            let inner = t.split_once('<')?.1.rsplit_once('>')?.0;
+            Some(inner.trim().to_string())
+        }));
+    let declared = props_type.map(|t| t.split('<').next().unwrap_or("").trim().to_string())
+        .and_then(|t| find_ts_type(func, &t, source))
+        .map(|decl| extract_fields(decl, source, lang))
+        .unwrap_or_default();
+    if props.is_empty() {
+        props = declared;
+    } else {
+        for prop in props.iter_mut() {
+            prop.type_annotation = declared.iter().find(|d| d.name == prop.name).and_then(|d| d.type_annotation.clone());
+        }
+    }
+    sym.fields = props;
+}
+
+fn contains_jsx(node: Node) -> bool {
+    let mut stack = vec![node];
+    while let Some(n) = stack.pop() {
+        if matches!(n.kind(), "jsx_element" | "jsx_self_closing_element" | "jsx_fragment") { return true; }
+        let mut walk = n.walk();
+        stack.extend(n.named_children(&mut walk));
+    }
+    false
+}
+
+// Top-level `interface Name` / `type Name = {..}` in the file containing `node`
+fn find_ts_type<'a>(node: Node<'a>, name: &str, source: &str) -> Option<Node<'a>> {
+    let mut root = node;
+    while let Some(p) = root.parent() { root = p; }
+    let mut walk = root.walk();
+    let found = root.named_children(&mut walk)
+        .map(|c| if c.kind() == "export_statement" { c.child_by_field_name("declaration").unwrap_or(c) } else { c })
+        .filter(|c| matches!(c.kind(), "interface_declaration" | "type_alias_declaration"))
+        .find(|c| c.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()) == Some(name));
+    found
+}
+
+fn byte_span(start: Node, end: Node) -> (usize, usize) {
+    (start.start_byte(), end.end_byte())
+}
+
+fn column_span(start: Node, end: Node) -> (usize, usize) {
+    (start.start_position().column, end.end_position().column)
+}
+
+// Line-based scanners only know line ranges: span whole lines
+fn fill_line_spans(result: &mut ParsingResult, content: &str) {
+    let mut line_starts = vec![0];
+    line_starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
+    let line_end = |line: usize| line_starts.get(line).map(|&next| next.saturating_sub(1)).unwrap_or(content.len());
+    for sym in result.symbols.iter_mut().filter(|s| s.byte_range == (0, 0) && s.range.0 > 0) {
+        let start = line_starts.get(sym.range.0 - 1).copied().unwrap_or(content.len());
+        let end = line_end(sym.range.1.max(sym.range.0)).max(start);
+        let last_start = line_starts.get(sym.range.1.max(sym.range.0) - 1).copied().unwrap_or(start);
+        sym.byte_range = (start, end);
+        sym.columns = (0, end.saturating_sub(last_start));
+    }
+}
+
+// Module-level constant/global. `decl` is the declaration itself, `stmt` the statement that owns its comments.
+fn build_constant(decl: Node, stmt: Node, name_node: Node, type_node: Option<Node>, source: &str, lang: Language, visibility: Option<String>) -> Symbol {
+    let text = decl.utf8_text(source.as_bytes()).unwrap_or("");
+    let first_line = text.lines().next().unwrap_or("").trim();
+    let mut preview_end = first_line.len().min(120);
+    while !first_line.is_char_boundary(preview_end) { preview_end -= 1; }
+    let mut sym = Symbol {
+        name: name_node.utf8_text(source.as_bytes()).unwrap_or("").to_string(),
+        kind: "constant".to_string(),
+        range: (stmt.start_position().row + 1, stmt.end_position().row + 1),
+        byte_range: byte_span(stmt, stmt),
+        columns: column_span(stmt, stmt),
+        content_preview: first_line[..preview_end].to_string(),
+        docstring: constant_docstring(stmt, source, lang),
+        signature: Some(first_line.trim_end_matches(['{', '[', '(', '=']).trim().to_string()),
+        return_type: type_node
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+            .map(|s| s.trim_start_matches(':').trim().to_string()),
+        visibility,
+        ..Default::default()
+    };
+    if lang == Language::Rust { apply_rustdoc(&mut sym, stmt, source); }
+    sym
+}
+
+fn constant_docstring(stmt: Node, source: &str, lang: Language) -> Option<String> {
+    if lang != Language::Python { return extract_docstring(stmt, source, lang); }
+    // Attribute docstring: a bare string right after the assignment, else a comment above it
+    let next = stmt.next_named_sibling().filter(|n| n.kind() == "expression_statement")
+        .and_then(|n| n.named_child(0)).filter(|n| n.kind() == "string");
+    if let Some(doc) = next {
+        return doc.utf8_text(source.as_bytes()).ok()
+            .map(|s| s.trim_matches('"').trim_matches('\'').trim().to_string());
+    }
+    let prev = stmt.prev_named_sibling().filter(|p| p.kind() == "comment" && p.end_position().row + 1 == stmt.start_position().row)?;
+    prev.utf8_text(source.as_bytes()).ok().map(|s| s.trim_start_matches('#').trim().to_string())
+}
+
+// (method names, embedded interfaces) declared by a Go interface type
+fn go_interface_elements(iface: Node, source: &str) -> (Vec<String>, Vec<String>) {
+    let mut methods = vec![];
+    let mut embedded = vec![];
+    let mut walk = iface.walk();
+    for elem in iface.named_children(&mut walk) {
+        match elem.kind() {
+            "method_spec" | "method_elem" => {
+                if let Some(name) = elem.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()) {
+                    methods.push(name.to_string());
+                }
+            }
+            // Embedded interface; type-set constraints like `~int | ~string` are not interfaces
+            "constraint_elem" | "type_elem" => {
+                if let Ok(text) = elem.utf8_text(source.as_bytes()) {
+                    if !text.contains(['|', '~']) { embedded.push(text.trim().to_string()); }
+                }
+            }
+            _ => {}
+        }
+    }
+    (methods, embedded)
+}
+
+// Go satisfies interfaces implicitly: a type implements an interface when its methods (declared
+// anywhere in the repo) cover the interface's method set, including methods of embedded interfaces.
+// What `infer_go_implementations` needs of a Go file: its interfaces, methods and types. None for
+// other languages
+pub fn go_outline(result: &ParsingResult) -> Option<ParsingResult> {
+    if result.language != Language::Go {
+        return None;
+    }
+    let symbols = result.symbols.iter()
+        .filter(|s| matches!(s.kind.as_str(), "interface" | "method" | "class"))
+        .cloned()
+        .collect();
+    Some(ParsingResult { language: Language::Go, symbols, ..Default::default() })
+}
+
+pub fn infer_go_implementations(results: &[(String, ParsingResult)]) -> Vec<Implementation> {
+    let go_symbols = || results.iter().filter(|(_, r)| r.language == Language::Go).flat_map(|(_, r)| &r.symbols);
+    let interfaces: HashMap<&str, &Symbol> = go_symbols().filter(|s| s.kind == "interface").map(|s| (s.name.as_str(), s)).collect();
+    let mut method_sets: HashMap<&str, std::collections::HashSet<&str>> = HashMap::new();
+    for sym in go_symbols().filter(|s| s.kind == "method") {
+        if let Some(owner) = &sym.parent_class { method_sets.entry(owner.as_str()).or_default().insert(sym.name.as_str()); }
+    }
+
+    let mut impls = vec![];
+    for iface in interfaces.values() {
+        let required = go_interface_method_set(iface, &interfaces, &mut vec![]);
+        if required.is_empty() { continue; }
+        for ty in go_symbols().filter(|s| s.kind == "class") {
+            let Some(methods) = method_sets.get(ty.name.as_str()) else { continue };
+            if required.iter().all(|m| methods.contains(m.as_str())) {
+                impls.push(Implementation { type_name: ty.name.clone(), trait_name: iface.name.clone(), line: ty.range.0 });
+            }
+        }
+    }
+    impls
+}
+
+fn go_interface_method_set(iface: &Symbol, interfaces: &HashMap<&str, &Symbol>, visiting: &mut Vec<String>) -> Vec<String> {
+    if visiting.contains(&iface.name) { return vec![]; }
+    visiting.push(iface.name.clone());
+    let mut methods = iface.members.clone();
+    for embedded in &iface.bases {
+        // Only interfaces defined in the repo can be resolved; io.Reader & co. are left out
+        if let Some(inner) = interfaces.get(embedded.as_str()) {
+            methods.extend(go_interface_method_set(inner, interfaces, visiting));
+        }
+    }
+    methods
+}
+
+// const_spec/var_spec nodes of a Go declaration, whether written inline or as a ( ... ) block
+fn go_value_specs(decl: Node) -> Vec<Node> {
+    let mut specs = vec![];
+    let mut walk = decl.walk();
+    for child in decl.named_children(&mut walk) {
+        match child.kind() {
+            "const_spec" | "var_spec" => specs.push(child),
+            "var_spec_list" => specs.extend(go_value_specs(child)),
+            _ => {}
+        }
+    }
+    specs
+}
+
+// Outer attributes (#[must_use], #[deprecated], #[doc(hidden)], derives, ...) and rustdoc sections/examples
+fn apply_rustdoc(sym: &mut Symbol, node: Node, source: &str) {
+    let mut attrs = vec![];
+    let mut sibling = node.prev_named_sibling();
+    while let Some(s) = sibling {
+        match s.kind() {
+            "attribute_item" => {
+                if let Ok(text) = s.utf8_text(source.as_bytes()) { attrs.push(text.trim().to_string()); }
+            }
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = s.prev_named_sibling();
+    }
+    attrs.reverse();
+    sym.decorators = attrs;
+
+    let Some(doc) = sym.docstring.clone() else { return };
+    let mut heading = String::new();
+    let mut body: Vec<&str> = vec![];
+    // Some(runnable) while inside a ``` fence
+    let mut fence: Option<bool> = None;
+    let mut example: Vec<&str> = vec![];
+    for line in doc.lines() {
+        let trimmed = line.trim();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            match fence.take() {
+                Some(runnable) => {
+                    if runnable { sym.examples.push(example.join("\n")); }
+                    example.clear();
+                }
+                None => fence = Some(is_rust_fence(info)),
+            }
+            body.push(line);
+            continue;
+        }
+        if fence.is_some() {
+            // Lines hidden with `# ` still compile as part of the example
+            let code = if trimmed == "#" { "" } else { line.strip_prefix("# ").unwrap_or(line) };
+            example.push(code);
+            body.push(line);
+            continue;
+        }
+        if let Some(title) = trimmed.strip_prefix("# ") {
+            push_doc_section(sym, &heading, &body);
+            heading = title.trim().to_string();
+            body.clear();
+            continue;
+        }
+        body.push(line);
+    }
+    push_doc_section(sym, &heading, &body);
+}
+
+fn push_doc_section(sym: &mut Symbol, heading: &str, body: &[&str]) {
+    let body = body.join("\n").trim().to_string();
+    if heading.is_empty() && body.is_empty() { return; }
+    let heading = if heading.is_empty() { "Summary".to_string() } else { heading.to_string() };
+    sym.doc_sections.push(DocSection { heading, body });
+}
+
+// Untagged fences are Rust to rustdoc; ignore/compile_fail and other languages aren't runnable
+fn is_rust_fence(info: &str) -> bool {
+    info.split([',', ' ']).map(str::trim).filter(|t| !t.is_empty()).all(|t| {
+        matches!(t, "rust" | "no_run" | "should_panic") || t.starts_with("edition")
+    })
+}
+
+fn extract_full_signature(node: Node, source: &str, lang: Language) -> Option<String> {
+    // Get everything from the start of the node to the start of the body
+    let body_field = match lang {
+        Language::Python => "body",
+        Language::Rust => "body",
+        Language::Go => "body",
+        Language::Java => "body",
+        Language::TypeScript | Language::JavaScript => "body",
+        _ => "body",
+    };
+    let start = node.start_byte();
+    let end = node.child_by_field_name(body_field)
+        .or_else(|| node.child_by_field_name("value").and_then(|v| v.child_by_field_name(body_field)))
+        .map(|b| b.start_byte())
+        .unwrap_or_else(|| std::cmp::min(start + 300, node.end_byte()));
+    let mut safe_end = end;
+    while safe_end < source.len() && !source.is_char_boundary(safe_end) { safe_end += 1; }
+    let sig = source[start..safe_end].trim_end().trim_end_matches('{').trim_end_matches("=>").trim_end_matches(':').trim();
+    if sig.is_empty() { None } else { Some(sig.to_string()) }
+}
+
+fn extract_params(node: Node, source: &str, _lang: Language) -> Vec<Param> {
+    let params_node = node.child_by_field_name("parameters")
+        .or_else(|| node.child_by_field_name("formal_parameters"));
+    let params_node = match params_node {
+        Some(n) => n,
+        None => return vec![],
+    };
+    let mut params = Vec::new();
+    let mut walk = params_node.walk();
+    for child in params_node.named_children(&mut walk) {
+        let name = child.child_by_field_name("name")
+            .or_else(|| child.child_by_field_name("pattern"))
+            // Python `x: int` / `*args: str` has no name field
+            .or_else(|| (child.kind() == "typed_parameter").then(|| child.named_child(0)).flatten())
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+            .unwrap_or_else(|| child.utf8_text(source.as_bytes()).unwrap_or(""))
+            .to_string();
+        let type_ann = child.child_by_field_name("type")
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+            .map(|s| s.to_string());
+        let default = child.child_by_field_name("value")
+            .or_else(|| child.child_by_field_name("default_value"))
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+            .map(|s| s.to_string());
+        if !name.is_empty() && name != "self" && name != "cls" {
+            params.push(Param { name, type_annotation: type_ann, default });
+        }
+    }
+    params
+}
+
+fn extract_return_type(node: Node, source: &str, _lang: Language) -> Option<String> {
+    node.child_by_field_name("return_type")
+        .or_else(|| node.child_by_field_name("result"))
+        .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+        .map(|s| s.trim_start_matches("->").trim_start_matches(':').trim().to_string())
+}
+
+fn normalize_python_hints(symbols: &mut [Symbol]) {
+    for sym in symbols {
+        for param in sym.params.iter_mut().chain(sym.fields.iter_mut()) {
+            param.type_annotation = param.type_annotation.as_deref().map(normalize_py_type);
+        }
+        sym.return_type = sym.return_type.as_deref().map(normalize_py_type);
+    }
+}
+
+// One spelling per Python annotation: builtin generics (list[int]), `X | None` unions, no quotes
+// around forward references and no typing. prefix. Unparseable text only has whitespace collapsed
+fn normalize_py_type(ty: &str) -> String {
+    let chars: Vec<char> = ty.chars().collect();
+    let mut pos = 0;
+    match py_union(&chars, &mut pos) {
+        Some(members) if pos == chars.len() => members.join(" | "),
+        _ => ty.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+fn py_union(chars: &[char], pos: &mut usize) -> Option<Vec<String>> {
+    let mut members: Vec<String> = vec![];
+    loop {
+        for member in py_primary(chars, pos)? {
+            if !members.contains(&member) { members.push(member); }
+        }
+        py_skip_ws(chars, pos);
+        if chars.get(*pos) != Some(&'|') { break; }
+        *pos += 1;
+    }
+    // Optional[X] and Union[None, X] both read `X | None`
+    if let Some(i) = members.iter().position(|m| m == "None") {
+        let none = members.remove(i);
+        members.push(none);
+    }
+    Some(members)
+}
+
+fn py_primary(chars: &[char], pos: &mut usize) -> Option<Vec<String>> {
+    py_skip_ws(chars, pos);
+    match *chars.get(*pos)? {
+        quote @ ('"' | '\'') => {
+            let start = *pos + 1;
+            let end = start + chars[start..].iter().position(|&c| c == quote)?;
+            *pos = end + 1;
+            let mut inner = 0;
+            let members = py_union(&chars[start..end], &mut inner)?;
+            (inner == end - start).then_some(members)
+        }
+        '[' => {
+            // Callable's argument list
+            *pos += 1;
+            let args = py_args(chars, pos)?;
+            Some(vec![format!("[{}]", args.iter().map(|a| a.join(" | ")).collect::<Vec<_>>().join(", "))])
+        }
+        _ => {
+            let start = *pos;
+            while chars.get(*pos).is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '.')) { *pos += 1; }
+            if *pos == start { return None; }
+            let name = canonical_py_name(&chars[start..*pos].iter().collect::<String>());
+            py_skip_ws(chars, pos);
+            if chars.get(*pos) != Some(&'[') { return Some(vec![name]); }
+            if name == "Literal" {
+                // Literal values are kept verbatim: quoted strings there are not forward references
+                let open = *pos;
+                let mut depth = 0;
+                while let Some(&c) = chars.get(*pos) {
+                    *pos += 1;
+                    match c {
+                        '[' => depth += 1,
+                        ']' => { depth -= 1; if depth == 0 { break; } }
+                        _ => {}
+                    }
+                }
+                let args: String = chars[open..*pos].iter().collect();
+                return Some(vec![format!("Literal{}", args.split_whitespace().collect::<Vec<_>>().join(" "))]);
+            }
+            *pos += 1;
+            let args = py_args(chars, pos)?;
+            Some(match name.as_str() {
+                "Optional" => args.into_iter().flatten().chain(["None".to_string()]).collect(),
+                "Union" => args.into_iter().flatten().collect(),
+                _ => vec![format!("{}[{}]", name, args.iter().map(|a| a.join(" | ")).collect::<Vec<_>>().join(", "))],
+            })
+        }
+    }
+}
+
+// Comma-separated annotations up to the closing bracket
+fn py_args(chars: &[char], pos: &mut usize) -> Option<Vec<Vec<String>>> {
+    let mut args = vec![];
+    loop {
+        py_skip_ws(chars, pos);
+        if chars.get(*pos) == Some(&']') {
+            *pos += 1;
+            return Some(args);
+        }
+        args.push(py_union(chars, pos)?);
+        py_skip_ws(chars, pos);
+        match chars.get(*pos)? {
+            ',' => *pos += 1,
+            ']' => {}
+            _ => return None,
+        }
+    }
+}
+
+fn py_skip_ws(chars: &[char], pos: &mut usize) {
+    while chars.get(*pos).is_some_and(|c| c.is_whitespace()) { *pos += 1; }
+}
+
+fn canonical_py_name(name: &str) -> String {
+    let name = match name.rsplit_once('.') {
+        Some(("typing" | "typing_extensions" | "t" | "collections.abc", last)) => last,
+        _ => name,
+    };
+    match name {
+        "List" => "list",
+        "Dict" => "dict",
+        "Set" => "set",
+        "FrozenSet" => "frozenset",
+        "Tuple" => "tuple",
+        "Type" => "type",
+        "Deque" => "deque",
+        "DefaultDict" => "defaultdict",
+        "Text" => "str",
+        "NoneType" => "None",
+        other => other,
+    }.to_string()
+}
+
+// Names a normalized annotation refers to, e.g. `dict[str, models.User] | None` -> [dict, str, User]
+pub fn py_type_names(ty: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for token in ty.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')) {
+        let name = token.rsplit('.').next().unwrap_or(token);
+        if name.starts_with(|c: char| c.is_alphabetic() || c == '_') && name != "None" && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+fn extract_type_params(node: Node, source: &str, lang: Language) -> Vec<TypeParam> {
+    if lang != Language::TypeScript { return vec![]; }
+    let Some(list) = node.child_by_field_name("type_parameters") else { return vec![] };
+    let text = |n: Node| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+    let mut walk = list.walk();
+    let params = list.named_children(&mut walk)
+        .filter(|p| p.kind() == "type_parameter")
+        .filter_map(|p| Some(TypeParam {
+            name: p.child_by_field_name("name").and_then(text)?,
+            constraint: p.child_by_field_name("constraint").and_then(text)
+                .map(|c| c.trim_start_matches("extends").trim_start_matches(':').trim().to_string()),
+            default: p.child_by_field_name("value").and_then(text)
+                .map(|d| d.trim_start_matches('=').trim().to_string()),
+        }))
+        .collect();
+    params
+}
+
+fn extract_visibility(node: Node, source: &str, lang: Language) -> Option<String> {
+    match lang {
+        Language::Rust => {
+            // Check for visibility_modifier child
+            let mut walk = node.walk();
+            for child in node.children(&mut walk) {
+                if child.kind() == "visibility_modifier" {
+                    return child.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+                }
+            }
+            None
+        }
+        Language::Java | Language::Php | Language::Cpp => {
+            // Check for modifiers
+            if let Some(mods) = node.child_by_field_name("modifiers") {
+                return mods.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+            }
+            let mut walk = node.walk();
+            let found = node.children(&mut walk)
+                .find(|c| c.kind() == "modifiers" || c.kind() == "access_specifier")
+                .and_then(|n| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string()));
+            found
+        }
+        Language::TypeScript | Language::JavaScript => {
+            // Check for export_statement parent
+            if let Some(p) = node.parent() {
+                if p.kind() == "export_statement" {
+                    return Some("export".to_string());
+                }
+            }
+            None
+        }
+        Language::Python => {
+            // Convention: _ prefix = private
+            let name = node.child_by_field_name("name")
+                .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+                .unwrap_or("");
+            if name.starts_with("__") && name.ends_with("__") { Some("dunder".to_string()) }
+            else if name.starts_with('_') { Some("private".to_string()) }
+            else { Some("public".to_string()) }
+        }
+        _ => None,
+    }
+}
+
+// (describe|it|test, title, callback) for a Jest/Mocha-style test call, including `.only`/`.skip` variants
+fn js_test_block<'a>(stmt: Node<'a>, source: &str) -> Option<(String, String, Option<Node<'a>>)> {
+    let call = stmt.named_child(0).filter(|c| c.kind() == "call_expression")?;
+    let func = call.child_by_field_name("function")?;
+    let func = if func.kind() == "member_expression" { func.child_by_field_name("object")? } else { func };
+    let func_name = func.utf8_text(source.as_bytes()).ok()?;
+    if func.kind() != "identifier" || !matches!(func_name, "describe" | "it" | "test") { return None; }
+    let args = call.child_by_field_name("arguments")?;
+    let title_node = args.named_child(0).filter(|a| a.kind() == "string" || a.kind() == "template_string")?;
+    let title = title_node.utf8_text(source.as_bytes()).ok()?.trim_matches(['"', '\'', '`']).to_string();
+    let mut walk = args.walk();
+    let callback = args.named_children(&mut walk)
+        .find(|a| a.kind() == "arrow_function" || a.kind() == "function_expression" || a.kind() == "function");
+    let callback = callback.and_then(|cb| cb.child_by_field_name("body"));
+    Some((func_name.to_string(), title, callback))
+}
+
+const HTTP_METHODS: &[&str] = &["get", "post", "put", "patch", "delete", "del", "head", "options", "all"];
+
+fn js_routes(stmt: Node, source: &str, parent: Option<&str>) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let literal = |n: Node| matches!(n.kind(), "string" | "template_string").then(|| text(n).trim_matches(['"', '\'', '`']).to_string());
+    let mut routes = vec![];
+    let Some(mut call) = stmt.named_child(0).filter(|c| c.kind() == "call_expression") else { return routes };
+    // Walk a chain like app.route('/book').get(list).post(create) from the outermost call inwards
+    let mut chain = vec![];
+    while let Some(func) = call.child_by_field_name("function").filter(|f| f.kind() == "member_expression") {
+        let (Some(object), Some(property)) = (func.child_by_field_name("object"), func.child_by_field_name("property")) else { break };
+        chain.push((text(property), call));
+        if object.kind() != "call_expression" { break; }
+        call = object;
+    }
+    let mut shared_path: Option<String> = None;
+    for (method, call) in chain.into_iter().rev() {
+        let Some(args) = call.child_by_field_name("arguments") else { continue };
+        let mut walk = args.walk();
+        let args: Vec<Node> = args.named_children(&mut walk).filter(|a| a.kind() != "comment").collect();
+        let (method, path, handler) = match method.as_str() {
+            "route" if args.first().is_some_and(|a| a.kind() == "object") => {
+                // fastify.route({ method: 'GET', url: '/x', handler })
+                let mut fields: HashMap<String, Node> = HashMap::new();
+                let mut walk = args[0].walk();
+                for prop in args[0].named_children(&mut walk) {
+                    match prop.kind() {
+                        "pair" => if let (Some(k), Some(v)) = (prop.child_by_field_name("key"), prop.child_by_field_name("value")) { fields.insert(text(k), v); },
+                        "shorthand_property_identifier" => { fields.insert(text(prop), prop); }
+                        _ => {}
+                    }
+                }
+                let Some(path) = fields.get("url").or_else(|| fields.get("path")).and_then(|n| literal(*n)) else { continue };
+                let method = fields.get("method").map(|n| text(*n).trim_matches(['"', '\'', '`', '[', ']']).to_string()).unwrap_or_default();
+                (method, path, fields.get("handler").copied())
+            }
+            "route" => {
+                shared_path = args.first().and_then(|a| literal(*a));
+                continue;
+            }
+            m if HTTP_METHODS.contains(&m) => {
+                let (path, rest) = match (args.first().and_then(|a| literal(*a)), &shared_path) {
+                    (Some(path), _) => (path, &args[1.min(args.len())..]),
+                    (None, Some(path)) => (path.clone(), &args[..]),
+                    (None, None) => continue,
+                };
+                // map.get('key', fallback) also looks like this; real routes are paths
+                if !(path.starts_with('/') || path == "*") || rest.is_empty() { continue; }
+                // Koa router spells DELETE as .del()
+                let method = if m == "del" { "delete" } else { m };
+                (method.to_string(), path, rest.last().copied())
+            }
+            _ => continue,
+        };
+        let method = if method == "all" || method.is_empty() { "ANY".to_string() } else { method.to_uppercase() };
+        let handler_name = handler.and_then(|h| match h.kind() {
+            "identifier" | "shorthand_property_identifier" => Some(text(h)),
+            "member_expression" => h.child_by_field_name("property").map(text),
+            _ => None,
+        });
+        let first_line = text(call).lines().next().unwrap_or("").to_string();
+        routes.push(Symbol {
+            name: format!("{} {}", method, path),
+            kind: "route".to_string(),
+            range: (call.start_position().row + 1, call.end_position().row + 1),
+            byte_range: byte_span(call, call),
+            columns: column_span(call, call),
+            content_preview: first_line.chars().take(120).collect(),
+            signature: Some(first_line.trim_end_matches(['{', '(']).trim().to_string()),
+            parent_class: parent.map(|s| s.to_string()),
+            // Inline handlers' calls belong to the route itself
+            calls: handler.filter(|_| handler_name.is_none()).map(|h| collect_calls_in_node(h, source)).unwrap_or_default(),
+            route: Some(Route { method, path, handler: handler_name }),
+            ..Default::default()
+        });
+    }
+    routes
+}
+
+// FastAPI/Flask route decorators on `handler`'s decorated_definition
+fn python_routes(decorated: Node, handler: &Symbol, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let literal = |n: Node| (n.kind() == "string").then(|| text(n).trim_start_matches(['r', 'b', 'u', 'R', 'B', 'U']).trim_matches(['"', '\'']).to_string());
+    let mut routes = vec![];
+    let mut walk = decorated.walk();
+    for deco in decorated.children(&mut walk).filter(|c| c.kind() == "decorator") {
+        let Some(call) = deco.named_child(0).filter(|c| c.kind() == "call") else { continue };
+        let Some(attr) = call.child_by_field_name("function").filter(|f| f.kind() == "attribute") else { continue };
+        let Some(verb) = attr.child_by_field_name("attribute").map(text) else { continue };
+        if !matches!(verb.as_str(), "get" | "post" | "put" | "patch" | "delete" | "head" | "options" | "route" | "api_route" | "websocket") { continue; }
+        let Some(args) = call.child_by_field_name("arguments") else { continue };
+        let mut path = None;
+        let mut methods: Vec<String> = vec![];
+        let mut walk2 = args.walk();
+        for arg in args.named_children(&mut walk2) {
+            match arg.kind() {
+                "string" if path.is_none() => path = literal(arg),
+                "keyword_argument" => {
+                    let (Some(key), Some(value)) = (arg.child_by_field_name("name"), arg.child_by_field_name("value")) else { continue };
+                    match text(key).as_str() {
+                        "path" | "rule" => path = literal(value),
+                        "methods" => {
+                            let mut walk3 = value.walk();
+                            methods = value.named_children(&mut walk3).filter_map(literal).map(|m| m.to_uppercase()).collect();
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(path) = path else { continue };
+        if methods.is_empty() {
+            methods.push(match verb.as_str() {
+                "route" | "api_route" => "GET".to_string(),
+                "websocket" => "WS".to_string(),
+                v => v.to_uppercase(),
+            });
+        }
+        let signature = text(deco);
+        for method in methods {
+            routes.push(Symbol {
+                name: format!("{} {}", method, path),
+                kind: "route".to_string(),
+                range: (deco.start_position().row + 1, handler.range.1),
+                byte_range: byte_span(deco, decorated),
+                columns: column_span(deco, decorated),
+                content_preview: signature.chars().take(120).collect(),
+                docstring: handler.docstring.clone(),
+                signature: Some(signature.clone()),
+                params: handler.params.clone(),
+                return_type: handler.return_type.clone(),
+                parent_class: handler.parent_class.clone(),
+                route: Some(Route { method, path: path.clone(), handler: Some(handler.name.clone()) }),
+                ..Default::default()
+            });
+        }
+    }
+    routes
+}
+
+// Django URLconf: path('users/<int:pk>/', views.detail) / re_path(r'^x$', UserView.as_view())
+fn django_routes(assign: Node, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let mut routes = vec![];
+    let Some(value) = assign.child_by_field_name("right") else { return routes };
+    let mut stack = vec![value];
+    while let Some(n) = stack.pop() {
+        let mut walk = n.walk();
+        stack.extend(n.named_children(&mut walk).filter(|c| matches!(c.kind(), "list" | "binary_operator" | "parenthesized_expression")));
+        let mut walk = n.walk();
+        for call in n.named_children(&mut walk).filter(|c| c.kind() == "call") {
+            let func = call.child_by_field_name("function").map(text).unwrap_or_default();
+            if !matches!(func.rsplit('.').next(), Some("path" | "re_path" | "url")) { continue; }
+            let Some(args) = call.child_by_field_name("arguments") else { continue };
+            let mut walk2 = args.walk();
+            let positional: Vec<Node> = args.named_children(&mut walk2).filter(|a| a.kind() != "keyword_argument" && a.kind() != "comment").collect();
+            let (Some(pattern), Some(view)) = (positional.first().filter(|p| p.kind() == "string"), positional.get(1)) else { continue };
+            let path = text(*pattern).trim_start_matches(['r', 'R']).trim_matches(['"', '\'']).to_string();
+            // include('app.urls') mounts another URLconf rather than a view
+            let handler = match view.kind() {
+                "identifier" => Some(text(*view)),
+                "attribute" => view.child_by_field_name("attribute").map(text),
+                // UserView.as_view() -> the class itself
+                "call" => view.child_by_field_name("function")
+                    .filter(|f| f.kind() == "attribute" && f.child_by_field_name("attribute").map(text).as_deref() == Some("as_view"))
+                    .and_then(|f| f.child_by_field_name("object"))
+                    .map(|o| text(o).rsplit('.').next().unwrap_or("").to_string()),
+                _ => None,
+            };
+            let Some(handler) = handler.filter(|h| !h.is_empty()) else { continue };
+            let line = text(call).lines().next().unwrap_or("").to_string();
+            routes.push(Symbol {
+                name: format!("ANY {}", path),
+                kind: "route".to_string(),
+                range: (call.start_position().row + 1, call.end_position().row + 1),
+                byte_range: byte_span(call, call),
+                columns: column_span(call, call),
+                content_preview: line.chars().take(120).collect(),
+                signature: Some(line.trim_end_matches(',').to_string()),
+                route: Some(Route { method: "ANY".to_string(), path, handler: Some(handler) }),
+                ..Default::default()
+            });
+        }
+    }
+    routes.sort_by_key(|r| r.range.0);
+    routes
+}
+
+// Spring @GetMapping/@RequestMapping and JAX-RS @GET/@Path annotations -> (HTTP methods, paths).
+// None when the annotation isn't route-related.
+fn java_mapping(annotation: &str) -> Option<(Vec<String>, Vec<String>)> {
+    let body = annotation.trim().strip_prefix('@')?;
+    let (name, args) = match body.split_once('(') {
+        Some((name, args)) => (name.trim(), args.trim_end().trim_end_matches(')')),
+        None => (body.trim(), ""),
+    };
+    let name = name.rsplit('.').next().unwrap_or(name);
+    let mut methods: Vec<String> = match name {
+        "GetMapping" | "PostMapping" | "PutMapping" | "DeleteMapping" | "PatchMapping" => {
+            vec![name.trim_end_matches("Mapping").to_uppercase()]
+        }
+        "RequestMapping" | "Path" => vec![],
+        "GET" | "POST" | "PUT" | "DELETE" | "PATCH" | "HEAD" | "OPTIONS" => return Some((vec![name.to_string()], vec![])),
+        _ => return None,
+    };
+    let mut paths = vec![];
+    for arg in split_top_level(args) {
+        let (key, value) = match arg.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => ("value", arg.trim()),
+        };
+        match key {
+            "value" | "path" => paths.extend(value.split('"').skip(1).step_by(2).map(|p| p.to_string())),
+            "method" => methods.extend(value.split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                .filter_map(|m| m.strip_prefix("RequestMethod.").map(|m| m.to_string()))),
+            _ => {}
+        }
+    }
+    Some((methods, paths))
+}
+
+fn split_top_level(args: &str) -> Vec<&str> {
+    let (mut parts, mut depth, mut in_str, mut start) = (vec![], 0, false, 0);
+    for (i, c) in args.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '{' | '(' if !in_str => depth += 1,
+            '}' | ')' if !in_str => depth -= 1,
+            ',' if !in_str && depth == 0 => {
+                parts.push(&args[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if !args[start..].trim().is_empty() { parts.push(&args[start..]); }
+    parts
+}
+
+fn java_routes(method: &Symbol, prefix: &str) -> Vec<Symbol> {
+    let mut verbs: Vec<String> = vec![];
+    let mut paths: Vec<String> = vec![];
+    let mut mapped = false;
+    for (m, p) in method.decorators.iter().filter_map(|a| java_mapping(a)) {
+        mapped = true;
+        verbs.extend(m);
+        paths.extend(p);
+    }
+    if !mapped { return vec![]; }
+    // JAX-RS resource methods need an HTTP verb; a bare @Path is a sub-resource locator
+    if verbs.is_empty() && method.decorators.iter().all(|a| !a.contains("Mapping")) { return vec![]; }
+    if verbs.is_empty() { verbs.push("ANY".to_string()); }
+    if paths.is_empty() { paths.push(String::new()); }
+    let mut routes = vec![];
+    for path in &paths {
+        let full = join_url(prefix, path);
+        for verb in &verbs {
+            routes.push(Symbol {
+                name: format!("{} {}", verb, full),
+                kind: "route".to_string(),
+                range: method.range,
+                byte_range: method.byte_range,
+                columns: method.columns,
+                content_preview: method.content_preview.clone(),
+                docstring: method.docstring.clone(),
+                signature: method.signature.clone(),
+                params: method.params.clone(),
+                return_type: method.return_type.clone(),
+                parent_class: method.parent_class.clone(),
+                route: Some(Route { method: verb.clone(), path: full.clone(), handler: Some(method.name.clone()) }),
+                ..Default::default()
+            });
+        }
+    }
+    routes
+}
+
+const STRING_KINDS: &[&str] = &[
+    "string", "template_string", "concatenated_string", "string_literal", "raw_string_literal",
+    "interpreted_string_literal", "text_block", "heredoc_body", "encapsed_string", "heredoc", "nowdoc", "raw_string",
+];
+
+// SQL, GraphQL and HTML held in string literals or sql`..` / gql`..` / html`..` tagged templates,
+// attributed to the function they appear in
+fn embedded_languages(root: Node, source: &str, symbols: &[Symbol]) -> Vec<Symbol> {
+    let mut out = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if STRING_KINDS.contains(&node.kind()) {
+            out.extend(embedded_symbol(node, source, symbols));
+            // Interpolations can hold further literals; the parts of a concatenation were read above
+            if node.kind() == "concatenated_string" {
+                continue;
+            }
+        }
+        let mut walk = node.walk();
+        stack.extend(node.named_children(&mut walk));
+    }
+    out.sort_by_key(|s| s.byte_range.0);
+    out
+}
+
+fn embedded_symbol(node: Node, source: &str, symbols: &[Symbol]) -> Option<Symbol> {
+    let parent = node.parent()?;
+    // Docstrings and directives like "use strict"
+    if parent.kind() == "expression_statement" {
+        return None;
+    }
+    let tag = (parent.kind() == "call_expression")
+        .then(|| parent.child_by_field_name("function"))
+        .flatten()
+        .and_then(|f| f.utf8_text(source.as_bytes()).ok())
+        .map(|f| f.rsplit('.').next().unwrap_or(f).to_ascii_lowercase());
+    let text = if node.kind() == "concatenated_string" {
+        let mut walk = node.walk();
+        node.named_children(&mut walk).map(|c| literal_text(c, source)).collect::<Vec<_>>().join("")
+    } else {
+        literal_text(node, source)
+    };
+    let text = text.trim();
+    let language = classify_embedded(text, tag.as_deref())?;
+    let (name, targets) = match language {
+        "sql" => {
+            let keyword = text.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("").to_ascii_uppercase();
+            let tables = sql_tables(text);
+            (tables.first().map(|t| format!("{} {}", keyword, t)).unwrap_or(keyword), tables)
+        }
+        "graphql" => {
+            let head: Vec<&str> = text.split(['{', '(']).next().unwrap_or("").split_whitespace().take(2).collect();
+            (if head.is_empty() { "query".to_string() } else { head.join(" ") }, graphql_root_fields(text))
+        }
+        _ => {
+            let tag_name: String = text.trim_start_matches(['<', '!']).chars().take_while(|c| c.is_ascii_alphanumeric() || *c == '-').collect();
+            (format!("<{}>", tag_name), vec![])
+        }
+    };
+    let container = symbols.iter()
+        .filter(|s| matches!(s.kind.as_str(), "function" | "method" | "component" | "hook" | "procedure"))
+        .filter(|s| s.byte_range.0 <= node.start_byte() && node.end_byte() <= s.byte_range.1)
+        .min_by_key(|s| s.byte_range.1 - s.byte_range.0)
+        .map(|s| s.name.clone());
+    Some(Symbol {
+        name,
+        kind: "embedded".to_string(),
+        range: (node.start_position().row + 1, node.end_position().row + 1),
+        byte_range: byte_span(node, node),
+        columns: column_span(node, node),
+        content_preview: text.lines().next().unwrap_or("").trim().chars().take(120).collect(),
+        parent_class: symbols.iter().find(|s| Some(&s.name) == container.as_ref()).and_then(|s| s.parent_class.clone()),
+        embedded: Some(Embedded { language: language.to_string(), text: text.to_string(), targets, container }),
+        ..Default::default()
+    })
+}
+
+// Literal contents without prefixes (r, b, f, R) and quote delimiters; heredoc bodies lose their end marker
+fn literal_text(node: Node, source: &str) -> String {
+    let raw = node.utf8_text(source.as_bytes()).unwrap_or("");
+    if node.kind() == "heredoc_body" {
+        return raw.rsplit_once('\n').map(|(body, _)| body).unwrap_or(raw).to_string();
+    }
+    let quote = raw.find(['"', '\'', '`']).unwrap_or(0);
+    let raw = if raw[..quote].chars().all(|c| c.is_ascii_alphanumeric() || c == '#' || c == '@' || c == '$') { &raw[quote..] } else { raw };
+    raw.trim_matches(['"', '\'', '`', '#']).to_string()
+}
+
+fn classify_embedded(text: &str, tag: Option<&str>) -> Option<&'static str> {
+    match tag {
+        Some("sql") => return Some("sql"),
+        Some("gql" | "graphql") => return Some("graphql"),
+        Some("html") => return Some("html"),
+        _ => {}
+    }
+    let words: Vec<String> = text.split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .take(64)
+        .map(|w| w.to_ascii_uppercase())
+        .collect();
+    let first = text.split(|c: char| !c.is_ascii_alphabetic()).next().unwrap_or("");
+    let has = |w: &str| words.iter().any(|x| x == w);
+    let second = words.get(1).map(|w| w.as_str()).unwrap_or("");
+    let sql = match words.first().map(|w| w.as_str()) {
+        Some("SELECT") => has("FROM"),
+        Some("INSERT") => second == "INTO",
+        Some("UPDATE") => has("SET"),
+        Some("DELETE") => second == "FROM",
+        Some("CREATE" | "ALTER" | "DROP") => words.iter().skip(1).take(3).any(|w| matches!(w.as_str(), "TABLE" | "INDEX" | "VIEW")),
+        Some("WITH") => has("AS") && has("SELECT"),
+        _ => false,
+    };
+    // Lowercase SQL has to look like code rather than a sentence
+    if sql && (first.chars().all(|c| c.is_ascii_uppercase()) || (text.contains(['*', '=', '?', '(', '%', '$']) && !text.ends_with('.'))) {
+        return Some("sql");
+    }
+    if matches!(first, "query" | "mutation" | "subscription" | "fragment") && text.contains('{') && text.trim_end().ends_with('}') {
+        return Some("graphql");
+    }
+    let after = text.strip_prefix('<').and_then(|t| t.chars().next());
+    if after.is_some_and(|c| c.is_ascii_alphabetic() || c == '!') && (text.contains("</") || text.contains("/>")) {
+        return Some("html");
+    }
+    None
+}
+
+// Table names following FROM/JOIN/INTO/UPDATE/TABLE
+fn sql_tables(text: &str) -> Vec<String> {
+    let tokens: Vec<&str> = text.split(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')' | ';')).filter(|t| !t.is_empty()).collect();
+    let mut tables: Vec<String> = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        if !matches!(token.to_ascii_uppercase().as_str(), "FROM" | "JOIN" | "INTO" | "UPDATE" | "TABLE") {
+            continue;
+        }
+        let next = tokens[i + 1..].iter()
+            .find(|t| !matches!(t.to_ascii_uppercase().as_str(), "IF" | "NOT" | "EXISTS" | "ONLY"));
+        let Some(next) = next else { continue };
+        let name = next.trim_matches(['"', '`', '[', ']', '\'']);
+        if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && !tables.iter().any(|t| t == name) {
+            tables.push(name.to_string());
+        }
+    }
+    tables
+}
+
+// Fields selected directly under the operation's top-level braces, ignoring aliases and arguments
+fn graphql_root_fields(text: &str) -> Vec<String> {
+    let mut fields: Vec<String> = vec![];
+    let (mut braces, mut parens) = (0, 0);
+    let mut word = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if (c.is_ascii_alphanumeric() || c == '_') && braces == 1 && parens == 0 {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            let mut rest = chars.clone();
+            let alias = c == ':' || (c.is_whitespace() && rest.find(|n| !n.is_whitespace()) == Some(':'));
+            if !alias && !word.starts_with(|ch: char| ch.is_ascii_digit()) && !fields.contains(&word) {
+                fields.push(word.clone());
+            }
+            word.clear();
+        }
+        match c {
+            '{' => braces += 1,
+            '}' => braces -= 1,
+            '(' => parens += 1,
+            ')' => parens -= 1,
+            _ => {}
+        }
+    }
+    fields
+}
+
+fn command_symbol(node: Node, name: String, docstring: Option<String>, command: Command, source: &str) -> Symbol {
+    let line = node.utf8_text(source.as_bytes()).unwrap_or("").lines().next().unwrap_or("").trim().to_string();
+    Symbol {
+        name,
+        kind: "command".to_string(),
+        range: (node.start_position().row + 1, node.end_position().row + 1),
+        byte_range: byte_span(node, node),
+        columns: column_span(node, node),
+        content_preview: line.chars().take(120).collect(),
+        docstring,
+        signature: Some(line),
+        command: Some(command),
+        ..Default::default()
+    }
+}
+
+fn unquote(text: &str) -> String {
+    text.trim_start_matches(['r', 'b', 'u', 'f']).trim_matches(['"', '\'', '`']).to_string()
+}
+
+// `--port`, `-p` and positional names out of a click/argparse declaration
+fn cli_option_from_decl(names: &[String]) -> CliOption {
+    let long = names.iter().find(|n| n.starts_with("--"));
+    let short = names.iter().find(|n| n.starts_with('-') && !n.starts_with("--"));
+    let name = long.or(short).or(names.first()).cloned().unwrap_or_default();
+    CliOption {
+        positional: !name.starts_with('-'),
+        short: short.filter(|_| long.is_some()).cloned(),
+        name,
+        ..Default::default()
+    }
+}
+
+// Python click: @click.group() / @cli.command("name") with @click.option / @click.argument
+fn click_commands(root: Node, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let mut commands: Vec<Symbol> = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut walk = node.walk();
+        stack.extend(node.named_children(&mut walk).filter(|c| matches!(c.kind(), "module" | "decorated_definition" | "class_definition" | "block" | "if_statement")));
+        if node.kind() != "decorated_definition" { continue; }
+        let Some(def) = node.child_by_field_name("definition").filter(|d| d.kind() == "function_definition") else { continue };
+        let func = def.child_by_field_name("name").map(text).unwrap_or_default();
+        let mut command: Option<(String, Option<String>, Option<String>)> = None;
+        let mut options = vec![];
+        let mut walk = node.walk();
+        for deco in node.children(&mut walk).filter(|c| c.kind() == "decorator") {
+            let Some(expr) = deco.named_child(0) else { continue };
+            let (callee, args) = match expr.kind() {
+                "call" => (expr.child_by_field_name("function"), expr.child_by_field_name("arguments")),
+                _ => (Some(expr), None),
+            };
+            let Some(callee) = callee else { continue };
+            let (object, attr) = match callee.kind() {
+                "attribute" => (callee.child_by_field_name("object").map(text), callee.child_by_field_name("attribute").map(text).unwrap_or_default()),
+                _ => (None, text(callee)),
+            };
+            let mut positional: Vec<String> = vec![];
+            let mut kwargs: HashMap<String, String> = HashMap::new();
+            if let Some(args) = args {
+                let mut walk2 = args.walk();
+                for arg in args.named_children(&mut walk2) {
+                    match arg.kind() {
+                        "string" => positional.push(unquote(&text(arg))),
+                        "keyword_argument" => if let (Some(k), Some(v)) = (arg.child_by_field_name("name"), arg.child_by_field_name("value")) {
+                            kwargs.insert(text(k), text(v));
+                        },
+                        _ => {}
+                    }
+                }
+            }
+            match attr.as_str() {
+                "command" | "group" => {
+                    let name = positional.first().cloned().or_else(|| kwargs.get("name").map(|n| unquote(n)))
+                        .unwrap_or_else(|| func.replace('_', "-"));
+                    let parent = object.filter(|o| o != "click");
+                    command = Some((name, parent, kwargs.get("help").map(|h| unquote(h))));
+                }
+                "option" | "argument" => {
+                    // click's python-name form ("--port", "port_num") keeps only the dashed names
+                    let dashed: Vec<String> = positional.iter().filter(|n| n.starts_with('-')).cloned().collect();
+                    let mut option = cli_option_from_decl(if dashed.is_empty() { &positional } else { &dashed });
+                    option.positional = attr == "argument";
+                    option.type_annotation = kwargs.get("type").cloned().or_else(|| kwargs.contains_key("is_flag").then(|| "bool".to_string()));
+                    option.default = kwargs.get("default").cloned();
+                    option.help = kwargs.get("help").map(|h| unquote(h));
+                    options.push(option);
+                }
+                _ => {}
+            }
+        }
+        let Some((name, parent, help)) = command else { continue };
+        let docstring = help.or_else(|| extract_docstring(def, source, Language::Python));
+        commands.push(command_symbol(node, name, docstring, Command {
+            parent,
+            binding: Some(func.clone()),
+            handler: Some(func),
+            options,
+        }, source));
+    }
+    commands.sort_by_key(|c| c.range.0);
+    commands
+}
+
+// Python argparse: ArgumentParser / add_subparsers / add_parser / add_argument / set_defaults(func=..)
+fn argparse_commands(root: Node, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let mut commands: Vec<Symbol> = vec![];
+    // parser variable -> index in `commands`; subparsers variable -> owning parser index
+    let mut parsers: HashMap<String, usize> = HashMap::new();
+    let mut subparsers: HashMap<String, usize> = HashMap::new();
+    let mut calls = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "call" { calls.push(node); }
+        let mut walk = node.walk();
+        let children: Vec<Node> = node.named_children(&mut walk).collect();
+        stack.extend(children.into_iter().rev());
+    }
+    for call in calls {
+        let Some(func) = call.child_by_field_name("function") else { continue };
+        let (object, method) = match func.kind() {
+            "attribute" => (func.child_by_field_name("object").map(text).unwrap_or_default(), func.child_by_field_name("attribute").map(text).unwrap_or_default()),
+            _ => (String::new(), text(func)),
+        };
+        let target = call.parent().filter(|p| p.kind() == "assignment")
+            .and_then(|a| a.child_by_field_name("left")).filter(|l| l.kind() == "identifier").map(text);
+        let mut positional: Vec<String> = vec![];
+        let mut kwargs: HashMap<String, String> = HashMap::new();
+        if let Some(args) = call.child_by_field_name("arguments") {
+            let mut walk = args.walk();
+            for arg in args.named_children(&mut walk) {
+                match arg.kind() {
+                    "string" => positional.push(unquote(&text(arg))),
+                    "keyword_argument" => if let (Some(k), Some(v)) = (arg.child_by_field_name("name"), arg.child_by_field_name("value")) {
+                        kwargs.insert(text(k), text(v));
+                    },
+                    _ => {}
+                }
+            }
+        }
+        let help = |kw: &HashMap<String, String>| kw.get("help").or_else(|| kw.get("description")).map(|h| unquote(h));
+        match method.as_str() {
+            "ArgumentParser" => {
+                let name = kwargs.get("prog").map(|p| unquote(p)).unwrap_or_else(|| "main".to_string());
+                if let Some(var) = &target { parsers.insert(var.clone(), commands.len()); }
+                commands.push(command_symbol(call, name, help(&kwargs), Command { binding: target, ..Default::default() }, source));
+            }
+            "add_subparsers" => {
+                if let (Some(var), Some(&owner)) = (&target, parsers.get(&object)) { subparsers.insert(var.clone(), owner); }
+            }
+            "add_parser" => {
+                let (Some(&owner), Some(name)) = (subparsers.get(&object), positional.first()) else { continue };
+                let parent = Some(commands[owner].name.clone());
+                if let Some(var) = &target { parsers.insert(var.clone(), commands.len()); }
+                commands.push(command_symbol(call, name.clone(), help(&kwargs), Command { parent, binding: target, ..Default::default() }, source));
+            }
+            "add_argument" => {
+                let Some(&owner) = parsers.get(&object) else { continue };
+                let mut option = cli_option_from_decl(&positional);
+                option.type_annotation = kwargs.get("type").cloned()
+                    .or_else(|| kwargs.get("action").filter(|a| a.contains("store_true") || a.contains("store_false")).map(|_| "bool".to_string()));
+                option.default = kwargs.get("default").cloned();
+                option.help = kwargs.get("help").map(|h| unquote(h));
+                if let Some(cmd) = commands[owner].command.as_mut() { cmd.options.push(option); }
+            }
+            "set_defaults" => {
+                let (Some(&owner), Some(func)) = (parsers.get(&object), kwargs.get("func")) else { continue };
+                if let Some(cmd) = commands[owner].command.as_mut() { cmd.handler = Some(func.clone()); }
+            }
+            _ => {}
+        }
+    }
+    commands
+}
+
+// Attributes and `///` doc lines directly above a Rust item, field or variant
+fn rust_item_meta(node: Node, source: &str) -> (Vec<String>, Option<String>) {
+    let mut attrs = vec![];
+    let mut doc = vec![];
+    let mut sibling = node.prev_named_sibling();
+    while let Some(s) = sibling {
+        let text = s.utf8_text(source.as_bytes()).unwrap_or("").trim();
+        match s.kind() {
+            "attribute_item" => attrs.push(text.to_string()),
+            "line_comment" if text.starts_with("///") => doc.push(text.trim_start_matches('/').trim().to_string()),
+            "line_comment" | "block_comment" => {}
+            _ => break,
+        }
+        sibling = s.prev_named_sibling();
+    }
+    attrs.reverse();
+    doc.reverse();
+    (attrs, (!doc.is_empty()).then(|| doc.join("\n")))
+}
+
+// `#[arg(short, long = "x")]` -> [("short", None), ("long", Some("x"))] when the attribute is `name`
+fn rust_attr_args(attrs: &[String], names: &[&str]) -> Vec<(String, Option<String>)> {
+    let mut out = vec![];
+    for attr in attrs {
+        let inner = attr.trim_start_matches("#[").trim_end_matches(']');
+        let Some((name, args)) = inner.split_once('(') else { continue };
+        if !names.contains(&name.trim()) { continue; }
+        for arg in split_top_level(args.trim_end().trim_end_matches(')')) {
+            match arg.split_once('=') {
+                Some((k, v)) => out.push((k.trim().to_string(), Some(v.trim().to_string()))),
+                None => out.push((arg.trim().to_string(), None)),
+            }
+        }
+    }
+    out
+}
+
+fn kebab_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 { out.push('-'); }
+        if c == '_' { out.push('-'); } else { out.push(c.to_ascii_lowercase()); }
+    }
+    out
+}
+
+// Rust clap derive: #[derive(Parser)] roots, #[derive(Subcommand)] enums, #[derive(Args)] groups
+fn clap_commands(root: Node, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let mut items: HashMap<String, Node> = HashMap::new();
+    let mut walk = root.walk();
+    for item in root.named_children(&mut walk).filter(|c| matches!(c.kind(), "struct_item" | "enum_item")) {
+        if let Some(name) = item.child_by_field_name("name").map(text) { items.insert(name, item); }
+    }
+    let derives = |item: Node, what: &str| rust_item_meta(item, source).0.iter()
+        .any(|a| a.starts_with("#[derive(") && a.split(|c: char| !c.is_alphanumeric()).any(|w| w == what));
+
+    let mut commands = vec![];
+    let mut roots: Vec<&Node> = items.values().filter(|i| i.kind() == "struct_item" && derives(**i, "Parser")).collect();
+    roots.sort_by_key(|n| n.start_byte());
+    for item in roots {
+        let (attrs, doc) = rust_item_meta(*item, source);
+        let type_name = item.child_by_field_name("name").map(text).unwrap_or_default();
+        let name = rust_attr_args(&attrs, &["command", "clap"]).into_iter()
+            .find(|(k, _)| k == "name").and_then(|(_, v)| v).map(|v| unquote(&v))
+            .unwrap_or_else(|| kebab_case(&type_name));
+        let mut subcommands = vec![];
+        let options = clap_fields(*item, source, &items, &mut subcommands);
+        commands.push(command_symbol(*item, name.clone(), doc, Command { binding: Some(type_name), options, ..Default::default() }, source));
+        for enum_name in subcommands {
+            clap_subcommands(&enum_name, &name, source, &items, &mut commands, 0);
+        }
+    }
+    commands
+}
+
+// Options from a struct's (or struct variant's) fields; `#[command(subcommand)]` fields name the nested enum
+fn clap_fields(item: Node, source: &str, items: &HashMap<String, Node>, subcommands: &mut Vec<String>) -> Vec<CliOption> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let Some(body) = item.child_by_field_name("body").filter(|b| b.kind() == "field_declaration_list") else { return vec![] };
+    let mut options = vec![];
+    let mut walk = body.walk();
+    for field in body.named_children(&mut walk).filter(|f| f.kind() == "field_declaration") {
+        let (attrs, doc) = rust_item_meta(field, source);
+        let name = field.child_by_field_name("name").map(text).unwrap_or_default();
+        let ty = field.child_by_field_name("type").map(text).unwrap_or_default();
+        let command_args = rust_attr_args(&attrs, &["command", "clap"]);
+        if command_args.iter().any(|(k, _)| k == "subcommand") {
+            subcommands.push(bare_type_name(&ty));
+            continue;
+        }
+        if command_args.iter().any(|(k, _)| k == "flatten") {
+            if let Some(group) = items.get(&bare_type_name(&ty)) { options.extend(clap_fields(*group, source, items, subcommands)); }
+            continue;
+        }
+        let args = rust_attr_args(&attrs, &["arg", "clap"]);
+        let get = |key: &str| args.iter().find(|(k, _)| k == key);
+        let long = get("long").map(|(_, v)| format!("--{}", v.as_deref().map(unquote).unwrap_or_else(|| kebab_case(&name))));
+        let short = get("short").map(|(_, v)| format!("-{}", v.as_deref().map(unquote).unwrap_or_else(|| name.chars().next().unwrap_or_default().to_string())));
+        options.push(CliOption {
+            name: long.clone().or_else(|| short.clone()).unwrap_or_else(|| name.to_uppercase()),
+            short: short.filter(|_| long.is_some()),
+            positional: get("long").is_none() && get("short").is_none(),
+            type_annotation: Some(ty),
+            default: get("default_value").or_else(|| get("default_value_t")).and_then(|(_, v)| v.as_deref().map(unquote)),
+            help: get("help").and_then(|(_, v)| v.as_deref().map(unquote)).or(doc),
+        });
+    }
+    options
+}
+
+fn clap_subcommands(enum_name: &str, parent: &str, source: &str, items: &HashMap<String, Node>, out: &mut Vec<Symbol>, depth: usize) {
+    let Some(item) = items.get(enum_name).filter(|i| i.kind() == "enum_item") else { return };
+    let Some(body) = item.child_by_field_name("body") else { return };
+    if depth > 8 { return; }
+    let mut walk = body.walk();
+    for variant in body.named_children(&mut walk).filter(|v| v.kind() == "enum_variant") {
+        let (attrs, doc) = rust_item_meta(variant, source);
+        let variant_name = variant.child_by_field_name("name").and_then(|n| n.utf8_text(source.as_bytes()).ok()).unwrap_or("").to_string();
+        let name = rust_attr_args(&attrs, &["command", "clap"]).into_iter()
+            .find(|(k, _)| k == "name").and_then(|(_, v)| v).map(|v| unquote(&v))
+            .unwrap_or_else(|| kebab_case(&variant_name));
+        let mut nested = vec![];
+        let options = match variant.child_by_field_name("body") {
+            Some(fields) if fields.kind() == "field_declaration_list" => clap_fields(variant, source, items, &mut nested),
+            // Build(BuildArgs): options come from the Args struct, or the tuple wraps another Subcommand enum
+            Some(tuple) => {
+                let ty = tuple.named_child(0).and_then(|t| t.utf8_text(source.as_bytes()).ok()).unwrap_or("");
+                match items.get(&bare_type_name(ty)) {
+                    Some(inner) if inner.kind() == "struct_item" => clap_fields(*inner, source, items, &mut nested),
+                    Some(_) => { nested.push(bare_type_name(ty)); vec![] }
+                    None => vec![],
+                }
+            }
+            None => vec![],
+        };
+        out.push(command_symbol(variant, name.clone(), doc, Command {
+            parent: Some(parent.to_string()),
+            binding: Some(format!("{}::{}", enum_name, variant_name)),
+            options,
+            ..Default::default()
+        }, source));
+        for inner in nested {
+            clap_subcommands(&inner, &name, source, items, out, depth + 1);
+        }
+    }
+}
+
+// Go cobra: &cobra.Command{Use: "serve", RunE: runServe}, X.AddCommand(Y), X.Flags().IntVarP(..)
+fn cobra_commands(root: Node, source: &str) -> Vec<Symbol> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let mut commands: Vec<Symbol> = vec![];
+    let mut bindings: HashMap<String, usize> = HashMap::new();
+    let mut calls = vec![];
+    let mut stack = vec![root];
+    while let Some(node) = stack.pop() {
+        let mut walk = node.walk();
+        let children: Vec<Node> = node.named_children(&mut walk).collect();
+        stack.extend(children.into_iter().rev());
+        if node.kind() == "call_expression" { calls.push(node); continue; }
+        if node.kind() != "composite_literal" { continue; }
+        if !node.child_by_field_name("type").is_some_and(|t| text(t).ends_with("cobra.Command")) { continue; }
+        let mut fields: HashMap<String, String> = HashMap::new();
+        if let Some(body) = node.child_by_field_name("body") {
+            let mut walk = body.walk();
+            for element in body.named_children(&mut walk).filter(|e| e.kind() == "keyed_element") {
+                if let (Some(k), Some(v)) = (element.named_child(0), element.named_child(1)) { fields.insert(text(k), text(v)); }
+            }
+        }
+        let Some(name) = fields.get("Use").map(|u| unquote(u)).and_then(|u| u.split_whitespace().next().map(|w| w.to_string())) else { continue };
+        // var serveCmd = &cobra.Command{..}, cmd := .., or `return &cobra.Command{..}` from a constructor
+        let mut binding = None;
+        let mut up = node.parent();
+        while let Some(p) = up {
+            match p.kind() {
+                "var_spec" => { binding = p.child_by_field_name("name").map(text); break; }
+                "short_var_declaration" | "assignment_statement" => {
+                    binding = p.child_by_field_name("left").and_then(|l| l.named_child(0)).map(text);
+                    break;
+                }
+                "function_declaration" | "method_declaration" => { binding = p.child_by_field_name("name").map(text); break; }
+                "unary_expression" | "expression_list" | "return_statement" | "parenthesized_expression" | "block" => up = p.parent(),
+                _ => break,
+            }
+        }
+        if let Some(b) = &binding { bindings.insert(b.clone(), commands.len()); }
+        let docstring = fields.get("Long").or_else(|| fields.get("Short")).map(|d| unquote(d));
+        let handler = fields.get("RunE").or_else(|| fields.get("Run")).filter(|h| !h.starts_with("func")).cloned();
+        commands.push(command_symbol(node, name, docstring, Command { binding, handler, ..Default::default() }, source));
+    }
+    for call in calls {
+        let Some(func) = call.child_by_field_name("function").filter(|f| f.kind() == "selector_expression") else { continue };
+        let (Some(operand), Some(method)) = (func.child_by_field_name("operand"), func.child_by_field_name("field").map(text)) else { continue };
+        let Some(args) = call.child_by_field_name("arguments") else { continue };
+        let mut walk = args.walk();
+        let args: Vec<Node> = args.named_children(&mut walk).collect();
+        if method == "AddCommand" {
+            // The parent may be declared in another file; keep its binding for the graph to resolve
+            for arg in &args {
+                let child = text(*arg);
+                let child = child.trim_end_matches("()");
+                if let Some(cmd) = bindings.get(child).and_then(|&i| commands[i].command.as_mut()) {
+                    cmd.parent = Some(text(operand));
+                }
+            }
+            continue;
+        }
+        // serveCmd.Flags().IntVarP(&port, "port", "p", 8080, "usage")
+        let Some(owner) = operand.child_by_field_name("function")
+            .filter(|f| f.kind() == "selector_expression")
+            .filter(|f| f.child_by_field_name("field").is_some_and(|m| matches!(text(m).as_str(), "Flags" | "PersistentFlags")))
+            .and_then(|f| f.child_by_field_name("operand")) else { continue };
+        let Some(&index) = bindings.get(&text(owner)) else { continue };
+        let with_var = method.contains("Var");
+        let with_short = method.ends_with('P');
+        let kind = method.trim_end_matches('P').trim_end_matches("Var").to_string();
+        let rest = &args[usize::from(with_var).min(args.len())..];
+        let (name, short, default, usage) = match (with_short, rest) {
+            (true, [name, short, default, usage, ..]) => (name, Some(short), default, usage),
+            (false, [name, default, usage, ..]) => (name, None, default, usage),
+            _ => continue,
+        };
+        let option = CliOption {
+            name: format!("--{}", unquote(&text(*name))),
+            short: short.map(|s| format!("-{}", unquote(&text(*s)))).filter(|s| s != "-"),
+            positional: false,
+            type_annotation: Some(kind.to_lowercase()),
+            default: Some(text(*default)),
+            help: Some(unquote(&text(*usage))),
+        };
+        if let Some(cmd) = commands[index].command.as_mut() { cmd.options.push(option); }
+    }
+    commands
+}
+
+fn ruby_visibility_modifiers(class: Node, source: &str) -> Vec<(usize, String)> {
+    let Some(body) = class.child_by_field_name("body") else { return vec![] };
+    let mut walk = body.walk();
+    let modifiers = body.named_children(&mut walk)
+        .filter(|c| c.kind() == "identifier")
+        .filter_map(|c| {
+            let word = c.utf8_text(source.as_bytes()).ok()?;
+            matches!(word, "private" | "protected" | "public").then(|| (c.start_position().row + 1, word.to_string()))
+        })
+        .collect();
+    modifiers
+}
+
+// Where we are inside config/routes.rb: URL prefix, controller module, and the
+// resource controller for member/collection blocks
+#[derive(Clone, Default)]
+struct RailsScope {
+    path: String,
+    module: String,
+    controller: Option<String>,
+}
+
+// Rails.application.routes.draw do ... end
+fn rails_routes(root: Node, source: &str) -> Vec<Symbol> {
+    let mut routes = vec![];
+    let mut walk = root.walk();
+    for call in root.named_children(&mut walk).filter(|c| c.kind() == "call") {
+        let is_draw = call.child_by_field_name("method").and_then(|m| m.utf8_text(source.as_bytes()).ok()) == Some("draw")
+            && call.child_by_field_name("receiver").and_then(|r| r.utf8_text(source.as_bytes()).ok()).is_some_and(|r| r.ends_with("routes"));
+        if !is_draw { continue; }
+        if let Some(body) = call.child_by_field_name("block").and_then(|b| b.child_by_field_name("body")) {
+            rails_route_block(body, source, &RailsScope::default(), &mut routes);
+        }
+    }
+    routes
+}
+
+fn rails_route_block(body: Node, source: &str, scope: &RailsScope, out: &mut Vec<Symbol>) {
+    let mut walk = body.walk();
+    for call in body.named_children(&mut walk).filter(|c| c.kind() == "call") {
+        rails_route_call(call, source, scope, out);
+    }
+}
+
+fn rails_route_call(call: Node, source: &str, scope: &RailsScope, out: &mut Vec<Symbol>) {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    // "str", :sym and hash keys all name things in the DSL
+    let word = |n: Node| text(n).trim_start_matches(':').trim_end_matches(':').trim_matches(['"', '\'']).to_string();
+    if call.child_by_field_name("receiver").is_some() { return; }
+    let Some(method) = call.child_by_field_name("method").map(text) else { return };
+    let mut positional = vec![];
+    let mut options: HashMap<String, Node> = HashMap::new();
+    if let Some(args) = call.child_by_field_name("arguments") {
+        let mut walk2 = args.walk();
+        for arg in args.named_children(&mut walk2) {
+            match arg.kind() {
+                "pair" => if let (Some(k), Some(v)) = (arg.child_by_field_name("key"), arg.child_by_field_name("value")) {
+                    // get "login" => "sessions#create"
+                    if k.kind() == "string" { positional.push(k); options.insert("to".into(), v); } else { options.insert(word(k), v); }
+                },
+                "comment" => {}
+                _ => positional.push(arg),
+            }
+        }
+    }
+    let block = call.child_by_field_name("block").and_then(|b| b.child_by_field_name("body"));
+    let opt = |key: &str| options.get(key).map(|n| word(*n));
+    match method.as_str() {
+        "get" | "post" | "put" | "patch" | "delete" | "match" | "root" => {
+            let path = if method == "root" { String::new() } else { positional.first().map(|p| word(*p)).unwrap_or_default() };
+            let target = opt("to").or_else(|| opt("action").map(|a| format!("{}#{}", opt("controller").unwrap_or_default(), a)))
+                .or_else(|| (method == "root").then(|| positional.first().map(|p| word(*p))).flatten());
+            // get :preview inside member/collection -> resource#preview; get "photos/search" -> photos#search
+            let target = target.or_else(|| match &scope.controller {
+                Some(controller) => Some(format!("{}#{}", controller, path)),
+                None => path.trim_matches('/').rsplit_once('/').map(|(c, a)| format!("{}#{}", c, a)),
+            });
+            let verbs = match method.as_str() {
+                "root" => vec!["GET".to_string()],
+                "match" => options.get("via").map(|v| {
+                    let v = text(*v);
+                    v.split(|c: char| !c.is_ascii_alphabetic()).filter(|m| !m.is_empty()).map(|m| m.to_uppercase()).collect()
+                }).unwrap_or_else(|| vec!["ANY".to_string()]),
+                m => vec![m.to_uppercase()],
+            };
+            for verb in verbs {
+                out.push(rails_route(call, &verb, &join_url(&scope.path, &path), target.as_deref(), scope, source));
             }
-            (Language::Php, "class_declaration") => {
-                let name = child.child_by_field_name("name")
-                    .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-                    .unwrap_or("").to_string();
-                if let Some(sym) = build_symbol(child, source, lang, "class", parent, vec![]) {
-                    out.push(sym);
+        }
+        "resources" | "resource" => {
+            let plural = method == "resources";
+            for name in positional.iter().map(|p| word(*p)).filter(|n| !n.is_empty()) {
+                let controller = opt("controller").unwrap_or_else(|| if plural { name.clone() } else { pluralize(&name) });
+                let base = join_url(&scope.path, opt("path").as_deref().unwrap_or(&name));
+                let member = if plural { join_url(&base, ":id") } else { base.clone() };
+                let only = options.get("only").map(|n| text(*n));
+                let except = options.get("except").map(|n| text(*n));
+                let actions: &[(&str, &str, bool)] = &[
+                    ("index", "GET", false), ("create", "POST", false), ("new", "GET", false), ("edit", "GET", true),
+                    ("show", "GET", true), ("update", "PATCH", true), ("destroy", "DELETE", true),
+                ];
+                for (action, verb, on_member) in actions {
+                    if !plural && *action == "index" { continue; }
+                    let listed = |list: &Option<String>| list.as_ref().is_some_and(|l| l.split(|c: char| !c.is_ascii_alphanumeric() && c != '_').any(|a| a == *action));
+                    if (only.is_some() && !listed(&only)) || listed(&except) { continue; }
+                    let path = match *action {
+                        "new" => join_url(&base, "new"),
+                        "edit" => join_url(&member, "edit"),
+                        _ if *on_member => member.clone(),
+                        _ => base.clone(),
+                    };
+                    out.push(rails_route(call, verb, &path, Some(&format!("{}#{}", controller, action)), scope, source));
                 }
-                if !name.is_empty() {
-                    collect_symbols(child, source, lang, Some(&name), out, depth + 1);
+                if let Some(block) = block {
+                    // Nested resources hang off /parents/:parent_id; member/collection blocks add actions
+                    let nested_id = format!(":{}_id", name.trim_end_matches('s'));
+                    let nested = RailsScope {
+                        path: if plural { join_url(&base, &nested_id) } else { base.clone() },
+                        module: scope.module.clone(),
+                        controller: Some(controller.clone()),
+                    };
+                    rails_resource_block(block, source, &nested, &base, &member, out);
                 }
             }
-
-            _ => {
-                // Recurse into other nodes to find nested definitions
-                collect_symbols(child, source, lang, parent, out, depth + 1);
+        }
+        "namespace" => {
+            let Some(name) = positional.first().map(|p| word(*p)) else { return };
+            if let Some(block) = block {
+                let inner = RailsScope {
+                    path: join_url(&scope.path, &name),
+                    module: if scope.module.is_empty() { name.clone() } else { format!("{}/{}", scope.module, name) },
+                    controller: None,
+                };
+                rails_route_block(block, source, &inner, out);
+            }
+        }
+        "scope" => {
+            let path = positional.first().map(|p| word(*p)).or_else(|| opt("path")).unwrap_or_default();
+            let module = match opt("module") {
+                Some(m) if scope.module.is_empty() => m,
+                Some(m) => format!("{}/{}", scope.module, m),
+                None => scope.module.clone(),
+            };
+            if let Some(block) = block {
+                rails_route_block(block, source, &RailsScope { path: join_url(&scope.path, &path), module, controller: scope.controller.clone() }, out);
             }
         }
+        // constraints, defaults, concerns etc. just wrap more routes
+        _ => if let Some(block) = block { rails_route_block(block, source, scope, out) },
     }
 }
 
-fn build_symbol(node: Node, source: &str, lang: Language, kind: &str, parent: Option<&str>, decorators: Vec<String>) -> Option<Symbol> {
-    let name_node = node.child_by_field_name("name")
-        .or_else(|| node.child_by_field_name("declarator")); // C++ function_declarator
-    let name = match name_node {
-        Some(n) => {
-            // For C++ nested declarators
-            let actual = n.child_by_field_name("declarator").unwrap_or(n);
-            actual.utf8_text(source.as_bytes()).ok()?.to_string()
+fn rails_resource_block(body: Node, source: &str, nested: &RailsScope, base: &str, member: &str, out: &mut Vec<Symbol>) {
+    let mut walk = body.walk();
+    for call in body.named_children(&mut walk).filter(|c| c.kind() == "call") {
+        let method = call.child_by_field_name("method").and_then(|m| m.utf8_text(source.as_bytes()).ok()).unwrap_or("");
+        match (method, call.child_by_field_name("block").and_then(|b| b.child_by_field_name("body"))) {
+            ("member" | "collection", Some(block)) => {
+                let path = if method == "member" { member } else { base };
+                rails_route_block(block, source, &RailsScope { path: path.to_string(), ..nested.clone() }, out);
+            }
+            _ => rails_route_call(call, source, nested, out),
         }
-        None => return None,
-    };
-    if name.is_empty() { return None; }
-
-    let range = (node.start_position().row + 1, node.end_position().row + 1);
-
-    // Full signature: everything up to the body
-    let sig = extract_full_signature(node, source, lang);
-
-    let docstring = extract_docstring(node, source, lang);
-    let params = extract_params(node, source, lang);
-    let return_type = extract_return_type(node, source, lang);
-    let visibility = extract_visibility(node, source, lang);
+    }
+}
 
-    let preview = {
-        let start = node.start_byte();
-        let mut end = std::cmp::min(start + 120, node.end_byte());
-        while end < source.len() && !source.is_char_boundary(end) { end += 1; }
-        source[start..end].lines().next().unwrap_or("").to_string()
+fn rails_route(call: Node, verb: &str, path: &str, target: Option<&str>, scope: &RailsScope, source: &str) -> Symbol {
+    let line = call.utf8_text(source.as_bytes()).unwrap_or("").lines().next().unwrap_or("").trim().to_string();
+    // "admin/dashboard#stats" -> DashboardController#stats
+    let (owner, action) = match target.and_then(|t| t.split_once('#')) {
+        Some((controller, action)) => {
+            let controller = if scope.module.is_empty() || controller.contains('/') { controller.to_string() } else { format!("{}/{}", scope.module, controller) };
+            let class = controller.rsplit('/').next().unwrap_or("").split('_')
+                .map(|w| { let mut c = w.chars(); c.next().map(|f| f.to_uppercase().chain(c).collect::<String>()).unwrap_or_default() })
+                .collect::<String>();
+            (Some(format!("{}Controller", class)), Some(action.to_string()))
+        }
+        None => (None, None),
     };
+    Symbol {
+        name: format!("{} {}", verb, path),
+        kind: "route".to_string(),
+        range: (call.start_position().row + 1, call.end_position().row + 1),
+        byte_range: byte_span(call, call),
+        columns: column_span(call, call),
+        content_preview: line.chars().take(120).collect(),
+        signature: Some(line.trim_end_matches(" do").to_string()),
+        parent_class: owner,
+        route: Some(Route { method: verb.to_string(), path: path.to_string(), handler: action }),
+        ..Default::default()
+    }
+}
 
-    Some(Symbol {
-        name,
-        kind: kind.to_string(),
-        range,
-        content_preview: preview,
-        docstring,
-        signature: sig,
-        params,
-        return_type,
-        visibility,
-        parent_class: parent.map(|s| s.to_string()),
-        decorators,
-        calls: vec![],
-        bases: vec![],
-    })
+fn join_url(prefix: &str, path: &str) -> String {
+    let segments: Vec<&str> = prefix.split('/').chain(path.split('/')).filter(|s| !s.is_empty()).collect();
+    format!("/{}", segments.join("/"))
 }
 
-fn extract_full_signature(node: Node, source: &str, lang: Language) -> Option<String> {
-    // Get everything from the start of the node to the start of the body
-    let body_field = match lang {
-        Language::Python => "body",
-        Language::Rust => "body",
-        Language::Go => "body",
-        Language::Java => "body",
-        Language::TypeScript | Language::JavaScript => "body",
-        _ => "body",
-    };
-    let start = node.start_byte();
-    let end = node.child_by_field_name(body_field)
-        .map(|b| b.start_byte())
-        .unwrap_or_else(|| std::cmp::min(start + 300, node.end_byte()));
-    let mut safe_end = end;
-    while safe_end < source.len() && !source.is_char_boundary(safe_end) { safe_end += 1; }
-    let sig = source[start..safe_end].trim_end().trim_end_matches('{').trim_end_matches(':').trim();
-    if sig.is_empty() { None } else { Some(sig.to_string()) }
+fn pluralize(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix('y') { format!("{}ies", stem) }
+    else if word.ends_with('s') || word.ends_with("sh") || word.ends_with("ch") || word.ends_with('x') { format!("{}es", word) }
+    else { format!("{}s", word) }
 }
 
-fn extract_params(node: Node, source: &str, _lang: Language) -> Vec<Param> {
-    let params_node = node.child_by_field_name("parameters")
-        .or_else(|| node.child_by_field_name("formal_parameters"));
-    let params_node = match params_node {
-        Some(n) => n,
-        None => return vec![],
-    };
-    let mut params = Vec::new();
-    let mut walk = params_node.walk();
-    for child in params_node.named_children(&mut walk) {
-        let name = child.child_by_field_name("name")
-            .or_else(|| child.child_by_field_name("pattern"))
-            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-            .unwrap_or_else(|| child.utf8_text(source.as_bytes()).unwrap_or(""))
-            .to_string();
-        let type_ann = child.child_by_field_name("type")
-            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-            .map(|s| s.to_string());
-        let default = child.child_by_field_name("value")
-            .or_else(|| child.child_by_field_name("default_value"))
-            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-            .map(|s| s.to_string());
-        if !name.is_empty() && name != "self" && name != "cls" {
-            params.push(Param { name, type_annotation: type_ann, default });
+fn extract_raises(node: Node, source: &str, lang: Language) -> Vec<String> {
+    let mut raises = vec![];
+    // Java declares checked exceptions up front: `void f() throws IOException`
+    if lang == Language::Java {
+        let mut walk = node.walk();
+        let throws = node.children(&mut walk).find(|c| c.kind() == "throws");
+        if let Some(throws) = throws {
+            let mut walk2 = throws.walk();
+            for ty in throws.named_children(&mut walk2) {
+                if let Ok(t) = ty.utf8_text(source.as_bytes()) { raises.push(t.to_string()); }
+            }
         }
     }
-    params
+    let node = node.child_by_field_name("value").filter(|_| node.kind() == "variable_declarator").unwrap_or(node);
+    if let Some(body) = node.child_by_field_name("body") {
+        collect_raises(body, source, &mut raises);
+    }
+    let mut seen = std::collections::HashSet::new();
+    raises.retain(|r| seen.insert(r.clone()));
+    raises
 }
 
-fn extract_return_type(node: Node, source: &str, _lang: Language) -> Option<String> {
-    node.child_by_field_name("return_type")
-        .or_else(|| node.child_by_field_name("result"))
-        .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-        .map(|s| s.trim_start_matches("->").trim_start_matches(':').trim().to_string())
-}
+const NESTED_SCOPE_KINDS: &[&str] = &[
+    "function_definition", "function_declaration", "function_item", "function_expression", "arrow_function",
+    "lambda", "lambda_expression", "closure_expression", "class_definition", "class_declaration", "method_declaration",
+];
 
-fn extract_visibility(node: Node, source: &str, lang: Language) -> Option<String> {
-    match lang {
-        Language::Rust => {
-            // Check for visibility_modifier child
-            let mut walk = node.walk();
-            for child in node.children(&mut walk) {
-                if child.kind() == "visibility_modifier" {
-                    return child.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
-                }
-            }
-            None
+fn collect_raises(node: Node, source: &str, out: &mut Vec<String>) {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    match node.kind() {
+        // raise X(...) / throw new X(...) / throw err
+        "raise_statement" | "throw_statement" | "throw_expression" => {
+            if let Some(ty) = node.named_child(0).and_then(|e| error_type_name(e, source)) { out.push(ty); }
         }
-        Language::Java | Language::Php | Language::Cpp => {
-            // Check for modifiers
-            if let Some(mods) = node.child_by_field_name("modifiers") {
-                return mods.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+        // Rust: Err(MyError::NotFound) / return Err(Error::Io(e))
+        "call_expression" if node.child_by_field_name("function").is_some_and(|f| text(f) == "Err") => {
+            if let Some(ty) = node.child_by_field_name("arguments").and_then(|a| a.named_child(0)).and_then(|e| error_type_name(e, source)) {
+                out.push(ty);
             }
-            let mut walk = node.walk();
-            let found = node.children(&mut walk)
-                .find(|c| c.kind() == "modifiers" || c.kind() == "access_specifier")
-                .and_then(|n| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string()));
-            found
         }
-        Language::TypeScript | Language::JavaScript => {
-            // Check for export_statement parent
-            if let Some(p) = node.parent() {
-                if p.kind() == "export_statement" {
-                    return Some("export".to_string());
-                }
+        // Ruby: raise ArgumentError, "msg"
+        "call" if node.child_by_field_name("receiver").is_none()
+            && node.child_by_field_name("method").is_some_and(|m| matches!(text(m).as_str(), "raise" | "fail")) => {
+            if let Some(ty) = node.child_by_field_name("arguments").and_then(|a| a.named_child(0)).and_then(|e| error_type_name(e, source)) {
+                out.push(ty);
             }
-            None
-        }
-        Language::Python => {
-            // Convention: _ prefix = private
-            let name = node.child_by_field_name("name")
-                .and_then(|n| n.utf8_text(source.as_bytes()).ok())
-                .unwrap_or("");
-            if name.starts_with("__") && name.ends_with("__") { Some("dunder".to_string()) }
-            else if name.starts_with('_') { Some("private".to_string()) }
-            else { Some("public".to_string()) }
         }
+        _ => {}
+    }
+    let mut walk = node.walk();
+    for child in node.named_children(&mut walk) {
+        if !NESTED_SCOPE_KINDS.contains(&child.kind()) { collect_raises(child, source, out); }
+    }
+}
+
+// The type named by a raised/thrown expression; None for re-raised variables and plain values
+fn error_type_name(expr: Node, source: &str) -> Option<String> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+    let name = match expr.kind() {
+        "call" | "call_expression" => expr.child_by_field_name("function").and_then(text)
+            .or_else(|| expr.child_by_field_name("method").and_then(text)),
+        "new_expression" => expr.child_by_field_name("constructor").and_then(text),
+        "object_creation_expression" => expr.child_by_field_name("type").and_then(text),
+        "struct_expression" => expr.child_by_field_name("name").and_then(text),
+        "macro_invocation" => expr.child_by_field_name("macro").and_then(text).map(|m| format!("{}!", m)),
+        "identifier" | "constant" | "attribute" | "scoped_identifier" | "member_expression" | "scope_resolution" => text(expr),
         _ => None,
+    }?;
+    // Bare lowercase identifiers are variables being re-thrown, not types
+    let last = name.rsplit(['.', ':']).next().unwrap_or(&name);
+    if !last.starts_with(|c: char| c.is_uppercase()) && !name.ends_with('!') { return None; }
+    Some(name)
+}
+
+// (async, generator, unsafe) from the function's modifier tokens, or `yield` in a Python body
+fn function_flags(node: Node, lang: Language) -> (bool, bool, bool) {
+    // `const f = async () => {}`: the flags are on the value
+    let node = node.child_by_field_name("value").filter(|_| node.kind() == "variable_declarator").unwrap_or(node);
+    let mut tokens = vec![];
+    let mut walk = node.walk();
+    for child in node.children(&mut walk) {
+        if child.kind() == "function_modifiers" {
+            let mut walk2 = child.walk();
+            tokens.extend(child.children(&mut walk2).map(|c| c.kind()));
+        } else {
+            tokens.push(child.kind());
+        }
     }
+    let is_async = tokens.contains(&"async");
+    let is_unsafe = lang == Language::Rust && tokens.contains(&"unsafe");
+    let is_generator = node.kind().contains("generator")
+        || (node.kind() == "method_definition" && tokens.contains(&"*"))
+        || (lang == Language::Python && node.kind() == "function_definition"
+            && node.child_by_field_name("body").is_some_and(contains_yield));
+    (is_async, is_generator, is_unsafe)
+}
+
+// `yield` in this scope, not counting nested functions, lambdas and classes
+fn contains_yield(node: Node) -> bool {
+    let mut walk = node.walk();
+    let found = node.children(&mut walk).any(|child| match child.kind() {
+        "yield" => true,
+        "function_definition" | "lambda" | "class_definition" | "decorated_definition" => false,
+        _ => contains_yield(child),
+    });
+    found
+}
+
+fn extract_java_annotations(node: Node, source: &str) -> Vec<String> {
+    let mut walk = node.walk();
+    let Some(mods) = node.children(&mut walk).find(|c| c.kind() == "modifiers") else { return vec![] };
+    let mut walk2 = mods.walk();
+    let annotations = mods.named_children(&mut walk2)
+        .filter(|c| c.kind() == "marker_annotation" || c.kind() == "annotation")
+        .filter_map(|c| c.utf8_text(source.as_bytes()).ok().map(|t| t.trim().to_string()))
+        .collect();
+    annotations
 }
 
 fn extract_decorators(node: Node, source: &str) -> Vec<String> {
@@ -629,6 +3768,172 @@ fn extract_decorators(node: Node, source: &str) -> Vec<String> {
     decos
 }
 
+fn extract_enum_members(node: Node, source: &str, lang: Language) -> Vec<String> {
+    let variant_kinds: &[&str] = match (lang, node.kind()) {
+        (Language::Rust, "enum_item") => &["enum_variant"],
+        (Language::TypeScript, "enum_declaration") => &["property_identifier", "enum_assignment"],
+        (Language::Java, "enum_declaration") => &["enum_constant"],
+        _ => return vec![],
+    };
+    let Some(body) = node.child_by_field_name("body") else { return vec![] };
+    let mut members = vec![];
+    let mut walk = body.walk();
+    for child in body.named_children(&mut walk) {
+        if !variant_kinds.contains(&child.kind()) { continue; }
+        let name_node = child.child_by_field_name("name").unwrap_or(child);
+        if let Ok(name) = name_node.utf8_text(source.as_bytes()) {
+            members.push(name.to_string());
+        }
+    }
+    members
+}
+
+fn extract_fields(node: Node, source: &str, lang: Language) -> Vec<Param> {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).ok().map(|s| s.to_string());
+    let mut fields = vec![];
+    match (lang, node.kind()) {
+        (Language::Rust, "struct_item") => {
+            let Some(body) = node.child_by_field_name("body") else { return fields };
+            let mut walk = body.walk();
+            if body.kind() == "ordered_field_declaration_list" {
+                // Tuple struct: positional fields named 0, 1, ...
+                let mut walk2 = body.walk();
+                for ty in body.children_by_field_name("type", &mut walk2) {
+                    fields.push(Param { name: fields.len().to_string(), type_annotation: text(ty), default: None });
+                }
+                return fields;
+            }
+            for child in body.named_children(&mut walk).filter(|c| c.kind() == "field_declaration") {
+                let Some(name) = child.child_by_field_name("name").and_then(text) else { continue };
+                fields.push(Param { name, type_annotation: child.child_by_field_name("type").and_then(text), default: None });
+            }
+        }
+        (Language::Python, "class_definition") => {
+            let Some(body) = node.child_by_field_name("body") else { return fields };
+            let mut walk = body.walk();
+            for stmt in body.named_children(&mut walk) {
+                match stmt.kind() {
+                    // Class attributes and dataclass fields: `x: int = 0`
+                    "expression_statement" => {
+                        let Some(assign) = stmt.named_child(0).filter(|a| a.kind() == "assignment") else { continue };
+                        let Some(name) = assign.child_by_field_name("left").filter(|n| n.kind() == "identifier").and_then(text) else { continue };
+                        fields.push(Param {
+                            name,
+                            type_annotation: assign.child_by_field_name("type").and_then(text),
+                            default: assign.child_by_field_name("right").and_then(text),
+                        });
+                    }
+                    "function_definition" | "decorated_definition" => collect_self_assignments(stmt, source, &mut fields),
+                    _ => {}
+                }
+            }
+        }
+        (Language::TypeScript | Language::JavaScript, "class_declaration") => {
+            let Some(body) = node.child_by_field_name("body") else { return fields };
+            let mut walk = body.walk();
+            for child in body.named_children(&mut walk) {
+                if child.kind() != "public_field_definition" && child.kind() != "field_definition" { continue; }
+                let Some(name) = child.child_by_field_name("name").or_else(|| child.child_by_field_name("property")).and_then(text) else { continue };
+                fields.push(Param {
+                    name,
+                    type_annotation: child.child_by_field_name("type").and_then(text).map(|t| t.trim_start_matches(':').trim().to_string()),
+                    default: child.child_by_field_name("value").and_then(text),
+                });
+            }
+        }
+        (Language::TypeScript, "interface_declaration" | "type_alias_declaration") => {
+            let Some(body) = node.child_by_field_name("body")
+                .or_else(|| node.child_by_field_name("value"))
+                .filter(|b| matches!(b.kind(), "interface_body" | "object_type")) else { return fields };
+            let mut walk = body.walk();
+            for child in body.named_children(&mut walk).filter(|c| c.kind() == "property_signature") {
+                let Some(name) = child.child_by_field_name("name").and_then(text) else { continue };
+                fields.push(Param {
+                    name,
+                    type_annotation: child.child_by_field_name("type").and_then(text).map(|t| t.trim_start_matches(':').trim().to_string()),
+                    default: None,
+                });
+            }
+        }
+        (Language::Go, "type_spec") => {
+            let Some(list) = node.child_by_field_name("type")
+                .filter(|t| t.kind() == "struct_type")
+                .and_then(|t| t.named_child(0)) else { return fields };
+            let mut walk = list.walk();
+            for decl in list.named_children(&mut walk).filter(|d| d.kind() == "field_declaration") {
+                let ty = decl.child_by_field_name("type").and_then(text);
+                let mut walk2 = decl.walk();
+                let names: Vec<String> = decl.children_by_field_name("name", &mut walk2).filter_map(text).filter(|n| n != ",").collect();
+                if names.is_empty() {
+                    // Embedded field: the type doubles as the name
+                    if let Some(ty) = &ty { fields.push(Param { name: ty.trim_start_matches('*').to_string(), type_annotation: Some(ty.clone()), default: None }); }
+                }
+                for name in names {
+                    fields.push(Param { name, type_annotation: ty.clone(), default: None });
+                }
+            }
+        }
+        (Language::Java, "class_declaration") => {
+            let Some(body) = node.child_by_field_name("body") else { return fields };
+            let mut walk = body.walk();
+            for decl in body.named_children(&mut walk).filter(|d| d.kind() == "field_declaration") {
+                let ty = decl.child_by_field_name("type").and_then(text);
+                let mut walk2 = decl.walk();
+                for var in decl.children_by_field_name("declarator", &mut walk2) {
+                    let Some(name) = var.child_by_field_name("name").and_then(text) else { continue };
+                    fields.push(Param { name, type_annotation: ty.clone(), default: var.child_by_field_name("value").and_then(text) });
+                }
+            }
+        }
+        _ => {}
+    }
+    fields
+}
+
+// `self.x = ...` / `self.x: T = ...` anywhere inside a method, first assignment wins
+fn collect_self_assignments(node: Node, source: &str, fields: &mut Vec<Param>) {
+    if node.kind() == "assignment" {
+        let target = node.child_by_field_name("left").filter(|l| l.kind() == "attribute");
+        let is_self = target.and_then(|t| t.child_by_field_name("object"))
+            .is_some_and(|o| o.utf8_text(source.as_bytes()).ok() == Some("self"));
+        let name = target.and_then(|t| t.child_by_field_name("attribute")).and_then(|a| a.utf8_text(source.as_bytes()).ok());
+        if let (true, Some(name)) = (is_self, name) {
+            if !fields.iter().any(|f| f.name == name) {
+                fields.push(Param {
+                    name: name.to_string(),
+                    type_annotation: node.child_by_field_name("type").and_then(|t| t.utf8_text(source.as_bytes()).ok()).map(|t| t.to_string()),
+                    default: None,
+                });
+            }
+        }
+    }
+    let mut walk = node.walk();
+    for child in node.named_children(&mut walk) {
+        // Nested classes own their own attributes
+        if child.kind() != "class_definition" { collect_self_assignments(child, source, fields); }
+    }
+}
+
+fn is_python_enum_base(base: &str) -> bool {
+    let name = base.rsplit('.').next().unwrap_or(base);
+    name.ends_with("Enum") || name == "Flag" || name == "IntFlag"
+}
+
+// Python Enum members: plain NAME = value assignments in the class body
+fn python_enum_members(node: Node, source: &str) -> Vec<String> {
+    let Some(body) = node.child_by_field_name("body") else { return vec![] };
+    let mut members = vec![];
+    let mut walk = body.walk();
+    for stmt in body.named_children(&mut walk) {
+        let Some(assign) = stmt.named_child(0).filter(|a| stmt.kind() == "expression_statement" && a.kind() == "assignment") else { continue };
+        let Some(target) = assign.child_by_field_name("left").filter(|n| n.kind() == "identifier") else { continue };
+        if let Ok(name) = target.utf8_text(source.as_bytes()) {
+            if !name.starts_with('_') { members.push(name.to_string()); }
+        }
+    }
+    members
+}
+
 fn extract_bases(node: Node, source: &str, lang: Language) -> Vec<String> {
     let mut bases = vec![];
     match lang {
@@ -659,13 +3964,31 @@ fn extract_bases(node: Node, source: &str, lang: Language) -> Vec<String> {
                 }
             }
         }
+        Language::Php => {
+            let mut walk = node.walk();
+            for child in node.children(&mut walk) {
+                if child.kind() == "base_clause" || child.kind() == "class_interface_clause" {
+                    let mut clause_walk = child.walk();
+                    for base in child.named_children(&mut clause_walk) {
+                        if let Ok(text) = base.utf8_text(source.as_bytes()) {
+                            bases.push(text.to_string());
+                        }
+                    }
+                }
+            }
+        }
+        Language::Ruby => {
+            if let Some(superclass) = node.child_by_field_name("superclass") {
+                if let Ok(text) = superclass.utf8_text(source.as_bytes()) {
+                    bases.push(text.trim_start_matches('<').trim().to_string());
+                }
+            }
+        }
         _ => {}
     }
     bases
 }
 
-use std::collections::HashMap;
-
 fn extract_call_graph(root: Node, source: &str, lang: Language) -> HashMap<String, Vec<String>> {
     // For each function/method, find what function names it calls
     let query_str = match lang {
@@ -687,19 +4010,26 @@ fn extract_call_graph(root: Node, source: &str, lang: Language) -> HashMap<Strin
         Language::Java => r#"
             (method_declaration name: (identifier) @fn_name body: (block) @body) @fn
         "#,
+        Language::Shell => r#"
+            (function_definition name: (word) @fn_name body: (_) @body) @fn
+        "#,
+        Language::Php => r#"
+            (function_definition name: (name) @fn_name body: (compound_statement) @body) @fn
+            (method_declaration name: (name) @fn_name body: (compound_statement) @body) @fn
+        "#,
         _ => return HashMap::new(),
     };
 
-    let ts_lang = get_ts_language(lang);
-    let Ok(query) = Query::new(&ts_lang, query_str) else { return HashMap::new() };
+    static QUERIES: QueryCache = [const { OnceLock::new() }; GRAMMAR_COUNT];
+    let Some(query) = cached_query(&QUERIES, lang, root, query_str) else { return HashMap::new() };
     let mut cursor = QueryCursor::new();
     let mut result: HashMap<String, Vec<String>> = HashMap::new();
 
-    for m in cursor.matches(&query, root, source.as_bytes()) {
+    for m in cursor.matches(query, root, source.as_bytes()) {
         let mut fn_name = String::new();
         let mut body_node: Option<Node> = None;
         for capture in m.captures {
-            let cap_name: &str = &query.capture_names()[capture.index as usize];
+            let cap_name: &str = query.capture_names()[capture.index as usize];
             if cap_name == "fn_name" {
                 fn_name = capture.node.utf8_text(source.as_bytes()).unwrap_or("").to_string();
             } else if cap_name == "body" {
@@ -717,6 +4047,13 @@ fn extract_call_graph(root: Node, source: &str, lang: Language) -> HashMap<Strin
     result
 }
 
+const STD_MACROS: &[&str] = &[
+    "println", "print", "eprintln", "eprint", "format", "format_args", "write", "writeln", "vec", "panic",
+    "assert", "assert_eq", "assert_ne", "debug_assert", "debug_assert_eq", "debug_assert_ne",
+    "todo", "unimplemented", "unreachable", "matches", "dbg", "concat", "stringify", "include_str",
+    "include_bytes", "env", "cfg", "line", "file", "column", "module_path",
+];
+
 fn collect_calls_in_node(node: Node, source: &str) -> Vec<String> {
     let mut calls = Vec::new();
     let mut stack = vec![node];
@@ -724,14 +4061,87 @@ fn collect_calls_in_node(node: Node, source: &str) -> Vec<String> {
         if n.kind() == "call_expression" || n.kind() == "call" {
             // Get the function name being called
             if let Some(func) = n.child_by_field_name("function") {
+                // C++ foo<int>(): drop the template arguments
+                let func = if func.kind() == "template_function" { func.child_by_field_name("name").unwrap_or(func) } else { func };
                 if let Ok(text) = func.utf8_text(source.as_bytes()) {
-                    // Extract just the function name (last part of dotted access)
-                    let name = text.rsplit('.').next().unwrap_or(text).to_string();
+                    // Keep the receiver/qualifier (self.db.save, utils.parse, Foo::new) so the graph can
+                    // resolve which function is meant; -> and ?. are just member access
+                    let text: String = text.split_whitespace().collect::<String>().replace("->", ".").replace("?.", ".");
+                    let name = if text.ends_with('>') { text.split('<').next().unwrap_or(&text).trim_end_matches("::") } else { &text };
+                    // Computed receivers (foo().bar, items[0].bar) say nothing useful about the callee
+                    let name = match name.rsplit_once('.') {
+                        Some((receiver, method)) if receiver.contains(['(', '[', '"', '\'', '`']) => method,
+                        _ => name,
+                    }.to_string();
                     if !name.is_empty() && !calls.contains(&name) {
                         calls.push(name);
                     }
                 }
             }
+        } else if n.kind() == "macro_invocation" {
+            // Rust: user macros are callable API too; skip the std formatting/assertion ones
+            if let Some(name) = n.child_by_field_name("macro").and_then(|m| m.utf8_text(source.as_bytes()).ok()) {
+                let name = name.rsplit("::").next().unwrap_or(name).to_string();
+                if !STD_MACROS.contains(&name.as_str()) && !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "function_call_expression" {
+            // PHP: keep namespace qualifiers so they can be resolved against `use` aliases
+            if let Some(func) = n.child_by_field_name("function").filter(|f| matches!(f.kind(), "name" | "qualified_name")) {
+                let name = func.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+                if !name.is_empty() && !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "scoped_call_expression" {
+            // PHP: Foo::bar() / self::bar() / parent::bar()
+            let scope = n.child_by_field_name("scope").and_then(|c| c.utf8_text(source.as_bytes()).ok());
+            let method = n.child_by_field_name("name").and_then(|c| c.utf8_text(source.as_bytes()).ok());
+            if let (Some(scope), Some(method)) = (scope, method) {
+                let name = format!("{}::{}", scope, method);
+                if !scope.starts_with('$') && !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "member_call_expression" || n.kind() == "nullsafe_member_call_expression" {
+            if let Some(name) = n.child_by_field_name("name").and_then(|c| c.utf8_text(source.as_bytes()).ok()) {
+                let name = name.to_string();
+                if !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "new_expression" || n.kind() == "object_creation_expression" {
+            // new Foo(...) / new Foo<T>(...): a call to the class, so instantiation shows up with the calls
+            let mut walk = n.walk();
+            let class = n.child_by_field_name("constructor")
+                .or_else(|| n.child_by_field_name("type"))
+                .or_else(|| n.named_children(&mut walk).find(|c| matches!(c.kind(), "name" | "qualified_name")));
+            if let Some(text) = class.and_then(|c| c.utf8_text(source.as_bytes()).ok()) {
+                let name = text.split('<').next().unwrap_or(text).trim().to_string();
+                if !name.is_empty() && !name.contains(['(', '[', '$']) && !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "struct_expression" || n.kind() == "composite_literal" {
+            // Rust's Foo { .. } and Go's Foo{...} build a value of the type without calling anything;
+            // slice, map and array literals name no type of the repo's
+            let ty = n.child_by_field_name("name").or_else(|| n.child_by_field_name("type"))
+                .filter(|t| matches!(t.kind(), "type_identifier" | "scoped_type_identifier" | "qualified_type" | "generic_type"));
+            if let Some(text) = ty.and_then(|t| t.utf8_text(source.as_bytes()).ok()) {
+                let name = text.split('<').next().unwrap_or(text).split('[').next().unwrap_or(text).trim().to_string();
+                if !name.is_empty() && !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
+        } else if n.kind() == "command" {
+            // Shell: every command invocation is a potential call to a script function
+            if let Some(name) = n.child_by_field_name("name").and_then(|c| c.utf8_text(source.as_bytes()).ok()) {
+                let name = name.to_string();
+                if !calls.contains(&name) {
+                    calls.push(name);
+                }
+            }
         }
         let mut walk = n.walk();
         for child in n.children(&mut walk) {
@@ -740,3 +4150,69 @@ fn collect_calls_in_node(node: Node, source: &str) -> Vec<String> {
     }
     calls
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typescript_dialects() {
+        let cast = "function first<T>(xs: unknown[]): T {\n  return <T>xs[0];\n}\n";
+        for filename in ["a.ts", "a.mts", "a.cts"] {
+            let result = parse_content(filename, cast);
+            assert!(result.parse_errors.is_empty(), "{}: {:?}", filename, result.parse_errors);
+            assert_eq!(result.symbols[0].name, "first");
+        }
+        let jsx = "import React from 'react';\nexport function App() {\n  return <div className=\"app\">hi</div>;\n}\n";
+        let result = parse_content("App.tsx", jsx);
+        assert!(result.parse_errors.is_empty(), "{:?}", result.parse_errors);
+        assert_eq!(result.imports.len(), 1);
+    }
+
+    #[test]
+    fn inline_test_module_keeps_source_file() {
+        let source = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn adds() {\n        assert_eq!(add(1, 2), 3);\n    }\n}\n";
+        let result = parse_content("src/math.rs", source);
+        assert!(!result.test_file);
+        assert!(result.symbols.iter().any(|s| s.name == "adds" && s.is_test));
+        assert!(result.symbols.iter().any(|s| s.name == "add" && !s.is_test));
+        assert!(parse_content("tests/math.rs", source).test_file);
+    }
+
+    #[test]
+    fn cached_results_follow_the_path() {
+        let source = "def area(side):\n    return side * side\n";
+        let (first, hash, _) = parse_content_incremental("geo/area.py", source, None, &[]);
+        let (second, same_hash, _) = parse_content_incremental("geo/test_area.py", source, None, &[]);
+        assert_eq!(hash, same_hash);
+        assert!(!first.test_file);
+        assert!(second.test_file);
+        let (again, _, _) = parse_content_incremental("geo/area.py", source, None, &[]);
+        assert!(Arc::ptr_eq(&first, &again));
+    }
+
+    #[test]
+    fn edits_reparse_from_the_same_files_tree() {
+        let before = "def area(side):\n    return side * side\n";
+        let start = before.find("* side").unwrap();
+        let edits = || vec![TextEdit { start_byte: start, old_end_byte: start + 6, text: "** 2".to_string() }];
+        let after = "def area(side):\n    return side ** 2\n";
+        let (_, hash, _) = parse_content_incremental("a/shape.py", before, None, &[]);
+        let (result, _, incremental) = parse_content_incremental("a/shape.py", after, Some(&hash), &edits());
+        assert!(incremental);
+        assert_eq!(result.symbols[0].name, "area");
+        // Same content under another path has no tree of its own yet
+        let (_, _, incremental) = parse_content_incremental("b/shape.py", after, Some(&hash), &edits());
+        assert!(!incremental);
+    }
+
+    #[test]
+    fn instantiations_are_references() {
+        let python = parse_content("app.py", "class App:\n    pass\n\napp = App()\n");
+        assert!(python.references.contains(&"App".to_string()));
+        let rust = parse_content("src/point.rs", "struct Point { x: i32 }\n\nfn origin() -> Point {\n    Point { x: 0 }\n}\n");
+        assert!(rust.references.contains(&"Point".to_string()));
+        let go = parse_content("point.go", "package geo\n\ntype Point struct{ X int }\n\nvar origin = Point{X: 0}\nvar names = []string{\"a\"}\n");
+        assert_eq!(go.references, vec!["Point".to_string()]);
+    }
+}
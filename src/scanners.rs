@@ -0,0 +1,1609 @@
+use crate::parsing::{Document, Import, Language, Param, ParsingResult, Symbol};
+
+// Lightweight extractors for languages we don't ship a tree-sitter grammar for.
+// They work on statement/line structure only, so they favour recall over precision.
+
+pub fn scan(lang: Language, content: &str) -> Option<ParsingResult> {
+    let (mut symbols, imports, exports) = match lang {
+        Language::Sql => (scan_sql(content), vec![], vec![]),
+        Language::Hcl => {
+            let (symbols, imports) = scan_hcl(content);
+            (symbols, imports, vec![])
+        }
+        Language::Protobuf => {
+            let (symbols, imports) = scan_proto(content);
+            (symbols, imports, vec![])
+        }
+        Language::GraphQl => (scan_graphql(content), vec![], vec![]),
+        Language::Markdown => return Some(scan_markdown(content)),
+        Language::ObjC => {
+            let (symbols, imports) = scan_objc(content);
+            (symbols, imports, vec![])
+        }
+        Language::R => scan_r(content),
+        Language::Julia => scan_julia(content),
+        Language::Erlang => scan_erlang(content),
+        Language::Perl => scan_perl(content),
+        _ => return None,
+    };
+    // Protobuf/GraphQL enum values are collected as params; expose them as members too
+    for sym in symbols.iter_mut().filter(|s| s.kind == "enum") {
+        sym.members = sym.params.iter().map(|p| p.name.clone()).collect();
+    }
+    Some(ParsingResult { language: lang, symbols, imports, exports, ..Default::default() })
+}
+
+fn new_symbol(name: &str, kind: &str, range: (usize, usize), preview: &str) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        range,
+        content_preview: preview.chars().take(120).collect(),
+        ..Default::default()
+    }
+}
+
+// Largest char boundary <= `at`, for capping previews/signatures
+fn floor_char(s: &str, at: usize) -> usize {
+    let mut at = at.min(s.len());
+    while !s.is_char_boundary(at) { at -= 1; }
+    at
+}
+
+// Byte offset each of `content.lines()` starts at. Adding up their lengths would miss the `\r` of
+// CRLF line endings
+fn line_starts(content: &str) -> Vec<usize> {
+    content.split_inclusive('\n')
+        .scan(0, |at, chunk| {
+            let start = *at;
+            *at += chunk.len();
+            Some(start)
+        })
+        .collect()
+}
+
+fn line_of(content: &str, byte: usize) -> usize {
+    content[..byte].matches('\n').count() + 1
+}
+
+// Split on commas that aren't nested inside (), [], {} or <>
+fn split_top_level(s: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut cur = String::new();
+    for c in s.chars() {
+        match c {
+            '(' | '[' | '{' | '<' => { depth += 1; cur.push(c); }
+            ')' | ']' | '}' | '>' => { depth -= 1; cur.push(c); }
+            ',' if depth == 0 => { parts.push(cur.trim().to_string()); cur.clear(); }
+            _ => cur.push(c),
+        }
+    }
+    if !cur.trim().is_empty() { parts.push(cur.trim().to_string()); }
+    parts
+}
+
+// Text between the first `open` at or after `from` and its matching `close`
+fn balanced(s: &str, from: usize, open: char, close: char) -> Option<(usize, usize)> {
+    let start = from + s[from..].find(open)?;
+    let mut depth = 0;
+    for (i, c) in s[start..].char_indices() {
+        if c == open { depth += 1; }
+        if c == close {
+            depth -= 1;
+            if depth == 0 { return Some((start + 1, start + i)); }
+        }
+    }
+    None
+}
+
+// Comment lines directly above `line` (1-based), with the given prefix stripped
+fn comment_block_above(lines: &[&str], line: usize, prefix: &str) -> Option<String> {
+    let mut docs = vec![];
+    let mut i = line.saturating_sub(1);
+    while i > 0 {
+        let l = lines[i - 1].trim();
+        if !l.starts_with(prefix) { break; }
+        docs.push(l.trim_start_matches(prefix).trim().to_string());
+        i -= 1;
+    }
+    docs.reverse();
+    if docs.is_empty() { None } else { Some(docs.join("\n")) }
+}
+
+// ---------------------------------------------------------------- SQL
+//
+// Not tree-sitter-sql: each published SQL grammar follows one dialect, while repos mix Postgres
+// $$-bodies with T-SQL and MySQL BEGIN..END routines that a grammar for another dialect turns into
+// ERROR nodes, right where the procedures are. Symbols only need statement boundaries and the
+// CREATE header, so scripts are split here instead of pulling in a grammar for that
+
+struct SqlStatement {
+    start: usize,
+    end: usize,
+}
+
+// Split a script into statements on `;`, respecting quotes, comments, $$-bodies and BEGIN/END blocks
+fn split_sql_statements(content: &str) -> Vec<SqlStatement> {
+    let bytes = content.as_bytes();
+    let mut out = vec![];
+    let mut start = 0;
+    let mut i = 0;
+    let mut block_depth = 0i32;
+    let mut dollar_tag: Option<String> = None;
+    while i < bytes.len() {
+        let c = bytes[i];
+        if let Some(tag) = &dollar_tag {
+            if bytes[i..].starts_with(tag.as_bytes()) {
+                i += tag.len();
+                dollar_tag = None;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+        match c {
+            b'\'' | b'"' | b'`' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != c { i += 1; }
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' { i += 1; }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < bytes.len() && !(bytes[i] == b'*' && bytes[i + 1] == b'/') { i += 1; }
+                i += 2;
+            }
+            b'$' => {
+                let tag_end = content[i + 1..].find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).map(|e| i + 1 + e);
+                match tag_end {
+                    Some(e) if bytes[e] == b'$' => {
+                        dollar_tag = Some(content[i..=e].to_string());
+                        i = e + 1;
+                    }
+                    _ => i += 1,
+                }
+            }
+            b';' if block_depth <= 0 => {
+                out.push(SqlStatement { start, end: i + 1 });
+                start = i + 1;
+                block_depth = 0;
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() && (i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_')) => {
+                let word_end = content[i..].find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).map(|e| i + e).unwrap_or(bytes.len());
+                let word = content[i..word_end].to_ascii_uppercase();
+                match word.as_str() {
+                    "BEGIN" if block_depth > 0 || opens_routine_body(&content[start..], &content[word_end..]) => block_depth += 1,
+                    "CASE" => block_depth += 1,
+                    "END" => {
+                        let next = content[word_end..].trim_start().split(|ch: char| !ch.is_alphanumeric()).next().unwrap_or("").to_ascii_uppercase();
+                        if !matches!(next.as_str(), "IF" | "LOOP" | "WHILE" | "REPEAT" | "FOR") {
+                            block_depth -= 1;
+                        }
+                    }
+                    _ => {}
+                }
+                i = word_end;
+            }
+            _ => i += 1,
+        }
+    }
+    if !content[start..].trim().is_empty() {
+        out.push(SqlStatement { start, end: content.len() });
+    }
+    out
+}
+
+// Whether a BEGIN opens the body of the CREATE FUNCTION/PROCEDURE/TRIGGER `statement` starts,
+// rather than a transaction (`BEGIN;`, `BEGIN TRANSACTION`, `BEGIN WORK`) wrapping a migration
+fn opens_routine_body(statement: &str, after: &str) -> bool {
+    let after = after.trim_start();
+    let next = after.split(|ch: char| !(ch.is_alphanumeric() || ch == '_')).next().unwrap_or("").to_ascii_uppercase();
+    if after.is_empty() || after.starts_with(';') || matches!(next.as_str(), "TRANSACTION" | "TRAN" | "WORK") {
+        return false;
+    }
+    let words: Vec<String> = statement.split(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+        .filter(|w| !w.is_empty())
+        .map(str::to_ascii_uppercase)
+        .collect();
+    words.iter().any(|w| w == "CREATE") && words.iter().any(|w| matches!(w.as_str(), "FUNCTION" | "PROCEDURE" | "TRIGGER"))
+}
+
+fn scan_sql(content: &str) -> Vec<Symbol> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols = vec![];
+    for stmt in split_sql_statements(content) {
+        // Skip leading comments/whitespace so the statement starts at its keyword
+        let mut offset = stmt.start;
+        loop {
+            let rest = &content[offset..stmt.end];
+            let trimmed = rest.trim_start();
+            offset += rest.len() - trimmed.len();
+            if trimmed.starts_with("--") {
+                offset += trimmed.find('\n').map(|n| n + 1).unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+        let text = &content[offset..stmt.end];
+        let words: Vec<&str> = text.split_whitespace().take(12).collect();
+        if words.first().map(|w| !w.eq_ignore_ascii_case("create")).unwrap_or(true) { continue; }
+
+        // CREATE [OR REPLACE] [TEMP|TEMPORARY|MATERIALIZED|UNIQUE ...] <object> [IF NOT EXISTS] <name>
+        let mut idx = 1;
+        let mut object = None;
+        while idx < words.len() {
+            let w = words[idx].to_ascii_uppercase();
+            match w.as_str() {
+                "OR" | "REPLACE" | "TEMP" | "TEMPORARY" | "MATERIALIZED" | "UNLOGGED" | "DEFINER" | "ALGORITHM" | "SECURITY" => idx += 1,
+                "TABLE" | "VIEW" | "FUNCTION" | "PROCEDURE" | "TRIGGER" => { object = Some(w); idx += 1; break; }
+                _ if w.starts_with("DEFINER=") || w.starts_with("ALGORITHM=") => idx += 1,
+                _ => break,
+            }
+        }
+        let Some(object) = object else { continue };
+        if words.get(idx).map(|w| w.eq_ignore_ascii_case("if")).unwrap_or(false) { idx += 3; }
+        let Some(raw_name) = words.get(idx) else { continue };
+        let name = raw_name.split('(').next().unwrap_or("")
+            .trim_matches(|c| c == '"' || c == '`' || c == '[' || c == ']')
+            .replace(['"', '`', '[', ']'], "");
+        if name.is_empty() { continue; }
+
+        let kind = match object.as_str() {
+            "TABLE" => "table",
+            "VIEW" => "view",
+            "PROCEDURE" => "procedure",
+            "TRIGGER" => "trigger",
+            _ => "function",
+        };
+        let line_start = line_of(content, offset);
+        let line_end = line_of(content, stmt.end.saturating_sub(1).max(offset));
+        let mut sym = new_symbol(&name, kind, (line_start, line_end), lines.get(line_start - 1).copied().unwrap_or(""));
+        sym.docstring = comment_block_above(&lines, line_start, "--");
+
+        let upper = text.to_ascii_uppercase();
+        match kind {
+            "table" => {
+                // Columns become params: "<name> <type> [constraints]"
+                if let Some((s, e)) = balanced(text, 0, '(', ')') {
+                    for col in split_top_level(&text[s..e]) {
+                        let mut parts = col.split_whitespace();
+                        let Some(col_name) = parts.next() else { continue };
+                        let col_upper = col_name.to_ascii_uppercase();
+                        if matches!(col_upper.as_str(), "PRIMARY" | "FOREIGN" | "UNIQUE" | "CONSTRAINT" | "CHECK" | "KEY" | "INDEX") { continue; }
+                        let col_type = parts.next().map(|t| t.to_string());
+                        let default = col.to_ascii_uppercase().find(" DEFAULT ")
+                            .map(|d| col[d + 9..].split_whitespace().next().unwrap_or("").to_string());
+                        sym.params.push(Param {
+                            name: col_name.trim_matches(|c| c == '"' || c == '`').to_string(),
+                            type_annotation: col_type,
+                            default,
+                        });
+                    }
+                }
+                sym.signature = Some(text[..text.find('(').unwrap_or(text.len())].split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+            "function" | "procedure" => {
+                if let Some((s, e)) = balanced(text, 0, '(', ')') {
+                    for p in split_top_level(&text[s..e]) {
+                        let mut parts: Vec<&str> = p.split_whitespace().collect();
+                        if parts.first().map(|w| matches!(w.to_ascii_uppercase().as_str(), "IN" | "OUT" | "INOUT")).unwrap_or(false) {
+                            parts.remove(0);
+                        }
+                        let Some(pname) = parts.first() else { continue };
+                        let default_at = parts.iter().position(|w| w.eq_ignore_ascii_case("default") || *w == "=");
+                        let ty_end = default_at.unwrap_or(parts.len());
+                        sym.params.push(Param {
+                            name: pname.to_string(),
+                            type_annotation: if ty_end > 1 { Some(parts[1..ty_end].join(" ")) } else { None },
+                            default: default_at.map(|d| parts[d + 1..].join(" ")).filter(|d| !d.is_empty()),
+                        });
+                    }
+                }
+                if let Some(r) = upper.find("RETURNS ") {
+                    let ret = text[r + 8..].split_whitespace()
+                        .take_while(|w| !matches!(w.to_ascii_uppercase().as_str(), "AS" | "LANGUAGE" | "BEGIN" | "IMMUTABLE" | "STABLE" | "VOLATILE" | "DETERMINISTIC"))
+                        .collect::<Vec<_>>().join(" ");
+                    if !ret.is_empty() { sym.return_type = Some(ret); }
+                }
+                let body_at = [" AS ", "\nAS", " AS\n", "BEGIN", "$$"].iter()
+                    .filter_map(|m| upper.find(m)).min().unwrap_or(floor_char(text, 300));
+                sym.signature = Some(text[..body_at].split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+            _ => {
+                let header = upper.find(" AS ").or_else(|| upper.find("\nAS")).unwrap_or(floor_char(text, 300));
+                sym.signature = Some(text[..header].split_whitespace().collect::<Vec<_>>().join(" "));
+            }
+        }
+        symbols.push(sym);
+    }
+    symbols
+}
+
+// ---------------------------------------------------------------- Terraform / HCL
+
+// Byte offset of the `}` closing the block whose `{` is at `open`, skipping strings and comments;
+// the end of `content` when it is never closed
+fn closing_brace(content: &str, open: usize, quotes: &[u8]) -> usize {
+    let bytes = content.as_bytes();
+    let mut depth = 0;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            q if quotes.contains(&q) => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != q {
+                    if bytes[i] == b'\\' { i += 1; }
+                    i += 1;
+                }
+            }
+            b'#' => { while i < bytes.len() && bytes[i] != b'\n' { i += 1; } }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => { while i < bytes.len() && bytes[i] != b'\n' { i += 1; } }
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 { return i; }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
+
+// Top-level `key = value` attributes of a block body (nested blocks are skipped)
+fn hcl_attributes(body: &str) -> Vec<(String, String)> {
+    let mut attrs = vec![];
+    let mut depth = 0i32;
+    for line in body.lines() {
+        let t = line.trim();
+        if depth == 0 {
+            if let Some((k, v)) = t.split_once('=') {
+                let k = k.trim();
+                if !k.is_empty() && k.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                    attrs.push((k.to_string(), v.trim().trim_matches('"').to_string()));
+                }
+            }
+        }
+        depth += t.matches(['{', '[', '(']).count() as i32 - t.matches(['}', ']', ')']).count() as i32;
+    }
+    attrs
+}
+
+fn scan_hcl(content: &str) -> (Vec<Symbol>, Vec<Import>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols = vec![];
+    let mut imports = vec![];
+    let starts = line_starts(content);
+    for (idx, line) in lines.iter().enumerate() {
+        let line_start_byte = starts[idx];
+        // Only top-level block headers: `resource "type" "name" {`, `module "name" {`, ...
+        if line.starts_with([' ', '\t']) { continue; }
+        let Some(brace) = line.find('{') else { continue };
+        let header = &line[..brace];
+        let mut words = header.split_whitespace();
+        let Some(block_type) = words.next() else { continue };
+        let labels: Vec<String> = words.map(|w| w.trim_matches('"').to_string()).collect();
+        let (name, kind) = match (block_type, labels.as_slice()) {
+            ("resource", [ty, name]) => (format!("{}.{}", ty, name), "resource"),
+            ("data", [ty, name]) => (format!("data.{}.{}", ty, name), "data"),
+            ("module", [name]) => (format!("module.{}", name), "module"),
+            ("variable", [name]) => (format!("var.{}", name), "variable"),
+            ("output", [name]) => (name.clone(), "output"),
+            ("provider", [name]) => (name.clone(), "provider"),
+            _ => continue,
+        };
+
+        let open = line_start_byte + brace;
+        let close = closing_brace(content, open, b"\"");
+        let body = content.get(open + 1..close).unwrap_or("");
+        let attrs = hcl_attributes(body);
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone());
+
+        let line_start = idx + 1;
+        let mut sym = new_symbol(&name, kind, (line_start, line_of(content, close).min(lines.len())), line);
+        sym.signature = Some(header.trim().to_string());
+        sym.docstring = attr("description").or_else(|| {
+            comment_block_above(&lines, line_start, "#").or_else(|| comment_block_above(&lines, line_start, "//"))
+        });
+        match kind {
+            "variable" => {
+                sym.return_type = attr("type");
+                sym.params.push(Param { name: labels[0].clone(), type_annotation: attr("type"), default: attr("default") });
+            }
+            "module" => {
+                if let Some(src) = attr("source") {
+                    imports.push(Import {
+                        raw: format!("module \"{}\" {{ source = \"{}\" }}", labels[0], src),
+                        source: Some(src),
+                        names: vec![labels[0].clone()],
+                    });
+                }
+                // Module inputs are the block's arguments
+                for (k, v) in &attrs {
+                    if matches!(k.as_str(), "source" | "version" | "providers" | "count" | "for_each" | "depends_on") { continue; }
+                    let default = if v == "{" || v == "[" { None } else { Some(v.clone()) };
+                    sym.params.push(Param { name: k.clone(), type_annotation: None, default });
+                }
+            }
+            _ => {}
+        }
+        symbols.push(sym);
+    }
+    (symbols, imports)
+}
+
+// ---------------------------------------------------------------- Protobuf
+
+// Drop a trailing `// ...` comment, leaving string literals alone
+fn strip_line_comment(line: &str) -> &str {
+    let mut in_str = false;
+    let bytes = line.as_bytes();
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'"' => in_str = !in_str,
+            b'/' if !in_str && bytes.get(i + 1) == Some(&b'/') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn scan_proto(content: &str) -> (Vec<Symbol>, Vec<Import>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut imports = vec![];
+    // (symbol index, brace depth at open) for messages/enums/services currently open
+    let mut stack: Vec<(Option<usize>, i32)> = vec![];
+    let mut depth = 0i32;
+    let mut pending = String::new();
+    let mut pending_line = 0;
+
+    for (idx, raw_line) in lines.iter().enumerate() {
+        let line = strip_line_comment(raw_line).trim();
+        if line.is_empty() { continue; }
+        // rpc definitions may span several lines; buffer until `;` or `{`
+        if pending.is_empty() { pending_line = idx + 1; }
+        pending.push_str(line);
+        pending.push(' ');
+        let starts_rpc = pending.trim_start().starts_with("rpc ");
+        if starts_rpc && !line.contains(';') && !line.contains('{') { continue; }
+        let stmt = std::mem::take(&mut pending);
+        let stmt = stmt.trim();
+        let line_no = pending_line;
+
+        let parent = stack.iter().rev().find_map(|(i, _)| *i);
+        let parent_name = parent.map(|i| symbols[i].name.clone());
+        let parent_kind = parent.map(|i| symbols[i].kind.clone()).unwrap_or_default();
+        let mut words = stmt.split_whitespace();
+        let first = words.next().unwrap_or("");
+        let opened = stmt.matches('{').count() as i32;
+        let closed = stmt.matches('}').count() as i32;
+
+        match first {
+            "import" => {
+                let path = stmt.split('"').nth(1).unwrap_or("").to_string();
+                if !path.is_empty() {
+                    imports.push(Import { raw: stmt.to_string(), source: Some(path.clone()), names: vec![path] });
+                }
+            }
+            "message" | "enum" | "service" => {
+                let name = words.next().unwrap_or("").trim_end_matches('{').to_string();
+                let kind = match first { "message" => "message", "enum" => "enum", _ => "service" };
+                let mut sym = new_symbol(&name, kind, (line_no, line_no), raw_line);
+                sym.signature = Some(format!("{} {}", first, name));
+                sym.docstring = comment_block_above(&lines, line_no, "//");
+                sym.parent_class = parent_name;
+                symbols.push(sym);
+                stack.push((Some(symbols.len() - 1), depth));
+            }
+            "rpc" => {
+                // rpc Name(stream Req) returns (stream Resp) [{ options }];
+                let name = stmt[3..].trim_start().split(|c: char| c == '(' || c.is_whitespace()).next().unwrap_or("").to_string();
+                let mut groups = vec![];
+                let mut from = 0;
+                while let Some((s, e)) = balanced(stmt, from, '(', ')') {
+                    groups.push(stmt[s..e].trim().to_string());
+                    from = e + 1;
+                }
+                let mut sym = new_symbol(&name, "method", (line_no, idx + 1), raw_line);
+                let sig_end = stmt.find('{').or_else(|| stmt.find(';')).unwrap_or(stmt.len());
+                sym.signature = Some(stmt[..sig_end].trim().to_string());
+                sym.docstring = comment_block_above(&lines, line_no, "//");
+                sym.parent_class = parent_name;
+                if let Some(req) = groups.first() {
+                    sym.params.push(Param { name: "request".into(), type_annotation: Some(req.clone()), default: None });
+                }
+                sym.return_type = groups.get(1).cloned();
+                symbols.push(sym);
+                if opened > closed { stack.push((Some(symbols.len() - 1), depth)); }
+            }
+            "oneof" => stack.push((None, depth)),
+            "}" | "};" => {}
+            _ if parent_kind == "message" || parent_kind == "enum" => {
+                // Field: `[repeated|optional] type name = N [opts];` or enum value: `NAME = N;`
+                if let Some((decl, num)) = stmt.trim_end_matches(';').split_once('=') {
+                    let parts: Vec<&str> = decl.split_whitespace().collect();
+                    if let (Some(field), Some(i)) = (parts.last(), parent) {
+                        let ty = if parts.len() > 1 { Some(parts[..parts.len() - 1].join(" ")) } else { None };
+                        let number = num.split_whitespace().next().unwrap_or("").to_string();
+                        symbols[i].params.push(Param { name: field.to_string(), type_annotation: ty, default: Some(number) });
+                    }
+                }
+                if opened > closed { stack.push((None, depth)); }
+            }
+            _ => {
+                if opened > closed { stack.push((None, depth)); }
+            }
+        }
+
+        depth += opened - closed;
+        while let Some(&(i, d)) = stack.last() {
+            if depth > d { break; }
+            if let Some(i) = i { symbols[i].range.1 = idx + 1; }
+            stack.pop();
+        }
+    }
+    (symbols, imports)
+}
+
+// ---------------------------------------------------------------- GraphQL
+
+struct GraphQlField {
+    name: String,
+    args: Vec<Param>,
+    ty: Option<String>,
+    default: Option<String>,
+}
+
+// `name(args): Type = default @directive`
+fn parse_graphql_field(text: &str) -> Option<GraphQlField> {
+    let text = text.split(" @").next().unwrap_or(text).trim();
+    let name_end = text.find(['(', ':']).unwrap_or(text.len());
+    let name = text[..name_end].trim().to_string();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') { return None; }
+    let mut args = vec![];
+    let mut rest = &text[name_end..];
+    if rest.starts_with('(') {
+        let (s, e) = balanced(text, name_end, '(', ')')?;
+        for arg in split_top_level(&text[s..e].replace('\n', ",")) {
+            if let Some(f) = parse_graphql_field(&arg) {
+                args.push(Param { name: f.name, type_annotation: f.ty, default: f.default });
+            }
+        }
+        rest = &text[e + 1..];
+    }
+    let rest = rest.trim_start().trim_start_matches(':').trim();
+    let (ty, default) = match rest.split_once('=') {
+        Some((t, d)) => (t.trim().to_string(), Some(d.trim().to_string())),
+        None => (rest.to_string(), None),
+    };
+    Some(GraphQlField { name, args, ty: if ty.is_empty() { None } else { Some(ty) }, default })
+}
+
+fn scan_graphql(content: &str) -> Vec<Symbol> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut current: Option<(usize, String)> = None; // (symbol index, root operation kind or "")
+    let mut description: Vec<String> = vec![];
+    let mut in_block_string = false;
+    let mut pending = String::new();
+    let mut pending_line = 0;
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let line = raw.trim();
+
+        // Descriptions: """block""", "single line" or # comments preceding a definition
+        if in_block_string {
+            let (text, done) = match line.find("\"\"\"") { Some(e) => (&line[..e], true), None => (line, false) };
+            if !text.is_empty() { description.push(text.to_string()); }
+            in_block_string = !done;
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("\"\"\"") {
+            match rest.find("\"\"\"") {
+                Some(e) => description.push(rest[..e].trim().to_string()),
+                None => {
+                    if !rest.is_empty() { description.push(rest.to_string()); }
+                    in_block_string = true;
+                }
+            }
+            continue;
+        }
+        if line.starts_with('"') && line.ends_with('"') && line.len() > 1 {
+            description.push(line.trim_matches('"').to_string());
+            continue;
+        }
+        let line = line.split(" #").next().unwrap_or(line).trim();
+        if let Some(c) = line.strip_prefix('#') {
+            description.push(c.trim().to_string());
+            continue;
+        }
+        if line.is_empty() { continue; }
+        let doc = if description.is_empty() { None } else { Some(std::mem::take(&mut description).join("\n")) };
+
+        match &current {
+            None => {
+                let mut words = line.split_whitespace().peekable();
+                if words.peek() == Some(&"extend") { words.next(); }
+                let Some(keyword) = words.next() else { continue };
+                let kind = match keyword {
+                    "type" => "type",
+                    "interface" => "interface",
+                    "input" => "input",
+                    "enum" => "enum",
+                    "union" => "union",
+                    "scalar" => "scalar",
+                    "directive" => "directive",
+                    _ => continue,
+                };
+                let name = words.next().unwrap_or("").trim_start_matches('@')
+                    .split(['(', '{']).next().unwrap_or("").to_string();
+                if name.is_empty() { continue; }
+                let mut sym = new_symbol(&name, kind, (idx + 1, idx + 1), raw);
+                sym.docstring = doc;
+                sym.signature = Some(line.trim_end_matches('{').trim().to_string());
+                if let Some(imp) = line.split(" implements ").nth(1) {
+                    sym.bases = imp.trim_end_matches('{').split(['&', ',']).map(|b| b.trim().to_string()).filter(|b| !b.is_empty()).collect();
+                }
+                if kind == "union" {
+                    if let Some((_, members)) = line.split_once('=') {
+                        sym.bases = members.split('|').map(|b| b.trim().to_string()).filter(|b| !b.is_empty()).collect();
+                    }
+                }
+                // Single-line bodies, e.g. `enum Role { ADMIN USER }`
+                if let (Some(open), Some(close)) = (line.find('{'), line.rfind('}')) {
+                    for value in line[open + 1..close].split([' ', ',']).filter(|v| !v.is_empty()) {
+                        sym.params.push(Param { name: value.to_string(), type_annotation: None, default: None });
+                    }
+                }
+                symbols.push(sym);
+                if line.ends_with('{') {
+                    let root = match name.as_str() {
+                        "Query" => "query",
+                        "Mutation" => "mutation",
+                        "Subscription" => "subscription",
+                        _ => "",
+                    };
+                    current = Some((symbols.len() - 1, root.to_string()));
+                }
+            }
+            Some((type_idx, root)) => {
+                let (type_idx, root) = (*type_idx, root.clone());
+                if line.starts_with('}') && pending.is_empty() {
+                    symbols[type_idx].range.1 = idx + 1;
+                    current = None;
+                    continue;
+                }
+                // Field arguments can span lines; buffer until the parens balance
+                if pending.is_empty() { pending_line = idx + 1; }
+                pending.push_str(line);
+                pending.push('\n');
+                if pending.matches('(').count() > pending.matches(')').count() { continue; }
+                let field_text = std::mem::take(&mut pending);
+                let Some(field) = parse_graphql_field(field_text.trim()) else { continue };
+                if root.is_empty() {
+                    symbols[type_idx].params.push(Param { name: field.name, type_annotation: field.ty, default: field.default });
+                } else {
+                    let parent = symbols[type_idx].name.clone();
+                    let mut sym = new_symbol(&field.name, &root, (pending_line, idx + 1), lines[pending_line - 1]);
+                    sym.signature = Some(field_text.split_whitespace().collect::<Vec<_>>().join(" "));
+                    sym.docstring = doc;
+                    sym.params = field.args;
+                    sym.return_type = field.ty;
+                    sym.parent_class = Some(parent);
+                    symbols.push(sym);
+                }
+            }
+        }
+    }
+    symbols
+}
+
+// ---------------------------------------------------------------- Markdown
+
+fn scan_markdown(content: &str) -> ParsingResult {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut doc = Document::default();
+    // (level, symbol index) of the headings enclosing the current line
+    let mut open: Vec<(usize, usize)> = vec![];
+    let mut fence: Option<&str> = None;
+    let mut in_front_matter = false;
+
+    for (idx, raw) in lines.iter().enumerate() {
+        let line = raw.trim_end();
+        if idx == 0 && line == "---" { in_front_matter = true; continue; }
+        if in_front_matter {
+            if line == "---" { in_front_matter = false; }
+            continue;
+        }
+        let trimmed = line.trim_start();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            } else if let Some(&(_, i)) = open.last() {
+                symbols[i].mentions.extend(fenced_names(trimmed));
+            }
+            continue;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = &trimmed[..3];
+            let lang = trimmed[3..].trim().split([' ', '{', ',']).next().unwrap_or("").to_lowercase();
+            *doc.code_fences.entry(if lang.is_empty() { "text".to_string() } else { lang }).or_default() += 1;
+            fence = Some(marker);
+            continue;
+        }
+
+        // ATX headings (`## Title`) and setext headings (`Title` underlined with === / ---)
+        let heading = if trimmed.starts_with('#') {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            let text = trimmed[level..].trim().trim_end_matches('#').trim();
+            if level <= 6 && trimmed[level..].starts_with(' ') && !text.is_empty() { Some((level, text.to_string())) } else { None }
+        } else {
+            match lines.get(idx + 1).map(|l| l.trim()) {
+                Some(next) if !trimmed.is_empty() && !next.is_empty() && next.chars().all(|c| c == '=') => Some((1, trimmed.to_string())),
+                Some(next) if !trimmed.is_empty() && next.len() > 1 && next.chars().all(|c| c == '-') => Some((2, trimmed.to_string())),
+                _ => None,
+            }
+        };
+
+        match heading {
+            Some((level, text)) => {
+                while let Some(&(l, i)) = open.last() {
+                    if l < level { break; }
+                    symbols[i].range.1 = idx;
+                    open.pop();
+                }
+                let mut sym = new_symbol(&text, "section", (idx + 1, idx + 1), line);
+                sym.signature = Some(format!("{} {}", "#".repeat(level), text));
+                sym.parent_class = open.last().map(|&(_, i)| symbols[i].name.clone());
+                if doc.title.is_none() && level == 1 { doc.title = Some(text); }
+                doc.heading_count += 1;
+                symbols.push(sym);
+                open.push((level, symbols.len() - 1));
+            }
+            None => {
+                let is_underline = !trimmed.is_empty() && trimmed.chars().all(|c| c == '=' || c == '-');
+                if is_underline { continue; }
+                doc.word_count += trimmed.split_whitespace().count();
+                // First paragraph under a heading doubles as its docstring
+                if let Some(&(_, i)) = open.last() {
+                    if !trimmed.is_empty() && symbols[i].range.0 + 4 > idx && symbols[i].docstring.is_none() {
+                        symbols[i].docstring = Some(trimmed.to_string());
+                    }
+                    // `code spans` are the odd pieces between backticks
+                    let spans = trimmed.split('`').skip(1).step_by(2);
+                    symbols[i].mentions.extend(spans.flat_map(identifiers).map(|(name, _)| name.to_string()));
+                }
+            }
+        }
+    }
+    for (_, i) in open {
+        symbols[i].range.1 = lines.len();
+    }
+    for sym in &mut symbols {
+        sym.mentions.sort();
+        sym.mentions.dedup();
+    }
+    if doc.title.is_none() {
+        doc.title = symbols.first().map(|s| s.name.clone());
+    }
+    ParsingResult { language: Language::Markdown, symbols, document: Some(doc), ..Default::default() }
+}
+
+// Names a line of fenced code refers to: what it calls and the capitalised (type) names. Other
+// identifiers in examples are mostly local variables
+fn fenced_names(line: &str) -> Vec<String> {
+    identifiers(line)
+        .filter(|(name, rest)| rest.trim_start().starts_with('(') || name.starts_with(|c: char| c.is_ascii_uppercase()))
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+// Identifiers of three or more characters in `text`, each with the text after it
+fn identifiers(text: &str) -> impl Iterator<Item = (&str, &str)> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        loop {
+            let start = rest.find(|c: char| is_ident(c))?;
+            let end = rest[start..].find(|c: char| !is_ident(c)).map_or(rest.len(), |end| start + end);
+            let (name, after) = (&rest[start..end], &rest[end..]);
+            rest = after;
+            if name.len() >= 3 && !name.starts_with(|c: char| c.is_ascii_digit()) {
+                return Some((name, after));
+            }
+        }
+    })
+}
+
+// ---------------------------------------------------------------- Objective-C
+
+// Comment directly above `line` as `//` lines or a `/** ... */` block
+fn c_style_doc_above(lines: &[&str], line: usize) -> Option<String> {
+    if let Some(doc) = comment_block_above(lines, line, "//") {
+        return Some(doc.trim_start_matches('/').trim().to_string());
+    }
+    let prev = lines.get(line.checked_sub(2)?)?.trim();
+    if !prev.ends_with("*/") { return None; }
+    let mut i = line - 2;
+    let mut block = vec![];
+    loop {
+        let l = lines[i].trim();
+        block.push(l.trim_start_matches("/**").trim_start_matches("/*").trim_end_matches("*/").trim_start_matches('*').trim().to_string());
+        if l.starts_with("/*") || i == 0 { break; }
+        i -= 1;
+    }
+    block.reverse();
+    let doc = block.into_iter().filter(|l| !l.is_empty()).collect::<Vec<_>>().join("\n");
+    if doc.is_empty() { None } else { Some(doc) }
+}
+
+// `- (NSString *)greet:(NSString *)name times:(int)n` -> (selector, params, return type)
+fn parse_objc_method(decl: &str) -> (String, Vec<Param>, Option<String>) {
+    let mut rest = decl[1..].trim_start();
+    let mut return_type = None;
+    if rest.starts_with('(') {
+        if let Some((s, e)) = balanced(rest, 0, '(', ')') {
+            return_type = Some(rest[s..e].trim().to_string());
+            rest = rest[e + 1..].trim_start();
+        }
+    }
+    let mut selector = String::new();
+    let mut params = vec![];
+    loop {
+        let label_end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        let label = &rest[..label_end];
+        rest = rest[label_end..].trim_start();
+        if !rest.starts_with(':') {
+            if params.is_empty() { selector.push_str(label); }
+            break;
+        }
+        selector.push_str(label);
+        selector.push(':');
+        rest = rest[1..].trim_start();
+        let mut ty = None;
+        if rest.starts_with('(') {
+            if let Some((s, e)) = balanced(rest, 0, '(', ')') {
+                ty = Some(rest[s..e].trim().to_string());
+                rest = rest[e + 1..].trim_start();
+            }
+        }
+        let name_end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        params.push(Param { name: rest[..name_end].to_string(), type_annotation: ty, default: None });
+        rest = rest[name_end..].trim_start();
+        if rest.is_empty() || rest.starts_with(['{', ';']) { break; }
+    }
+    (selector, params, return_type)
+}
+
+fn scan_objc(content: &str) -> (Vec<Symbol>, Vec<Import>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut imports = vec![];
+    let mut current: Option<(usize, bool)> = None; // (class symbol index, inside @implementation)
+    let mut method: Option<(usize, i32)> = None; // (symbol index, brace depth) while inside a method body
+    let mut depth = 0i32;
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let raw = lines[idx];
+        let line = raw.trim();
+        let line_no = idx + 1;
+
+        if line.starts_with("#import") || line.starts_with("#include") || line.starts_with("@import") {
+            let target = line.split(['"', '<', '>']).nth(1)
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| line.trim_start_matches("@import").trim().trim_end_matches(';').to_string());
+            imports.push(Import { raw: line.to_string(), source: Some(target.clone()), names: vec![target] });
+        } else if line.starts_with("@interface") || line.starts_with("@implementation") || line.starts_with("@protocol") {
+            let is_impl = line.starts_with("@implementation");
+            let header = line.split('{').next().unwrap_or(line);
+            let mut words = header.split_whitespace().skip(1);
+            let name = words.next().unwrap_or("").split(['(', '<', ':']).next().unwrap_or("").to_string();
+            // Forward declarations (`@protocol Foo;`) carry no body
+            if !name.is_empty() && !line.ends_with(';') {
+                let existing = symbols.iter().position(|s| s.kind == "class" && s.name == name);
+                let sym_idx = match existing {
+                    Some(i) => i,
+                    None => {
+                        let mut sym = new_symbol(&name, "class", (line_no, line_no), raw);
+                        sym.signature = Some(header.trim().to_string());
+                        sym.docstring = c_style_doc_above(&lines, line_no);
+                        if let Some((_, sup)) = header.split_once(':') {
+                            if let Some(base) = sup.split_whitespace().next() {
+                                sym.bases.push(base.split('<').next().unwrap_or(base).to_string());
+                            }
+                        }
+                        if let Some((s, e)) = header.find('<').and_then(|_| balanced(header, 0, '<', '>')) {
+                            sym.bases.extend(header[s..e].split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()));
+                        }
+                        symbols.push(sym);
+                        symbols.len() - 1
+                    }
+                };
+                current = Some((sym_idx, is_impl));
+            }
+        } else if line.starts_with("@end") {
+            if let Some((i, _)) = current.take() {
+                symbols[i].range.1 = symbols[i].range.1.max(line_no);
+            }
+        } else if method.is_none() && (line.starts_with("- ") || line.starts_with("-(") || line.starts_with("+ ") || line.starts_with("+(")) {
+            if let Some((class_idx, is_impl)) = current {
+                // Declarations can wrap; gather until `;` or `{`
+                let mut decl = line.to_string();
+                let mut end = idx;
+                while !decl.contains(';') && !decl.contains('{') && end + 1 < lines.len() {
+                    end += 1;
+                    decl.push(' ');
+                    decl.push_str(lines[end].trim());
+                }
+                let header = decl.split(['{', ';']).next().unwrap_or(&decl).trim().to_string();
+                let (selector, params, return_type) = parse_objc_method(&header);
+                let class_name = symbols[class_idx].name.clone();
+                let existing = symbols.iter().position(|s| s.kind == "method" && s.name == selector && s.parent_class.as_deref() == Some(&class_name));
+                let sym_idx = match existing {
+                    // Implementation of a method already declared in the @interface
+                    Some(i) => i,
+                    None => {
+                        let mut sym = new_symbol(&selector, "method", (line_no, end + 1), raw);
+                        sym.signature = Some(header.clone());
+                        sym.docstring = c_style_doc_above(&lines, line_no);
+                        sym.params = params;
+                        sym.return_type = return_type;
+                        sym.parent_class = Some(class_name);
+                        if header.starts_with('+') { sym.decorators.push("class".to_string()); }
+                        symbols.push(sym);
+                        symbols.len() - 1
+                    }
+                };
+                if is_impl && decl.contains('{') {
+                    symbols[sym_idx].range = (line_no, end + 1);
+                    method = Some((sym_idx, depth));
+                }
+                for l in &lines[idx..=end] {
+                    depth += l.matches('{').count() as i32 - l.matches('}').count() as i32;
+                }
+                if let Some((m, d)) = method {
+                    if depth <= d { symbols[m].range.1 = end + 1; method = None; }
+                }
+                idx = end + 1;
+                continue;
+            }
+        }
+
+        depth += raw.matches('{').count() as i32 - raw.matches('}').count() as i32;
+        if let Some((m, d)) = method {
+            if depth <= d {
+                symbols[m].range.1 = line_no;
+                method = None;
+            }
+        }
+        idx += 1;
+    }
+    (symbols, imports)
+}
+
+// ---------------------------------------------------------------- R
+
+const R_KEYWORDS: &[&str] = &["if", "else", "for", "while", "repeat", "function", "return", "switch", "next", "break", "c", "list"];
+
+// Identifiers immediately followed by `(`, i.e. function calls (`pkg::fn(` counts as `fn`)
+fn called_identifiers(body: &str, skip: &[&str]) -> Vec<String> {
+    let mut calls: Vec<String> = vec![];
+    let bytes = body.as_bytes();
+    for (i, _) in body.match_indices('(') {
+        let mut start = i;
+        while start > 0 && (bytes[start - 1].is_ascii_alphanumeric() || bytes[start - 1] == b'_' || bytes[start - 1] == b'.') {
+            start -= 1;
+        }
+        let name = &body[start..i];
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit() || c == '.') || skip.contains(&name) { continue; }
+        if !calls.iter().any(|c| c == name) { calls.push(name.to_string()); }
+    }
+    calls
+}
+
+fn scan_r(content: &str) -> (Vec<Symbol>, Vec<Import>, Vec<String>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols = vec![];
+    let mut imports = vec![];
+    let mut exports = vec![];
+    let starts = line_starts(content);
+    for (idx, raw) in lines.iter().enumerate() {
+        let line_start_byte = starts[idx];
+        let line = raw.trim();
+        if line.starts_with('#') { continue; }
+
+        // library(pkg), require(pkg), requireNamespace("pkg"), source("file.R")
+        for call in ["library(", "require(", "requireNamespace(", "source("] {
+            if let Some(pos) = line.find(call) {
+                if pos > 0 && line.as_bytes()[pos - 1].is_ascii_alphanumeric() { continue; }
+                let arg = line[pos + call.len()..].split([')', ',']).next().unwrap_or("").trim().trim_matches(|c| c == '"' || c == '\'');
+                if !arg.is_empty() {
+                    imports.push(Import { raw: line.to_string(), source: Some(arg.to_string()), names: vec![arg.to_string()] });
+                }
+            }
+        }
+
+        // name <- function(args) / name = function(args) / name <<- function(args)
+        let Some(fn_pos) = line.find("function(").or_else(|| line.find("function (")) else { continue };
+        let lhs = line[..fn_pos].trim_end();
+        let Some(lhs) = lhs.strip_suffix("<<-").or_else(|| lhs.strip_suffix("<-")).or_else(|| lhs.strip_suffix('=')) else { continue };
+        let name = lhs.trim().trim_matches('`');
+        if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '(' || c == '$') { continue; }
+
+        let abs_fn = line_start_byte + (raw.len() - raw.trim_start().len()) + fn_pos;
+        let Some((ps, pe)) = balanced(content, abs_fn, '(', ')') else { continue };
+        let params = split_top_level(&content[ps..pe]).into_iter().map(|p| match p.split_once('=') {
+            Some((n, d)) => Param { name: n.trim().to_string(), type_annotation: None, default: Some(d.trim().to_string()) },
+            None => Param { name: p.trim().to_string(), type_annotation: None, default: None },
+        }).collect();
+        let after = &content[pe + 1..];
+        let body_start = after.trim_start();
+        let (end_byte, body) = if body_start.starts_with('{') {
+            let open = pe + 1 + (after.len() - body_start.len());
+            let close = closing_brace(content, open, b"\"'");
+            (close, &content[open..(close + 1).min(content.len())])
+        } else {
+            let end = pe + 1 + after.find('\n').unwrap_or(after.len());
+            (end, &content[pe + 1..end])
+        };
+
+        // roxygen2 block (#') above the definition
+        let docstring = comment_block_above(&lines, idx + 1, "#'");
+        let mut sym = new_symbol(name, "function", (idx + 1, line_of(content, end_byte).min(lines.len())), raw);
+        sym.signature = Some(format!("{} <- function({})", name, &content[ps..pe].split_whitespace().collect::<Vec<_>>().join(" ")));
+        sym.params = params;
+        sym.calls = called_identifiers(body, R_KEYWORDS);
+        if let Some(doc) = &docstring {
+            if doc.lines().any(|l| l.trim_start().starts_with("@export")) {
+                sym.visibility = Some("export".to_string());
+                exports.push(name.to_string());
+            }
+            if let Some(ret) = doc.lines().find_map(|l| l.trim_start().strip_prefix("@return")) {
+                sym.return_type = Some(ret.trim().to_string());
+            }
+        }
+        sym.docstring = docstring;
+        symbols.push(sym);
+    }
+    (symbols, imports, exports)
+}
+
+// ---------------------------------------------------------------- Julia
+
+const JULIA_BLOCK_OPENERS: &[&str] = &["function", "macro", "struct", "module", "baremodule", "if", "for", "while", "begin", "let", "quote", "try", "do"];
+const JULIA_KEYWORDS: &[&str] = &["if", "elseif", "for", "while", "function", "return", "macro", "struct", "println"];
+
+// Words outside strings, comments and brackets (where `end` means "last index" rather than a block end)
+fn julia_block_words(line: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let bytes = line.as_bytes();
+    let mut i = 0;
+    let mut brackets = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => break,
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' { i += 1; }
+                    i += 1;
+                }
+            }
+            b'(' | b'[' | b'{' => brackets += 1,
+            b')' | b']' | b'}' => brackets -= 1,
+            c if (c.is_ascii_alphabetic() || c == b'_') && (i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_' || bytes[i - 1] == b'.' || bytes[i - 1] == b'@' || bytes[i - 1] == b':')) => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'!') { i += 1; }
+                if brackets == 0 { words.push(&line[start..i]); }
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    words
+}
+
+// `x::Int = 1` / `y=2` / `args...` -> Param
+fn julia_param(p: &str) -> Param {
+    let (decl, default) = match p.split_once('=') {
+        Some((d, v)) => (d.trim(), Some(v.trim().to_string())),
+        None => (p.trim(), None),
+    };
+    let (name, ty) = match decl.split_once("::") {
+        Some((n, t)) => (n.trim(), Some(t.trim().to_string())),
+        None => (decl, None),
+    };
+    Param { name: name.to_string(), type_annotation: ty, default }
+}
+
+fn scan_julia(content: &str) -> (Vec<Symbol>, Vec<Import>, Vec<String>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut imports = vec![];
+    let mut exports = vec![];
+    // Open blocks: symbol index for definitions we emitted, None for control flow
+    let mut stack: Vec<Option<usize>> = vec![];
+    let mut pending_doc: Option<String> = None;
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let raw = lines[idx];
+        let line = raw.trim();
+        let line_no = idx + 1;
+        idx += 1;
+
+        // """docstring""" or "docstring" immediately before a definition
+        if let Some(rest) = line.strip_prefix("\"\"\"") {
+            let mut doc = vec![];
+            match rest.find("\"\"\"") {
+                Some(e) => doc.push(rest[..e].to_string()),
+                None => {
+                    if !rest.is_empty() { doc.push(rest.to_string()); }
+                    while idx < lines.len() {
+                        let l = lines[idx].trim();
+                        idx += 1;
+                        if let Some(e) = l.find("\"\"\"") {
+                            if e > 0 { doc.push(l[..e].to_string()); }
+                            break;
+                        }
+                        doc.push(l.to_string());
+                    }
+                }
+            }
+            pending_doc = Some(doc.join("\n").trim().to_string());
+            continue;
+        }
+        if line.len() > 1 && line.starts_with('"') && line.ends_with('"') && stack.iter().all(|s| s.is_none() || symbols[s.unwrap()].kind == "module") {
+            pending_doc = Some(line.trim_matches('"').to_string());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') { continue; }
+
+        let words = julia_block_words(line);
+        let first = words.first().copied().unwrap_or("");
+        let doc = pending_doc.take();
+
+        match first {
+            "using" | "import" => {
+                for module in line[first.len()..].split(':').next().unwrap_or("").split(',') {
+                    let module = module.trim();
+                    if !module.is_empty() {
+                        imports.push(Import { raw: line.to_string(), source: Some(module.to_string()), names: vec![module.to_string()] });
+                    }
+                }
+            }
+            "export" => exports.extend(line[6..].split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty())),
+            _ => {}
+        }
+        if let Some(path) = line.strip_prefix("include(") {
+            let path = path.trim_end_matches(')').trim_matches('"').to_string();
+            imports.push(Import { raw: line.to_string(), source: Some(path.clone()), names: vec![path] });
+        }
+
+        // Definitions
+        let def = match first {
+            "function" | "macro" => {
+                let header = line[first.len()..].trim();
+                let name = header.split(['(', '{', ' ']).next().unwrap_or("").to_string();
+                let mut sym = new_symbol(&name, if first == "macro" { "macro" } else { "function" }, (line_no, line_no), raw);
+                if let Some((s, e)) = balanced(header, 0, '(', ')') {
+                    sym.params = split_top_level(&header[s..e].replace(';', ",")).iter().map(|p| julia_param(p)).collect();
+                    sym.return_type = header[e + 1..].trim().strip_prefix("::")
+                        .map(|r| r.split_whitespace().next().unwrap_or("").to_string());
+                }
+                sym.signature = Some(line.to_string());
+                Some(sym)
+            }
+            "struct" | "mutable" if words.contains(&"struct") => {
+                let header = line.split("struct").nth(1).unwrap_or("").trim();
+                let (name, base) = match header.split_once("<:") {
+                    Some((n, b)) => (n.trim(), Some(b.trim())),
+                    None => (header.split_whitespace().next().unwrap_or(""), None),
+                };
+                let mut sym = new_symbol(name.split('{').next().unwrap_or(name), "class", (line_no, line_no), raw);
+                sym.bases.extend(base.map(|b| b.split_whitespace().next().unwrap_or(b).to_string()));
+                sym.signature = Some(line.to_string());
+                Some(sym)
+            }
+            "abstract" | "primitive" if words.get(1) == Some(&"type") => {
+                let name = words.get(2).copied().unwrap_or("");
+                let mut sym = new_symbol(name, "class", (line_no, line_no), raw);
+                if let Some((_, base)) = line.split_once("<:") {
+                    sym.bases.push(base.split_whitespace().next().unwrap_or("").to_string());
+                }
+                sym.signature = Some(line.trim_end_matches("end").trim().to_string());
+                sym.docstring = doc.clone();
+                symbols.push(sym);
+                None
+            }
+            "module" | "baremodule" => {
+                let mut sym = new_symbol(words.get(1).copied().unwrap_or(""), "module", (line_no, line_no), raw);
+                sym.signature = Some(line.to_string());
+                Some(sym)
+            }
+            _ => {
+                // Short form: `name(args) = expr`
+                let ident_end = line.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '!')).unwrap_or(line.len());
+                let in_definition_scope = stack.iter().all(|s| s.map(|i| symbols[i].kind == "module").unwrap_or(false));
+                match (ident_end > 0 && in_definition_scope && line[ident_end..].starts_with('('), balanced(line, ident_end, '(', ')')) {
+                    (true, Some((s, e))) => {
+                        let rest = line[e + 1..].trim_start();
+                        let rest = rest.strip_prefix("::").map(|r| r.trim_start_matches(|c: char| !c.is_whitespace() && c != '=').trim_start()).unwrap_or(rest);
+                        let rest = rest.strip_prefix("where").map(|r| r.trim_start_matches(|c: char| c != '=')).unwrap_or(rest);
+                        if rest.starts_with('=') && !rest.starts_with("==") {
+                            let mut sym = new_symbol(&line[..ident_end], "function", (line_no, line_no), raw);
+                            sym.params = split_top_level(&line[s..e].replace(';', ",")).iter().map(|p| julia_param(p)).collect();
+                            sym.signature = Some(line[..line.find('=').unwrap_or(line.len())].trim().to_string());
+                            sym.calls = called_identifiers(&line[e + 1..], JULIA_KEYWORDS);
+                            sym.docstring = doc.clone();
+                            symbols.push(sym);
+                        }
+                        None
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        let mut pushed = false;
+        if let Some(mut sym) = def {
+            if sym.name.is_empty() { continue; }
+            sym.docstring = doc;
+            symbols.push(sym);
+            stack.push(Some(symbols.len() - 1));
+            pushed = true;
+        }
+
+        // Struct bodies hold fields; function bodies hold calls
+        if let Some(Some(top)) = stack.last() {
+            let top = *top;
+            if symbols[top].kind == "class" && !pushed && !words.contains(&"end") {
+                if let Some((name, ty)) = line.split_once("::") {
+                    symbols[top].params.push(Param { name: name.trim().to_string(), type_annotation: Some(ty.trim().to_string()), default: None });
+                }
+            }
+        }
+        if let Some(top) = stack.iter().rev().flatten().find(|&&i| symbols[i].kind == "function" || symbols[i].kind == "macro") {
+            let body = if pushed { line.split_once(')').map(|(_, b)| b).unwrap_or("") } else { line };
+            for call in called_identifiers(body, JULIA_KEYWORDS) {
+                if !symbols[*top].calls.contains(&call) { symbols[*top].calls.push(call); }
+            }
+        }
+
+        // `abstract type X end` is complete on its own line
+        if matches!(first, "abstract" | "primitive") { continue; }
+        for (i, w) in words.iter().enumerate() {
+            let opens = JULIA_BLOCK_OPENERS.contains(w) && !(i == 0 && pushed) && !(*w == "struct" && pushed);
+            if opens { stack.push(None); }
+            if *w == "end" {
+                if let Some(Some(sym_idx)) = stack.pop() {
+                    symbols[sym_idx].range.1 = line_no;
+                }
+            }
+        }
+    }
+    (symbols, imports, exports)
+}
+
+// ---------------------------------------------------------------- Erlang
+
+const ERLANG_KEYWORDS: &[&str] = &["fun", "case", "if", "receive", "when", "try", "catch", "of", "begin"];
+
+// Drop a trailing `% ...` comment, leaving string literals alone
+fn strip_erlang_comment(line: &str) -> &str {
+    let mut in_str = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_str = !in_str,
+            '%' if !in_str => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn scan_erlang(content: &str) -> (Vec<Symbol>, Vec<Import>, Vec<String>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut imports = vec![];
+    let mut exports: Vec<String> = vec![];
+    let mut behaviours = vec![];
+    let mut module: Option<usize> = None;
+    // Index of the function whose clauses we're inside, until the terminating `.`
+    let mut current: Option<usize> = None;
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let raw = lines[idx];
+        let line = strip_erlang_comment(raw).trim_end();
+        let line_no = idx + 1;
+        idx += 1;
+        if line.trim().is_empty() { continue; }
+
+        if let Some(f) = current {
+            // A further clause head (`name(...) ->`) only contributes its body
+            let body = if raw.starts_with(symbols[f].name.as_str()) { line.split_once("->").map(|(_, b)| b).unwrap_or("") } else { line };
+            for call in called_identifiers(body, ERLANG_KEYWORDS) {
+                if !symbols[f].calls.contains(&call) { symbols[f].calls.push(call); }
+            }
+            symbols[f].range.1 = line_no;
+            if line.ends_with('.') { current = None; }
+            // Subsequent clauses (`name(...) ->` after a `;`) belong to the same function
+            continue;
+        }
+
+        if let Some(attr) = line.strip_prefix('-') {
+            // Attributes can span lines (long export lists); join until the closing `).`
+            let mut text = attr.to_string();
+            while !text.trim_end().ends_with('.') && idx < lines.len() {
+                text.push(' ');
+                text.push_str(strip_erlang_comment(lines[idx]).trim());
+                idx += 1;
+            }
+            let name = text.split('(').next().unwrap_or("").trim();
+            let arg = text.find('(').and_then(|_| balanced(&text, 0, '(', ')')).map(|(s, e)| text[s..e].trim().to_string()).unwrap_or_default();
+            match name {
+                "module" => {
+                    let mut sym = new_symbol(&arg, "module", (line_no, lines.len()), raw);
+                    sym.signature = Some(format!("-module({}).", arg));
+                    sym.docstring = comment_block_above(&lines, line_no, "%%");
+                    symbols.push(sym);
+                    module = Some(symbols.len() - 1);
+                }
+                "export" => {
+                    exports.extend(arg.trim_matches(['[', ']']).split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()));
+                }
+                "behaviour" | "behavior" => behaviours.push(arg.clone()),
+                "include" | "include_lib" => {
+                    let path = arg.trim_matches('"').to_string();
+                    imports.push(Import { raw: line.to_string(), source: Some(path.clone()), names: vec![path] });
+                }
+                "import" => {
+                    // -import(lists, [map/2, filter/2]).
+                    if let Some((m, names)) = arg.split_once(',') {
+                        let names = names.trim().trim_matches(['[', ']']).split(',').map(|n| n.trim().to_string()).collect();
+                        imports.push(Import { raw: line.to_string(), source: Some(m.trim().to_string()), names });
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        // Function clause heads start in column 0: `name(Args) [when Guard] ->`
+        if raw.starts_with(|c: char| c.is_ascii_lowercase()) {
+            let name_end = line.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '@')).unwrap_or(line.len());
+            if !line[name_end..].starts_with('(') { continue; }
+            let name = &line[..name_end];
+            let Some((s, e)) = balanced(line, name_end, '(', ')') else { continue };
+            let args = split_top_level(&line[s..e]);
+            let arity = format!("{}/{}", name, args.len());
+            let mut sym = new_symbol(name, "function", (line_no, line_no), raw);
+            sym.signature = Some(format!("{}({})", name, args.join(", ")));
+            sym.params = args.iter().map(|a| Param { name: a.clone(), type_annotation: None, default: None }).collect();
+            // Doc comments sit above the clause, or above its -spec
+            let spec_lines = lines[..idx - 1].iter().rev().take_while(|l| l.trim().starts_with("-spec") || l.starts_with("    ") && !l.trim().is_empty()).count();
+            let doc_anchor = if spec_lines > 0 && lines[idx - 1 - spec_lines].trim().starts_with("-spec") { line_no - spec_lines } else { line_no };
+            sym.docstring = comment_block_above(&lines, doc_anchor, "%%").or_else(|| comment_block_above(&lines, doc_anchor, "%"))
+                .map(|d| d.trim_start_matches('%').trim_start_matches("@doc").trim().to_string());
+            sym.parent_class = module.map(|m| symbols[m].name.clone());
+            sym.calls = called_identifiers(&line[e + 1..], ERLANG_KEYWORDS);
+            // `-spec name(Types) -> Ret.` directly above gives the types
+            if let Some(spec) = lines[..idx - 1].iter().rev().take(3).find_map(|l| l.trim().strip_prefix("-spec ")) {
+                if spec.starts_with(&format!("{}(", name)) {
+                    sym.return_type = spec.rsplit("->").next().map(|r| r.trim().trim_end_matches('.').to_string());
+                }
+            }
+            // Keep the arity in the visibility so `foo/1` and `foo/2` stay distinguishable
+            sym.visibility = Some(if exports.contains(&arity) { "export".to_string() } else { "private".to_string() });
+            symbols.push(sym);
+            if !line.ends_with('.') { current = Some(symbols.len() - 1); }
+        }
+    }
+
+    // -export can follow the functions it names; settle visibility once everything is read
+    for sym in symbols.iter_mut().filter(|s| s.kind == "function") {
+        let arity = format!("{}/{}", sym.name, sym.params.len());
+        sym.visibility = Some(if exports.contains(&arity) { "export".to_string() } else { "private".to_string() });
+    }
+    if let Some(m) = module {
+        symbols[m].bases = behaviours;
+    }
+    (symbols, imports, exports)
+}
+
+// ---------------------------------------------------------------- Perl
+
+const PERL_KEYWORDS: &[&str] = &["my", "our", "local", "if", "elsif", "unless", "while", "until", "for", "foreach", "return", "print", "printf", "die", "warn", "defined", "ref", "scalar", "keys", "values", "push", "pop", "shift", "unshift", "sub", "qw"];
+
+fn scan_perl(content: &str) -> (Vec<Symbol>, Vec<Import>, Vec<String>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut symbols: Vec<Symbol> = vec![];
+    let mut imports = vec![];
+    let mut exports = vec![];
+    let mut package: Option<usize> = None;
+    // POD paragraphs: the most recent block (for the next sub) and `=head2/=item name` sections by name
+    let mut last_pod: Option<(String, usize)> = None;
+    let mut pod_by_name: Vec<(String, String)> = vec![];
+    let starts = line_starts(content);
+    let mut idx = 0;
+    let mut skip_until = 0;
+
+    while idx < lines.len() {
+        let raw = lines[idx];
+        let line_start_byte = starts[idx];
+        let line_no = idx + 1;
+        idx += 1;
+        let line = raw.trim();
+
+        // POD block: =pod/=head1/... up to =cut
+        if raw.starts_with('=') && raw.len() > 1 && raw.as_bytes()[1].is_ascii_alphabetic() {
+            let mut block = vec![];
+            let mut section: Option<String> = None;
+            let mut l = raw;
+            loop {
+                if l.starts_with("=cut") { break; }
+                if let Some(h) = l.strip_prefix("=head2 ").or_else(|| l.strip_prefix("=head3 ")).or_else(|| l.strip_prefix("=item ")) {
+                    if let Some(name) = section.take() { pod_by_name.push((name, block.join("\n"))); block.clear(); }
+                    section = Some(h.trim().trim_start_matches(['$', '&']).split(['(', ' ']).next().unwrap_or("").to_string());
+                } else if !l.starts_with('=') && !l.trim().is_empty() {
+                    block.push(l.trim().to_string());
+                }
+                if idx >= lines.len() { break; }
+                l = lines[idx];
+                idx += 1;
+            }
+            if let Some(name) = section { pod_by_name.push((name, block.join("\n"))); }
+            else if !block.is_empty() { last_pod = Some((block.join("\n"), idx)); }
+            continue;
+        }
+        if line_no <= skip_until || line.starts_with('#') || line.is_empty() { continue; }
+
+        if let Some(rest) = line.strip_prefix("package ") {
+            let name = rest.split([';', ' ', '{']).next().unwrap_or("").to_string();
+            let mut sym = new_symbol(&name, "class", (line_no, line_no), raw);
+            sym.signature = Some(format!("package {}", name));
+            sym.docstring = comment_block_above(&lines, line_no, "#");
+            if let Some(p) = package { symbols[p].range.1 = line_no - 1; }
+            symbols.push(sym);
+            package = Some(symbols.len() - 1);
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("use ").or_else(|| line.strip_prefix("require ")) {
+            let module = rest.split([';', ' ', '(']).next().unwrap_or("").to_string();
+            if module.is_empty() || module.starts_with(|c: char| c.is_ascii_digit() || c == 'v') && module[1..].starts_with(|c: char| c.is_ascii_digit()) { continue; }
+            if matches!(module.as_str(), "strict" | "warnings" | "utf8" | "feature" | "lib") { continue; }
+            let args = rest[module.len()..].trim().trim_end_matches(';');
+            let names: Vec<String> = args.trim_start_matches("qw").trim_matches(['(', ')', '/', '\'', '"'])
+                .split([' ', ',']).map(|n| n.trim_matches(['\'', '"']).to_string())
+                .filter(|n| !n.is_empty() && !n.starts_with('-')).collect();
+            // use parent/base declare superclasses
+            if module == "parent" || module == "base" {
+                if let Some(p) = package { symbols[p].bases.extend(names.iter().cloned()); }
+            }
+            imports.push(Import { raw: line.to_string(), source: Some(module), names });
+            continue;
+        }
+        if line.starts_with("our @EXPORT") {
+            let list = line.split_once('=').map(|(_, l)| l).unwrap_or("");
+            exports.extend(list.trim().trim_end_matches(';').trim_start_matches("qw").trim_matches(['(', ')', '/'])
+                .split([' ', ',']).map(|n| n.trim_matches(['\'', '"']).to_string()).filter(|n| !n.is_empty()));
+            continue;
+        }
+
+        let Some(rest) = line.strip_prefix("sub ") else { continue };
+        let name = rest.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':')).next().unwrap_or("").to_string();
+        if name.is_empty() { continue; }
+        let indent = raw.len() - raw.trim_start().len();
+        let abs = line_start_byte + indent;
+        let Some(brace) = content[abs..].find('{').map(|b| abs + b) else { continue };
+        let close = closing_brace(content, brace, b"\"'");
+        let body = &content[brace + 1..close];
+        let end_line = line_of(content, close).min(lines.len());
+        skip_until = end_line;
+
+        let mut sym = new_symbol(&name, if package.is_some() { "method" } else { "function" }, (line_no, end_line), raw);
+        sym.parent_class = package.map(|p| symbols[p].name.clone());
+        sym.signature = Some(content[abs..brace].trim().to_string());
+        // Signatures feature: `sub foo ($x, $y = 1) {`; otherwise `my ($self, $x) = @_;`
+        let header = &content[abs..brace];
+        let param_list = match balanced(header, 0, '(', ')') {
+            Some((s, e)) => Some(header[s..e].to_string()),
+            None => body.lines().map(str::trim).find(|l| !l.is_empty())
+                .filter(|l| l.starts_with("my (") && l.contains("@_"))
+                .and_then(|l| balanced(l, 0, '(', ')').map(|(s, e)| l[s..e].to_string())),
+        };
+        for p in param_list.iter().flat_map(|l| split_top_level(l)) {
+            let (name, default) = match p.split_once('=') {
+                Some((n, d)) => (n.trim().to_string(), Some(d.trim().to_string())),
+                None => (p.trim().to_string(), None),
+            };
+            if name != "$self" && name != "$class" {
+                sym.params.push(Param { name, type_annotation: None, default });
+            }
+        }
+        sym.calls = called_identifiers(body, PERL_KEYWORDS);
+        sym.visibility = Some(if name.starts_with('_') { "private".to_string() } else { "public".to_string() });
+        sym.docstring = pod_by_name.iter().rev().find(|(n, _)| *n == name).map(|(_, d)| d.clone())
+            .or_else(|| last_pod.as_ref().filter(|(_, end)| end + 2 >= line_no).map(|(d, _)| d.clone()))
+            .or_else(|| comment_block_above(&lines, line_no, "#"));
+        last_pod = None;
+        symbols.push(sym);
+    }
+
+    // POD often documents subs after the code (`__END__` sections)
+    for sym in symbols.iter_mut().filter(|s| s.docstring.is_none()) {
+        sym.docstring = pod_by_name.iter().find(|(n, _)| *n == sym.name).map(|(_, d)| d.clone());
+    }
+    if let Some(p) = package { symbols[p].range.1 = symbols[p].range.1.max(lines.len()); }
+    (symbols, imports, exports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sql_transaction_wrapped_migration() {
+        let content = "BEGIN;\nCREATE TABLE users (\n  id INT PRIMARY KEY,\n  name TEXT\n);\nCREATE INDEX users_name ON users (name);\nCOMMIT;\n";
+        let symbols = scan_sql(content);
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["users"]);
+        assert_eq!(symbols[0].range, (2, 5));
+
+        let content = "BEGIN TRANSACTION;\nCREATE TABLE a (id INT);\nEND;\nCREATE TABLE b (id INT);\n";
+        let names: Vec<String> = scan_sql(content).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn sql_routine_bodies_stay_whole() {
+        let content = "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\n  BEGIN\n    SELECT 2;\n  END;\nEND;\nCREATE TABLE t (id INT);\n";
+        let symbols = scan_sql(content);
+        let names: Vec<&str> = symbols.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["p", "t"]);
+        assert_eq!(symbols[0].range, (1, 7));
+    }
+
+    #[test]
+    fn hcl_block_ranges_on_crlf() {
+        let content = "# bucket\r\n\r\nresource \"aws_s3_bucket\" \"b\" {\r\n  bucket = \"b\"\r\n}\r\n\r\nvariable \"v\" {\r\n";
+        let (symbols, _) = scan_hcl(content);
+        assert_eq!(symbols[0].name, "aws_s3_bucket.b");
+        assert_eq!(symbols[0].range, (3, 5));
+        // Never closed: runs to the end of the file
+        assert_eq!(symbols[1].range, (7, 7));
+    }
+
+    #[test]
+    fn r_functions_on_crlf() {
+        // Enough lines above for a byte lost per `\r` to reach back to the `c(` call
+        let content = format!("{}x <- c(1, 2)\r\nadd <- function(x, y = 1) {{\r\n  sum(x, y)\r\n}}\r\nf <- function(a) a\r\n", "# é\r\n".repeat(20));
+        let (symbols, _, _) = scan_r(&content);
+        assert_eq!(symbols[0].signature.as_deref(), Some("add <- function(x, y = 1)"));
+        assert_eq!(symbols[0].range, (22, 24));
+        assert_eq!(symbols[0].calls, vec!["sum"]);
+        assert_eq!(symbols[1].params[0].name, "a");
+    }
+
+    #[test]
+    fn perl_subs_on_crlf() {
+        let content = "package Foo;\r\n# one\r\n# two\r\n# señor\r\nsub foo {\r\n  my ($self, $x) = @_;\r\n}\r\nsub bar {\r\n  baz();\r\n}\r\n";
+        let (symbols, _, _) = scan_perl(content);
+        let subs: Vec<(&str, Option<&str>, (usize, usize))> = symbols.iter()
+            .filter(|s| s.kind == "method")
+            .map(|s| (s.name.as_str(), s.signature.as_deref(), s.range))
+            .collect();
+        assert_eq!(subs, vec![("foo", Some("sub foo"), (5, 7)), ("bar", Some("sub bar"), (8, 10))]);
+        assert_eq!(symbols[1].params[0].name, "$x");
+        assert_eq!(symbols[2].calls, vec!["baz"]);
+    }
+}
@@ -0,0 +1,747 @@
+use async_trait::async_trait;
+use neo4rs::{Error, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::git::{FileHistory, Submodule};
+use crate::graph::{self, CallEnd, Snapshot};
+use crate::classifier::Feedback;
+use crate::entrypoints::Entrypoint;
+use crate::licenses::License;
+use crate::manifests::Dependency;
+use crate::memory::MemoryGraph;
+use crate::parsing::{Implementation, ParsingResult, Symbol};
+use crate::store::GraphStore;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS files (
+        repo TEXT NOT NULL,
+        path TEXT NOT NULL,
+        language TEXT NOT NULL,
+        result TEXT NOT NULL,
+        PRIMARY KEY (repo, path)
+    );
+    CREATE TABLE IF NOT EXISTS symbols (
+        id TEXT PRIMARY KEY,
+        repo TEXT NOT NULL,
+        file TEXT NOT NULL,
+        label TEXT NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        qualified_name TEXT NOT NULL,
+        parent_class TEXT NOT NULL,
+        line_start INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS symbols_by_name ON symbols (repo, name);
+    CREATE INDEX IF NOT EXISTS symbols_by_file ON symbols (repo, file);
+    CREATE TABLE IF NOT EXISTS edges (
+        repo TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        source TEXT NOT NULL,
+        target TEXT NOT NULL,
+        PRIMARY KEY (repo, kind, source, target)
+    );
+    CREATE INDEX IF NOT EXISTS edges_by_target ON edges (repo, kind, target);
+    CREATE TABLE IF NOT EXISTS snapshots (
+        key TEXT PRIMARY KEY,
+        repo TEXT NOT NULL,
+        id TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        files INTEGER NOT NULL,
+        symbols INTEGER NOT NULL,
+        git_commit TEXT
+    );
+    CREATE INDEX IF NOT EXISTS snapshots_by_repo ON snapshots (repo);
+    CREATE TABLE IF NOT EXISTS repos (
+        name TEXT PRIMARY KEY,
+        indexed_commit TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS dependencies (
+        repo TEXT NOT NULL,
+        manifest TEXT NOT NULL,
+        name TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        ecosystem TEXT NOT NULL,
+        version_req TEXT NOT NULL,
+        package TEXT,
+        resolved_version TEXT,
+        transitive INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS dependencies_by_repo ON dependencies (repo);
+    CREATE TABLE IF NOT EXISTS licenses (
+        repo TEXT PRIMARY KEY,
+        spdx TEXT,
+        source TEXT,
+        category TEXT NOT NULL,
+        headers TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS submodules (
+        repo TEXT NOT NULL,
+        path TEXT NOT NULL,
+        url TEXT,
+        git_commit TEXT NOT NULL,
+        indexed_as TEXT,
+        PRIMARY KEY (repo, path)
+    );
+    CREATE TABLE IF NOT EXISTS entrypoints (
+        repo TEXT NOT NULL,
+        file TEXT NOT NULL,
+        line INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL,
+        target TEXT
+    );
+    CREATE INDEX IF NOT EXISTS entrypoints_by_repo ON entrypoints (repo);
+    CREATE TABLE IF NOT EXISTS availability (
+        repo TEXT PRIMARY KEY,
+        availability TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS classification_feedback (
+        repo TEXT PRIMARY KEY,
+        feedback TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS file_history (
+        repo TEXT NOT NULL,
+        path TEXT NOT NULL,
+        history TEXT NOT NULL,
+        PRIMARY KEY (repo, path)
+    );
+";
+
+// Single-file storage for deployments without a Neo4j server. Each file's parse result is kept
+// whole, with symbols and their CALLS, INHERITS and IMPLEMENTS edges in tables so traversals run
+// as recursive CTEs. Everything else is answered from the parse results through `MemoryGraph`
+pub struct SqliteGraph {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteGraph {
+    pub fn open(path: &str) -> Result<Self> {
+        let conn = Connection::open(path).map_err(sql_error)?;
+        conn.pragma_update(None, "journal_mode", "WAL").map_err(sql_error)?;
+        conn.execute_batch(SCHEMA).map_err(sql_error)?;
+        // Files created before snapshots recorded their commit; fails harmlessly once the column exists
+        let _ = conn.execute("ALTER TABLE snapshots ADD COLUMN git_commit TEXT", []);
+        // Likewise for dependencies from before lockfiles were read
+        let _ = conn.execute("ALTER TABLE dependencies ADD COLUMN resolved_version TEXT", []);
+        let _ = conn.execute("ALTER TABLE dependencies ADD COLUMN transitive INTEGER", []);
+        Ok(Self { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    // Run `f` on the connection off the async runtime; SQLite calls block
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> rusqlite::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            f(&mut conn)
+        })
+            .await
+            .map_err(|e| Error::UnexpectedMessage(format!("sqlite task failed: {}", e)))?
+            .map_err(sql_error)
+    }
+
+    // The repo's stored parse results as an in-memory graph
+    pub(crate) async fn load(&self, repo_name: &str) -> Result<MemoryGraph> {
+        let repo = repo_name.to_string();
+        let rows: Vec<(String, String)> = self.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT path, result FROM files WHERE repo = ?1")?;
+            let rows = stmt.query_map(params![repo], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        }).await?;
+        let mut files = BTreeMap::new();
+        for (path, result) in rows {
+            let result: ParsingResult = serde_json::from_str(&result)
+                .map_err(|e| Error::UnexpectedMessage(format!("stored parse result for {} is unreadable: {}", path, e)))?;
+            files.insert(path, result);
+        }
+        let repo = repo_name.to_string();
+        let rows: Vec<(String, String)> = self.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT path, history FROM file_history WHERE repo = ?1")?;
+            let rows = stmt.query_map(params![repo], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        }).await?;
+        let history = rows.into_iter()
+            .filter_map(|(path, history)| Some((path, serde_json::from_str(&history).ok()?)))
+            .collect();
+        Ok(MemoryGraph::from_files(repo_name, files, history))
+    }
+}
+
+#[async_trait]
+impl GraphStore for SqliteGraph {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    // Replace the file's row and symbols, then link its calls and bases against the repo's
+    // symbols with the same rules as the Neo4j ingest
+    async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()> {
+        let (repo, path, parsed) = (repo_name.to_string(), file_path.to_string(), result.clone());
+        let json = serde_json::to_string(result).map_err(|e| Error::UnexpectedMessage(e.to_string()))?;
+        self.with_conn(move |conn| {
+            let result = parsed;
+            let tx = conn.transaction()?;
+            let file_id = format!("{}::{}", repo, path);
+            tx.execute(
+                "DELETE FROM edges WHERE repo = ?1 AND source IN (SELECT id FROM symbols WHERE repo = ?1 AND file = ?2)",
+                params![repo, path],
+            )?;
+            tx.execute("DELETE FROM symbols WHERE repo = ?1 AND file = ?2", params![repo, path])?;
+            tx.execute(
+                "INSERT OR REPLACE INTO files (repo, path, language, result) VALUES (?1, ?2, ?3, ?4)",
+                params![repo, path, format!("{:?}", result.language), json],
+            )?;
+            {
+                let mut insert = tx.prepare(
+                    "INSERT OR REPLACE INTO symbols (id, repo, file, label, name, kind, qualified_name, parent_class, line_start) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                )?;
+                for s in &result.symbols {
+                    insert.execute(params![
+                        symbol_id(&file_id, s), repo, path, graph::label_for_kind(&s.kind), s.name, s.kind,
+                        s.qualified_name.clone().unwrap_or_default(), s.parent_class.clone().unwrap_or_default(), s.range.0 as i64,
+                    ])?;
+                }
+
+                let mut candidates = tx.prepare(
+                    "SELECT id, file, name, qualified_name, parent_class FROM symbols WHERE repo = ?1 AND name = ?2 AND label = ?3",
+                )?;
+                let mut link = tx.prepare("INSERT OR IGNORE INTO edges (repo, kind, source, target) VALUES (?1, ?2, ?3, ?4)")?;
+                for s in result.symbols.iter().filter(|s| graph::label_for_kind(&s.kind) == "Function") {
+                    let caller = symbol_id(&file_id, s);
+                    for callee in &s.calls {
+                        let target = graph::resolve_call(&result, s, callee);
+                        let rows = candidates.query_map(params![repo, graph::call_name(callee), "Function"], stored_symbol)?;
+                        for row in rows {
+                            let (id, file, symbol) = row?;
+                            if target.accepts(&symbol, &file) {
+                                link.execute(params![repo, "CALLS", caller, id])?;
+                            }
+                        }
+                    }
+                }
+                for s in result.symbols.iter().filter(|s| s.kind == "class") {
+                    let child = symbol_id(&file_id, s);
+                    for base in &s.bases {
+                        let rows = candidates.query_map(params![repo, base, "Class"], stored_symbol)?;
+                        for row in rows {
+                            link.execute(params![repo, "INHERITS", child, row?.0])?;
+                        }
+                    }
+                }
+            }
+            tx.commit()
+        }).await?;
+        self.link_implementations(repo_name, &result.implementations).await
+    }
+
+    async fn link_implementations(&self, repo_name: &str, implementations: &[Implementation]) -> Result<()> {
+        let (repo, implementations) = (repo_name.to_string(), implementations.to_vec());
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for imp in &implementations {
+                tx.execute(
+                    "INSERT OR IGNORE INTO edges (repo, kind, source, target) \
+                     SELECT ?1, 'IMPLEMENTS', t.id, tr.id FROM symbols t, symbols tr \
+                     WHERE t.repo = ?1 AND t.name = ?2 AND t.label = 'Class' \
+                       AND tr.repo = ?1 AND tr.name = ?3 AND tr.label = 'Class'",
+                    params![repo, imp.type_name, imp.trait_name],
+                )?;
+            }
+            tx.commit()
+        }).await
+    }
+
+    async fn set_declared_dependencies(&self, repo_name: &str, dependencies: &[Dependency]) -> Result<()> {
+        let (repo, dependencies) = (repo_name.to_string(), dependencies.to_vec());
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM dependencies WHERE repo = ?1", params![repo])?;
+            for d in &dependencies {
+                tx.execute(
+                    "INSERT INTO dependencies (repo, manifest, name, kind, ecosystem, version_req, package, resolved_version, transitive) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        repo, d.manifest, d.name, d.kind, d.ecosystem, d.version_req, d.package, d.resolved_version,
+                        d.transitive.map(|n| n as i64),
+                    ],
+                )?;
+            }
+            tx.commit()
+        }).await
+    }
+
+    async fn set_license(&self, repo_name: &str, license: &License) -> Result<()> {
+        let (repo, license) = (repo_name.to_string(), license.clone());
+        let headers = serde_json::to_string(&license.headers).unwrap_or_default();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO licenses (repo, spdx, source, category, headers) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![repo, license.spdx, license.source, license.category, headers],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn set_entrypoints(&self, repo_name: &str, entrypoints: &[Entrypoint]) -> Result<()> {
+        let (repo, entrypoints) = (repo_name.to_string(), entrypoints.to_vec());
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM entrypoints WHERE repo = ?1", params![repo])?;
+            for e in &entrypoints {
+                tx.execute(
+                    "INSERT INTO entrypoints (repo, file, line, kind, name, target) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![repo, e.file, e.line as i64, e.kind, e.name, e.target],
+                )?;
+            }
+            tx.commit()
+        }).await
+    }
+
+    async fn set_submodules(&self, repo_name: &str, submodules: &[Submodule]) -> Result<()> {
+        let (repo, submodules) = (repo_name.to_string(), submodules.to_vec());
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM submodules WHERE repo = ?1", params![repo])?;
+            for s in &submodules {
+                tx.execute(
+                    "INSERT OR REPLACE INTO submodules (repo, path, url, git_commit, indexed_as) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![repo, s.path, s.url, s.commit, s.repo],
+                )?;
+            }
+            tx.commit()
+        }).await
+    }
+
+    async fn set_file_history(&self, repo_name: &str, history: &HashMap<String, FileHistory>) -> Result<()> {
+        let repo = repo_name.to_string();
+        let rows: Vec<(String, String)> = history.iter()
+            .map(|(path, h)| (path.clone(), serde_json::to_string(h).unwrap_or_default()))
+            .collect();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            for (path, history) in &rows {
+                tx.execute(
+                    "INSERT OR REPLACE INTO file_history (repo, path, history) VALUES (?1, ?2, ?3)",
+                    params![repo, path, history],
+                )?;
+            }
+            tx.commit()
+        }).await
+    }
+
+    async fn delete_repo(&self, repo_name: &str) -> Result<i64> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let deleted = tx.execute("DELETE FROM files WHERE repo = ?1", params![repo])?
+                + tx.execute("DELETE FROM symbols WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM edges WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM snapshots WHERE key = ?1", params![repo])?;
+            tx.execute("DELETE FROM repos WHERE name = ?1", params![repo])?;
+            tx.execute("DELETE FROM dependencies WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM licenses WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM file_history WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM submodules WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM entrypoints WHERE repo = ?1", params![repo])?;
+            tx.execute("DELETE FROM availability WHERE repo = ?1", params![repo])?;
+            tx.commit()?;
+            Ok(deleted as i64)
+        }).await
+    }
+
+    // Symbols are replaced with their file, so only files that are gone can be stale
+    async fn remove_stale(&self, repo_name: &str, files: Option<&[String]>, seen: &HashSet<String>) -> Result<i64> {
+        let (repo, files, seen) = (repo_name.to_string(), files.map(<[String]>::to_vec), seen.clone());
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            let stale: Vec<String> = {
+                let mut stmt = tx.prepare("SELECT path FROM files WHERE repo = ?1")?;
+                let paths = stmt.query_map(params![repo], |row| row.get::<_, String>(0))?;
+                paths.collect::<rusqlite::Result<Vec<_>>>()?
+                    .into_iter()
+                    .filter(|path| files.as_ref().is_none_or(|f| f.contains(path)))
+                    .filter(|path| !seen.contains(&format!("{}::{}", repo, path)))
+                    .collect()
+            };
+            let mut removed = 0;
+            for path in &stale {
+                tx.execute(
+                    "DELETE FROM edges WHERE repo = ?1 AND (source IN (SELECT id FROM symbols WHERE repo = ?1 AND file = ?2) \
+                     OR target IN (SELECT id FROM symbols WHERE repo = ?1 AND file = ?2))",
+                    params![repo, path],
+                )?;
+                removed += tx.execute("DELETE FROM symbols WHERE repo = ?1 AND file = ?2", params![repo, path])?
+                    + tx.execute("DELETE FROM files WHERE repo = ?1 AND path = ?2", params![repo, path])?;
+                tx.execute("DELETE FROM file_history WHERE repo = ?1 AND path = ?2", params![repo, path])?;
+            }
+            tx.commit()?;
+            Ok(removed as i64)
+        }).await
+    }
+
+    async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        let snapshot = snapshot.clone();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO snapshots (key, repo, id, created_at, files, symbols, git_commit) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![snapshot.key, snapshot.repo, snapshot.id, snapshot.created_at, snapshot.files, snapshot.symbols, snapshot.commit],
+            )?;
+            Ok(())
+        }).await
+    }
+
+    async fn list_snapshots(&self, repo_name: &str) -> Result<Vec<Snapshot>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT repo, id, key, created_at, files, symbols, git_commit FROM snapshots WHERE repo = ?1 ORDER BY created_at DESC, id DESC",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok(Snapshot {
+                repo: row.get(0)?,
+                id: row.get(1)?,
+                key: row.get(2)?,
+                created_at: row.get(3)?,
+                files: row.get(4)?,
+                symbols: row.get(5)?,
+                commit: row.get(6)?,
+            }))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn indexed_commit(&self, repo_name: &str) -> Result<Option<String>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row("SELECT indexed_commit FROM repos WHERE name = ?1", params![repo], |row| row.get(0)).optional()
+        }).await
+    }
+
+    async fn set_indexed_commit(&self, repo_name: &str, commit: &str) -> Result<()> {
+        let (repo, commit) = (repo_name.to_string(), commit.to_string());
+        self.with_conn(move |conn| {
+            conn.execute("INSERT OR REPLACE INTO repos (name, indexed_commit) VALUES (?1, ?2)", params![repo, commit])?;
+            Ok(())
+        }).await
+    }
+
+    async fn availability(&self, repo_name: &str) -> Result<Option<String>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row("SELECT availability FROM availability WHERE repo = ?1", params![repo], |row| row.get(0)).optional()
+        }).await
+    }
+
+    async fn set_availability(&self, repo_name: &str, availability: &str) -> Result<()> {
+        let (repo, availability) = (repo_name.to_string(), availability.to_string());
+        self.with_conn(move |conn| {
+            conn.execute("INSERT OR REPLACE INTO availability (repo, availability) VALUES (?1, ?2)", params![repo, availability])?;
+            Ok(())
+        }).await
+    }
+
+    // Left alone by delete_repo
+    async fn classification_feedback(&self, repo_name: &str) -> Result<Option<Feedback>> {
+        let repo = repo_name.to_string();
+        let feedback: Option<String> = self.with_conn(move |conn| {
+            conn.query_row("SELECT feedback FROM classification_feedback WHERE repo = ?1", params![repo], |row| row.get(0)).optional()
+        }).await?;
+        Ok(feedback.and_then(|feedback| serde_json::from_str(&feedback).ok()))
+    }
+
+    async fn set_classification_feedback(&self, repo_name: &str, feedback: &Feedback) -> Result<()> {
+        let (repo, feedback) = (repo_name.to_string(), serde_json::to_string(feedback).unwrap_or_default());
+        self.with_conn(move |conn| {
+            conn.execute("INSERT OR REPLACE INTO classification_feedback (repo, feedback) VALUES (?1, ?2)", params![repo, feedback])?;
+            Ok(())
+        }).await
+    }
+
+    async fn all_classification_feedback(&self) -> Result<Vec<Feedback>> {
+        let feedback: Vec<String> = self.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT feedback FROM classification_feedback")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect()
+        }).await?;
+        Ok(feedback.iter().filter_map(|feedback| serde_json::from_str(feedback).ok()).collect())
+    }
+
+    // Read out of the stored results, so databases written before hashes were kept need no migration
+    async fn file_hashes(&self, repo_name: &str) -> Result<HashMap<String, String>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT path, coalesce(json_extract(result, '$.content_hash'), '') FROM files WHERE repo = ?1",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn call_chain(&self, repo_name: &str, symbol: &str, depth: usize, callers: bool) -> Result<Vec<Value>> {
+        let (repo, symbol) = (repo_name.to_string(), symbol.to_string());
+        // Walk CALLS backwards for callers by swapping which end the recursion follows
+        let (from, to) = if callers { ("target", "source") } else { ("source", "target") };
+        let sql = format!(
+            "WITH RECURSIVE chain(parent, child, hops) AS ( \
+                 SELECT e.{from}, e.{to}, 1 FROM edges e JOIN symbols r ON e.{from} = r.id \
+                 WHERE e.repo = ?1 AND e.kind = 'CALLS' AND r.repo = ?1 AND r.label = 'Function' \
+                   AND (r.id = ?2 OR r.qualified_name = ?2 OR r.name = ?2) \
+                 UNION \
+                 SELECT e.{from}, e.{to}, c.hops + 1 FROM edges e JOIN chain c ON e.{from} = c.child \
+                 WHERE e.repo = ?1 AND e.kind = 'CALLS' AND c.hops < ?3 \
+             ) SELECT DISTINCT parent, child FROM chain ORDER BY parent, child",
+            from = from, to = to
+        );
+        let (roots, links, info) = self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT id FROM symbols WHERE repo = ?1 AND label = 'Function' \
+                 AND (id = ?2 OR qualified_name = ?2 OR name = ?2) ORDER BY id",
+            )?;
+            let roots: Vec<String> = stmt.query_map(params![repo, symbol], |row| row.get(0))?.collect::<rusqlite::Result<_>>()?;
+            let mut stmt = conn.prepare(&sql)?;
+            let links: Vec<(String, String)> = stmt.query_map(params![repo, symbol, depth as i64], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            let mut ids: Vec<&String> = roots.iter().chain(links.iter().map(|(_, child)| child)).collect();
+            ids.sort();
+            ids.dedup();
+            let info = function_info(conn, &ids)?;
+            Ok((roots, links, info))
+        }).await?;
+
+        let mut next: HashMap<String, Vec<String>> = HashMap::new();
+        for (parent, child) in links {
+            next.entry(parent).or_default().push(child);
+        }
+        let mut budget = graph::MAX_CHAIN_NODES;
+        Ok(roots.iter()
+            .map(|root| graph::chain_node(root, depth, &next, &info, &mut vec![], &mut budget))
+            .collect())
+    }
+
+    async fn get_hotspots(&self, repo_name: &str, limit: usize) -> Result<Vec<Value>> {
+        let repo = repo_name.to_string();
+        let edges: Vec<(String, String)> = self.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT source, target FROM edges WHERE repo = ?1 AND kind = 'CALLS' AND source <> target")?;
+            let rows = stmt.query_map(params![repo], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect()
+        }).await?;
+        let ranks = graph::pagerank(&edges);
+        let mut ranked: Vec<(String, (f64, usize, usize))> = ranks.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.0.total_cmp(&a.1.0).then_with(|| b.1.1.cmp(&a.1.1)).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        let ids: Vec<String> = ranked.iter().map(|(id, _)| id.clone()).collect();
+        let info = self.with_conn(move |conn| function_info(conn, &ids.iter().collect::<Vec<_>>())).await?;
+        Ok(ranked.into_iter()
+            .map(|(id, (score, callers, callees))| {
+                let mut node = info.get(&id).cloned().unwrap_or_else(|| json!({ "id": id }));
+                node["pagerank"] = json!(score);
+                node["callers"] = json!(callers);
+                node["callees"] = json!(callees);
+                node
+            })
+            .collect())
+    }
+
+    async fn get_call_edges(&self, repo_name: &str) -> Result<Vec<(CallEnd, CallEnd)>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT a.id, a.name, a.qualified_name, a.file, b.id, b.name, b.qualified_name, b.file \
+                 FROM edges e JOIN symbols a ON a.id = e.source JOIN symbols b ON b.id = e.target \
+                 WHERE e.repo = ?1 AND e.kind = 'CALLS'",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok((
+                CallEnd { id: row.get(0)?, name: row.get(1)?, qualified_name: row.get(2)?, file: row.get(3)? },
+                CallEnd { id: row.get(4)?, name: row.get(5)?, qualified_name: row.get(6)?, file: row.get(7)? },
+            )))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn count_by_kind(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.count_by_kind(repo_name).await
+    }
+
+    async fn get_file_languages(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.get_file_languages(repo_name).await
+    }
+
+    async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_all_files(repo_name).await
+    }
+
+    async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_all_symbols(repo_name).await
+    }
+
+    async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_repo_structure(repo_name).await
+    }
+
+    async fn get_docs(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_docs(repo_name).await
+    }
+
+    async fn get_todos(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_todos(repo_name).await
+    }
+
+    async fn get_routes(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_routes(repo_name).await
+    }
+
+    async fn get_commands(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_commands(repo_name).await
+    }
+
+    async fn get_embedded(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_embedded(repo_name).await
+    }
+
+    async fn get_directories(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_directories(repo_name).await
+    }
+
+    async fn get_dependencies(&self, repo_name: &str) -> Result<Value> {
+        self.load(repo_name).await?.get_dependencies(repo_name).await
+    }
+
+    async fn get_packages(&self, repo_name: &str) -> Result<Vec<Value>> {
+        self.load(repo_name).await?.get_packages(repo_name).await
+    }
+
+    async fn get_declared_dependencies(&self, repo_name: &str) -> Result<Vec<Dependency>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, version_req, ecosystem, kind, manifest, package, resolved_version, transitive \
+                 FROM dependencies WHERE repo = ?1 ORDER BY manifest, name",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok(Dependency {
+                name: row.get(0)?,
+                version_req: row.get(1)?,
+                ecosystem: row.get(2)?,
+                kind: row.get(3)?,
+                manifest: row.get(4)?,
+                package: row.get(5)?,
+                resolved_version: row.get(6)?,
+                transitive: row.get::<_, Option<i64>>(7)?.map(|n| n as usize),
+            }))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn get_submodules(&self, repo_name: &str) -> Result<Vec<Submodule>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT path, url, git_commit, indexed_as FROM submodules WHERE repo = ?1 ORDER BY path",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok(Submodule {
+                path: row.get(0)?,
+                url: row.get(1)?,
+                commit: row.get(2)?,
+                repo: row.get(3)?,
+            }))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn get_license(&self, repo_name: &str) -> Result<Option<License>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            conn.query_row(
+                "SELECT spdx, source, category, headers FROM licenses WHERE repo = ?1",
+                params![repo],
+                |row| Ok(License {
+                    spdx: row.get(0)?,
+                    source: row.get(1)?,
+                    category: row.get(2)?,
+                    headers: serde_json::from_str(&row.get::<_, String>(3)?).unwrap_or_default(),
+                }),
+            ).optional()
+        }).await
+    }
+
+    async fn get_entrypoints(&self, repo_name: &str) -> Result<Vec<Entrypoint>> {
+        let repo = repo_name.to_string();
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT file, line, kind, name, target FROM entrypoints WHERE repo = ?1 ORDER BY file, line, kind, name",
+            )?;
+            let rows = stmt.query_map(params![repo], |row| Ok(Entrypoint {
+                file: row.get(0)?,
+                line: row.get::<_, i64>(1)? as usize,
+                kind: row.get(2)?,
+                name: row.get(3)?,
+                target: row.get(4)?,
+            }))?;
+            rows.collect()
+        }).await
+    }
+
+    async fn package_users(&self, package: &str) -> Result<Vec<Value>> {
+        let repos: Vec<String> = self.with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT DISTINCT repo FROM files ORDER BY repo")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect()
+        }).await?;
+        let mut out = vec![];
+        for repo in repos {
+            out.extend(self.load(&repo).await?.package_users(package).await?);
+        }
+        Ok(out)
+    }
+}
+
+fn symbol_id(file_id: &str, s: &Symbol) -> String {
+    format!("{}::{}:{}", file_id, s.name, s.range.0)
+}
+
+// (id, file, symbol) for a `symbols` row, with the fields CALLS matching looks at
+fn stored_symbol(row: &rusqlite::Row) -> rusqlite::Result<(String, String, Symbol)> {
+    let qualified: String = row.get(3)?;
+    let parent: String = row.get(4)?;
+    let symbol = Symbol {
+        name: row.get(2)?,
+        qualified_name: Some(qualified).filter(|q| !q.is_empty()),
+        parent_class: Some(parent).filter(|p| !p.is_empty()),
+        ..Default::default()
+    };
+    Ok((row.get(0)?, row.get(1)?, symbol))
+}
+
+// Same summary as the Neo4j `function_info`, keyed by id
+fn function_info(conn: &Connection, ids: &[&String]) -> rusqlite::Result<HashMap<String, Value>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, qualified_name, kind, parent_class, file, line_start FROM symbols WHERE id = ?1",
+    )?;
+    let mut info = HashMap::new();
+    for id in ids {
+        let row = stmt.query_row(params![id], |row| Ok(json!({
+            "id": row.get::<_, String>(0)?,
+            "name": row.get::<_, String>(1)?,
+            "qualified_name": Some(row.get::<_, String>(2)?).filter(|q| !q.is_empty()),
+            "kind": row.get::<_, String>(3)?,
+            "parent_class": Some(row.get::<_, String>(4)?).filter(|p| !p.is_empty()),
+            "file": row.get::<_, String>(5)?,
+            "line": row.get::<_, i64>(6)?,
+        }))).optional()?;
+        if let Some(row) = row {
+            info.insert(id.to_string(), row);
+        }
+    }
+    Ok(info)
+}
+
+fn sql_error(e: rusqlite::Error) -> Error {
+    Error::UnexpectedMessage(format!("sqlite: {}", e))
+}
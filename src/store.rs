@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use neo4rs::{Error, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::classifier::Feedback;
+use crate::entrypoints::Entrypoint;
+use crate::git::{FileHistory, Submodule};
+use crate::graph::{CallEnd, ExportEdge, ExportNode, Snapshot, SymbolEmbedding};
+use crate::licenses::License;
+use crate::manifests::Dependency;
+use crate::parsing::{Implementation, ParsingResult};
+
+// Everything the handlers, indexer, classifier and exports need from a graph backend. Neo4j
+// implements all of it; the other backends leave out what they can't answer (full-text and
+// vector search, raw Cypher, dumps), which then fails with an "unsupported" error
+#[async_trait]
+pub trait GraphStore: Send + Sync {
+    // Short name reported by /health
+    fn backend(&self) -> &'static str;
+
+    async fn ping(&self) -> bool {
+        true
+    }
+
+    // Ingest
+    async fn ingest_symbols(&self, repo_name: &str, file_path: &str, result: &ParsingResult) -> Result<()>;
+    async fn link_implementations(&self, repo_name: &str, implementations: &[Implementation]) -> Result<()>;
+    // Directory tree for the given paths; backends that derive it from file paths need nothing here
+    async fn link_hierarchy(&self, _repo_name: &str, _file_paths: &[String]) -> Result<()> {
+        Ok(())
+    }
+    // Shared Package nodes for the repo's external imports; backends that derive them from parse
+    // results need nothing here
+    async fn link_packages(&self, _repo_name: &str) -> Result<()> {
+        Ok(())
+    }
+    // Import targets resolved to the files defining them; same as above
+    async fn link_modules(&self, _repo_name: &str) -> Result<()> {
+        Ok(())
+    }
+    // Markdown sections to the symbols they mention; same as above
+    async fn link_mentions(&self, _repo_name: &str) -> Result<()> {
+        Ok(())
+    }
+    // Dependencies the repo's manifests declare, replacing the ones recorded before
+    async fn set_declared_dependencies(&self, repo_name: &str, dependencies: &[Dependency]) -> Result<()>;
+    async fn set_license(&self, repo_name: &str, license: &License) -> Result<()>;
+    // Entrypoints found in the repo, replacing the ones recorded before
+    async fn set_entrypoints(&self, repo_name: &str, entrypoints: &[Entrypoint]) -> Result<()>;
+    // Git history of the given files, keyed by path; files left out keep what they had
+    async fn set_file_history(&self, repo_name: &str, history: &HashMap<String, FileHistory>) -> Result<()>;
+    // Submodules found under the repo, replacing the ones recorded before
+    async fn set_submodules(&self, repo_name: &str, submodules: &[Submodule]) -> Result<()>;
+    async fn detect_clusters(&self, _repo_name: &str) -> Result<Vec<Value>> {
+        Err(unsupported(self.backend(), "clustering"))
+    }
+    async fn set_embeddings(&self, _repo_name: &str, _embeddings: &[SymbolEmbedding]) -> Result<i64> {
+        Err(unsupported(self.backend(), "embeddings"))
+    }
+
+    // Snapshots; each one's graph is an ordinary repo under its key, so deleting that repo drops
+    // the record too
+    async fn record_snapshot(&self, snapshot: &Snapshot) -> Result<()>;
+    // Newest first
+    async fn list_snapshots(&self, repo_name: &str) -> Result<Vec<Snapshot>>;
+    // Git commit the repo was last indexed at, the base of an incremental run
+    async fn indexed_commit(&self, repo_name: &str) -> Result<Option<String>>;
+    async fn set_indexed_commit(&self, repo_name: &str, commit: &str) -> Result<()>;
+    // How much of the repo an index run has written: "indexing", "partial" (the files docs start
+    // from are in) or "complete"
+    async fn availability(&self, repo_name: &str) -> Result<Option<String>>;
+    async fn set_availability(&self, repo_name: &str, availability: &str) -> Result<()>;
+    // A user's verdict on the repo's classification, replacing the one given before. Kept when
+    // the repo is deleted, since every repo's verdicts recalibrate the classifier
+    async fn classification_feedback(&self, repo_name: &str) -> Result<Option<Feedback>>;
+    async fn set_classification_feedback(&self, repo_name: &str, feedback: &Feedback) -> Result<()>;
+    async fn all_classification_feedback(&self) -> Result<Vec<Feedback>>;
+    // Content hash of every stored file by path; empty for files stored before hashes were kept
+    async fn file_hashes(&self, repo_name: &str) -> Result<HashMap<String, String>>;
+
+    // Delete
+    async fn delete_repo(&self, repo_name: &str) -> Result<i64>;
+    // Drop the repo's nodes an index run no longer produced; `seen` holds the id of every node it
+    // ingested (see `graph::node_ids`). An incremental run passes the files it looked at, and only
+    // their nodes are considered. Returns the number of nodes removed
+    async fn remove_stale(&self, repo_name: &str, files: Option<&[String]>, seen: &HashSet<String>) -> Result<i64>;
+
+    // Query
+    async fn count_by_kind(&self, repo_name: &str) -> Result<Value>;
+    async fn get_file_languages(&self, repo_name: &str) -> Result<Value>;
+    async fn get_all_files(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_all_symbols(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_repo_structure(&self, repo_name: &str) -> Result<Vec<Value>>;
+    // Prose files (README, CHANGELOG, docs/) with their sections and the symbols each mentions
+    async fn get_docs(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_todos(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_routes(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_commands(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_embedded(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_directories(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn get_dependencies(&self, repo_name: &str) -> Result<Value>;
+    async fn call_chain(&self, repo_name: &str, symbol: &str, depth: usize, callers: bool) -> Result<Vec<Value>>;
+    async fn get_hotspots(&self, repo_name: &str, limit: usize) -> Result<Vec<Value>>;
+    async fn get_call_edges(&self, repo_name: &str) -> Result<Vec<(CallEnd, CallEnd)>>;
+    // External packages the repo imports, and every repo (with its files) importing a package
+    async fn get_packages(&self, repo_name: &str) -> Result<Vec<Value>>;
+    async fn package_users(&self, package: &str) -> Result<Vec<Value>>;
+    // Ordered by manifest, then name
+    async fn get_declared_dependencies(&self, repo_name: &str) -> Result<Vec<Dependency>>;
+    // None until an index run has looked for one
+    async fn get_license(&self, repo_name: &str) -> Result<Option<License>>;
+    // Ordered by file, then line
+    async fn get_entrypoints(&self, repo_name: &str) -> Result<Vec<Entrypoint>>;
+    // Ordered by path
+    async fn get_submodules(&self, repo_name: &str) -> Result<Vec<Submodule>>;
+    async fn get_unresolved(&self, _repo_name: &str) -> Result<Value> {
+        Err(unsupported(self.backend(), "unresolved references"))
+    }
+    async fn get_dead_code(&self, _repo_name: &str) -> Result<Value> {
+        Err(unsupported(self.backend(), "dead code detection"))
+    }
+    async fn search_symbols(&self, _repo_name: &str, _text: &str, _limit: i64) -> Result<Vec<Value>> {
+        Err(unsupported(self.backend(), "full-text search"))
+    }
+    async fn similar_symbols(&self, _repo_name: &str, _embedding: &[f32], _k: i64) -> Result<Vec<Value>> {
+        Err(unsupported(self.backend(), "similarity search"))
+    }
+    async fn run_readonly_cypher(&self, _repo_name: &str, _cypher: &str, _max_rows: usize, _timeout: Duration) -> Result<(Vec<Value>, bool)> {
+        Err(unsupported(self.backend(), "cypher"))
+    }
+
+    // Export
+    async fn export_keys(&self, _repo_name: &str) -> Result<(Vec<String>, Vec<String>)> {
+        Err(unsupported(self.backend(), "GraphML export"))
+    }
+    async fn export_nodes(&self, _repo_name: &str, _label: &str, _skip: i64, _limit: i64) -> Result<Vec<ExportNode>> {
+        Err(unsupported(self.backend(), "GraphML export"))
+    }
+    async fn export_edges(&self, _repo_name: &str, _label: &str, _skip: i64, _limit: i64) -> Result<Vec<ExportEdge>> {
+        Err(unsupported(self.backend(), "GraphML export"))
+    }
+    async fn dump_repo(&self, _repo_name: &str) -> Result<Value> {
+        Err(unsupported(self.backend(), "dumps"))
+    }
+    async fn restore_repo(&self, _dump: &Value) -> Result<(i64, i64)> {
+        Err(unsupported(self.backend(), "restore"))
+    }
+}
+
+pub(crate) fn unsupported(backend: &str, what: &str) -> Error {
+    Error::UnexpectedMessage(format!("{} is not supported by the {} backend", what, backend))
+}